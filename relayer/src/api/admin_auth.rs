@@ -0,0 +1,160 @@
+use anyhow::{Context, Result, bail};
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{HeaderMap, StatusCode, request::Parts},
+};
+use std::net::{IpAddr, SocketAddr};
+
+use crate::api::rest_api::AppState;
+
+/// Source addresses allowed to call admin endpoints, as single IPs or CIDR ranges; layered onto
+/// the REST router as an extension. Empty allows any source
+#[derive(Debug, Clone, Default)]
+pub struct IpAllowlist(Vec<(IpAddr, u8)>);
+
+impl IpAllowlist {
+    /// Parse entries such as `10.0.0.5`, `10.0.0.0/8` or `::1`
+    pub fn parse(entries: &[String]) -> Result<Self> {
+        entries
+            .iter()
+            .map(|entry| parse_net(entry).with_context(|| format!("invalid address {:?}", entry)))
+            .collect::<Result<Vec<_>>>()
+            .map(Self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // Treat IPv4-mapped IPv6 sources (dual-stack listeners) as the IPv4 address they carry
+        let ip = ip.to_canonical();
+        self.0.iter().any(|(net, prefix)| in_net(ip, *net, *prefix))
+    }
+}
+
+fn parse_net(entry: &str) -> Result<(IpAddr, u8)> {
+    let entry = entry.trim();
+    let (addr, prefix) = match entry.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (entry, None),
+    };
+    let addr: IpAddr = addr.parse()?;
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(p) => p.parse::<u8>()?,
+        None => max,
+    };
+    if prefix > max {
+        bail!("prefix /{} is longer than {} bits", prefix, max);
+    }
+    Ok((addr, prefix))
+}
+
+fn in_net(ip: IpAddr, net: IpAddr, prefix: u8) -> bool {
+    match (ip, net) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(net) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(net)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(net) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Passed the admin checks: a source inside `output.admin_allowed_ips` when that list is set
+/// (403 otherwise), and the `X-Settlement-Token` header when a token is configured (401 otherwise)
+#[derive(Debug, Clone, Copy)]
+pub struct AdminAuth;
+
+impl FromRequestParts<AppState> for AdminAuth {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, StatusCode> {
+        let allowlist = parts.extensions.get::<IpAllowlist>();
+        if let Some(allowlist) = allowlist.filter(|a| !a.is_empty()) {
+            let source = parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip());
+            if !source.is_some_and(|ip| allowlist.contains(ip)) {
+                tracing::warn!(
+                    "Refused admin request {} from {:?}: source not in output.admin_allowed_ips",
+                    parts.uri.path(),
+                    source
+                );
+                return Err(StatusCode::FORBIDDEN);
+            }
+        }
+        if !is_token_valid(&parts.headers, state.settlement_token.as_deref()) {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Ok(AdminAuth)
+    }
+}
+
+fn is_token_valid(headers: &HeaderMap, expected: Option<&str>) -> bool {
+    match expected {
+        None => true, // no token configured -> allow
+        Some(token) => headers
+            .get("X-Settlement-Token")
+            .and_then(|h| h.to_str().ok())
+            .map(|v| v == token)
+            .unwrap_or(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowlist(entries: &[&str]) -> IpAllowlist {
+        IpAllowlist::parse(&entries.iter().map(|e| e.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    fn ip(addr: &str) -> IpAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn allowlist_matches_ips_and_ranges() {
+        let list = allowlist(&["127.0.0.1", " 10.0.0.0/8 ", "::1", "fd00::/16"]);
+        assert!(list.contains(ip("127.0.0.1")));
+        assert!(list.contains(ip("10.255.0.7")));
+        assert!(list.contains(ip("::1")));
+        assert!(list.contains(ip("fd00:1::5")));
+        // Dual-stack listeners report IPv4 peers as mapped IPv6
+        assert!(list.contains(ip("::ffff:10.1.2.3")));
+        assert!(!list.contains(ip("11.0.0.1")));
+        assert!(!list.contains(ip("127.0.0.2")));
+
+        let everything = allowlist(&["0.0.0.0/0"]);
+        assert!(everything.contains(ip("203.0.113.9")));
+        assert!(allowlist(&[]).is_empty());
+    }
+
+    #[test]
+    fn invalid_allowlist_entries_are_rejected() {
+        for entry in ["10.0.0.0/33", "::1/129", "localhost", "10.0.0.0/x"] {
+            assert!(
+                IpAllowlist::parse(&[entry.to_string()]).is_err(),
+                "{}",
+                entry
+            );
+        }
+    }
+
+    #[test]
+    fn token_is_required_only_when_configured() {
+        let mut headers = HeaderMap::new();
+        assert!(is_token_valid(&headers, None));
+        assert!(!is_token_valid(&headers, Some("secret")));
+        headers.insert("X-Settlement-Token", "wrong".parse().unwrap());
+        assert!(!is_token_valid(&headers, Some("secret")));
+        headers.insert("X-Settlement-Token", "secret".parse().unwrap());
+        assert!(is_token_valid(&headers, Some("secret")));
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// A captured error-level log event
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorRecord {
+    pub timestamp: String,
+    pub module: String,
+    pub message: String,
+}
+
+/// Bounded ring buffer of the most recent error-level events
+#[derive(Clone)]
+pub struct ErrorLog {
+    records: Arc<Mutex<VecDeque<ErrorRecord>>>,
+    capacity: usize,
+}
+
+impl ErrorLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    pub fn record(&self, record: ErrorRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        if records.len() >= self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// Recorded errors, newest first
+    pub fn snapshot(&self) -> Vec<ErrorRecord> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        records.iter().rev().cloned().collect()
+    }
+}
+
+/// Tracing layer feeding error-level events into an `ErrorLog`
+impl<S: Subscriber> Layer<S> for ErrorLog {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() != Level::ERROR {
+            return;
+        }
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        self.record(ErrorRecord {
+            timestamp: Utc::now().to_rfc3339(),
+            module: metadata
+                .module_path()
+                .unwrap_or_else(|| metadata.target())
+                .to_string(),
+            message: visitor.message,
+        });
+    }
+}
+
+/// Collects the `message` field followed by any structured fields
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.message, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn record(message: &str) -> ErrorRecord {
+        ErrorRecord {
+            timestamp: String::new(),
+            module: "test".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn keeps_newest_records_up_to_capacity() {
+        let log = ErrorLog::new(2);
+        for message in ["a", "b", "c"] {
+            log.record(record(message));
+        }
+        let messages: Vec<_> = log.snapshot().into_iter().map(|r| r.message).collect();
+        assert_eq!(messages, ["c", "b"]);
+
+        let disabled = ErrorLog::new(0);
+        disabled.record(record("a"));
+        assert!(disabled.snapshot().is_empty());
+    }
+
+    #[test]
+    fn layer_captures_only_error_events_with_fields() {
+        let log = ErrorLog::new(10);
+        let subscriber = tracing_subscriber::registry().with(log.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!("not captured");
+            tracing::error!(code = 7, "settlement failed");
+        });
+        let records = log.snapshot();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].message, "settlement failed code=7");
+        assert!(records[0].module.ends_with("error_log::tests"));
+    }
+}
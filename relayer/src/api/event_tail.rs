@@ -0,0 +1,65 @@
+use nostr_sdk::{Event, Timestamp};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Metadata of a raw inbound event; content is never decrypted or included
+#[derive(Debug, Clone, Serialize)]
+pub struct TailRecord {
+    pub id: String,
+    pub kind: u16,
+    pub pubkey: String,
+    pub created_at: u64,
+    pub size: usize,
+}
+
+/// Rate-limited broadcast of inbound event metadata for `/api/debug/tail`
+#[derive(Clone)]
+pub struct EventTail {
+    tx: broadcast::Sender<TailRecord>,
+    max_per_sec: u32,
+    /// (unix second, records sent in that second)
+    window: Arc<Mutex<(u64, u32)>>,
+}
+
+impl EventTail {
+    pub fn new(max_per_sec: u32) -> Self {
+        let (tx, _) = broadcast::channel(256);
+        Self {
+            tx,
+            max_per_sec,
+            window: Arc::new(Mutex::new((0, 0))),
+        }
+    }
+
+    /// Mirror an event's metadata to tail subscribers; a no-op when nobody is tailing
+    pub fn publish(&self, event: &Event) {
+        if self.max_per_sec == 0 || self.tx.receiver_count() == 0 {
+            return;
+        }
+
+        let now = Timestamp::now().as_secs();
+        {
+            let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+            if window.0 != now {
+                *window = (now, 0);
+            }
+            if window.1 >= self.max_per_sec {
+                return;
+            }
+            window.1 += 1;
+        }
+
+        let _ = self.tx.send(TailRecord {
+            id: event.id.to_hex(),
+            kind: event.kind.as_u16(),
+            pubkey: event.pubkey.to_hex(),
+            created_at: event.created_at.as_secs(),
+            size: event.content.len(),
+        });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TailRecord> {
+        self.tx.subscribe()
+    }
+}
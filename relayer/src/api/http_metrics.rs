@@ -0,0 +1,64 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::api::metrics::Metrics;
+
+/// Route whose own scrapes are not counted
+const METRICS_ROUTE: &str = "/metrics";
+
+/// Record request count and latency per method and matched route template.
+/// Installed with `route_layer`, so unmatched paths never create label values
+pub async fn track_http(State(metrics): State<Arc<Metrics>>, req: Request, next: Next) -> Response {
+    let Some(route) = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .filter(|p| p != METRICS_ROUTE)
+    else {
+        return next.run(req).await;
+    };
+    let method = req.method().clone();
+
+    let started = Instant::now();
+    let response = next.run(req).await;
+    metrics.record_http(method.as_str(), &route, started.elapsed());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Body, middleware, routing::get};
+    use prometheus::Registry;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn requests_are_counted_by_route_template() {
+        let metrics = Arc::new(Metrics::with_registry(&Registry::new()).unwrap());
+        let app = Router::new()
+            .route("/api/bots/{bot_pubkey}", get(|| async { "bot" }))
+            .route(METRICS_ROUTE, get(|| async { "" }))
+            .route_layer(middleware::from_fn_with_state(metrics.clone(), track_http));
+
+        for uri in ["/api/bots/a", "/api/bots/b", "/metrics", "/missing"] {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            app.clone().oneshot(request).await.unwrap();
+        }
+
+        let count = |route: &str| {
+            metrics
+                .http_requests
+                .with_label_values(&["GET", route])
+                .get()
+        };
+        assert_eq!(count("/api/bots/{bot_pubkey}"), 2);
+        // Neither scrapes nor unmatched paths are counted
+        assert_eq!(count(METRICS_ROUTE), 0);
+        assert_eq!(count("/missing"), 0);
+    }
+}
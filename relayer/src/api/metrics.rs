@@ -1,48 +1,329 @@
+use prometheus::core::Collector;
 use prometheus::{
-    Gauge, Histogram, IntCounter, register_gauge, register_histogram, register_int_counter,
+    Gauge, Histogram, HistogramVec, IntCounter, IntCounterVec, Registry,
+    register_gauge_with_registry, register_histogram_vec_with_registry,
+    register_histogram_with_registry, register_int_counter_vec_with_registry,
+    register_int_counter_with_registry,
 };
+use std::collections::BTreeMap;
+use std::time::Duration;
 
 /// Metrics for monitoring the relay system
 pub struct Metrics {
     pub events_processed: IntCounter,
-    pub duplicates_filtered: IntCounter,
+    pub duplicates_filtered: IntCounterVec,
     pub processing_latency: Histogram,
     pub memory_usage: Gauge,
     pub active_connections: Gauge,
     pub events_in_queue: Gauge,
+    pub pending_events_bytes: Gauge,
+    pub agent_register_rejected: IntCounter,
+    pub agent_register_ignored: IntCounter,
+    pub fanout_dropped: IntCounter,
+    pub resubscribe_total: IntCounter,
+    pub relay_rate_limited: IntCounter,
+    pub oversize_events_dropped: IntCounter,
+    pub rate_limited_events: IntCounter,
+    pub missing_tag_events: IntCounter,
+    pub replayed_events: IntCounter,
+    pub pending_trades: Gauge,
+    pub future_dated_events: IntCounter,
+    pub signal_persist_failures: IntCounter,
+    pub credit_awards_capped: IntCounter,
+    pub credit_awards_rejected: IntCounter,
+    pub nostr_publish_skipped: IntCounter,
+    pub paused_events_dropped: IntCounter,
+    pub paused_buffer_evicted: IntCounter,
+    pub paused_buffer_events: Gauge,
+    pub retired_key_decryptions: IntCounter,
+    pub ingestion_paused: Gauge,
+    pub followers_suspended: IntCounter,
+    pub settlement_invalid_tx_hash: IntCounter,
+    pub settlement_outcomes: IntCounterVec,
+    pub http_requests: IntCounterVec,
+    pub http_request_duration: HistogramVec,
+    pub dedupe_warm_remaining: Gauge,
+    pub fanout_disabled: Gauge,
+    pub fanout_connections: Gauge,
 }
 
 impl Metrics {
     /// Create and register all metrics
     pub fn new() -> Result<Self, prometheus::Error> {
+        Self::with_registry(prometheus::default_registry())
+    }
+
+    /// Create all metrics in `registry`; tests use a fresh one each so they can coexist
+    pub fn with_registry(registry: &Registry) -> Result<Self, prometheus::Error> {
         Ok(Self {
-            events_processed: register_int_counter!(
+            events_processed: register_int_counter_with_registry!(
                 "events_processed_total",
-                "Total events processed"
+                "Total events processed",
+                registry
             )?,
-            duplicates_filtered: register_int_counter!(
+            duplicates_filtered: register_int_counter_vec_with_registry!(
                 "duplicates_filtered_total",
-                "Total duplicates filtered"
+                "Total duplicates filtered, by event kind",
+                &["kind"],
+                registry
             )?,
-            processing_latency: register_histogram!(
+            processing_latency: register_histogram_with_registry!(
                 "processing_latency_seconds",
-                "Event processing latency in seconds"
+                "Event processing latency in seconds",
+                registry
             )?,
-            memory_usage: register_gauge!("memory_usage_mb", "Memory usage in Million Bytes")?,
-            active_connections: register_gauge!(
+            memory_usage: register_gauge_with_registry!(
+                "memory_usage_mb",
+                "Memory usage in Million Bytes",
+                registry
+            )?,
+            active_connections: register_gauge_with_registry!(
                 "active_connections",
-                "Number of active relay connections"
+                "Number of active relay connections",
+                registry
             )?,
-            events_in_queue: register_gauge!(
+            events_in_queue: register_gauge_with_registry!(
                 "events_in_queue",
-                "Number of events waiting in queue"
+                "Number of events waiting in queue",
+                registry
+            )?,
+            pending_events_bytes: register_gauge_with_registry!(
+                "pending_events_bytes",
+                "Estimated memory held by events waiting in queue",
+                registry
+            )?,
+            agent_register_rejected: register_int_counter_with_registry!(
+                "agent_register_rejected_total",
+                "Agent register events rejected for a missing or invalid signature",
+                registry
+            )?,
+            agent_register_ignored: register_int_counter_with_registry!(
+                "agent_register_ignored_total",
+                "Agent register events ignored as too old or older than the bot's last registration",
+                registry
+            )?,
+            fanout_dropped: register_int_counter_with_registry!(
+                "fanout_dropped_total",
+                "Fanout messages dropped because the fanout channel was full",
+                registry
+            )?,
+            resubscribe_total: register_int_counter_with_registry!(
+                "resubscribe_total",
+                "Relay subscriptions re-issued after a CLOSED message or idle timeout",
+                registry
+            )?,
+            relay_rate_limited: register_int_counter_with_registry!(
+                "relay_rate_limited_total",
+                "Rate-limit NOTICEs received from relays",
+                registry
+            )?,
+            oversize_events_dropped: register_int_counter_with_registry!(
+                "oversize_events_dropped_total",
+                "Events dropped for exceeding filters.max_content_bytes",
+                registry
+            )?,
+            rate_limited_events: register_int_counter_with_registry!(
+                "rate_limited_events_total",
+                "Events dropped because their pubkey exceeded filters.pubkey_burst/pubkey_per_sec",
+                registry
+            )?,
+            missing_tag_events: register_int_counter_with_registry!(
+                "missing_tag_events_total",
+                "Events dropped because they lack a tag listed in filters.required_tags",
+                registry
+            )?,
+            replayed_events: register_int_counter_with_registry!(
+                "replayed_events_total",
+                "Events dropped by filters.strict_monotonic as no newer than their pubkey's last event",
+                registry
+            )?,
+            pending_trades: register_gauge_with_registry!(
+                "pending_trades",
+                "Trades awaiting settlement",
+                registry
+            )?,
+            future_dated_events: register_int_counter_with_registry!(
+                "future_dated_events_total",
+                "Events whose created_at exceeded the allowed future skew",
+                registry
+            )?,
+            signal_persist_failures: register_int_counter_with_registry!(
+                "signal_persist_failures_total",
+                "Trade signals lost after exhausting persistence retries",
+                registry
+            )?,
+            credit_awards_capped: register_int_counter_with_registry!(
+                "credit_awards_capped_total",
+                "Trade credit awards clamped to settlement.credit.max_award",
+                registry
+            )?,
+            credit_awards_rejected: register_int_counter_with_registry!(
+                "credit_awards_rejected_total",
+                "Trade credit awards skipped as non-finite or implausibly large",
+                registry
+            )?,
+            nostr_publish_skipped: register_int_counter_with_registry!(
+                "nostr_publish_skipped_total",
+                "Follower nostr publishes skipped because follower publishing is disabled",
+                registry
+            )?,
+            followers_suspended: register_int_counter_with_registry!(
+                "followers_suspended_total",
+                "Followers whose nostr publishing was suspended after repeated delivery failures",
+                registry
+            )?,
+            paused_events_dropped: register_int_counter_with_registry!(
+                "paused_events_dropped_total",
+                "Inbound events discarded while ingestion was paused in drop mode",
+                registry
+            )?,
+            paused_buffer_evicted: register_int_counter_with_registry!(
+                "paused_buffer_evicted_total",
+                "Oldest buffered events dropped because the pause buffer was full",
+                registry
+            )?,
+            paused_buffer_events: register_gauge_with_registry!(
+                "paused_buffer_events",
+                "Events held in the pause buffer waiting for ingestion to resume",
+                registry
+            )?,
+            retired_key_decryptions: register_int_counter_with_registry!(
+                "retired_key_decryptions_total",
+                "Inbound events decrypted with a retired platform key",
+                registry
+            )?,
+            ingestion_paused: register_gauge_with_registry!(
+                "ingestion_paused",
+                "1 while event ingestion is paused through /api/admin/pause",
+                registry
+            )?,
+            settlement_invalid_tx_hash: register_int_counter_with_registry!(
+                "settlement_invalid_tx_hash_total",
+                "Trades failed by settlement for a malformed tx hash, without an explorer request",
+                registry
+            )?,
+            settlement_outcomes: register_int_counter_vec_with_registry!(
+                "settlement_outcomes_total",
+                "Settled trades by outcome (confirmed, failed, stale, credited)",
+                &["outcome"],
+                registry
+            )?,
+            http_requests: register_int_counter_vec_with_registry!(
+                "http_requests_total",
+                "REST API requests, by method and route template",
+                &["method", "route"],
+                registry
+            )?,
+            http_request_duration: register_histogram_vec_with_registry!(
+                "http_request_duration_seconds",
+                "REST API request latency in seconds, by method and route template",
+                &["method", "route"],
+                registry
+            )?,
+            dedupe_warm_remaining: register_gauge_with_registry!(
+                "dedupe_warm_remaining",
+                "Ids still to be loaded into the dedupe caches from RocksDB at startup",
+                registry
+            )?,
+            fanout_disabled: register_gauge_with_registry!(
+                "fanout_disabled",
+                "1 once the WebSocket fanout channel has closed and fanout sends were stopped",
+                registry
+            )?,
+            fanout_connections: register_gauge_with_registry!(
+                "fanout_connections",
+                "Clients connected to the /fanout WebSocket",
+                registry
             )?,
         })
     }
 }
 
+impl Metrics {
+    /// Count a filtered duplicate of `kind`
+    pub fn record_duplicate(&self, kind: u16) {
+        self.duplicates_filtered
+            .with_label_values(&[kind.to_string().as_str()])
+            .inc();
+    }
+
+    /// Count one REST request to `route` (the template, e.g. `/api/bots/{bot_pubkey}/stats`)
+    pub fn record_http(&self, method: &str, route: &str, elapsed: Duration) {
+        let labels = [method, route];
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_request_duration
+            .with_label_values(&labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Duplicates filtered so far, keyed by kind
+    pub fn duplicates_by_kind(&self) -> BTreeMap<String, u64> {
+        counts_by_label(&self.duplicates_filtered, "kind")
+    }
+
+    /// Settlement outcome counts keyed by outcome, every outcome listed even at zero
+    pub fn settlement_outcomes(&self) -> BTreeMap<String, u64> {
+        settlement_outcome_counts(&self.settlement_outcomes)
+    }
+}
+
+fn settlement_outcome_counts(vec: &IntCounterVec) -> BTreeMap<String, u64> {
+    let mut out = counts_by_label(vec, "outcome");
+    for outcome in SETTLEMENT_OUTCOMES {
+        out.entry(outcome.to_string()).or_insert(0);
+    }
+    out
+}
+
+/// Label values of `settlement_outcomes_total`
+const SETTLEMENT_OUTCOMES: [&str; 4] = ["confirmed", "failed", "stale", "credited"];
+
+fn counts_by_label(vec: &IntCounterVec, label: &str) -> BTreeMap<String, u64> {
+    let mut out = BTreeMap::new();
+    for family in vec.collect() {
+        for metric in family.get_metric() {
+            let value = metric
+                .get_label()
+                .iter()
+                .find(|l| l.name() == label)
+                .map(|l| l.value().to_string())
+                .unwrap_or_default();
+            out.insert(value, metric.get_counter().value() as u64);
+        }
+    }
+    out
+}
+
 impl Default for Metrics {
     fn default() -> Self {
         Self::new().expect("Failed to create metrics")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::Opts;
+
+    #[test]
+    fn settlement_outcomes_list_every_outcome() {
+        let outcomes = IntCounterVec::new(
+            Opts::new("settlement_outcomes_total", "Settled trades by outcome"),
+            &["outcome"],
+        )
+        .unwrap();
+        outcomes.with_label_values(&["confirmed"]).inc_by(2);
+        outcomes.with_label_values(&["credited"]).inc();
+        let counts = settlement_outcome_counts(&outcomes);
+        let expected: BTreeMap<String, u64> = [
+            ("confirmed", 2),
+            ("credited", 1),
+            ("failed", 0),
+            ("stale", 0),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+        assert_eq!(counts, expected);
+    }
+}
@@ -1,3 +1,10 @@
+pub mod admin_auth;
+pub mod error_log;
+pub mod event_tail;
+pub mod http_metrics;
 pub mod metrics;
+pub mod openmetrics;
+pub mod rate_limit;
 pub mod rest_api;
+pub mod timeseries;
 pub mod websocket;
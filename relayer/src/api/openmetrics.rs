@@ -0,0 +1,197 @@
+use prometheus::proto::{LabelPair, Metric, MetricFamily, MetricType};
+use std::fmt::Write;
+
+/// Content type negotiated by scrapers that prefer OpenMetrics
+pub const OPENMETRICS_FORMAT: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Whether an `Accept` header asks for OpenMetrics
+pub fn accepts_openmetrics(accept: &str) -> bool {
+    accept
+        .split(',')
+        .filter_map(|part| part.split(';').next())
+        .any(|media| {
+            media
+                .trim()
+                .eq_ignore_ascii_case("application/openmetrics-text")
+        })
+}
+
+/// Encode gathered families in the OpenMetrics 1.0 text exposition format
+pub fn encode(families: &[MetricFamily]) -> String {
+    let mut out = String::new();
+    for mf in families {
+        let name = mf.name();
+        let (family, kind) = match mf.get_field_type() {
+            // OpenMetrics names the counter family without its `_total` sample suffix
+            MetricType::COUNTER => (name.strip_suffix("_total").unwrap_or(name), "counter"),
+            MetricType::GAUGE => (name, "gauge"),
+            MetricType::HISTOGRAM => (name, "histogram"),
+            MetricType::SUMMARY => (name, "summary"),
+            MetricType::UNTYPED => (name, "unknown"),
+        };
+
+        let _ = writeln!(out, "# TYPE {} {}", family, kind);
+        if !mf.help().is_empty() {
+            let _ = writeln!(out, "# HELP {} {}", family, escape(mf.help()));
+        }
+
+        for m in mf.get_metric() {
+            match mf.get_field_type() {
+                MetricType::COUNTER => {
+                    let sample = format!("{}_total", family);
+                    write_sample(&mut out, &sample, m, None, m.get_counter().value());
+                }
+                MetricType::GAUGE => {
+                    write_sample(&mut out, family, m, None, m.get_gauge().value());
+                }
+                MetricType::UNTYPED => {
+                    write_sample(&mut out, family, m, None, m.untyped.value());
+                }
+                MetricType::HISTOGRAM => {
+                    let h = m.get_histogram();
+                    let bucket = format!("{}_bucket", family);
+                    let mut inf_seen = false;
+                    for b in h.get_bucket() {
+                        inf_seen |= b.upper_bound() == f64::INFINITY;
+                        let le = format_float(b.upper_bound());
+                        let count = b.cumulative_count() as f64;
+                        write_sample(&mut out, &bucket, m, Some(("le", &le)), count);
+                    }
+                    if !inf_seen {
+                        let count = h.get_sample_count() as f64;
+                        write_sample(&mut out, &bucket, m, Some(("le", "+Inf")), count);
+                    }
+                    let count = h.get_sample_count() as f64;
+                    write_sample(&mut out, &format!("{}_count", family), m, None, count);
+                    let sum = h.get_sample_sum();
+                    write_sample(&mut out, &format!("{}_sum", family), m, None, sum);
+                }
+                MetricType::SUMMARY => {
+                    let s = m.get_summary();
+                    for q in s.get_quantile() {
+                        let quantile = format_float(q.quantile());
+                        write_sample(
+                            &mut out,
+                            family,
+                            m,
+                            Some(("quantile", &quantile)),
+                            q.value(),
+                        );
+                    }
+                    let count = s.sample_count() as f64;
+                    write_sample(&mut out, &format!("{}_count", family), m, None, count);
+                    write_sample(
+                        &mut out,
+                        &format!("{}_sum", family),
+                        m,
+                        None,
+                        s.sample_sum(),
+                    );
+                }
+            }
+        }
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+fn write_sample(out: &mut String, name: &str, m: &Metric, extra: Option<(&str, &str)>, value: f64) {
+    out.push_str(name);
+    write_labels(out, m.get_label(), extra);
+    let _ = writeln!(out, " {}", format_float(value));
+}
+
+fn write_labels(out: &mut String, labels: &[LabelPair], extra: Option<(&str, &str)>) {
+    let pairs: Vec<(&str, &str)> = labels
+        .iter()
+        .map(|lp| (lp.name(), lp.value()))
+        .chain(extra)
+        .collect();
+    if pairs.is_empty() {
+        return;
+    }
+    let rendered: Vec<String> = pairs
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+        .collect();
+    let _ = write!(out, "{{{}}}", rendered.join(","));
+}
+
+/// OpenMetrics requires `+Inf`/`-Inf`/`NaN` and accepts Rust's shortest round-trip floats
+fn format_float(v: f64) -> String {
+    if v.is_nan() {
+        "NaN".to_string()
+    } else if v == f64::INFINITY {
+        "+Inf".to_string()
+    } else if v == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        format!("{:?}", v)
+    }
+}
+
+fn escape(v: &str) -> String {
+    v.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::{Gauge, Histogram, HistogramOpts, IntCounterVec, Opts, Registry};
+
+    #[test]
+    fn accept_header_negotiation() {
+        assert!(accepts_openmetrics(
+            "application/openmetrics-text; version=1.0.0, text/plain;q=0.5"
+        ));
+        assert!(accepts_openmetrics(
+            "text/plain, Application/OpenMetrics-Text"
+        ));
+        assert!(!accepts_openmetrics("text/plain; version=0.0.4"));
+        assert!(!accepts_openmetrics(""));
+    }
+
+    #[test]
+    fn encodes_counters_gauges_and_histograms() {
+        let registry = Registry::new();
+        let jobs = IntCounterVec::new(Opts::new("jobs_total", "Jobs \"done\""), &["kind"]).unwrap();
+        let queue = Gauge::new("queue", "Queue depth").unwrap();
+        let latency =
+            Histogram::with_opts(HistogramOpts::new("latency", "Latency").buckets(vec![0.5]))
+                .unwrap();
+        registry.register(Box::new(jobs.clone())).unwrap();
+        registry.register(Box::new(queue.clone())).unwrap();
+        registry.register(Box::new(latency.clone())).unwrap();
+        jobs.with_label_values(&["a\nb"]).inc_by(2);
+        queue.set(1.5);
+        latency.observe(0.25);
+        latency.observe(2.0);
+
+        assert_eq!(
+            encode(&registry.gather()),
+            "# TYPE jobs counter\n\
+             # HELP jobs Jobs \\\"done\\\"\n\
+             jobs_total{kind=\"a\\nb\"} 2.0\n\
+             # TYPE latency histogram\n\
+             # HELP latency Latency\n\
+             latency_bucket{le=\"0.5\"} 1.0\n\
+             latency_bucket{le=\"+Inf\"} 2.0\n\
+             latency_count 2.0\n\
+             latency_sum 2.25\n\
+             # TYPE queue gauge\n\
+             # HELP queue Queue depth\n\
+             queue 1.5\n\
+             # EOF\n"
+        );
+    }
+
+    #[test]
+    fn special_floats_use_openmetrics_spelling() {
+        assert_eq!(format_float(f64::INFINITY), "+Inf");
+        assert_eq!(format_float(f64::NEG_INFINITY), "-Inf");
+        assert_eq!(format_float(f64::NAN), "NaN");
+        assert_eq!(format_float(3.0), "3.0");
+    }
+}
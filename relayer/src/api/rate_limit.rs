@@ -0,0 +1,76 @@
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Shared token bucket: `burst` requests at once, refilled at `per_sec`
+#[derive(Clone)]
+pub struct TokenBucket {
+    burst: f64,
+    per_sec: f64,
+    /// (tokens available, last refill)
+    state: Arc<Mutex<(f64, Instant)>>,
+}
+
+impl TokenBucket {
+    /// A bucket with zero `burst` or `per_sec` never limits
+    pub fn new(burst: u32, per_sec: f64) -> Self {
+        let burst = burst as f64;
+        Self {
+            burst,
+            per_sec,
+            state: Arc::new(Mutex::new((burst, Instant::now()))),
+        }
+    }
+
+    /// Take one token; false when the bucket is empty
+    pub fn try_acquire(&self) -> bool {
+        if self.burst <= 0.0 || self.per_sec <= 0.0 {
+            return true;
+        }
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let refill = now.duration_since(state.1).as_secs_f64() * self.per_sec;
+        *state = ((state.0 + refill).min(self.burst), now);
+        if state.0 < 1.0 {
+            return false;
+        }
+        state.0 -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn allows_burst_then_limits() {
+        let bucket = TokenBucket::new(3, 0.001);
+        assert!((0..3).all(|_| bucket.try_acquire()));
+        assert!(!bucket.try_acquire());
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let bucket = TokenBucket::new(1, 1000.0);
+        assert!(bucket.try_acquire());
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(bucket.try_acquire());
+    }
+
+    #[test]
+    fn clones_share_tokens() {
+        let bucket = TokenBucket::new(1, 0.001);
+        let clone = bucket.clone();
+        assert!(bucket.try_acquire());
+        assert!(!clone.try_acquire());
+    }
+
+    #[test]
+    fn zero_settings_never_limit() {
+        let no_burst = TokenBucket::new(0, 1.0);
+        let no_refill = TokenBucket::new(1, 0.0);
+        assert!((0..100).all(|_| no_burst.try_acquire() && no_refill.try_acquire()));
+    }
+}
@@ -1,23 +1,53 @@
 use axum::{
-    Router,
-    extract::{Path, Query, State},
-    http::{HeaderMap, StatusCode},
-    response::Json,
-    routing::{delete, get, post},
+    Extension, Router,
+    body::Body,
+    extract::{FromRequest, Path, Query, Request, State, rejection::JsonRejection},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware,
+    response::{
+        IntoResponse, Json, Response,
+        sse::{Event as SseEvent, KeepAlive, Sse},
+    },
+    routing::{delete, get, post, put},
 };
-use chrono::{Datelike, Utc};
+use chrono::{DateTime, Datelike, Utc};
 use prometheus::{Encoder, TextEncoder};
-use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer, Serialize, de::DeserializeOwned};
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio_postgres::error::SqlState;
 
+use crate::api::admin_auth::AdminAuth;
+use crate::api::error_log::{ErrorLog, ErrorRecord};
+use crate::api::event_tail::EventTail;
+use crate::api::http_metrics;
 use crate::api::metrics::Metrics;
+use crate::api::openmetrics;
+use crate::api::rate_limit::TokenBucket;
+use crate::api::timeseries::EventTimeseries;
+use crate::api::websocket::ConnectionRegistry;
+use crate::config::{SettlementConfig, SettlementCreditConfig, SubscriptionsConfig};
 use crate::core::dedupe_engine::DeduplicationEngine;
+use crate::core::eth_signature::{
+    eip712_digest, eip712_domain_separator, eip712_string_struct_hash, recover_address,
+};
+use crate::core::event_router::follower_relay_allowed;
+use crate::core::ingestion::IngestionGate;
+use crate::core::pubkey::canonical_pubkey;
 use crate::core::relay_pool::RelayPool;
-use crate::core::subscription::SubscriptionService;
+use crate::core::settlement_worker::{
+    NotLeader, SettlementHandle, award_reason, compute_credit, log_award_error, trade_ref,
+};
+use crate::core::subscription::{
+    CreditAward, CreditLedgerEntry, FanoutEncryption, KeyedRegistration, SignalRecord,
+    SubscriptionService, TradeRecord, TradeRowKey, TradeStatus,
+};
+use futures::{Stream, StreamExt, stream};
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -27,7 +57,12 @@ pub struct AppState {
     pub subscriptions: Option<Arc<SubscriptionService>>,
     pub platform_pubkey: Option<String>,
     pub settlement_token: Option<String>,
+    pub settlement_credit: Option<SettlementCreditConfig>,
+    /// EIP-712 chain id when `/api/trades/record` requires a signature from the bot's eth address
+    pub trade_signature_chain_id: Option<u64>,
     pub subscription_daily_limit: u64,
+    pub max_followers_per_bot: u64,
+    pub secret_overlap_secs: u64,
     pub subscription_limiters: Arc<Mutex<HashMap<String, DailyLimit>>>,
 }
 
@@ -61,6 +96,49 @@ fn current_day_id() -> i32 {
     d.year() * 10_000 + d.month() as i32 * 100 + d.day() as i32
 }
 
+/// Copytrade role kinds; `/api/platform` advertises the ones the relay pool currently allows
+#[derive(Debug, Clone, Default)]
+pub struct SupportedKinds(pub Vec<u16>);
+
+/// JSON body extractor that reports malformed payloads as a structured error
+/// (`{"error": {"status", "kind", "message"}}`) instead of axum's plain-text rejection
+pub struct JsonBody<T>(pub T);
+
+impl<S, T> FromRequest<S> for JsonBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, Json<serde_json::Value>);
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(Self(value)),
+            Err(rejection) => Err(json_rejection_error(rejection)),
+        }
+    }
+}
+
+fn json_rejection_error(rejection: JsonRejection) -> (StatusCode, Json<serde_json::Value>) {
+    let kind = match &rejection {
+        JsonRejection::JsonDataError(_) => "invalid_data",
+        JsonRejection::JsonSyntaxError(_) => "invalid_json",
+        JsonRejection::MissingJsonContentType(_) => "missing_content_type",
+        _ => "invalid_body",
+    };
+    let status = rejection.status();
+    (
+        status,
+        Json(json!({
+            "error": {
+                "status": status.as_u16(),
+                "kind": kind,
+                "message": rejection.body_text(),
+            }
+        })),
+    )
+}
+
 /// Create the REST API router
 pub fn create_router(
     pool: Arc<RelayPool>,
@@ -68,9 +146,18 @@ pub fn create_router(
     metrics: Arc<Metrics>,
     subscriptions: Option<Arc<SubscriptionService>>,
     platform_pubkey: Option<String>,
-    settlement_token: Option<String>,
-    subscription_daily_limit: u64,
+    settlement_cfg: Option<SettlementConfig>,
+    subscriptions_cfg: SubscriptionsConfig,
 ) -> Router {
+    let (settlement_token, settlement_credit, trade_signature_chain_id) = match settlement_cfg {
+        Some(s) => (
+            s.token,
+            s.credit,
+            s.require_trade_signature
+                .then_some(s.trade_signature_chain_id),
+        ),
+        None => (None, None, None),
+    };
     let state = AppState {
         pool,
         dedupe,
@@ -78,28 +165,65 @@ pub fn create_router(
         subscriptions,
         platform_pubkey,
         settlement_token,
-        subscription_daily_limit,
+        settlement_credit,
+        trade_signature_chain_id,
+        subscription_daily_limit: subscriptions_cfg.daily_limit,
+        max_followers_per_bot: subscriptions_cfg.max_followers_per_bot,
+        secret_overlap_secs: subscriptions_cfg.secret_overlap_secs,
         subscription_limiters: Arc::new(Mutex::new(HashMap::new())),
     };
+    let request_metrics = state.metrics.clone();
     Router::new()
         .route("/health", get(health))
         .route("/metrics", get(prometheus_metrics))
         .route("/status", get(status))
+        .route("/api/platform", get(platform_info))
         .route("/api/metrics/summary", get(metrics_summary))
         .route("/api/metrics/memory", get(memory))
+        .route("/api/metrics/timeseries", get(metrics_timeseries))
         .route("/api/relays", get(list_relays))
         .route("/api/relays/add", post(add_relay))
         .route("/api/relays/remove", delete(remove_relay))
+        .route("/api/relays/kinds", put(set_relay_kinds))
         .route("/api/bots/register", post(register_bot))
+        .route("/api/bots/{bot_pubkey}", delete(deregister_bot))
+        .route("/api/bots/{bot_pubkey}/stats", get(bot_stats))
+        .route("/api/bots/{bot_pubkey}/followers", get(bot_followers))
+        .route("/api/bots/{bot_pubkey}/pnl", get(bot_pnl))
+        .route("/api/bots/{bot_pubkey}/positions", get(bot_positions))
+        .route("/api/bots/{bot_pubkey}/pause", post(pause_bot))
+        .route("/api/bots/{bot_pubkey}/resume", post(resume_bot))
+        .route("/api/bots/{bot_pubkey}/api-key", post(rotate_bot_api_key))
         .route("/api/subscriptions", post(add_subscription))
+        .route(
+            "/api/subscriptions/rotate",
+            post(rotate_subscription_secret),
+        )
         .route("/api/subscriptions/{bot_pubkey}", get(list_subscriptions))
         .route(
             "/api/subscriptions/by-eth/{eth_address}",
             get(list_subscriptions_by_eth),
         )
+        .route("/api/signals/search", get(search_signals))
+        .route("/api/trades", get(list_trades))
         .route("/api/trades/record", post(record_trade))
         .route("/api/trades/settlement", post(update_trade_settlement))
+        .route("/api/trades/force-settle", post(force_settle_trade))
+        .route("/api/settlement/tick", post(trigger_settlement_tick))
         .route("/api/credits", get(list_credits))
+        .route("/api/credits/award", post(award_credits))
+        .route("/api/credits/history", get(credit_history))
+        .route("/api/export", get(export_csv))
+        .route("/api/admin/pause", post(pause_ingestion))
+        .route("/api/admin/resume", post(resume_ingestion))
+        .route("/api/debug/errors", get(debug_errors))
+        .route("/api/debug/tail", get(debug_tail))
+        .route("/api/ws/connections", get(ws_connections))
+        .route("/api/batch", post(batch_read))
+        .route_layer(middleware::from_fn_with_state(
+            request_metrics,
+            http_metrics::track_http,
+        ))
         .with_state(state)
 }
 
@@ -111,24 +235,150 @@ async fn health() -> Json<serde_json::Value> {
     }))
 }
 
-/// Metrics endpoint for Prometheus
-async fn prometheus_metrics() -> Result<String, StatusCode> {
-    let encoder = TextEncoder::new();
+/// Metrics endpoint for Prometheus; serves OpenMetrics when the scraper's `Accept` asks for it
+async fn prometheus_metrics(headers: HeaderMap) -> Result<Response, StatusCode> {
     let metric_families = prometheus::gather();
+    let wants_openmetrics = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(openmetrics::accepts_openmetrics);
+    if wants_openmetrics {
+        return Ok((
+            [(header::CONTENT_TYPE, openmetrics::OPENMETRICS_FORMAT)],
+            openmetrics::encode(&metric_families),
+        )
+            .into_response());
+    }
+
+    let encoder = TextEncoder::new();
     let mut buffer = Vec::new();
 
     encoder
         .encode(&metric_families, &mut buffer)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    Ok(String::from_utf8_lossy(&buffer).to_string())
+    Ok(String::from_utf8_lossy(&buffer).to_string().into_response())
+}
+
+/// Recent error-level log events, newest first (requires token if configured)
+async fn debug_errors(
+    _admin: AdminAuth,
+    Extension(errors): Extension<ErrorLog>,
+) -> Result<Json<Vec<ErrorRecord>>, StatusCode> {
+    Ok(Json(errors.snapshot()))
+}
+
+/// A connected `/fanout` client
+#[derive(Debug, Serialize)]
+struct WsConnectionItem {
+    follower_pubkey: Option<String>,
+    connected_at: String,
+}
+
+/// Clients currently connected to `/fanout` (requires token if configured)
+async fn ws_connections(
+    _admin: AdminAuth,
+    Extension(connections): Extension<ConnectionRegistry>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let items: Vec<WsConnectionItem> = connections
+        .snapshot()
+        .into_iter()
+        .map(|c| WsConnectionItem {
+            follower_pubkey: c.follower_pubkey,
+            connected_at: c.connected_at.to_rfc3339(),
+        })
+        .collect();
+    Ok(Json(json!({
+        "count": items.len(),
+        "connections": items,
+    })))
+}
+
+/// Server-sent stream of sampled raw inbound event metadata, before filtering and dedupe (requires token if configured)
+async fn debug_tail(
+    _admin: AdminAuth,
+    Extension(tail): Extension<EventTail>,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, StatusCode> {
+    let events = stream::unfold(tail.subscribe(), |mut rx| async move {
+        loop {
+            let sse = match rx.recv().await {
+                Ok(record) => match SseEvent::default().event("event").json_data(&record) {
+                    Ok(sse) => sse,
+                    Err(e) => {
+                        tracing::warn!("Failed to encode tail record: {}", e);
+                        continue;
+                    }
+                },
+                // Slow client: report how many records it missed and keep going
+                Err(RecvError::Lagged(skipped)) => SseEvent::default()
+                    .event("lagged")
+                    .data(skipped.to_string()),
+                Err(RecvError::Closed) => return None,
+            };
+            return Some((Ok(sse), rx));
+        }
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Stop processing inbound events until resumed; buffered or dropped per `output.pause_mode` (requires token if configured)
+async fn pause_ingestion(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Extension(gate): Extension<IngestionGate>,
+) -> Json<serde_json::Value> {
+    set_ingestion_paused(&state, &gate, true)
+}
+
+/// Resume processing inbound events after `/api/admin/pause` (requires token if configured)
+async fn resume_ingestion(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Extension(gate): Extension<IngestionGate>,
+) -> Json<serde_json::Value> {
+    set_ingestion_paused(&state, &gate, false)
+}
+
+fn set_ingestion_paused(
+    state: &AppState,
+    gate: &IngestionGate,
+    paused: bool,
+) -> Json<serde_json::Value> {
+    let changed = gate.set_paused(paused);
+    if changed {
+        tracing::warn!(
+            "Event ingestion {} by admin request",
+            if paused { "paused" } else { "resumed" }
+        );
+    }
+    state
+        .metrics
+        .ingestion_paused
+        .set(if paused { 1.0 } else { 0.0 });
+    Json(json!({
+        "ingestion_paused": paused,
+        "changed": changed,
+        "pause_mode": gate.mode().as_str(),
+        "paused_since": gate.paused_since().map(|t| t.to_rfc3339()),
+    }))
 }
 
 /// Get connection status
-async fn status(State(state): State<AppState>) -> Json<serde_json::Value> {
+async fn status(
+    State(state): State<AppState>,
+    Extension(settlement): Extension<Option<SettlementHandle>>,
+    Extension(gate): Extension<IngestionGate>,
+) -> Json<serde_json::Value> {
     let statuses = state.pool.get_connection_statuses().await;
     let active = state.pool.active_connections();
     let deque_status = state.dedupe.get_stats().await;
+    let settlement = settlement.map(|s| {
+        json!({
+            "interval_secs": s.interval().as_secs(),
+            "next_tick_at": s.next_tick_at().map(|t| t.to_rfc3339()),
+        })
+    });
 
     Json(json!({
         "active_connections": active,
@@ -139,12 +389,36 @@ async fn status(State(state): State<AppState>) -> Json<serde_json::Value> {
             })
         }).collect::<Vec<_>>(),
         "relayer_nostr_pubkey": state.platform_pubkey,
+        "subscriptions_enabled": state.subscriptions.is_some(),
+        "ingestion_paused": gate.is_paused(),
+        "ingestion_paused_since": gate.paused_since().map(|t| t.to_rfc3339()),
         "deduplication_engine": {
             "bloom_filter_size": deque_status.bloom_filter_size,
+            "bloom_bits": deque_status.bloom_bits,
+            "bloom_hashes": deque_status.bloom_hashes,
             "lru_cache_size": deque_status.lru_cache_size,
             "rocksdb_entry_count": deque_status.rocksdb_approximate_count,
             "hot_set_size": deque_status.hot_set_size,
-        }
+        },
+        "settlement": settlement,
+    }))
+}
+
+/// Platform pubkey and what clients may use to send it encrypted events
+async fn platform_info(
+    State(state): State<AppState>,
+    Extension(kinds): Extension<SupportedKinds>,
+) -> Json<serde_json::Value> {
+    // Inbound events are decrypted with nip04 only; without keys nothing can be decrypted
+    let encryption_schemes: Vec<&str> = match state.platform_pubkey {
+        Some(_) => vec![FanoutEncryption::Nip04.as_str()],
+        None => Vec::new(),
+    };
+    let allowed = state.pool.allowed_kinds();
+    Json(json!({
+        "platform_pubkey": state.platform_pubkey,
+        "encryption_schemes": encryption_schemes,
+        "supported_kinds": kinds.0.iter().filter(|k| allowed.allows(**k)).collect::<Vec<_>>(),
     }))
 }
 
@@ -160,6 +434,14 @@ struct RemoveRelayRequest {
     url: String,
 }
 
+/// Request body for changing the kinds relays are subscribed to
+#[derive(Debug, Deserialize)]
+struct SetRelayKindsRequest {
+    /// Empty or missing subscribes to every kind
+    #[serde(default)]
+    kinds: Option<Vec<u16>>,
+}
+
 /// Response for relay operations
 #[derive(Debug, Serialize)]
 struct RelayResponse {
@@ -170,11 +452,13 @@ struct RelayResponse {
 /// Add a new relay
 async fn add_relay(
     State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(payload): Json<AddRelayRequest>,
+    _admin: AdminAuth,
+    Extension(limiter): Extension<TokenBucket>,
+    JsonBody(payload): JsonBody<AddRelayRequest>,
 ) -> Result<Json<RelayResponse>, StatusCode> {
-    if !is_token_valid(&headers, state.settlement_token.as_deref()) {
-        return Err(StatusCode::UNAUTHORIZED);
+    if !limiter.try_acquire() {
+        tracing::warn!("Rate limited relay add for {}", payload.url);
+        return Err(StatusCode::TOO_MANY_REQUESTS);
     }
 
     match state.pool.connect_and_subscribe(payload.url.clone()).await {
@@ -189,14 +473,35 @@ async fn add_relay(
     }
 }
 
+/// Replace the allowed kinds and resubscribe connected relays with the new filter
+async fn set_relay_kinds(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Extension(limiter): Extension<TokenBucket>,
+    JsonBody(payload): JsonBody<SetRelayKindsRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if !limiter.try_acquire() {
+        tracing::warn!("Rate limited relay kinds change");
+        return Err(StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    let resubscribed = state.pool.set_allowed_kinds(payload.kinds).await;
+    Ok(Json(json!({
+        "allowed_kinds": state.pool.allowed_kinds().get(),
+        "resubscribed": resubscribed,
+    })))
+}
+
 /// Remove a relay
 async fn remove_relay(
     State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(payload): Json<RemoveRelayRequest>,
+    _admin: AdminAuth,
+    Extension(limiter): Extension<TokenBucket>,
+    JsonBody(payload): JsonBody<RemoveRelayRequest>,
 ) -> Result<Json<RelayResponse>, StatusCode> {
-    if !is_token_valid(&headers, state.settlement_token.as_deref()) {
-        return Err(StatusCode::UNAUTHORIZED);
+    if !limiter.try_acquire() {
+        tracing::warn!("Rate limited relay remove for {}", payload.url);
+        return Err(StatusCode::TOO_MANY_REQUESTS);
     }
 
     match state.pool.disconnect_relay(&payload.url).await {
@@ -215,18 +520,27 @@ async fn remove_relay(
 async fn list_relays(State(state): State<AppState>) -> Json<serde_json::Value> {
     let _relay_urls = state.pool.list_relays();
     let statuses = state.pool.get_connection_statuses().await;
+    let mut notices = state.pool.last_notices().await;
 
     let mut relay_info = Vec::new();
     for (url, status) in statuses {
+        let last_notice = notices.remove(&url).map(|n| {
+            json!({
+                "message": n.message,
+                "received_at": n.received_at.to_rfc3339(),
+            })
+        });
         relay_info.push(json!({
             "url": url,
-            "status": format!("{:?}", status)
+            "status": format!("{:?}", status),
+            "last_notice": last_notice
         }));
     }
 
     Json(json!({
         "relays": relay_info,
-        "count": relay_info.len()
+        "count": relay_info.len(),
+        "allowed_kinds": state.pool.allowed_kinds().get()
     }))
 }
 
@@ -243,6 +557,67 @@ struct RegisterBotResponse {
     success: bool,
     message: String,
     platform_pubkey: Option<String>,
+    /// Only on the registration that issued it; send as `X-Bot-Api-Key` when recording trades
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct BotApiKeyResponse {
+    bot_pubkey: String,
+    api_key: String,
+}
+
+#[derive(Debug, Serialize)]
+struct BotStatsResponse {
+    bot_pubkey: String,
+    follower_count: i64,
+    max_followers: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct FollowerDetailItem {
+    follower_pubkey: String,
+    subscribed_at: String,
+    credits: Decimal,
+    last_signal_at: Option<String>,
+    /// Nostr publishing suspended after repeated delivery failures, since this time
+    suspended_at: Option<String>,
+    /// Authenticated on `/fanout` right now
+    connected: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct BotPnlQuery {
+    #[serde(default)]
+    include_test: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct SymbolPnlItem {
+    symbol: String,
+    pnl_usd: f64,
+    trade_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct BotPnlResponse {
+    bot_pubkey: String,
+    symbols: Vec<SymbolPnlItem>,
+}
+
+#[derive(Debug, Serialize)]
+struct SymbolPositionItem {
+    symbol: String,
+    net_size: f64,
+    trade_count: i64,
+    skipped_trades: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct BotPositionsResponse {
+    bot_pubkey: String,
+    positions: Vec<SymbolPositionItem>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -256,40 +631,280 @@ struct RecordTradeRequest {
     price: f64,
     tx_hash: Option<String>,
     oid: Option<String>,
+    /// EIP-712 signature by the bot's eth address; required when `settlement.require_trade_signature` is set
+    signature: Option<String>,
+}
+
+const TRADE_RECORD_TYPE: &str = "TradeRecord(string botPubkey,string followerPubkey,string role,string symbol,string side,string size,string price,string txHash,string oid)";
+
+impl RecordTradeRequest {
+    /// EIP-712 digest over the submitted fields; absent optionals sign as "" and numbers as
+    /// their shortest decimal form (`0.5`, `100`)
+    fn typed_data_digest(&self, chain_id: u64) -> [u8; 32] {
+        let size = self.size.to_string();
+        let price = self.price.to_string();
+        let struct_hash = eip712_string_struct_hash(
+            TRADE_RECORD_TYPE,
+            &[
+                &self.bot_pubkey,
+                self.follower_pubkey.as_deref().unwrap_or(""),
+                &self.role,
+                &self.symbol,
+                &self.side,
+                &size,
+                &price,
+                self.tx_hash.as_deref().unwrap_or(""),
+                self.oid.as_deref().unwrap_or(""),
+            ],
+        );
+        let domain = eip712_domain_separator("Moltrade Relayer", "1", chain_id);
+        eip712_digest(&domain, &struct_hash)
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct UpdateSettlementRequest {
+    tx_hash: Option<String>,
+    oid: Option<String>,
+    /// Which row to settle when leader and follower share the ref; leader unless "follower"
+    #[serde(default)]
+    role: String,
+    follower_pubkey: Option<String>,
+    status: String,
+    pnl: Option<f64>,
+    pnl_usd: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForceSettleRequest {
+    tx_hash: Option<String>,
+    oid: Option<String>,
+    status: String,
+    pnl_usd: Option<f64>,
+    /// Award credits when forcing to `confirmed` (default true)
+    #[serde(default = "default_true")]
+    award_credits: bool,
+    /// Operator name and reason, recorded in the audit log line
+    actor: Option<String>,
+    reason: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Accept follower pubkeys as hex or npub and store them as hex
+fn de_pubkey<'de, D: Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+    String::deserialize(d).map(|s| canonical_pubkey(&s))
+}
+
+fn de_opt_pubkey<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    Option::<String>::deserialize(d).map(|s| s.map(|s| canonical_pubkey(&s)))
+}
+
+#[derive(Debug, Deserialize)]
+struct TradesQuery {
+    bot_pubkey: Option<String>,
+    /// RFC3339 lower bound on `created_at` (inclusive)
+    from: Option<String>,
+    /// RFC3339 upper bound on `created_at` (inclusive)
+    to: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct TradeItem {
+    id: i64,
+    bot_pubkey: String,
+    follower_pubkey: Option<String>,
+    role: String,
+    symbol: String,
+    side: String,
+    size: f64,
+    price: f64,
     tx_hash: Option<String>,
     oid: Option<String>,
     status: String,
     pnl: Option<f64>,
     pnl_usd: Option<f64>,
+    is_test: bool,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TradesResponse {
+    trades: Vec<TradeItem>,
+}
+
+/// Longest accepted `GET /api/signals/search` query
+const MAX_SEARCH_QUERY_LEN: usize = 200;
+
+#[derive(Debug, Deserialize)]
+struct SignalSearchQuery {
+    q: String,
+    bot_pubkey: Option<String>,
+    limit: Option<i64>,
+}
+
+impl SignalSearchQuery {
+    /// Trimmed query text and row limit (default 50, at most 200); 400 for an empty or
+    /// overlong query
+    fn terms(&self) -> Result<(&str, i64), StatusCode> {
+        let query = self.q.trim();
+        if query.is_empty() || query.len() > MAX_SEARCH_QUERY_LEN {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Ok((query, self.limit.unwrap_or(50).clamp(1, 200)))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SignalItem {
+    event_id: String,
+    kind: i32,
+    bot_pubkey: Option<String>,
+    leader_pubkey: String,
+    follower_pubkey: Option<String>,
+    agent_eth_address: Option<String>,
+    role: Option<String>,
+    symbol: Option<String>,
+    side: Option<String>,
+    size: Option<f64>,
+    price: Option<f64>,
+    status: Option<String>,
+    tx_hash: Option<String>,
+    pnl: Option<f64>,
+    pnl_usd: Option<f64>,
+    event_created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SignalsResponse {
+    signals: Vec<SignalItem>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ExportTable {
+    Signals,
+    Trades,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    table: ExportTable,
+    bot_pubkey: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct CreditsQuery {
     bot_pubkey: Option<String>,
+    #[serde(default, deserialize_with = "de_opt_pubkey")]
     follower_pubkey: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct CreditHistoryQuery {
+    bot_pubkey: String,
+    #[serde(deserialize_with = "de_pubkey")]
+    follower_pubkey: String,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreditHistoryItem {
+    /// `award` for grants, `debit` for negative entries
+    kind: &'static str,
+    amount: Decimal,
+    reason: String,
+    /// tx hash or oid of the trade behind a settlement award
+    trade_ref: Option<String>,
+    created_at: String,
+}
+
+impl From<CreditLedgerEntry> for CreditHistoryItem {
+    fn from(e: CreditLedgerEntry) -> Self {
+        Self {
+            kind: if e.amount < Decimal::ZERO {
+                "debit"
+            } else {
+                "award"
+            },
+            amount: e.amount,
+            reason: e.reason,
+            trade_ref: e.trade_ref,
+            created_at: e.created_at.to_rfc3339(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct CreditItem {
     bot_pubkey: String,
     follower_pubkey: String,
-    credits: f64,
+    /// Serialized as a decimal string so large balances keep full precision
+    credits: Decimal,
 }
 
 #[derive(Debug, Serialize)]
 struct CreditsResponse {
+    currency: String,
     credits: Vec<CreditItem>,
 }
 
+/// Upper bound on rows in one `POST /api/credits/award`
+const MAX_CREDIT_AWARDS: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+struct AwardCreditsItem {
+    bot_pubkey: String,
+    #[serde(deserialize_with = "de_pubkey")]
+    follower_pubkey: String,
+    amount: Decimal,
+    reason: String,
+}
+
+#[derive(Debug, Serialize)]
+struct AwardCreditsResult {
+    bot_pubkey: String,
+    follower_pubkey: String,
+    amount: Decimal,
+    /// Balance after this row was applied
+    credits: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+struct AwardCreditsResponse {
+    success: bool,
+    currency: String,
+    results: Vec<AwardCreditsResult>,
+}
+
 #[derive(Debug, Deserialize)]
 struct AddSubscriptionRequest {
     bot_pubkey: String,
+    #[serde(deserialize_with = "de_pubkey")]
     follower_pubkey: String,
     shared_secret: String,
+    /// `nip04` or `nip44`; omitted keeps the follower's current scheme (nip04 for new followers)
+    encryption: Option<String>,
+    /// Kinds to fan out to this follower; omitted keeps the current preference, `[]` resets to all
+    kinds: Option<Vec<u16>>,
+    /// Follower inbox relays for nostr fanout; omitted keeps the current list, `[]` resets to platform relays
+    relays: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RotateSecretRequest {
+    bot_pubkey: String,
+    #[serde(deserialize_with = "de_pubkey")]
+    follower_pubkey: String,
+    new_secret: String,
+    /// Overrides `[subscriptions].secret_overlap_secs` for this rotation
+    overlap_secs: Option<u64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -300,12 +915,17 @@ struct SubscriptionsResponse {
 #[derive(Debug, Serialize)]
 struct SubscriptionItem {
     follower_pubkey: String,
+    /// null means every kind is fanned out
+    kinds: Option<Vec<u16>>,
+    /// null means events are published to the platform relays
+    relays: Option<Vec<String>>,
 }
 
-/// Register or upsert a bot
+/// Register or upsert a bot; updating a bot that has an API key requires that key
 async fn register_bot(
     State(state): State<AppState>,
-    Json(payload): Json<RegisterBotRequest>,
+    headers: HeaderMap,
+    JsonBody(payload): JsonBody<RegisterBotRequest>,
 ) -> Result<Json<RegisterBotResponse>, StatusCode> {
     if !is_valid_eth_address(&payload.eth_address) {
         return Err(StatusCode::BAD_REQUEST);
@@ -316,91 +936,462 @@ async fn register_bot(
         None => return Err(StatusCode::SERVICE_UNAVAILABLE),
     };
 
-    svc.register_bot(
-        &payload.bot_pubkey,
-        &payload.nostr_pubkey,
-        &payload.eth_address,
-        &payload.name,
-    )
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to register bot: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let api_key = headers
+        .get(BOT_API_KEY_HEADER)
+        .and_then(|h| h.to_str().ok());
+    let registration = svc
+        .register_bot_with_key(
+            &payload.bot_pubkey,
+            &payload.nostr_pubkey,
+            &payload.eth_address,
+            &payload.name,
+            api_key,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to register bot: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let api_key = match registration {
+        KeyedRegistration::Registered { api_key } => api_key,
+        KeyedRegistration::KeyRejected => {
+            tracing::warn!(
+                "register_bot rejected: missing or invalid api key for existing bot {}",
+                payload.bot_pubkey
+            );
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+    };
 
     Ok(Json(RegisterBotResponse {
         success: true,
         message: "bot registered".to_string(),
         platform_pubkey: state.platform_pubkey.clone(),
+        api_key,
     }))
 }
 
-/// Add or update a subscription
-async fn add_subscription(
+/// Remove a bot with its subscriptions, trades and credits (requires token if configured).
+/// Idempotent: an unknown bot is reported with `existed: false`
+async fn deregister_bot(
     State(state): State<AppState>,
-    Json(payload): Json<AddSubscriptionRequest>,
-) -> Result<Json<RelayResponse>, StatusCode> {
+    _admin: AdminAuth,
+    Path(bot_pubkey): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
     let svc = match &state.subscriptions {
         Some(s) => s,
         None => return Err(StatusCode::SERVICE_UNAVAILABLE),
     };
 
-    let eth_addr = svc
-        .get_bot_eth_address(&payload.bot_pubkey)
+    let removed = svc.deregister_bot(&bot_pubkey).await.map_err(|e| {
+        tracing::error!("Failed to deregister bot {}: {}", bot_pubkey, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if removed.is_some() {
+        tracing::info!("Deregistered bot {}: {:?}", bot_pubkey, removed);
+    }
+
+    Ok(Json(json!({
+        "bot_pubkey": bot_pubkey,
+        "existed": removed.is_some(),
+        "removed": removed,
+    })))
+}
+
+/// Replace a bot's API key, e.g. after it was lost or leaked (requires token if configured)
+async fn rotate_bot_api_key(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(bot_pubkey): Path<String>,
+) -> Result<Json<BotApiKeyResponse>, StatusCode> {
+    let svc = match &state.subscriptions {
+        Some(s) => s,
+        None => return Err(StatusCode::SERVICE_UNAVAILABLE),
+    };
+
+    let api_key = svc
+        .issue_bot_api_key(&bot_pubkey)
         .await
         .map_err(|e| {
-            tracing::error!("Failed to query bot eth address: {}", e);
+            tracing::error!("Failed to rotate bot api key: {}", e);
             StatusCode::INTERNAL_SERVER_ERROR
         })?
-        .ok_or(StatusCode::BAD_REQUEST)?;
-
-    enforce_subscription_limit(&state, &eth_addr).await?;
-
-    svc.add_subscription(
-        &payload.bot_pubkey,
-        &payload.follower_pubkey,
-        &payload.shared_secret,
-    )
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to add subscription: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+        .ok_or(StatusCode::NOT_FOUND)?;
 
-    Ok(Json(RelayResponse {
-        success: true,
-        message: "subscription saved".to_string(),
+    Ok(Json(BotApiKeyResponse {
+        bot_pubkey,
+        api_key,
     }))
 }
 
-/// Record a trade tx hash for later settlement/PnL tracking
-async fn record_trade(
+/// Follower count and cap for a bot
+async fn bot_stats(
     State(state): State<AppState>,
-    Json(payload): Json<RecordTradeRequest>,
-) -> Result<Json<RelayResponse>, StatusCode> {
+    Path(bot_pubkey): Path<String>,
+) -> Result<Json<BotStatsResponse>, StatusCode> {
     let svc = match &state.subscriptions {
         Some(s) => s,
         None => return Err(StatusCode::SERVICE_UNAVAILABLE),
     };
 
-    // Ensure bot exists to avoid FK errors
-    let exists = svc.bot_exists(&payload.bot_pubkey).await.map_err(|e| {
-        tracing::error!("Failed to verify bot before recording trade: {}", e);
+    let exists = svc.bot_exists(&bot_pubkey).await.map_err(|e| {
+        tracing::error!("Failed to verify bot for stats: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
     if !exists {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(StatusCode::NOT_FOUND);
     }
 
-    let role = if payload.role.eq_ignore_ascii_case("follower") {
-        "follower"
-    } else {
-        "leader"
+    let follower_count = svc.count_subscriptions(&bot_pubkey).await.map_err(|e| {
+        tracing::error!("Failed to count subscriptions: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(BotStatsResponse {
+        bot_pubkey,
+        follower_count,
+        max_followers: (state.max_followers_per_bot > 0).then_some(state.max_followers_per_bot),
+    }))
+}
+
+/// A bot's followers with subscription time, credits, latest signal and live `/fanout` status
+async fn bot_followers(
+    State(state): State<AppState>,
+    Path(bot_pubkey): Path<String>,
+    Extension(connections): Extension<ConnectionRegistry>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let svc = match &state.subscriptions {
+        Some(s) => s,
+        None => return Err(StatusCode::SERVICE_UNAVAILABLE),
+    };
+
+    let exists = svc.bot_exists(&bot_pubkey).await.map_err(|e| {
+        tracing::error!("Failed to verify bot for followers: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if !exists {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let details = svc.follower_details(&bot_pubkey).await.map_err(|e| {
+        tracing::error!("Failed to query follower details: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let connected: HashSet<String> = connections
+        .snapshot()
+        .into_iter()
+        .filter_map(|c| c.follower_pubkey)
+        .collect();
+    let followers: Vec<FollowerDetailItem> = details
+        .into_iter()
+        .map(|d| FollowerDetailItem {
+            connected: connected.contains(&canonical_pubkey(&d.follower_pubkey)),
+            follower_pubkey: d.follower_pubkey,
+            subscribed_at: d.subscribed_at.to_rfc3339(),
+            credits: d.credits,
+            last_signal_at: d.last_signal_at.map(|t| t.to_rfc3339()),
+            suspended_at: d.suspended_at.map(|t| t.to_rfc3339()),
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "bot_pubkey": bot_pubkey,
+        "count": followers.len(),
+        "followers": followers,
+    })))
+}
+
+/// Stop fanning out a bot's signals without touching its subscriptions
+async fn pause_bot(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(bot_pubkey): Path<String>,
+) -> Result<Json<RelayResponse>, StatusCode> {
+    set_bot_paused(&state, &bot_pubkey, true).await?;
+    Ok(Json(RelayResponse {
+        success: true,
+        message: format!("bot {} paused", bot_pubkey),
+    }))
+}
+
+/// Resume fanout for a previously paused bot
+async fn resume_bot(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(bot_pubkey): Path<String>,
+) -> Result<Json<RelayResponse>, StatusCode> {
+    set_bot_paused(&state, &bot_pubkey, false).await?;
+    Ok(Json(RelayResponse {
+        success: true,
+        message: format!("bot {} resumed", bot_pubkey),
+    }))
+}
+
+async fn set_bot_paused(
+    state: &AppState,
+    bot_pubkey: &str,
+    paused: bool,
+) -> Result<(), StatusCode> {
+    let svc = match &state.subscriptions {
+        Some(s) => s,
+        None => return Err(StatusCode::SERVICE_UNAVAILABLE),
     };
 
+    let updated = svc.set_bot_paused(bot_pubkey, paused).await.map_err(|e| {
+        tracing::error!("Failed to update bot paused flag: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if !updated {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(())
+}
+
+/// Per-symbol realized PnL for a bot (test trades excluded unless `include_test=true`)
+async fn bot_pnl(
+    State(state): State<AppState>,
+    Path(bot_pubkey): Path<String>,
+    Query(q): Query<BotPnlQuery>,
+) -> Result<Json<BotPnlResponse>, StatusCode> {
+    let svc = match &state.subscriptions {
+        Some(s) => s,
+        None => return Err(StatusCode::SERVICE_UNAVAILABLE),
+    };
+
+    let rows = svc
+        .pnl_by_symbol(&bot_pubkey, q.include_test)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to query pnl by symbol: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(BotPnlResponse {
+        bot_pubkey,
+        symbols: rows
+            .into_iter()
+            .map(|r| SymbolPnlItem {
+                symbol: r.symbol,
+                pnl_usd: r.pnl_usd,
+                trade_count: r.trade_count,
+            })
+            .collect(),
+    }))
+}
+
+/// Net position per symbol from a bot's recorded trades; test trades are excluded unless `include_test=true`
+async fn bot_positions(
+    State(state): State<AppState>,
+    Path(bot_pubkey): Path<String>,
+    Query(q): Query<BotPnlQuery>,
+) -> Result<Json<BotPositionsResponse>, StatusCode> {
+    let svc = match &state.subscriptions {
+        Some(s) => s,
+        None => return Err(StatusCode::SERVICE_UNAVAILABLE),
+    };
+
+    let rows = svc
+        .positions(&bot_pubkey, q.include_test)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to query positions: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(BotPositionsResponse {
+        bot_pubkey,
+        positions: rows
+            .into_iter()
+            .map(|r| SymbolPositionItem {
+                symbol: r.symbol,
+                net_size: r.net_size,
+                trade_count: r.trade_count,
+                skipped_trades: r.skipped_trades,
+            })
+            .collect(),
+    }))
+}
+
+/// Most inbox relays a follower may list
+const MAX_FOLLOWER_RELAYS: usize = 10;
+
+/// Trimmed inbox relay list; 400 for more than [`MAX_FOLLOWER_RELAYS`] or a URL that is not a
+/// public `wss://` relay
+fn follower_relays(relays: Option<&[String]>) -> Result<Option<Vec<String>>, StatusCode> {
+    let Some(relays) = relays else {
+        return Ok(None);
+    };
+    let relays: Vec<String> = relays.iter().map(|url| url.trim().to_string()).collect();
+    let valid =
+        relays.len() <= MAX_FOLLOWER_RELAYS && relays.iter().all(|url| follower_relay_allowed(url));
+    if !valid {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(Some(relays))
+}
+
+/// Add or update a subscription
+async fn add_subscription(
+    State(state): State<AppState>,
+    JsonBody(payload): JsonBody<AddSubscriptionRequest>,
+) -> Result<Json<RelayResponse>, StatusCode> {
+    let svc = match &state.subscriptions {
+        Some(s) => s,
+        None => return Err(StatusCode::SERVICE_UNAVAILABLE),
+    };
+
+    let eth_addr = svc
+        .get_bot_eth_address(&payload.bot_pubkey)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to query bot eth address: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let encryption = match payload.encryption.as_deref() {
+        Some(e) => Some(FanoutEncryption::parse(e).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+    let relays = follower_relays(payload.relays.as_deref())?;
+
+    enforce_subscription_limit(&state, &eth_addr).await?;
+
+    let saved = svc
+        .add_subscription(
+            &payload.bot_pubkey,
+            &payload.follower_pubkey,
+            &payload.shared_secret,
+            encryption,
+            payload.kinds.as_deref(),
+            relays.as_deref(),
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to add subscription: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if !saved {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    Ok(Json(RelayResponse {
+        success: true,
+        message: "subscription saved".to_string(),
+    }))
+}
+
+/// Rotate a follower's secret; the previous one keeps receiving fanout during the overlap window
+async fn rotate_subscription_secret(
+    State(state): State<AppState>,
+    JsonBody(payload): JsonBody<RotateSecretRequest>,
+) -> Result<Json<RelayResponse>, StatusCode> {
+    let svc = match &state.subscriptions {
+        Some(s) => s,
+        None => return Err(StatusCode::SERVICE_UNAVAILABLE),
+    };
+
+    if payload.new_secret.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let overlap_secs = payload.overlap_secs.unwrap_or(state.secret_overlap_secs);
+
+    let rotated = svc
+        .rotate_subscription_secret(
+            &payload.bot_pubkey,
+            &payload.follower_pubkey,
+            &payload.new_secret,
+            overlap_secs as i64,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to rotate subscription secret: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if !rotated {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(RelayResponse {
+        success: true,
+        message: format!(
+            "secret rotated, previous secret valid for {}s",
+            overlap_secs
+        ),
+    }))
+}
+
+/// Record a trade tx hash for later settlement/PnL tracking
+async fn record_trade(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    JsonBody(payload): JsonBody<RecordTradeRequest>,
+) -> Result<Json<RelayResponse>, StatusCode> {
+    let svc = match &state.subscriptions {
+        Some(s) => s,
+        None => return Err(StatusCode::SERVICE_UNAVAILABLE),
+    };
+
+    // Ensure bot exists to avoid FK errors
+    let exists = svc.bot_exists(&payload.bot_pubkey).await.map_err(|e| {
+        tracing::error!("Failed to verify bot before recording trade: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if !exists {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let api_key = headers
+        .get(BOT_API_KEY_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    let key_valid = svc
+        .verify_bot_api_key(&payload.bot_pubkey, api_key)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to verify bot api key: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if !key_valid {
+        tracing::warn!(
+            "record_trade rejected: invalid api key for bot {}",
+            payload.bot_pubkey
+        );
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    if let Some(chain_id) = state.trade_signature_chain_id {
+        let eth_address = svc
+            .get_bot_eth_address(&payload.bot_pubkey)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to query bot eth address: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+            .ok_or(StatusCode::BAD_REQUEST)?;
+        let signature = payload
+            .signature
+            .as_deref()
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        let signed_by_bot = recover_address(&payload.typed_data_digest(chain_id), signature)
+            .is_ok_and(|signer| signer.eq_ignore_ascii_case(&eth_address));
+        if !signed_by_bot {
+            tracing::warn!(
+                "record_trade rejected: signature does not match eth address of bot {}",
+                payload.bot_pubkey
+            );
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    let role = trade_role(&payload.role);
+    // Normalized only after the signature check, which covers the pubkey as submitted
+    let follower_pubkey = payload.follower_pubkey.as_deref().map(canonical_pubkey);
+
     svc.record_trade_tx(
         &payload.bot_pubkey,
-        payload.follower_pubkey.as_deref(),
+        follower_pubkey.as_deref(),
         role,
         &payload.symbol,
         &payload.side,
@@ -437,25 +1428,37 @@ async fn record_trade(
     }))
 }
 
+/// Stored trade role: "follower" in any case, otherwise leader
+fn trade_role(role: &str) -> &'static str {
+    if role.eq_ignore_ascii_case("follower") {
+        "follower"
+    } else {
+        "leader"
+    }
+}
+
 /// Update trade settlement/PnL after chain confirmation
 async fn update_trade_settlement(
     State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(payload): Json<UpdateSettlementRequest>,
+    _admin: AdminAuth,
+    JsonBody(payload): JsonBody<UpdateSettlementRequest>,
 ) -> Result<Json<RelayResponse>, StatusCode> {
     let svc = match &state.subscriptions {
         Some(s) => s,
         None => return Err(StatusCode::SERVICE_UNAVAILABLE),
     };
 
-    if !is_token_valid(&headers, state.settlement_token.as_deref()) {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
+    let status = TradeStatus::parse(&payload.status).ok_or(StatusCode::BAD_REQUEST)?;
+    let follower_pubkey = payload.follower_pubkey.as_deref().map(canonical_pubkey);
 
     svc.update_trade_settlement(
-        payload.tx_hash.as_deref(),
-        payload.oid.as_deref(),
-        &payload.status,
+        TradeRowKey {
+            tx_hash: payload.tx_hash.as_deref(),
+            oid: payload.oid.as_deref(),
+            role: trade_role(&payload.role),
+            follower_pubkey: follower_pubkey.as_deref(),
+        },
+        Some(status),
         payload.pnl,
         payload.pnl_usd,
     )
@@ -471,6 +1474,355 @@ async fn update_trade_settlement(
     }))
 }
 
+/// Force a trade's settlement status without explorer verification (requires token if configured)
+async fn force_settle_trade(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    headers: HeaderMap,
+    JsonBody(payload): JsonBody<ForceSettleRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let svc = match &state.subscriptions {
+        Some(s) => s,
+        None => return Err(StatusCode::SERVICE_UNAVAILABLE),
+    };
+
+    let status = TradeStatus::parse(&payload.status).ok_or(StatusCode::BAD_REQUEST)?;
+    if payload.tx_hash.is_none() && payload.oid.is_none() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("-");
+    tracing::warn!(
+        "force-settle requested: actor={} reason={} user_agent={} tx_hash={:?} oid={:?} status={} pnl_usd={:?}",
+        payload.actor.as_deref().unwrap_or("-"),
+        payload.reason.as_deref().unwrap_or("-"),
+        user_agent,
+        payload.tx_hash,
+        payload.oid,
+        status,
+        payload.pnl_usd
+    );
+
+    let trades = svc
+        .force_trade_settlement(
+            payload.tx_hash.as_deref(),
+            payload.oid.as_deref(),
+            status,
+            payload.pnl_usd,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to force trade settlement: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if trades.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let mut credited = Decimal::ZERO;
+    let credit_cfg = state
+        .settlement_credit
+        .as_ref()
+        .filter(|_| status == TradeStatus::Confirmed && payload.award_credits);
+    if let Some(cfg) = credit_cfg {
+        for t in &trades {
+            let Some(credit) = compute_credit(cfg, t, Some(&state.metrics)) else {
+                continue;
+            };
+            let recipient = t.follower_pubkey.as_deref().unwrap_or(&t.bot_pubkey);
+            let reason = award_reason("force-settle", t);
+            match svc
+                .award_credits(&t.bot_pubkey, recipient, credit, &reason, trade_ref(t))
+                .await
+            {
+                Ok(()) => credited += credit,
+                Err(e) => log_award_error(&e, &t.bot_pubkey, recipient),
+            }
+        }
+    }
+
+    tracing::warn!(
+        "force-settle applied: actor={} updated={} status={} credits_awarded={}",
+        payload.actor.as_deref().unwrap_or("-"),
+        trades.len(),
+        status,
+        credited
+    );
+
+    Ok(Json(json!({
+        "success": true,
+        "updated": trades.len(),
+        "status": status.as_str(),
+        "credits_awarded": credited,
+    })))
+}
+
+/// Full-text search over signal content and key fields (requires token if configured)
+async fn search_signals(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Query(q): Query<SignalSearchQuery>,
+) -> Result<Json<SignalsResponse>, StatusCode> {
+    let svc = match &state.subscriptions {
+        Some(s) => s,
+        None => return Err(StatusCode::SERVICE_UNAVAILABLE),
+    };
+
+    let (query, limit) = q.terms()?;
+
+    let rows = svc
+        .search_signals(query, q.bot_pubkey.as_deref(), limit)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to search signals: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(SignalsResponse {
+        signals: rows
+            .into_iter()
+            .map(|s| SignalItem {
+                event_id: s.event_id,
+                kind: s.kind,
+                bot_pubkey: s.bot_pubkey,
+                leader_pubkey: s.leader_pubkey,
+                follower_pubkey: s.follower_pubkey,
+                agent_eth_address: s.agent_eth_address,
+                role: s.role,
+                symbol: s.symbol,
+                side: s.side,
+                size: s.size,
+                price: s.price,
+                status: s.status,
+                tx_hash: s.tx_hash,
+                pnl: s.pnl,
+                pnl_usd: s.pnl_usd,
+                event_created_at: s.event_created_at.to_rfc3339(),
+            })
+            .collect(),
+    }))
+}
+
+/// Run a settlement pass now instead of waiting for the next interval (requires token if configured)
+async fn trigger_settlement_tick(
+    Extension(settlement): Extension<Option<SettlementHandle>>,
+    _admin: AdminAuth,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let settlement = settlement.ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let summary = settlement.tick_now().await.map_err(|e| {
+        if e.is::<NotLeader>() {
+            return StatusCode::CONFLICT;
+        }
+        tracing::error!("Triggered settlement tick failed: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "summary": summary,
+        "next_tick_at": settlement.next_tick_at().map(|t| t.to_rfc3339()),
+    })))
+}
+
+/// List trades (optionally filter by bot and a `from`/`to` created_at range)
+async fn list_trades(
+    State(state): State<AppState>,
+    Query(q): Query<TradesQuery>,
+) -> Result<Json<TradesResponse>, StatusCode> {
+    let svc = match &state.subscriptions {
+        Some(s) => s,
+        None => return Err(StatusCode::SERVICE_UNAVAILABLE),
+    };
+
+    let from = parse_rfc3339(q.from.as_deref())?;
+    let to = parse_rfc3339(q.to.as_deref())?;
+    if matches!((from, to), (Some(f), Some(t)) if f > t) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let limit = q.limit.unwrap_or(100).clamp(1, 1000);
+
+    let rows = svc
+        .list_trades(q.bot_pubkey.as_deref(), from, to, limit)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to list trades: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(TradesResponse {
+        trades: rows
+            .into_iter()
+            .map(|r| TradeItem {
+                id: r.id,
+                bot_pubkey: r.bot_pubkey,
+                follower_pubkey: r.follower_pubkey,
+                role: r.role,
+                symbol: r.symbol,
+                side: r.side,
+                size: r.size,
+                price: r.price,
+                tx_hash: r.tx_hash,
+                oid: r.oid,
+                status: r.status,
+                pnl: r.pnl,
+                pnl_usd: r.pnl_usd,
+                is_test: r.is_test,
+                created_at: r.created_at.to_rfc3339(),
+            })
+            .collect(),
+    }))
+}
+
+/// Stream signals or trades as a CSV attachment (requires token if configured)
+async fn export_csv(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Query(q): Query<ExportQuery>,
+) -> Result<Response, StatusCode> {
+    let svc = match &state.subscriptions {
+        Some(s) => s,
+        None => return Err(StatusCode::SERVICE_UNAVAILABLE),
+    };
+
+    let from = parse_rfc3339(q.from.as_deref())?;
+    let to = parse_rfc3339(q.to.as_deref())?;
+    if matches!((from, to), (Some(f), Some(t)) if f > t) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let (filename, body) = match q.table {
+        ExportTable::Trades => {
+            let rows = svc
+                .stream_trades(q.bot_pubkey, from, to)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to export trades: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+            (
+                "trades.csv",
+                csv_body(TRADE_CSV_HEADER, rows, trade_csv_row),
+            )
+        }
+        ExportTable::Signals => {
+            let rows = svc
+                .stream_signals(q.bot_pubkey, from, to)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to export signals: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+            (
+                "signals.csv",
+                csv_body(SIGNAL_CSV_HEADER, rows, signal_csv_row),
+            )
+        }
+    };
+
+    let disposition = HeaderValue::from_str(&format!("attachment; filename=\"{}\"", filename))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("text/csv; charset=utf-8"),
+            ),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+const TRADE_CSV_HEADER: &str = "id,bot_pubkey,follower_pubkey,role,symbol,side,size,price,tx_hash,oid,status,pnl,pnl_usd,is_test,created_at\n";
+const SIGNAL_CSV_HEADER: &str = "event_id,kind,bot_pubkey,leader_pubkey,follower_pubkey,agent_eth_address,role,symbol,side,size,price,status,tx_hash,pnl,pnl_usd,event_created_at\n";
+
+/// Header line followed by one CSV line per row; a row error aborts the response body
+fn csv_body<T: 'static, S>(header: &'static str, rows: S, to_row: fn(&T) -> String) -> Body
+where
+    S: futures::Stream<Item = anyhow::Result<T>> + Send + 'static,
+{
+    let lines = rows.map(move |row| row.map(|r| to_row(&r)));
+    Body::from_stream(stream::once(async move { Ok(header.to_string()) }).chain(lines))
+}
+
+fn trade_csv_row(t: &TradeRecord) -> String {
+    csv_line(&[
+        t.id.to_string(),
+        csv_escape(&t.bot_pubkey),
+        csv_opt(t.follower_pubkey.as_deref()),
+        csv_escape(&t.role),
+        csv_escape(&t.symbol),
+        csv_escape(&t.side),
+        t.size.to_string(),
+        t.price.to_string(),
+        csv_opt(t.tx_hash.as_deref()),
+        csv_opt(t.oid.as_deref()),
+        csv_escape(&t.status),
+        csv_num(t.pnl),
+        csv_num(t.pnl_usd),
+        t.is_test.to_string(),
+        t.created_at.to_rfc3339(),
+    ])
+}
+
+fn signal_csv_row(s: &SignalRecord) -> String {
+    csv_line(&[
+        csv_escape(&s.event_id),
+        s.kind.to_string(),
+        csv_opt(s.bot_pubkey.as_deref()),
+        csv_escape(&s.leader_pubkey),
+        csv_opt(s.follower_pubkey.as_deref()),
+        csv_opt(s.agent_eth_address.as_deref()),
+        csv_opt(s.role.as_deref()),
+        csv_opt(s.symbol.as_deref()),
+        csv_opt(s.side.as_deref()),
+        csv_num(s.size),
+        csv_num(s.price),
+        csv_opt(s.status.as_deref()),
+        csv_opt(s.tx_hash.as_deref()),
+        csv_num(s.pnl),
+        csv_num(s.pnl_usd),
+        s.event_created_at.to_rfc3339(),
+    ])
+}
+
+fn csv_line(fields: &[String]) -> String {
+    let mut line = fields.join(",");
+    line.push('\n');
+    line
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_opt(value: Option<&str>) -> String {
+    value.map(csv_escape).unwrap_or_default()
+}
+
+fn csv_num(value: Option<f64>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn parse_rfc3339(value: Option<&str>) -> Result<Option<DateTime<Utc>>, StatusCode> {
+    value
+        .map(|v| {
+            DateTime::parse_from_rfc3339(v)
+                .map(|dt| dt.with_timezone(&Utc))
+                .map_err(|_| StatusCode::BAD_REQUEST)
+        })
+        .transpose()
+}
+
 /// List credits (optionally filter by bot or follower)
 async fn list_credits(
     State(state): State<AppState>,
@@ -490,6 +1842,11 @@ async fn list_credits(
         })?;
 
     Ok(Json(CreditsResponse {
+        currency: state
+            .settlement_credit
+            .as_ref()
+            .map(|c| c.currency.clone())
+            .unwrap_or_else(|| "credits".to_string()),
         credits: rows
             .into_iter()
             .map(|r| CreditItem {
@@ -501,17 +1858,128 @@ async fn list_credits(
     }))
 }
 
-fn is_token_valid(headers: &HeaderMap, expected: Option<&str>) -> bool {
-    match expected {
-        None => true, // no token configured -> allow
-        Some(token) => headers
-            .get("X-Settlement-Token")
-            .and_then(|h| h.to_str().ok())
-            .map(|v| v == token)
-            .unwrap_or(false),
+/// A follower's credit ledger under a bot, newest first
+async fn credit_history(
+    State(state): State<AppState>,
+    Query(q): Query<CreditHistoryQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let svc = match &state.subscriptions {
+        Some(s) => s,
+        None => return Err(StatusCode::SERVICE_UNAVAILABLE),
+    };
+
+    let limit = q.limit.unwrap_or(50).clamp(1, 200);
+    let offset = q.offset.unwrap_or(0).max(0);
+    let entries = svc
+        .credit_history(&q.bot_pubkey, &q.follower_pubkey, limit, offset)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to query credit history: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let history: Vec<CreditHistoryItem> =
+        entries.into_iter().map(CreditHistoryItem::from).collect();
+
+    Ok(Json(json!({
+        "bot_pubkey": q.bot_pubkey,
+        "follower_pubkey": q.follower_pubkey,
+        "currency": state
+            .settlement_credit
+            .as_ref()
+            .map(|c| c.currency.as_str())
+            .unwrap_or("credits"),
+        "history": history,
+    })))
+}
+
+/// Validated award rows: 1 to [`MAX_CREDIT_AWARDS`] of them, each with pubkeys, a reason and
+/// a positive amount; 400 otherwise
+fn credit_awards(payload: Vec<AwardCreditsItem>) -> Result<Vec<CreditAward>, StatusCode> {
+    if payload.is_empty() || payload.len() > MAX_CREDIT_AWARDS {
+        tracing::warn!(
+            "credit award rejected: {} rows (max {})",
+            payload.len(),
+            MAX_CREDIT_AWARDS
+        );
+        return Err(StatusCode::BAD_REQUEST);
     }
+    if let Some(i) = payload.iter().position(|a| {
+        a.bot_pubkey.trim().is_empty()
+            || a.follower_pubkey.trim().is_empty()
+            || a.reason.trim().is_empty()
+            || a.amount <= Decimal::ZERO
+    }) {
+        tracing::warn!(
+            "credit award rejected: row {} needs pubkeys, a reason and a positive amount",
+            i
+        );
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    Ok(payload
+        .into_iter()
+        .map(|a| CreditAward {
+            bot_pubkey: a.bot_pubkey.trim().to_string(),
+            follower_pubkey: a.follower_pubkey.trim().to_string(),
+            amount: a.amount,
+            reason: a.reason.trim().to_string(),
+            trade_ref: None,
+        })
+        .collect())
+}
+
+/// Grant credits to many followers at once; all rows apply in one transaction or none do
+async fn award_credits(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    JsonBody(payload): JsonBody<Vec<AwardCreditsItem>>,
+) -> Result<Json<AwardCreditsResponse>, StatusCode> {
+    let svc = match &state.subscriptions {
+        Some(s) => s,
+        None => return Err(StatusCode::SERVICE_UNAVAILABLE),
+    };
+
+    let awards = credit_awards(payload)?;
+
+    let balances = svc.award_credits_batch(&awards).await.map_err(|e| {
+        let fk_violation = e
+            .downcast_ref::<tokio_postgres::Error>()
+            .and_then(|db_err| db_err.code())
+            .is_some_and(|code| code == &SqlState::FOREIGN_KEY_VIOLATION);
+        if fk_violation {
+            tracing::warn!("credit award rejected (unregistered bot): {:?}", e);
+            return StatusCode::BAD_REQUEST;
+        }
+        tracing::error!("Failed to award credits: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    tracing::info!("credit award applied: {} rows", awards.len());
+
+    Ok(Json(AwardCreditsResponse {
+        success: true,
+        currency: state
+            .settlement_credit
+            .as_ref()
+            .map(|c| c.currency.clone())
+            .unwrap_or_else(|| "credits".to_string()),
+        results: awards
+            .into_iter()
+            .zip(balances)
+            .map(|(a, credits)| AwardCreditsResult {
+                bot_pubkey: a.bot_pubkey,
+                follower_pubkey: a.follower_pubkey,
+                amount: a.amount,
+                credits,
+            })
+            .collect(),
+    }))
 }
 
+/// Header carrying the per-bot API key issued at registration
+const BOT_API_KEY_HEADER: &str = "X-Bot-Api-Key";
+
 fn is_valid_eth_address(addr: &str) -> bool {
     if addr.len() != 42 || !addr.starts_with("0x") {
         return false;
@@ -559,6 +2027,8 @@ async fn list_subscriptions(
             .into_iter()
             .map(|s| SubscriptionItem {
                 follower_pubkey: s.follower_pubkey,
+                kinds: s.kinds,
+                relays: s.relays,
             })
             .collect(),
     }))
@@ -594,6 +2064,8 @@ async fn list_subscriptions_by_eth(
             .into_iter()
             .map(|s| SubscriptionItem {
                 follower_pubkey: s.follower_pubkey,
+                kinds: s.kinds,
+                relays: s.relays,
             })
             .collect(),
     }))
@@ -604,12 +2076,28 @@ async fn metrics_summary(State(state): State<AppState>) -> Json<serde_json::Valu
     let m = &state.metrics;
     // Convert the kb to MB（1 MB = 1024 * 1024 bytes）
     let memory_usage_mb = m.memory_usage.get() as f64 / 1024.0;
+    let duplicates_by_kind = m.duplicates_by_kind();
     Json(serde_json::json!({
         "events_processed_total": m.events_processed.get(),
-        "duplicates_filtered_total": m.duplicates_filtered.get(),
+        "duplicates_filtered_total": duplicates_by_kind.values().sum::<u64>(),
+        "duplicates_filtered_by_kind": duplicates_by_kind,
         "events_in_queue": m.events_in_queue.get(),
         "active_connections": m.active_connections.get(),
         "memory_usage_mb": memory_usage_mb,
+        "pending_trades": m.pending_trades.get(),
+        "settlement_outcomes": m.settlement_outcomes(),
+    }))
+}
+
+/// Events processed per minute over the configured window, oldest minute first
+async fn metrics_timeseries(
+    Extension(timeseries): Extension<EventTimeseries>,
+) -> Json<serde_json::Value> {
+    let buckets = timeseries.snapshot();
+    Json(json!({
+        "window_minutes": timeseries.window_minutes(),
+        "total": buckets.iter().map(|b| b.events).sum::<u64>(),
+        "buckets": buckets,
     }))
 }
 
@@ -621,3 +2109,846 @@ async fn memory(State(state): State<AppState>) -> Json<serde_json::Value> {
         "memory_usage_mb": memory_usage_mb,
     }))
 }
+
+/// Most queries a single `/api/batch` request may carry
+const MAX_BATCH_QUERIES: usize = 20;
+/// Largest single result body `/api/batch` will buffer
+const MAX_BATCH_RESULT_BYTES: usize = 4 * 1024 * 1024;
+
+/// One read query in a `/api/batch` request: a whitelisted `op` and its path/query parameters
+#[derive(Debug, Deserialize)]
+struct BatchQuery {
+    op: String,
+    #[serde(default)]
+    params: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchBotParams {
+    bot_pubkey: String,
+}
+
+/// Run several read queries concurrently and return their results in request order
+async fn batch_read(
+    State(state): State<AppState>,
+    admin: Result<AdminAuth, StatusCode>,
+    Extension(settlement): Extension<Option<SettlementHandle>>,
+    Extension(kinds): Extension<SupportedKinds>,
+    Extension(connections): Extension<ConnectionRegistry>,
+    Extension(gate): Extension<IngestionGate>,
+    JsonBody(queries): JsonBody<Vec<BatchQuery>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if queries.len() > MAX_BATCH_QUERIES {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    let results = futures::future::join_all(queries.into_iter().map(|query| {
+        run_batch_query(
+            state.clone(),
+            admin,
+            settlement.clone(),
+            kinds.clone(),
+            connections.clone(),
+            gate.clone(),
+            query,
+        )
+    }))
+    .await;
+    Ok(Json(json!({ "results": results })))
+}
+
+/// Dispatch one batched query to the handler of the matching GET endpoint, reporting its
+/// HTTP status and JSON body
+async fn run_batch_query(
+    state: AppState,
+    admin: Result<AdminAuth, StatusCode>,
+    settlement: Option<SettlementHandle>,
+    kinds: SupportedKinds,
+    connections: ConnectionRegistry,
+    gate: IngestionGate,
+    query: BatchQuery,
+) -> serde_json::Value {
+    let BatchQuery { op, params } = query;
+    let params = if params.is_null() { json!({}) } else { params };
+    let response = match op.as_str() {
+        "status" => status(State(state), Extension(settlement), Extension(gate))
+            .await
+            .into_response(),
+        "platform" => platform_info(State(state), Extension(kinds))
+            .await
+            .into_response(),
+        "metrics_summary" => metrics_summary(State(state)).await.into_response(),
+        "relays" => list_relays(State(state)).await.into_response(),
+        "bot_stats" => match batch_params::<BatchBotParams>(&params) {
+            Ok(p) => bot_stats(State(state), Path(p.bot_pubkey))
+                .await
+                .into_response(),
+            Err(e) => return e,
+        },
+        "bot_followers" => match batch_params::<BatchBotParams>(&params) {
+            Ok(p) => bot_followers(State(state), Path(p.bot_pubkey), Extension(connections))
+                .await
+                .into_response(),
+            Err(e) => return e,
+        },
+        "bot_pnl" => match (
+            batch_params::<BatchBotParams>(&params),
+            batch_params::<BotPnlQuery>(&params),
+        ) {
+            (Ok(p), Ok(q)) => bot_pnl(State(state), Path(p.bot_pubkey), Query(q))
+                .await
+                .into_response(),
+            (Err(e), _) | (_, Err(e)) => return e,
+        },
+        "bot_positions" => match (
+            batch_params::<BatchBotParams>(&params),
+            batch_params::<BotPnlQuery>(&params),
+        ) {
+            (Ok(p), Ok(q)) => bot_positions(State(state), Path(p.bot_pubkey), Query(q))
+                .await
+                .into_response(),
+            (Err(e), _) | (_, Err(e)) => return e,
+        },
+        "subscriptions" => match batch_params::<BatchBotParams>(&params) {
+            Ok(p) => list_subscriptions(State(state), Path(p.bot_pubkey))
+                .await
+                .into_response(),
+            Err(e) => return e,
+        },
+        "signals_search" => match (admin, batch_params(&params)) {
+            (Ok(admin), Ok(q)) => search_signals(State(state), admin, Query(q))
+                .await
+                .into_response(),
+            (Err(code), _) => code.into_response(),
+            (_, Err(e)) => return e,
+        },
+        "trades" => match batch_params(&params) {
+            Ok(q) => list_trades(State(state), Query(q)).await.into_response(),
+            Err(e) => return e,
+        },
+        "credits" => match batch_params(&params) {
+            Ok(q) => list_credits(State(state), Query(q)).await.into_response(),
+            Err(e) => return e,
+        },
+        "credit_history" => match batch_params(&params) {
+            Ok(q) => credit_history(State(state), Query(q)).await.into_response(),
+            Err(e) => return e,
+        },
+        _ => {
+            return json!({
+                "status": StatusCode::BAD_REQUEST.as_u16(),
+                "error": format!("unsupported op: {}", op),
+            });
+        }
+    };
+
+    let status = response.status();
+    let body = match axum::body::to_bytes(response.into_body(), MAX_BATCH_RESULT_BYTES).await {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null),
+        Err(e) => {
+            tracing::warn!("Batched {} result not buffered: {}", op, e);
+            return json!({
+                "status": StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                "error": "result too large",
+            });
+        }
+    };
+    json!({ "status": status.as_u16(), "body": body })
+}
+
+/// Parameters of a batched query, or the 400 result reported in its place
+fn batch_params<T: DeserializeOwned>(params: &serde_json::Value) -> Result<T, serde_json::Value> {
+    T::deserialize(params).map_err(|e| {
+        json!({
+            "status": StatusCode::BAD_REQUEST.as_u16(),
+            "error": format!("invalid params: {}", e),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::subscription::SignalInsert;
+    use crate::storage::rocksdb_store::RocksDBStore;
+    use chrono::TimeZone;
+    use nostr_sdk::{Event, EventBuilder, Keys, Kind};
+    use prometheus::Registry;
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    /// The REST router over a throwaway RocksDB; keep the `TempDir` alive while using it
+    fn test_app(subscriptions: Option<Arc<SubscriptionService>>) -> (Router, TempDir) {
+        let (router, dir) = test_app_with(subscriptions, None, None);
+        (router.layer(Extension(ConnectionRegistry::default())), dir)
+    }
+
+    /// Like `test_app`, with no `/fanout` connection registry layered on
+    fn test_app_with(
+        subscriptions: Option<Arc<SubscriptionService>>,
+        platform_pubkey: Option<String>,
+        settlement: Option<SettlementConfig>,
+    ) -> (Router, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let store = Arc::new(RocksDBStore::new(dir.path()).unwrap());
+        let (pool, _) = RelayPool::new(Duration::from_secs(30), 10, None);
+        let router = create_router(
+            Arc::new(pool),
+            Arc::new(DeduplicationEngine::new(store)),
+            Arc::new(Metrics::with_registry(&Registry::new()).unwrap()),
+            subscriptions,
+            platform_pubkey,
+            settlement,
+            SubscriptionsConfig::default(),
+        )
+        .layer(Extension(IngestionGate::new(Default::default())))
+        .layer(Extension(SupportedKinds(vec![30931, 30932])))
+        .layer(Extension(None::<SettlementHandle>));
+        (router, dir)
+    }
+
+    async fn send(app: Router, request: Request) -> (StatusCode, serde_json::Value) {
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    fn post_json(uri: &str, body: &str) -> Request {
+        Request::post(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    fn trade(body: serde_json::Value) -> RecordTradeRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    fn base() -> serde_json::Value {
+        json!({
+            "bot_pubkey": "bot",
+            "role": "leader",
+            "symbol": "ETH-USDC",
+            "side": "buy",
+            "size": 1.0,
+            "price": 0.25,
+        })
+    }
+
+    #[test]
+    fn trade_digest_signs_numbers_in_shortest_form_and_absent_fields_as_empty() {
+        let expected = eip712_digest(
+            &eip712_domain_separator("Moltrade Relayer", "1", 999),
+            &eip712_string_struct_hash(
+                TRADE_RECORD_TYPE,
+                &["bot", "", "leader", "ETH-USDC", "buy", "1", "0.25", "", ""],
+            ),
+        );
+        assert_eq!(trade(base()).typed_data_digest(999), expected);
+    }
+
+    #[test]
+    fn trade_digest_covers_every_field_and_the_chain() {
+        let digest = trade(base()).typed_data_digest(999);
+        assert_ne!(trade(base()).typed_data_digest(1), digest);
+        for (field, value) in [
+            ("bot_pubkey", json!("other")),
+            ("follower_pubkey", json!("f")),
+            ("side", json!("sell")),
+            ("size", json!(2.0)),
+            ("tx_hash", json!("0xabc")),
+            ("oid", json!("o1")),
+        ] {
+            let mut body = base();
+            body[field] = value;
+            assert_ne!(trade(body).typed_data_digest(999), digest, "{field}");
+        }
+    }
+
+    #[test]
+    fn follower_relays_must_be_public_wss_urls() {
+        assert_eq!(follower_relays(None), Ok(None));
+        let relays = vec![
+            " wss://inbox.example.com ".to_string(),
+            "wss://relay.example.org:7777/inbox".to_string(),
+        ];
+        assert_eq!(
+            follower_relays(Some(&relays)),
+            Ok(Some(vec![
+                "wss://inbox.example.com".to_string(),
+                "wss://relay.example.org:7777/inbox".to_string()
+            ]))
+        );
+        // An empty list resets to the platform relays
+        assert_eq!(follower_relays(Some(&[])), Ok(Some(Vec::new())));
+        for url in [
+            "https://inbox.example.com",
+            "ws://inbox.example.com",
+            "wss://localhost:7777",
+            "wss://127.0.0.1:7777",
+            "wss://2130706433",
+            "wss://10.1.2.3",
+            "wss://192.168.0.10",
+            "wss://169.254.169.254",
+            "wss://[::1]:7777",
+            "wss://[fd00::1]",
+            "wss://[::ffff:10.0.0.1]",
+            "wss://db.internal",
+            "wss://intranet",
+        ] {
+            assert_eq!(
+                follower_relays(Some(&[url.to_string()])),
+                Err(StatusCode::BAD_REQUEST),
+                "{url}"
+            );
+        }
+        let too_many = vec!["wss://inbox.example.com".to_string(); MAX_FOLLOWER_RELAYS + 1];
+        assert_eq!(
+            follower_relays(Some(&too_many)),
+            Err(StatusCode::BAD_REQUEST)
+        );
+    }
+
+    #[test]
+    fn signal_search_terms_are_checked() {
+        let search = |q: &str, limit: Option<i64>| SignalSearchQuery {
+            q: q.to_string(),
+            bot_pubkey: None,
+            limit,
+        };
+        assert_eq!(search(" eth ", None).terms(), Ok(("eth", 50)));
+        assert_eq!(search("eth", Some(0)).terms(), Ok(("eth", 1)));
+        assert_eq!(search("eth", Some(10_000)).terms(), Ok(("eth", 200)));
+        assert_eq!(search("  ", None).terms(), Err(StatusCode::BAD_REQUEST));
+        let overlong = "x".repeat(MAX_SEARCH_QUERY_LEN + 1);
+        assert_eq!(
+            search(&overlong, None).terms(),
+            Err(StatusCode::BAD_REQUEST)
+        );
+    }
+
+    fn award(bot: &str, follower: &str, amount: Decimal, reason: &str) -> AwardCreditsItem {
+        AwardCreditsItem {
+            bot_pubkey: bot.to_string(),
+            follower_pubkey: follower.to_string(),
+            amount,
+            reason: reason.to_string(),
+        }
+    }
+
+    #[test]
+    fn credit_award_rows_are_validated_together() {
+        let awards =
+            credit_awards(vec![award(" bot ", "f1", Decimal::new(5, 1), " promo ")]).unwrap();
+        assert_eq!(awards.len(), 1);
+        assert_eq!(awards[0].bot_pubkey, "bot");
+        assert_eq!(awards[0].reason, "promo");
+        assert_eq!(awards[0].trade_ref, None);
+
+        // One bad row rejects the whole request
+        for bad in [
+            award("bot", "f2", Decimal::ZERO, "promo"),
+            award("bot", "f2", Decimal::new(1, 0), " "),
+            award(" ", "f2", Decimal::new(1, 0), "promo"),
+        ] {
+            let rows = vec![award("bot", "f1", Decimal::new(1, 0), "promo"), bad];
+            assert_eq!(credit_awards(rows).unwrap_err(), StatusCode::BAD_REQUEST);
+        }
+        assert_eq!(
+            credit_awards(Vec::new()).unwrap_err(),
+            StatusCode::BAD_REQUEST
+        );
+        let too_many = (0..=MAX_CREDIT_AWARDS)
+            .map(|_| award("bot", "f1", Decimal::new(1, 0), "promo"))
+            .collect();
+        assert_eq!(
+            credit_awards(too_many).unwrap_err(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn credit_history_marks_debits() {
+        let created_at = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        let settlement = CreditHistoryItem::from(CreditLedgerEntry {
+            amount: Decimal::new(2, 0),
+            reason: "settlement 0xabc".to_string(),
+            trade_ref: Some("0xabc".to_string()),
+            created_at,
+        });
+        assert_eq!(settlement.kind, "award");
+        assert_eq!(settlement.trade_ref.as_deref(), Some("0xabc"));
+        assert_eq!(settlement.created_at, "2026-01-02T03:04:05+00:00");
+
+        let debit = CreditHistoryItem::from(CreditLedgerEntry {
+            amount: Decimal::new(-1, 0),
+            reason: "refund".to_string(),
+            trade_ref: None,
+            created_at,
+        });
+        assert_eq!(debit.kind, "debit");
+    }
+
+    #[tokio::test]
+    async fn malformed_subscription_body_gets_a_structured_error() {
+        let (app, _dir) = test_app(None);
+        let (status, body) = send(app.clone(), post_json("/api/subscriptions", "{not json")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["status"], 400);
+        assert_eq!(body["error"]["kind"], "invalid_json");
+        assert!(
+            body["error"]["message"]
+                .as_str()
+                .is_some_and(|m| !m.is_empty())
+        );
+
+        // Well-formed JSON missing required fields
+        let (status, body) = send(
+            app,
+            post_json("/api/subscriptions", r#"{"bot_pubkey":"b"}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"]["status"], 422);
+        assert_eq!(body["error"]["kind"], "invalid_data");
+    }
+
+    #[tokio::test]
+    async fn status_reports_whether_subscriptions_are_enabled() {
+        let (app, _dir) = test_app(None);
+        let (status, body) = send(app, get("/status")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["subscriptions_enabled"], false);
+
+        let Some(svc) = SubscriptionService::for_test("rest_status").await else {
+            return;
+        };
+        let (app, _dir) = test_app(Some(Arc::new(svc)));
+        let (_, body) = send(app, get("/status")).await;
+        assert_eq!(body["subscriptions_enabled"], true);
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        assert_eq!(csv_escape("plain"), "plain");
+        assert_eq!(csv_escape("a,b"), "\"a,b\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_escape("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_escape("cr\r"), "\"cr\r\"");
+        assert_eq!(csv_opt(None), "");
+        assert_eq!(csv_num(None), "");
+    }
+
+    fn csv_trade(id: i64, symbol: &str, tx_hash: Option<&str>, pnl: Option<f64>) -> TradeRecord {
+        TradeRecord {
+            id,
+            bot_pubkey: "bot".to_string(),
+            follower_pubkey: None,
+            role: "leader".to_string(),
+            symbol: symbol.to_string(),
+            side: "buy".to_string(),
+            size: 1.5,
+            price: 2500.0,
+            tx_hash: tx_hash.map(str::to_string),
+            oid: None,
+            status: "pending".to_string(),
+            pnl,
+            pnl_usd: None,
+            is_test: false,
+            created_at: Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn trade_csv_has_a_header_and_one_line_per_row() {
+        let rows = vec![
+            Ok(csv_trade(1, "ETH", Some("0xabc"), Some(-2.5))),
+            Ok(csv_trade(2, "BTC,PERP", None, None)),
+        ];
+        let body = csv_body(TRADE_CSV_HEADER, stream::iter(rows), trade_csv_row);
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        let lines: Vec<&str> = std::str::from_utf8(&bytes).unwrap().lines().collect();
+        assert_eq!(
+            lines,
+            [
+                TRADE_CSV_HEADER.trim_end(),
+                "1,bot,,leader,ETH,buy,1.5,2500,0xabc,,pending,-2.5,,false,2026-01-02T03:04:05+00:00",
+                "2,bot,,leader,\"BTC,PERP\",buy,1.5,2500,,,pending,,,false,2026-01-02T03:04:05+00:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn signal_csv_row_leaves_missing_fields_empty() {
+        let signal = SignalRecord {
+            event_id: "evt".to_string(),
+            kind: 30931,
+            bot_pubkey: None,
+            leader_pubkey: "leader".to_string(),
+            follower_pubkey: None,
+            agent_eth_address: None,
+            role: None,
+            symbol: Some("ETH".to_string()),
+            side: None,
+            size: Some(2.0),
+            price: None,
+            status: None,
+            tx_hash: None,
+            pnl: None,
+            pnl_usd: None,
+            event_created_at: Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+        };
+        let row = signal_csv_row(&signal);
+        assert_eq!(
+            row,
+            "evt,30931,,leader,,,,ETH,,2,,,,,,2026-01-02T03:04:05+00:00\n"
+        );
+        assert_eq!(row.split(',').count(), SIGNAL_CSV_HEADER.split(',').count());
+    }
+
+    #[tokio::test]
+    async fn batch_results_follow_the_request_order() {
+        let (app, _dir) = test_app(None);
+        let queries = json!([
+            { "op": "relays" },
+            { "op": "platform" },
+            { "op": "no_such_op" },
+            { "op": "metrics_summary" },
+        ]);
+        let (status, body) = send(app, post_json("/api/batch", &queries.to_string())).await;
+        assert_eq!(status, StatusCode::OK);
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0]["status"], 200);
+        assert!(results[0]["body"]["relays"].is_array());
+        assert_eq!(results[1]["status"], 200);
+        assert_eq!(results[1]["body"]["supported_kinds"], json!([30931, 30932]));
+        assert_eq!(results[2]["status"], 400);
+        assert_eq!(results[2]["error"], "unsupported op: no_such_op");
+        assert_eq!(results[3]["status"], 200);
+        assert_eq!(results[3]["body"]["events_processed_total"], 0);
+    }
+
+    #[tokio::test]
+    async fn batch_over_the_query_limit_is_rejected() {
+        let (app, _dir) = test_app(None);
+        let queries = vec![json!({ "op": "relays" }); MAX_BATCH_QUERIES + 1];
+        let body = serde_json::Value::from(queries).to_string();
+        let (status, _) = send(app, post_json("/api/batch", &body)).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    fn get(uri: &str) -> Request {
+        Request::get(uri).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn trades_are_listed_within_an_inclusive_range() {
+        let Some(svc) = SubscriptionService::for_test("rest_trade_range").await else {
+            return;
+        };
+        svc.register_test_bot("bot1").await;
+        for oid in ["jan1", "jan2", "jan3"] {
+            svc.record_trade_tx(
+                "bot1",
+                None,
+                "leader",
+                "ETH",
+                "buy",
+                1.0,
+                1.0,
+                None,
+                Some(oid),
+                false,
+            )
+            .await
+            .unwrap();
+        }
+        svc.execute_test_sql(
+            "UPDATE trade_executions
+             SET created_at = ('2026-01-0' || substr(oid, 4) || 'T12:00:00Z')::timestamptz",
+        )
+        .await;
+        let (app, _dir) = test_app(Some(Arc::new(svc)));
+
+        let uri = "/api/trades?from=2026-01-02T12:00:00Z&to=2026-01-03T12:00:00Z";
+        let (status, body) = send(app.clone(), get(uri)).await;
+        assert_eq!(status, StatusCode::OK);
+        let mut oids: Vec<&str> = body["trades"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["oid"].as_str().unwrap())
+            .collect();
+        oids.sort();
+        assert_eq!(oids, ["jan2", "jan3"]);
+
+        let uri = "/api/trades?from=2026-01-03T00:00:00Z&to=2026-01-02T00:00:00Z";
+        let (status, _) = send(app, get(uri)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    async fn seed_trade(svc: &SubscriptionService, symbol: &str, side: &str, size: f64, oid: &str) {
+        svc.record_trade_tx(
+            "bot1",
+            None,
+            "leader",
+            symbol,
+            side,
+            size,
+            1.0,
+            None,
+            Some(oid),
+            oid.starts_with("test"),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn bot_pnl_is_summed_per_symbol() {
+        let Some(svc) = SubscriptionService::for_test("rest_bot_pnl").await else {
+            return;
+        };
+        svc.register_test_bot("bot1").await;
+        for (symbol, oid) in [
+            ("ETH", "o1"),
+            ("ETH", "o2"),
+            ("BTC", "o3"),
+            ("SOL", "o4"),
+            ("ETH", "test1"),
+        ] {
+            seed_trade(&svc, symbol, "buy", 1.0, oid).await;
+        }
+        svc.execute_test_sql(
+            "UPDATE trade_executions SET pnl_usd = CASE oid
+                 WHEN 'o1' THEN 10 WHEN 'o2' THEN -4 WHEN 'o3' THEN 20 WHEN 'test1' THEN 100
+             END",
+        )
+        .await;
+        let (app, _dir) = test_app(Some(Arc::new(svc)));
+
+        let (status, body) = send(app.clone(), get("/api/bots/bot1/pnl")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["symbols"],
+            json!([
+                { "symbol": "BTC", "pnl_usd": 20.0, "trade_count": 1 },
+                { "symbol": "ETH", "pnl_usd": 6.0, "trade_count": 2 },
+                { "symbol": "SOL", "pnl_usd": 0.0, "trade_count": 1 },
+            ])
+        );
+
+        let (_, body) = send(app, get("/api/bots/bot1/pnl?include_test=true")).await;
+        assert_eq!(body["symbols"][0]["symbol"], "ETH");
+        assert_eq!(body["symbols"][0]["pnl_usd"], 106.0);
+        assert_eq!(body["symbols"][0]["trade_count"], 3);
+    }
+
+    #[tokio::test]
+    async fn positions_net_only_the_bots_own_trades() {
+        let Some(svc) = SubscriptionService::for_test("rest_positions").await else {
+            return;
+        };
+        svc.register_test_bot("bot1").await;
+        for (symbol, side, size, oid) in [
+            ("ETH", "buy", 2.0, "o1"),
+            ("ETH", "sell", 0.5, "o2"),
+            ("ETH", "buy", 5.0, "failed"),
+            ("ETH", "hold", 1.0, "o3"),
+            ("BTC", "short", 1.0, "o4"),
+            ("BTC", "LONG", 0.25, "o5"),
+        ] {
+            seed_trade(&svc, symbol, side, size, oid).await;
+        }
+        // Neither a failed trade nor a follower's copy moves the bot's position
+        svc.execute_test_sql("UPDATE trade_executions SET status = 'failed' WHERE oid = 'failed'")
+            .await;
+        svc.record_trade_tx(
+            "bot1",
+            Some("f1"),
+            "follower",
+            "ETH",
+            "buy",
+            3.0,
+            1.0,
+            None,
+            Some("o1"),
+            false,
+        )
+        .await
+        .unwrap();
+        let (app, _dir) = test_app(Some(Arc::new(svc)));
+
+        let (status, body) = send(app, get("/api/bots/bot1/positions")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["positions"],
+            json!([
+                { "symbol": "BTC", "net_size": -0.75, "trade_count": 2, "skipped_trades": 0 },
+                { "symbol": "ETH", "net_size": 1.5, "trade_count": 2, "skipped_trades": 1 },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn force_settle_confirms_a_pending_trade_and_awards_credits() {
+        let Some(svc) = SubscriptionService::for_test("rest_force_settle").await else {
+            return;
+        };
+        svc.register_test_bot("bot1").await;
+        seed_trade(&svc, "ETH", "buy", 2.0, "o1").await;
+        let svc = Arc::new(svc);
+        let settlement = serde_json::from_value(json!({ "credit": { "enable": true } })).unwrap();
+        let (app, _dir) = test_app_with(Some(svc.clone()), None, Some(settlement));
+
+        // Looked up by oid alone, so the tx_hash parameter is bound as NULL
+        let settle = r#"{"oid":"o1","status":"confirmed","actor":"ops"}"#;
+        let (status, body) = send(app.clone(), post_json("/api/trades/force-settle", settle)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["updated"], 1);
+        assert_eq!(body["status"], "confirmed");
+
+        let credits = svc.list_credits(Some("bot1"), None).await.unwrap();
+        assert_eq!(credits.len(), 1);
+        assert!(credits[0].credits > Decimal::ZERO);
+        assert_eq!(body["credits_awarded"], json!(credits[0].credits));
+        assert!(svc.list_pending_trades(10).await.unwrap().is_empty());
+
+        // Already confirmed, so there is nothing left to settle
+        let (status, _) = send(app, post_json("/api/trades/force-settle", settle)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn debug_tail_streams_the_metadata_of_published_events() {
+        let tail = EventTail::new(100);
+        let (app, _dir) = test_app(None);
+        let app = app.layer(Extension(tail.clone()));
+        let response = app.oneshot(get("/api/debug/tail")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The handler subscribed before returning, so these reach the stream
+        let keys = Keys::generate();
+        let published: Vec<Event> = [(30931, "{}"), (30932, "longer content")]
+            .into_iter()
+            .map(|(kind, content)| {
+                EventBuilder::new(Kind::Custom(kind), content)
+                    .sign_with_keys(&keys)
+                    .unwrap()
+            })
+            .collect();
+        for event in &published {
+            tail.publish(event);
+        }
+
+        let mut body = response.into_body().into_data_stream();
+        let mut records = Vec::new();
+        let mut text = String::new();
+        while records.len() < published.len() {
+            let chunk = tokio::time::timeout(Duration::from_secs(5), body.next())
+                .await
+                .expect("tail sent no record")
+                .unwrap()
+                .unwrap();
+            text.push_str(std::str::from_utf8(&chunk).unwrap());
+            while let Some(end) = text.find("\n\n") {
+                let frame: String = text.drain(..end + 2).collect();
+                if let Some(data) = frame.lines().find_map(|l| l.strip_prefix("data: ")) {
+                    records.push(serde_json::from_str::<serde_json::Value>(data).unwrap());
+                }
+            }
+        }
+
+        for (record, event) in records.iter().zip(&published) {
+            assert_eq!(
+                *record,
+                json!({
+                    "id": event.id.to_hex(),
+                    "kind": event.kind.as_u16(),
+                    "pubkey": event.pubkey.to_hex(),
+                    "created_at": event.created_at.as_secs(),
+                    "size": event.content.len(),
+                })
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn platform_info_lists_encryption_only_with_keys() {
+        let (app, _dir) = test_app(None);
+        let (status, body) = send(app, get("/api/platform")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            json!({
+                "platform_pubkey": null,
+                "encryption_schemes": [],
+                "supported_kinds": [30931, 30932],
+            })
+        );
+
+        let (app, _dir) = test_app_with(None, Some("platform".to_string()), None);
+        let (_, body) = send(app, get("/api/platform")).await;
+        assert_eq!(
+            body,
+            json!({
+                "platform_pubkey": "platform",
+                "encryption_schemes": ["nip04"],
+                "supported_kinds": [30931, 30932],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn bot_followers_include_credits_signals_and_connection_state() {
+        let Some(svc) = SubscriptionService::for_test("rest_bot_followers").await else {
+            return;
+        };
+        svc.register_test_bot("bot1").await;
+        let online = Keys::generate().public_key().to_hex();
+        let offline = Keys::generate().public_key().to_hex();
+        svc.add_subscription("bot1", &online, "secret", None, None, None)
+            .await
+            .unwrap();
+        svc.add_subscription("bot1", &offline, "secret", None, Some(&[30932]), None)
+            .await
+            .unwrap();
+        svc.award_credits("bot1", &online, Decimal::ONE, "test", None)
+            .await
+            .unwrap();
+        svc.set_subscription_suspended("bot1", &offline, true)
+            .await
+            .unwrap();
+        // A kind only `online` wants, so `offline` has no latest signal
+        let mut signal = SignalInsert::for_test("signal1");
+        signal.bot_pubkey = Some("bot1".to_string());
+        svc.record_signal(signal).await.unwrap();
+        let connections = ConnectionRegistry::default();
+        connections.connect_for_test(&online);
+        let (app, _dir) = test_app_with(Some(Arc::new(svc)), None, None);
+        let app = app.layer(Extension(connections));
+
+        let (status, body) = send(app.clone(), get("/api/bots/bot1/followers")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["count"], 2);
+        let followers = body["followers"].as_array().unwrap();
+        let (first, second) = (&followers[0], &followers[1]);
+        assert_eq!(first["follower_pubkey"], online.as_str());
+        assert_eq!(first["credits"], json!(Decimal::ONE));
+        assert!(first["last_signal_at"].is_string());
+        assert!(first["suspended_at"].is_null());
+        assert_eq!(first["connected"], true);
+        assert_eq!(second["follower_pubkey"], offline.as_str());
+        assert_eq!(second["credits"], json!(Decimal::ZERO));
+        assert!(second["last_signal_at"].is_null());
+        assert!(second["suspended_at"].is_string());
+        assert_eq!(second["connected"], false);
+
+        let (status, _) = send(app, get("/api/bots/unknown/followers")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
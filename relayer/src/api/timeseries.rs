@@ -0,0 +1,120 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+
+/// Events processed during one wall-clock minute
+#[derive(Debug, Clone, Serialize)]
+pub struct MinuteCount {
+    /// RFC3339 start of the minute
+    pub minute: String,
+    pub events: u64,
+}
+
+/// Per-minute processed-event counts over a fixed window of minutes, for
+/// `/api/metrics/timeseries`; memory stays at one slot per minute of the window
+#[derive(Clone)]
+pub struct EventTimeseries {
+    /// Slot `minute % len` holds (unix minute, events counted in it); stale slots read as zero
+    buckets: Arc<Mutex<Vec<(u64, u64)>>>,
+}
+
+impl EventTimeseries {
+    /// Keep the last `minutes` minutes; zero disables counting
+    pub fn new(minutes: usize) -> Self {
+        Self {
+            buckets: Arc::new(Mutex::new(vec![(0, 0); minutes])),
+        }
+    }
+
+    pub fn window_minutes(&self) -> usize {
+        self.buckets.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Count one processed event in the current minute
+    pub fn record(&self) {
+        self.record_at(current_minute());
+    }
+
+    /// Count one processed event in unix minute `minute`, reusing the slot of the minute one
+    /// window earlier
+    pub fn record_at(&self, minute: u64) {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.is_empty() {
+            return;
+        }
+        let len = buckets.len() as u64;
+        let slot = &mut buckets[(minute % len) as usize];
+        if slot.0 != minute {
+            *slot = (minute, 0);
+        }
+        slot.1 += 1;
+    }
+
+    /// Counts for every minute of the window up to and including the current one, oldest first
+    pub fn snapshot(&self) -> Vec<MinuteCount> {
+        self.snapshot_at(current_minute())
+    }
+
+    /// Counts for the window ending at unix minute `now`, oldest first; minutes with no events,
+    /// or whose slot was already reused, read as zero
+    pub fn snapshot_at(&self, now: u64) -> Vec<MinuteCount> {
+        let buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let len = buckets.len() as u64;
+        (now.saturating_sub(len.saturating_sub(1))..=now)
+            .take(buckets.len())
+            .map(|minute| {
+                let (stamp, count) = buckets[(minute % len) as usize];
+                MinuteCount {
+                    minute: DateTime::<Utc>::from_timestamp((minute * 60) as i64, 0)
+                        .map(|t| t.to_rfc3339())
+                        .unwrap_or_default(),
+                    events: if stamp == minute { count } else { 0 },
+                }
+            })
+            .collect()
+    }
+}
+
+fn current_minute() -> u64 {
+    Utc::now().timestamp().max(0) as u64 / 60
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(series: &EventTimeseries, now: u64) -> Vec<u64> {
+        series.snapshot_at(now).iter().map(|m| m.events).collect()
+    }
+
+    #[test]
+    fn counts_per_minute_oldest_first() {
+        let series = EventTimeseries::new(3);
+        series.record_at(100);
+        series.record_at(100);
+        series.record_at(101);
+        assert_eq!(counts(&series, 101), [0, 2, 1]);
+        assert_eq!(
+            series.snapshot_at(101)[1].minute,
+            "1970-01-01T01:40:00+00:00"
+        );
+    }
+
+    #[test]
+    fn reused_and_stale_slots_read_as_zero() {
+        let series = EventTimeseries::new(3);
+        series.record_at(100);
+        series.record_at(101);
+        // Minute 104 takes over minute 101's slot; 100 has left the window
+        series.record_at(104);
+        assert_eq!(counts(&series, 104), [0, 0, 1]);
+        assert_eq!(counts(&series, 110), [0, 0, 0]);
+    }
+
+    #[test]
+    fn zero_window_disables_counting() {
+        let series = EventTimeseries::new(0);
+        series.record_at(100);
+        assert!(series.snapshot_at(100).is_empty());
+    }
+}
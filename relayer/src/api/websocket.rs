@@ -1,34 +1,171 @@
 use axum::{
     Router,
     extract::{
-        State,
+        Query, State,
         ws::{Message, WebSocket, WebSocketUpgrade},
     },
     http::StatusCode,
     response::Response,
     routing::get,
 };
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
 use flume::Receiver;
 use futures_util::{SinkExt, StreamExt};
-use nostr_sdk::Event;
+use nostr_sdk::prelude::{Event, Timestamp};
+use prometheus::Gauge;
+use rand::RngCore;
+use serde::Deserialize;
 use serde_json;
 use std::sync::Arc;
-use tracing::{error, info};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
 
+use crate::core::pubkey::canonical_pubkey;
 use crate::core::subscription::FanoutMessage;
+use crate::storage::replay_buffer::ReplayBuffer;
 
 #[derive(Clone)]
 pub struct WsState {
     pub event_rx: Arc<Receiver<Event>>,
     pub fanout_rx: Option<Arc<Receiver<FanoutMessage>>>,
+    /// Every fanout message, for authenticated connections that each keep only their own
+    pub fanout_hub: Option<broadcast::Sender<Arc<FanoutMessage>>>,
+    pub fanout: FanoutOptions,
+    pub replay: ReplayBuffer,
+    pub connections: ConnectionRegistry,
+}
+
+/// A connected `/fanout` client
+#[derive(Debug, Clone)]
+pub struct FanoutClient {
+    /// Authenticated follower pubkey (hex); `None` when `/fanout` runs without AUTH
+    pub follower_pubkey: Option<String>,
+    pub connected_at: DateTime<Utc>,
+}
+
+/// Live `/fanout` connections, behind `/api/ws/connections` and the `fanout_connections` gauge
+#[derive(Clone, Default)]
+pub struct ConnectionRegistry {
+    clients: Arc<DashMap<u64, FanoutClient>>,
+    next_id: Arc<AtomicU64>,
+    gauge: Option<Gauge>,
+}
+
+/// Removes its connection from the registry when the socket handler returns
+struct RegisteredConnection {
+    registry: ConnectionRegistry,
+    id: u64,
+}
+
+impl ConnectionRegistry {
+    pub fn new(gauge: Option<Gauge>) -> Self {
+        Self {
+            gauge,
+            ..Self::default()
+        }
+    }
+
+    fn register(&self, follower_pubkey: Option<String>) -> RegisteredConnection {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.clients.insert(
+            id,
+            FanoutClient {
+                follower_pubkey,
+                connected_at: Utc::now(),
+            },
+        );
+        self.update_gauge();
+        RegisteredConnection {
+            registry: self.clone(),
+            id,
+        }
+    }
+
+    fn update_gauge(&self) {
+        if let Some(g) = &self.gauge {
+            g.set(self.clients.len() as f64);
+        }
+    }
+
+    /// Connected clients, longest-connected first
+    pub fn snapshot(&self) -> Vec<FanoutClient> {
+        let mut clients: Vec<FanoutClient> =
+            self.clients.iter().map(|e| e.value().clone()).collect();
+        clients.sort_by_key(|c| c.connected_at);
+        clients
+    }
+}
+
+#[cfg(test)]
+impl ConnectionRegistry {
+    /// Record a `/fanout` client authenticated as `follower_pubkey` that never disconnects
+    pub(crate) fn connect_for_test(&self, follower_pubkey: &str) {
+        std::mem::forget(self.register(Some(follower_pubkey.to_string())));
+    }
+}
+
+impl Drop for RegisteredConnection {
+    fn drop(&mut self) {
+        self.registry.clients.remove(&self.id);
+        self.registry.update_gauge();
+    }
+}
+
+/// Connection options for `/fanout`
+#[derive(Debug, Clone, Default)]
+pub struct FanoutOptions {
+    /// Subprotocol echoed back when the client offers it
+    pub subprotocol: Option<String>,
+    /// Require a NIP-42 style AUTH within this long, then stream only the proven pubkey's messages
+    pub auth_timeout: Option<Duration>,
+    /// Messages an authenticated connection may fall behind before it starts skipping
+    pub hub_capacity: usize,
+}
+
+/// NIP-42 client authentication kind
+const AUTH_KIND: u16 = 22242;
+/// How far an AUTH event's created_at may be from now
+const AUTH_MAX_SKEW_SECS: u64 = 600;
+
+/// Order of replayed events; live events that follow are always in arrival order
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ReplayOrder {
+    #[default]
+    Chronological,
+    Newest,
+}
+
+#[derive(Debug, Deserialize)]
+struct WsQuery {
+    /// Number of recent events to send before live streaming starts
+    #[serde(default)]
+    replay: usize,
+    #[serde(default)]
+    order: ReplayOrder,
 }
 
 // use crate::core::relay_pool::RelayPool;
 
 /// WebSocket handler for streaming events to downstream systems
-async fn websocket_handler(ws: WebSocketUpgrade, State(state): State<WsState>) -> Response {
+async fn websocket_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<WsState>,
+    Query(q): Query<WsQuery>,
+) -> Response {
     let rx = state.event_rx.clone();
-    ws.on_upgrade(|socket| handle_socket(socket, rx))
+    let replay = if q.replay > 0 {
+        state
+            .replay
+            .recent(q.replay, matches!(q.order, ReplayOrder::Newest))
+            .await
+    } else {
+        Vec::new()
+    };
+    ws.on_upgrade(|socket| handle_socket(socket, rx, replay))
 }
 
 /// WebSocket handler for fanout payloads to subscribers
@@ -36,24 +173,48 @@ async fn fanout_handler(
     ws: WebSocketUpgrade,
     State(state): State<WsState>,
 ) -> Result<Response, StatusCode> {
+    let ws = match &state.fanout.subprotocol {
+        Some(protocol) => ws.protocols([protocol.clone()]),
+        None => ws,
+    };
+
+    if let (Some(timeout), Some(hub)) = (state.fanout.auth_timeout, state.fanout_hub.as_ref()) {
+        let rx = hub.subscribe();
+        let connections = state.connections.clone();
+        return Ok(ws.on_upgrade(move |socket| {
+            handle_authed_fanout_socket(socket, rx, timeout, connections)
+        }));
+    }
+
     let fanout_rx = match state.fanout_rx.clone() {
         Some(rx) => rx,
         None => return Err(StatusCode::SERVICE_UNAVAILABLE),
     };
 
-    Ok(ws.on_upgrade(|socket| handle_fanout_socket(socket, fanout_rx)))
+    let connections = state.connections.clone();
+    Ok(ws.on_upgrade(move |socket| async move {
+        let _registered = connections.register(None);
+        handle_fanout_socket(socket, fanout_rx).await
+    }))
 }
 
 /// Handle individual WebSocket connection
-async fn handle_socket(socket: WebSocket, event_rx: Arc<Receiver<Event>>) {
-    info!("New WebSocket connection established");
+async fn handle_socket(socket: WebSocket, event_rx: Arc<Receiver<Event>>, replay: Vec<Event>) {
+    info!(
+        "New WebSocket connection established (replaying {} events)",
+        replay.len()
+    );
 
     let (mut sender, mut receiver) = socket.split();
 
-    // Spawn task to send events to client
+    // Spawn task to send events to client: the requested replay first, then live events
     let send_task = tokio::spawn(async move {
         let event_rx = event_rx.clone();
-        while let Ok(event) = event_rx.recv_async().await {
+        let live = futures_util::stream::unfold(event_rx, |rx| async move {
+            rx.recv_async().await.ok().map(|event| (event, rx))
+        });
+        let mut events = futures_util::stream::iter(replay).chain(live).boxed();
+        while let Some(event) = events.next().await {
             let json = match serde_json::to_string(&event) {
                 Ok(j) => j,
                 Err(e) => {
@@ -134,14 +295,179 @@ async fn handle_fanout_socket(socket: WebSocket, fanout_rx: Arc<Receiver<FanoutM
     info!("Fanout WebSocket connection closed");
 }
 
+/// Handle a fanout connection that must authenticate before it receives anything
+async fn handle_authed_fanout_socket(
+    mut socket: WebSocket,
+    mut hub_rx: broadcast::Receiver<Arc<FanoutMessage>>,
+    timeout: Duration,
+    connections: ConnectionRegistry,
+) {
+    let event = match authenticate(&mut socket, timeout).await {
+        Ok(event) => event,
+        Err(reason) => {
+            warn!("Fanout WebSocket authentication failed: {}", reason);
+            let notice = serde_json::json!(["NOTICE", format!("auth-required: {}", reason)]);
+            let _ = socket.send(Message::Text(notice.to_string().into())).await;
+            let _ = socket.send(Message::Close(None)).await;
+            return;
+        }
+    };
+
+    let ok = serde_json::json!(["OK", event.id.to_hex(), true, ""]);
+    if socket
+        .send(Message::Text(ok.to_string().into()))
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    let pubkey = event.pubkey.to_hex();
+    info!("Fanout WebSocket authenticated as {}", pubkey);
+    let _registered = connections.register(Some(pubkey.clone()));
+
+    let (mut sender, mut receiver) = socket.split();
+
+    let send_task = tokio::spawn(async move {
+        loop {
+            let msg = match hub_rx.recv().await {
+                Ok(msg) => msg,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(
+                        "Fanout WebSocket for {} lagged, skipped {} messages",
+                        pubkey, skipped
+                    );
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            // Rows stored before normalization may still hold an npub
+            if msg.target_pubkey != pubkey && canonical_pubkey(&msg.target_pubkey) != pubkey {
+                continue;
+            }
+
+            let json = match serde_json::to_string(msg.as_ref()) {
+                Ok(j) => j,
+                Err(e) => {
+                    error!("Failed to serialize fanout message: {}", e);
+                    continue;
+                }
+            };
+
+            if let Err(e) = sender.send(Message::Text(json.into())).await {
+                error!("Failed to send fanout WebSocket message: {}", e);
+                break;
+            }
+        }
+    });
+
+    let recv_task = tokio::spawn(async move {
+        while let Some(Ok(msg)) = receiver.next().await {
+            if let Message::Close(_) = msg {
+                break;
+            }
+        }
+    });
+
+    tokio::select! {
+        _ = send_task => {}
+        _ = recv_task => {}
+    }
+
+    info!("Fanout WebSocket connection closed");
+}
+
+/// Send a challenge and wait for `["AUTH", <signed kind 22242 event>]` that answers it
+async fn authenticate(socket: &mut WebSocket, timeout: Duration) -> Result<Event, String> {
+    let mut nonce = [0u8; 16];
+    rand::rng().fill_bytes(&mut nonce);
+    let challenge = hex::encode(nonce);
+
+    let frame = serde_json::json!(["AUTH", challenge]);
+    socket
+        .send(Message::Text(frame.to_string().into()))
+        .await
+        .map_err(|e| format!("failed to send challenge: {}", e))?;
+
+    let reply = tokio::time::timeout(timeout, next_text(socket))
+        .await
+        .map_err(|_| format!("no AUTH within {}s", timeout.as_secs()))?
+        .ok_or_else(|| "connection closed before AUTH".to_string())?;
+
+    let (label, event): (String, Event) =
+        serde_json::from_str(&reply).map_err(|_| "expected [\"AUTH\", <event>]".to_string())?;
+    if label != "AUTH" {
+        return Err(format!("expected AUTH, got {}", label));
+    }
+    verify_auth_event(&event, &challenge)?;
+    Ok(event)
+}
+
+fn verify_auth_event(event: &Event, challenge: &str) -> Result<(), String> {
+    if event.kind.as_u16() != AUTH_KIND {
+        return Err(format!("AUTH event must be kind {}", AUTH_KIND));
+    }
+    let answers_challenge = event.tags.iter().any(|tag| {
+        matches!(tag.as_slice(), [name, value, ..] if name == "challenge" && value == challenge)
+    });
+    if !answers_challenge {
+        return Err("AUTH event does not carry this connection's challenge".to_string());
+    }
+    let skew = event
+        .created_at
+        .as_secs()
+        .abs_diff(Timestamp::now().as_secs());
+    if skew > AUTH_MAX_SKEW_SECS {
+        return Err("AUTH event created_at is too far from now".to_string());
+    }
+    event
+        .verify()
+        .map_err(|_| "AUTH event signature is invalid".to_string())
+}
+
+/// Next text frame, skipping control frames; `None` once the client is gone
+async fn next_text(socket: &mut WebSocket) -> Option<String> {
+    loop {
+        match socket.recv().await? {
+            Ok(Message::Text(text)) => return Some(text.to_string()),
+            Ok(Message::Close(_)) | Err(_) => return None,
+            Ok(_) => continue,
+        }
+    }
+}
+
 /// Create WebSocket router
 pub fn create_websocket_router(
     event_rx: Arc<Receiver<Event>>,
     fanout_rx: Option<Arc<Receiver<FanoutMessage>>>,
+    replay: ReplayBuffer,
+    fanout: FanoutOptions,
+    connections: ConnectionRegistry,
 ) -> Router {
+    // Authenticated connections each filter the full stream, so drain the queue into a
+    // broadcast rather than letting connections compete for messages
+    let (fanout_rx, fanout_hub) = match (fanout_rx, fanout.auth_timeout) {
+        (Some(rx), Some(_)) => {
+            let (hub, _) = broadcast::channel(fanout.hub_capacity.max(1));
+            let tx = hub.clone();
+            tokio::spawn(async move {
+                while let Ok(msg) = rx.recv_async().await {
+                    // No authenticated connections: nobody to deliver to
+                    let _ = tx.send(Arc::new(msg));
+                }
+            });
+            (None, Some(hub))
+        }
+        (rx, _) => (rx, None),
+    };
+
     let state = WsState {
         event_rx,
         fanout_rx,
+        fanout_hub,
+        fanout,
+        replay,
+        connections,
     };
 
     Router::new()
@@ -149,3 +475,88 @@ pub fn create_websocket_router(
         .route("/fanout", get(fanout_handler))
         .with_state(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostr_sdk::prelude::{EventBuilder, Keys, Kind, Tag};
+
+    fn auth_event(kind: u16, challenge: &str, created_at: Timestamp) -> Event {
+        EventBuilder::new(Kind::Custom(kind), "")
+            .tag(Tag::parse(["relay", "wss://relay.example"]).unwrap())
+            .tag(Tag::parse(["challenge", challenge]).unwrap())
+            .custom_created_at(created_at)
+            .sign_with_keys(&Keys::generate())
+            .unwrap()
+    }
+
+    #[test]
+    fn auth_event_must_answer_this_challenge() {
+        let now = Timestamp::now();
+        assert_eq!(
+            verify_auth_event(&auth_event(AUTH_KIND, "abc", now), "abc"),
+            Ok(())
+        );
+
+        let err = verify_auth_event(&auth_event(AUTH_KIND, "other", now), "abc").unwrap_err();
+        assert!(err.contains("challenge"), "{}", err);
+
+        let err = verify_auth_event(&auth_event(1, "abc", now), "abc").unwrap_err();
+        assert!(err.contains("kind"), "{}", err);
+    }
+
+    #[test]
+    fn auth_event_is_rejected_when_skewed_or_tampered() {
+        let skew = Duration::from_secs(AUTH_MAX_SKEW_SECS + 60);
+        for created_at in [Timestamp::now() - skew, Timestamp::now() + skew] {
+            let err =
+                verify_auth_event(&auth_event(AUTH_KIND, "abc", created_at), "abc").unwrap_err();
+            assert!(err.contains("created_at"), "{}", err);
+        }
+
+        // Changing a signed field leaves the id and signature covering the old content
+        let mut event = auth_event(AUTH_KIND, "abc", Timestamp::now());
+        event.content = "tampered".to_string();
+        let err = verify_auth_event(&event, "abc").unwrap_err();
+        assert!(err.contains("signature"), "{}", err);
+    }
+
+    #[test]
+    fn registry_counts_open_connections() {
+        let gauge = Gauge::new("fanout_connections_test", "test gauge").unwrap();
+        let registry = ConnectionRegistry::new(Some(gauge.clone()));
+
+        let first = registry.register(None);
+        let second = registry.register(Some("ab".repeat(32)));
+        assert_eq!(registry.snapshot().len(), 2);
+        assert_eq!(gauge.get(), 2.0);
+
+        drop(first);
+        let clients = registry.snapshot();
+        assert_eq!(clients.len(), 1);
+        assert_eq!(clients[0].follower_pubkey, Some("ab".repeat(32)));
+        assert_eq!(gauge.get(), 1.0);
+
+        drop(second);
+        assert!(registry.snapshot().is_empty());
+        assert_eq!(gauge.get(), 0.0);
+    }
+
+    #[test]
+    fn replay_order_defaults_to_chronological() {
+        let parse = |uri: &str| {
+            let uri: axum::http::Uri = uri.parse().unwrap();
+            Query::<WsQuery>::try_from_uri(&uri).map(|q| (q.replay, q.order))
+        };
+        assert!(matches!(
+            parse("/ws?replay=5"),
+            Ok((5, ReplayOrder::Chronological))
+        ));
+        assert!(matches!(
+            parse("/ws?replay=5&order=newest"),
+            Ok((5, ReplayOrder::Newest))
+        ));
+        assert!(matches!(parse("/ws"), Ok((0, ReplayOrder::Chronological))));
+        assert!(parse("/ws?order=random").is_err());
+    }
+}
@@ -1,21 +1,81 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct RelayConfig {
     pub bootstrap_relays: Vec<String>,
     pub max_connections: usize,
     pub health_check_interval: u64,
+    /// Resubscribe to a relay after this many silent seconds; zero disables
+    #[serde(default = "default_resubscribe_idle_secs")]
+    pub resubscribe_idle_secs: u64,
+    /// Buffer historical events for this long after (re)subscribing and forward them as one batch; zero disables
+    #[serde(default = "default_backfill_coalesce_ms")]
+    pub backfill_coalesce_ms: u64,
+    /// Relay add/remove requests allowed in a burst; zero disables the limit
+    #[serde(default = "default_mutation_burst")]
+    pub mutation_burst: u32,
+    /// Sustained relay add/remove requests per second once the burst is spent
+    #[serde(default = "default_mutation_per_sec")]
+    pub mutation_per_sec: f64,
+    /// INSECURE: relay URLs (or `"*"` for all) connected to without TLS certificate verification,
+    /// for private relays with self-signed certificates
+    #[serde(default)]
+    pub insecure_tls_relays: Vec<String>,
+}
+
+fn default_resubscribe_idle_secs() -> u64 {
+    600
+}
+
+fn default_mutation_burst() -> u32 {
+    10
+}
+
+fn default_mutation_per_sec() -> f64 {
+    0.5
+}
+
+fn default_backfill_coalesce_ms() -> u64 {
+    500
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct DeduplicationConfig {
     pub hotset_size: usize,
     pub bloom_capacity: usize,
+    /// Target false-positive probability used to size the bloom filter
+    #[serde(default = "default_bloom_fp_rate")]
+    pub bloom_fp_rate: f64,
     pub lru_size: usize,
     pub rocksdb_path: String,
+    /// Kinds that go through id dedupe; other kinds (e.g. heartbeats) bypass it. Empty dedupes all kinds
+    #[serde(default = "default_dedupe_kinds")]
+    pub dedupe_kinds: Vec<u16>,
+    /// At startup, also warm dedupe from signals persisted in Postgres within this many seconds; zero disables
+    #[serde(default)]
+    pub warm_from_signals_secs: u64,
+    /// How long startup waits for the RocksDB warm before finishing it in the background; zero waits for all of it
+    #[serde(default = "default_warm_deadline_secs")]
+    pub warm_deadline_secs: u64,
+    /// Skip deduped kinds whose created_at is older than this many seconds, as if already seen; zero disables
+    #[serde(default)]
+    pub window_secs: u64,
+}
+
+fn default_warm_deadline_secs() -> u64 {
+    30
+}
+
+fn default_bloom_fp_rate() -> f64 {
+    0.01
+}
+
+fn default_dedupe_kinds() -> Vec<u16> {
+    vec![30931, 30932, 30934, 30935]
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -26,26 +86,275 @@ pub struct OutputConfig {
     pub bind_address: String,
     pub batch_size: usize,
     pub max_latency_ms: u64,
+    /// Flush early once pending events are estimated to hold this many bytes; zero disables the cap
+    #[serde(default)]
+    pub max_pending_bytes: usize,
+    /// Capacity of the follower fanout channel; messages are dropped when full
+    #[serde(default = "default_fanout_capacity")]
+    pub fanout_capacity: usize,
+    /// Recent events kept for `/ws?replay=N`; zero disables replay
+    #[serde(default = "default_replay_buffer_size")]
+    pub replay_buffer_size: usize,
+    /// What `processing_latency_seconds` measures
+    #[serde(default)]
+    pub latency_mode: LatencyMode,
+    /// What happens to inbound events while ingestion is paused through `/api/admin/pause`
+    #[serde(default)]
+    pub pause_mode: PauseMode,
+    /// Events held in memory while paused in buffer mode; the oldest are dropped beyond this
+    #[serde(default = "default_pause_buffer_capacity")]
+    pub pause_buffer_capacity: usize,
+    /// Record every routed event's metadata in Postgres `raw_events`, whatever its kind
+    #[serde(default)]
+    pub persist_all_events: bool,
+    /// Require `/fanout` clients to prove their follower pubkey and stream only its messages
+    #[serde(default)]
+    pub fanout_auth: bool,
+    /// How long a `/fanout` client has to answer the auth challenge
+    #[serde(default = "default_fanout_auth_timeout_secs")]
+    pub fanout_auth_timeout_secs: u64,
+    /// WebSocket subprotocol negotiated on `/fanout`; empty disables negotiation
+    #[serde(default)]
+    pub fanout_subprotocol: String,
+    /// Source IPs or CIDR ranges allowed on admin endpoints, on top of the settlement token; empty allows any
+    #[serde(default)]
+    pub admin_allowed_ips: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LatencyMode {
+    /// One observation per flushed batch: time spent routing the batch
+    #[default]
+    Batch,
+    /// One observation per routed event: time from enqueue until sent downstream
+    Event,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PauseMode {
+    /// Keep reading and hold events in a bounded buffer; they are processed on resume
+    #[default]
+    Buffer,
+    /// Keep reading and discard what arrives until resumed
+    Drop,
+}
+
+impl PauseMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Buffer => "buffer",
+            Self::Drop => "drop",
+        }
+    }
 }
 
 fn default_bind_address() -> String {
     "127.0.0.1".to_string()
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+fn default_fanout_capacity() -> usize {
+    10_000
+}
+
+fn default_fanout_auth_timeout_secs() -> u64 {
+    10
+}
+
+fn default_replay_buffer_size() -> usize {
+    1000
+}
+
+fn default_pause_buffer_capacity() -> usize {
+    10_000
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct FilterConfig {
     #[serde(default = "default_allowed_kinds")]
     pub allowed_kinds: Vec<u16>,
+    /// Require agent-register payloads to carry an eth signature over the bot_pubkey
+    #[serde(default)]
+    pub require_agent_signature: bool,
+    /// Ignore agent-register events created more than this many seconds ago; zero disables the check
+    #[serde(default = "default_agent_register_max_age_secs")]
+    pub agent_register_max_age_secs: u64,
+    /// Drop events whose content exceeds this many bytes; zero disables the cap
+    #[serde(default = "default_max_content_bytes")]
+    pub max_content_bytes: usize,
+    /// How far ahead of receive time an event's created_at may be
+    #[serde(default = "default_future_skew_secs")]
+    pub future_skew_secs: u64,
+    /// What to do with events dated beyond the allowed skew
+    #[serde(default)]
+    pub future_timestamp_policy: FutureTimestampPolicy,
+    /// Measure staleness from receive time rather than the client-supplied created_at
+    #[serde(default)]
+    pub stale_from_receive_time: bool,
+    /// Read trade metadata from tags (`["symbol","ETH"]`, ...) when an event's content is empty
+    #[serde(default)]
+    pub tag_payloads: bool,
+    /// Events a single pubkey may send in a burst before `pubkey_per_sec` applies; zero disables the limit
+    #[serde(default)]
+    pub pubkey_burst: u32,
+    /// Sustained events per second allowed per pubkey once its burst is spent
+    #[serde(default)]
+    pub pubkey_per_sec: f64,
+    /// Tags every event must carry; events missing any of them are dropped
+    #[serde(default)]
+    pub required_tags: Vec<RequiredTag>,
+    /// Drop events whose created_at is not strictly newer than the last accepted event from the same pubkey
+    #[serde(default)]
+    pub strict_monotonic: bool,
+    /// Extra JSON keys accepted per trade payload field, e.g. `price = ["px"]`; the first one
+    /// present fills a field the payload does not set under its own name
+    #[serde(default)]
+    pub field_map: HashMap<String, Vec<String>>,
+}
+
+/// A tag an event must carry, e.g. `{ name = "t", value = "moltrade" }`; without a value any value matches
+#[derive(Debug, Clone, Deserialize)]
+pub struct RequiredTag {
+    pub name: String,
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FutureTimestampPolicy {
+    /// Treat the event as created at receive time
+    #[default]
+    Clamp,
+    /// Drop the event
+    Drop,
+}
+
+fn default_agent_register_max_age_secs() -> u64 {
+    3600
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self {
+            allowed_kinds: Vec::new(),
+            require_agent_signature: false,
+            agent_register_max_age_secs: default_agent_register_max_age_secs(),
+            max_content_bytes: default_max_content_bytes(),
+            future_skew_secs: default_future_skew_secs(),
+            future_timestamp_policy: FutureTimestampPolicy::Clamp,
+            stale_from_receive_time: false,
+            tag_payloads: false,
+            pubkey_burst: 0,
+            pubkey_per_sec: 0.0,
+            required_tags: Vec::new(),
+            strict_monotonic: false,
+            field_map: HashMap::new(),
+        }
+    }
+}
+
+fn default_max_content_bytes() -> usize {
+    64 * 1024
+}
+
+fn default_future_skew_secs() -> u64 {
+    300
 }
 
 fn default_allowed_kinds() -> Vec<u16> {
     vec![30931, 30932, 30933, 30934, 30935]
 }
 
+/// Event kind used for each copytrade role
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct KindsConfig {
+    #[serde(default = "default_kind_trade_signal")]
+    pub trade_signal: u16,
+    #[serde(default = "default_kind_copytrade_intent")]
+    pub copytrade_intent: u16,
+    #[serde(default = "default_kind_heartbeat")]
+    pub heartbeat: u16,
+    #[serde(default = "default_kind_execution_report")]
+    pub execution_report: u16,
+    #[serde(default = "default_kind_agent_register")]
+    pub agent_register: u16,
+}
+
+impl KindsConfig {
+    /// Kinds that only do useful work when the subscription service is configured
+    pub fn all(&self) -> [u16; 5] {
+        [
+            self.trade_signal,
+            self.copytrade_intent,
+            self.heartbeat,
+            self.execution_report,
+            self.agent_register,
+        ]
+    }
+}
+
+impl Default for KindsConfig {
+    fn default() -> Self {
+        Self {
+            trade_signal: default_kind_trade_signal(),
+            copytrade_intent: default_kind_copytrade_intent(),
+            heartbeat: default_kind_heartbeat(),
+            execution_report: default_kind_execution_report(),
+            agent_register: default_kind_agent_register(),
+        }
+    }
+}
+
+fn default_kind_trade_signal() -> u16 {
+    30931
+}
+
+fn default_kind_copytrade_intent() -> u16 {
+    30932
+}
+
+fn default_kind_heartbeat() -> u16 {
+    30933
+}
+
+fn default_kind_execution_report() -> u16 {
+    30934
+}
+
+fn default_kind_agent_register() -> u16 {
+    30935
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct MonitoringConfig {
     pub prometheus_port: u16,
     pub log_level: String,
+    /// Number of recent error-level log events kept for `/api/debug/errors`
+    #[serde(default = "default_error_buffer_size")]
+    pub error_buffer_size: usize,
+    /// Upper bound on records per second mirrored to `/api/debug/tail`; zero disables tailing
+    #[serde(default = "default_tail_max_per_sec")]
+    pub tail_max_per_sec: u32,
+    /// Minutes of per-minute processed-event counts kept for `/api/metrics/timeseries`; zero disables them
+    #[serde(default = "default_timeseries_minutes")]
+    pub timeseries_minutes: usize,
+    /// Log decrypted payload previews at debug level; when false only length and hash are logged
+    #[serde(default)]
+    pub log_payloads: bool,
+}
+
+fn default_error_buffer_size() -> usize {
+    200
+}
+
+fn default_timeseries_minutes() -> usize {
+    60
+}
+
+fn default_tail_max_per_sec() -> u32 {
+    20
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -53,16 +362,158 @@ pub struct PostgresConfig {
     pub dsn: String,
     #[serde(default = "default_pg_pool_size")]
     pub max_connections: usize,
+    /// Extra startup attempts while Postgres is unreachable; zero fails on the first error
+    #[serde(default = "default_pg_connect_retries")]
+    pub connect_retries: u32,
+    /// Delay before the first retry, doubled after each failed attempt (capped at 30s)
+    #[serde(default = "default_pg_retry_delay_secs")]
+    pub retry_delay_secs: u64,
+    /// Buffer up to this many signals and write them with one multi-row INSERT; zero inserts each signal on its own
+    #[serde(default)]
+    pub signal_batch_size: usize,
+    /// Longest a buffered signal waits before its batch is written
+    #[serde(default = "default_signal_batch_ms")]
+    pub signal_batch_ms: u64,
 }
 
 fn default_pg_pool_size() -> usize {
     5
 }
 
+fn default_pg_connect_retries() -> u32 {
+    5
+}
+
+fn default_pg_retry_delay_secs() -> u64 {
+    2
+}
+
+fn default_signal_batch_ms() -> u64 {
+    200
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct NostrConfig {
     /// Platform nostr nsec (hex or bech32) used to decrypt inbound and encrypt outbound
+    #[serde(default)]
     pub secret_key: String,
+    /// File holding the nsec, read at startup instead of the inline `secret_key`
+    #[serde(default)]
+    pub secret_key_file: Option<PathBuf>,
+    /// OS keyring entry holding the nsec (needs the `keyring` cargo feature)
+    #[serde(default)]
+    pub secret_key_keyring: Option<KeyringEntry>,
+    /// Publish encrypted copies of signals to followers over nostr; when false fanout is WebSocket-only
+    #[serde(default = "default_publish_to_followers")]
+    pub publish_to_followers: bool,
+    /// Follower publishes in flight at once for a single signal
+    #[serde(default = "default_publish_concurrency")]
+    pub publish_concurrency: usize,
+    /// Most follower inbox relays connected at once; followers whose relays do not fit get the
+    /// platform relays, and zero sends every follower there
+    #[serde(default = "default_max_follower_relays")]
+    pub max_follower_relays: usize,
+    /// Consecutive signals that reach none of a follower's keys before publishing to it is
+    /// suspended; zero disables suspension
+    #[serde(default = "default_suspend_after_failures")]
+    pub suspend_after_failures: u32,
+    /// How long a suspended follower is skipped before publishing is tried again
+    #[serde(default = "default_suspend_cooldown_secs")]
+    pub suspend_cooldown_secs: u64,
+    /// Previous platform keys (nsec or hex), most recent first, still tried for inbound events
+    /// the current key cannot decrypt
+    #[serde(default)]
+    pub retired_secret_keys: Vec<String>,
+    /// Most retired keys kept; older entries beyond this are ignored
+    #[serde(default = "default_max_retired_keys")]
+    pub max_retired_keys: usize,
+}
+
+/// Service and user naming a secret in the OS keyring
+#[derive(Debug, Clone, Deserialize)]
+pub struct KeyringEntry {
+    pub service: String,
+    pub user: String,
+}
+
+impl NostrConfig {
+    /// The configured nsec from whichever single source is set: inline, file or keyring.
+    /// Returns None when none is set; errors when more than one is.
+    pub fn resolve_secret_key(&self) -> Result<Option<String>> {
+        let inline = !self.secret_key.trim().is_empty();
+        let sources = [
+            inline,
+            self.secret_key_file.is_some(),
+            self.secret_key_keyring.is_some(),
+        ];
+        if sources.iter().filter(|set| **set).count() > 1 {
+            bail!("nostr: set only one of secret_key, secret_key_file and secret_key_keyring");
+        }
+
+        if inline {
+            return Ok(Some(self.secret_key.trim().to_string()));
+        }
+        if let Some(path) = &self.secret_key_file {
+            let secret = fs::read_to_string(path).with_context(|| {
+                format!("Failed to read nostr.secret_key_file {}", path.display())
+            })?;
+            let secret = secret.trim();
+            if secret.is_empty() {
+                bail!("nostr.secret_key_file {} is empty", path.display());
+            }
+            return Ok(Some(secret.to_string()));
+        }
+        if let Some(entry) = &self.secret_key_keyring {
+            return read_keyring(entry).map(Some);
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(feature = "keyring")]
+fn read_keyring(entry: &KeyringEntry) -> Result<String> {
+    let secret = keyring::Entry::new(&entry.service, &entry.user)
+        .and_then(|e| e.get_password())
+        .with_context(|| {
+            format!(
+                "Failed to read nostr key from keyring {}/{}",
+                entry.service, entry.user
+            )
+        })?;
+    Ok(secret.trim().to_string())
+}
+
+#[cfg(not(feature = "keyring"))]
+fn read_keyring(entry: &KeyringEntry) -> Result<String> {
+    bail!(
+        "nostr.secret_key_keyring ({}/{}) is set but the relayer was built without the `keyring` feature",
+        entry.service,
+        entry.user
+    )
+}
+
+fn default_publish_to_followers() -> bool {
+    true
+}
+
+fn default_suspend_after_failures() -> u32 {
+    10
+}
+
+fn default_suspend_cooldown_secs() -> u64 {
+    3600
+}
+
+fn default_max_retired_keys() -> usize {
+    4
+}
+
+fn default_publish_concurrency() -> usize {
+    16
+}
+
+fn default_max_follower_relays() -> usize {
+    100
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -77,6 +528,61 @@ pub struct SettlementConfig {
     pub token: Option<String>,
     #[serde(default)]
     pub credit: Option<SettlementCreditConfig>,
+    /// Explorer HTTP connect timeout
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// Explorer HTTP total request timeout (also used as the read timeout)
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+    /// How long idle explorer connections are kept for reuse
+    #[serde(default = "default_pool_idle_timeout_secs")]
+    pub pool_idle_timeout_secs: u64,
+    /// Keep retrying a trade while the explorer answers 5xx/429/408 for this long before marking
+    /// it failed; zero fails it on the first such answer
+    #[serde(default = "default_unavailable_grace_secs")]
+    pub unavailable_grace_secs: u64,
+    /// Optional prefix before the tx hash digits (Hyperliquid/EVM: `0x`)
+    #[serde(default = "default_tx_hash_prefix")]
+    pub tx_hash_prefix: String,
+    /// Hex digits in a well-formed tx hash; malformed hashes fail without an explorer request. Zero disables
+    #[serde(default = "default_tx_hash_hex_len")]
+    pub tx_hash_hex_len: usize,
+    /// Confirmations the explorer must report before a trade is confirmed, for chains whose
+    /// answers carry a `confirmations` count. Zero confirms on the first 200
+    #[serde(default)]
+    pub min_confirmations: u64,
+    /// Require `/api/trades/record` bodies to carry an EIP-712 signature from the bot's eth address
+    #[serde(default)]
+    pub require_trade_signature: bool,
+    /// `chainId` of the EIP-712 domain trade records are signed under
+    #[serde(default = "default_trade_signature_chain_id")]
+    pub trade_signature_chain_id: u64,
+    /// Venue endpoint queried as `{pnl_lookup_base}/{tx_hash|oid}` for realized PnL; empty disables the backfill
+    #[serde(default)]
+    pub pnl_lookup_base: String,
+    /// Only backfill PnL for confirmed trades created within this many seconds
+    #[serde(default = "default_pnl_backfill_window_secs")]
+    pub pnl_backfill_window_secs: u64,
+}
+
+fn default_pnl_backfill_window_secs() -> u64 {
+    86_400
+}
+
+fn default_trade_signature_chain_id() -> u64 {
+    999
+}
+
+fn default_tx_hash_prefix() -> String {
+    "0x".to_string()
+}
+
+fn default_tx_hash_hex_len() -> usize {
+    64
+}
+
+fn default_unavailable_grace_secs() -> u64 {
+    3600
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -93,6 +599,18 @@ pub struct SettlementCreditConfig {
     pub test_multiplier: f64,
     #[serde(default = "default_credit_enable")]
     pub enable: bool,
+    /// Decimal places each award is rounded to before it is added to a balance
+    #[serde(default = "default_credit_precision")]
+    pub precision: u32,
+    /// Unit label reported alongside balances by `/api/credits`
+    #[serde(default = "default_credit_currency")]
+    pub currency: String,
+    /// Price per unit by symbol, used for notional when a trade was recorded with price 0
+    #[serde(default)]
+    pub reference_prices: HashMap<String, f64>,
+    /// Largest credit a single trade can earn; bigger awards are clamped to it. Zero disables the cap
+    #[serde(default)]
+    pub max_award: f64,
 }
 
 fn default_explorer_base() -> String {
@@ -107,6 +625,18 @@ fn default_batch_limit() -> i64 {
     50
 }
 
+fn default_connect_timeout_secs() -> u64 {
+    5
+}
+
+fn default_request_timeout_secs() -> u64 {
+    15
+}
+
+fn default_pool_idle_timeout_secs() -> u64 {
+    90
+}
+
 fn default_leader_rate() -> f64 {
     0.002
 }
@@ -131,16 +661,106 @@ fn default_credit_enable() -> bool {
     true
 }
 
+fn default_credit_precision() -> u32 {
+    6
+}
+
+fn default_credit_currency() -> String {
+    "credits".to_string()
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct SubscriptionsConfig {
     #[serde(default = "default_subscription_daily_limit")]
     pub daily_limit: u64,
+    /// Maximum followers per bot; zero disables the cap
+    #[serde(default)]
+    pub max_followers_per_bot: u64,
+    /// Seconds the previous secret keeps receiving fanout after a rotation
+    #[serde(default = "default_secret_overlap_secs")]
+    pub secret_overlap_secs: u64,
+    /// Seconds a bot's follower list is cached for fanout; zero queries Postgres per event
+    #[serde(default = "default_follower_cache_secs")]
+    pub follower_cache_secs: u64,
+}
+
+impl Default for SubscriptionsConfig {
+    fn default() -> Self {
+        Self {
+            daily_limit: default_subscription_daily_limit(),
+            max_followers_per_bot: 0,
+            secret_overlap_secs: default_secret_overlap_secs(),
+            follower_cache_secs: default_follower_cache_secs(),
+        }
+    }
 }
 
 fn default_subscription_daily_limit() -> u64 {
     1000
 }
 
+fn default_secret_overlap_secs() -> u64 {
+    3600
+}
+
+fn default_follower_cache_secs() -> u64 {
+    30
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PresenceConfig {
+    /// How often bot heartbeat staleness is rescanned
+    #[serde(default = "default_presence_scan_secs")]
+    pub scan_secs: u64,
+    /// A bot without a heartbeat for this long is considered offline
+    #[serde(default = "default_offline_after_secs")]
+    pub offline_after_secs: u64,
+    /// Optional URL receiving a POST on each online/offline transition
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            scan_secs: default_presence_scan_secs(),
+            offline_after_secs: default_offline_after_secs(),
+            webhook_url: None,
+        }
+    }
+}
+
+fn default_presence_scan_secs() -> u64 {
+    60
+}
+
+fn default_offline_after_secs() -> u64 {
+    // last_seen_at is only refreshed every 15 minutes per bot
+    30 * 60
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoordinationConfig {
+    /// Elect a leader via a Postgres advisory lock; only the leader runs settlement and presence scans
+    #[serde(default)]
+    pub enable: bool,
+    /// Advisory lock key shared by all instances of one deployment
+    #[serde(default = "default_coordination_lock_key")]
+    pub lock_key: i64,
+    /// How often followers retry the lock and the leader checks its lock connection
+    #[serde(default = "default_coordination_check_secs")]
+    pub check_secs: u64,
+}
+
+fn default_coordination_lock_key() -> i64 {
+    // "moltrade" packed into ASCII bytes
+    0x6d6f_6c74_7261_6465
+}
+
+fn default_coordination_check_secs() -> u64 {
+    10
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct AppConfig {
     pub relay: RelayConfig,
@@ -149,6 +769,8 @@ pub struct AppConfig {
     #[serde(default)]
     pub filters: FilterConfig,
     #[serde(default)]
+    pub kinds: KindsConfig,
+    #[serde(default)]
     pub postgres: Option<PostgresConfig>,
     #[serde(default)]
     pub nostr: Option<NostrConfig>,
@@ -156,6 +778,10 @@ pub struct AppConfig {
     pub settlement: Option<SettlementConfig>,
     #[serde(default)]
     pub subscriptions: Option<SubscriptionsConfig>,
+    #[serde(default)]
+    pub presence: Option<PresenceConfig>,
+    #[serde(default)]
+    pub coordination: Option<CoordinationConfig>,
     pub monitoring: MonitoringConfig,
 }
 
@@ -171,3 +797,67 @@ impl AppConfig {
         Ok(cfg)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nostr(toml: &str) -> NostrConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn secret_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "moltrade-config-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn secret_key_comes_from_the_single_configured_source() {
+        assert_eq!(nostr("").resolve_secret_key().unwrap(), None);
+        assert_eq!(
+            nostr(r#"secret_key = " nsec1inline ""#)
+                .resolve_secret_key()
+                .unwrap()
+                .as_deref(),
+            Some("nsec1inline")
+        );
+
+        let path = secret_file("nsec", "nsec1fromfile\n");
+        let cfg = nostr(&format!(
+            "secret_key_file = {:?}",
+            path.display().to_string()
+        ));
+        assert_eq!(
+            cfg.resolve_secret_key().unwrap().as_deref(),
+            Some("nsec1fromfile")
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn unusable_secret_key_sources_fail() {
+        let path = secret_file("both", "nsec1fromfile");
+        let both = nostr(&format!(
+            "secret_key = \"nsec1inline\"\nsecret_key_file = {:?}",
+            path.display().to_string()
+        ));
+        let err = both.resolve_secret_key().unwrap_err().to_string();
+        assert!(err.contains("set only one of"), "{}", err);
+        fs::remove_file(path).unwrap();
+
+        let path = secret_file("empty", " \n");
+        let empty = nostr(&format!(
+            "secret_key_file = {:?}",
+            path.display().to_string()
+        ));
+        assert!(empty.resolve_secret_key().is_err());
+        fs::remove_file(&path).unwrap();
+        // A missing file is an error, never a fallback to a generated key
+        assert!(empty.resolve_secret_key().is_err());
+    }
+}
@@ -2,11 +2,17 @@ use crate::storage::{
     bloom_filter::BloomFilter, memory_cache::MemoryCache, rocksdb_store::RocksDBStore,
 };
 // use anyhow::Result;
-use crate::api::metrics::Metrics;
 use dashmap::DashSet;
 use nostr_sdk::{Event, EventId};
+use prometheus::Gauge;
 use std::sync::Arc;
-use tracing::{debug, trace};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, trace, warn};
+
+/// Ids warmed between deadline checks and progress updates
+const WARM_CHUNK: usize = 10_000;
+/// Minimum spacing of warm progress log lines
+const WARM_LOG_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Multi-layer deduplication engine
 /// Layer 1: Bloom filter (fast, in-memory, may have false positives)
@@ -18,7 +24,6 @@ pub struct DeduplicationEngine {
     lru_cache: Arc<MemoryCache>,
     rocksdb: Arc<RocksDBStore>,
     hot_set: Arc<DashSet<String>>,
-    metrics: Option<Arc<Metrics>>,
 }
 
 impl DeduplicationEngine {
@@ -29,7 +34,6 @@ impl DeduplicationEngine {
             lru_cache: Arc::new(MemoryCache::new()),
             rocksdb,
             hot_set: Arc::new(DashSet::new()),
-            metrics: None,
         }
     }
 
@@ -38,47 +42,108 @@ impl DeduplicationEngine {
         rocksdb: Arc<RocksDBStore>,
         hot_set_size: usize,
         bloom_capacity: usize,
+        bloom_fp_rate: f64,
         lru_size: usize,
     ) -> Self {
         Self {
-            bloom: Arc::new(BloomFilter::with_capacity(bloom_capacity, 0.01)),
+            bloom: Arc::new(BloomFilter::with_capacity(bloom_capacity, bloom_fp_rate)),
             lru_cache: Arc::new(MemoryCache::with_capacity(lru_size)),
             rocksdb,
             hot_set: Arc::new(DashSet::with_capacity(hot_set_size)),
-            metrics: None,
         }
     }
 
-    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
-        self.metrics = Some(metrics);
-        self
-    }
-
     /// Warm in-memory structures from RocksDB successful-forward index.
-    /// Loads up to `limit` most recent successfully forwarded events into bloom, hot_set and LRU.
-    pub async fn warm_from_db(&self, limit: usize) {
+    /// Loads up to `limit` most recent successfully forwarded events into bloom, hot_set and LRU,
+    /// newest first. Whatever is left when `deadline` passes is warmed on a background task so
+    /// startup can continue; a zero deadline warms everything before returning. `remaining`
+    /// tracks how many ids are still to be warmed
+    pub async fn warm_from_db(
+        self: &Arc<Self>,
+        limit: usize,
+        deadline: Duration,
+        remaining: Option<Gauge>,
+    ) {
         if limit == 0 {
             return;
         }
+        let started = Instant::now();
         let ids = self.rocksdb.load_recent_success_ids(limit).await;
-        for id in &ids {
-            match EventId::from_hex(&id) {
+        let total = ids.len();
+        let set_remaining = move |done: usize| {
+            if let Some(g) = &remaining {
+                g.set((total - done) as f64);
+            }
+        };
+        set_remaining(0);
+
+        let mut done = 0;
+        let mut last_log = Instant::now();
+        while done < total && (deadline.is_zero() || started.elapsed() < deadline) {
+            let end = (done + WARM_CHUNK).min(total);
+            self.warm_ids(&ids[done..end]).await;
+            done = end;
+            set_remaining(done);
+            if last_log.elapsed() >= WARM_LOG_INTERVAL {
+                info!("Dedupe warm progress: {}/{} ids from RocksDB", done, total);
+                last_log = Instant::now();
+            }
+        }
+
+        if done == total {
+            info!(
+                "Deduplication engine warmed with {} IDs from RocksDB in {:?}",
+                total,
+                started.elapsed()
+            );
+            return;
+        }
+
+        warn!(
+            "Dedupe warm deadline {:?} reached after {}/{} ids; warming the rest in the background",
+            deadline, done, total
+        );
+        let engine = self.clone();
+        tokio::spawn(async move {
+            let mut warmed = done;
+            for chunk in ids[done..].chunks(WARM_CHUNK) {
+                engine.warm_ids(chunk).await;
+                warmed += chunk.len();
+                set_remaining(warmed);
+            }
+            info!(
+                "Background dedupe warm finished: {} IDs from RocksDB in {:?}",
+                total,
+                started.elapsed()
+            );
+        });
+    }
+
+    /// Seed bloom, hot_set and LRU with already-processed event ids from an external `source`
+    pub async fn warm_from_ids(&self, ids: &[String], source: &str) {
+        self.warm_ids(ids).await;
+        tracing::info!(
+            "Deduplication engine warmed with {} IDs from {}",
+            ids.len(),
+            source
+        );
+    }
+
+    async fn warm_ids(&self, ids: &[String]) {
+        for id in ids {
+            match EventId::from_hex(id) {
                 Ok(event_id) => {
                     // Best-effort: insert into bloom, lru and hot_set
                     self.bloom.insert(event_id.as_bytes()).await;
                 }
                 Err(err) => {
-                    tracing::warn!("Failed to parse event id {} from RocksDB: {}", id, err);
+                    tracing::warn!("Failed to parse warm event id {}: {}", id, err);
                     // continue best-effort using the string forms for caches
                 }
             }
-            self.lru_cache.put(id.clone()).await;
+            self.remember(id.clone()).await;
             self.hot_set.insert(id.to_string());
         }
-        tracing::info!(
-            "Deduplication engine warmed with {} IDs from RocksDB",
-            ids.len()
-        );
     }
 
     /// Check if an event is a duplicate
@@ -99,6 +164,7 @@ impl DeduplicationEngine {
         } else {
             // Bloom filter says it doesn't exist, definitely new
             self.bloom.insert(event.id.as_bytes()).await;
+            self.remember(event_id_hex.clone()).await;
             self.hot_set.insert(event_id_hex.clone());
             debug!("New event {} added to bloom filter", event_id_hex);
             return false;
@@ -108,21 +174,15 @@ impl DeduplicationEngine {
         if self.lru_cache.contains(&event_id_hex).await {
             trace!("Event {} found in LRU cache (duplicate)", event_id_hex);
             self.hot_set.insert(event_id_hex);
-            if let Some(m) = &self.metrics {
-                m.duplicates_filtered.inc();
-            }
             return true;
         }
 
         // Layer 3: RocksDB check (persistent storage, exact match)
         if self.rocksdb.exists(&event_id_hex).await {
             // Found in persistent storage, add to cache layers
-            self.lru_cache.put(event_id_hex.clone()).await;
+            self.remember(event_id_hex.clone()).await;
             self.hot_set.insert(event_id_hex.clone());
             trace!("Event {} found in RocksDB (duplicate)", event_id_hex);
-            if let Some(m) = &self.metrics {
-                m.duplicates_filtered.inc();
-            }
             return true;
         }
 
@@ -135,7 +195,7 @@ impl DeduplicationEngine {
         }
 
         // Store in cache layers
-        self.lru_cache.put(event_id_hex.clone()).await;
+        self.remember(event_id_hex.clone()).await;
         self.hot_set.insert(event_id_hex);
 
         // Limit hot set size to prevent unbounded growth
@@ -156,10 +216,33 @@ impl DeduplicationEngine {
         false
     }
 
+    /// Insert into the LRU, first persisting whichever id it would evict so that id is still
+    /// recognized as a duplicate once it is only in RocksDB
+    async fn remember(&self, event_id: String) {
+        if let Some(evicted) = self.lru_cache.eviction_candidate(&event_id).await {
+            let persisted = self.rocksdb.exists(&evicted).await
+                || match self.rocksdb.mark_seen(&evicted).await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        tracing::error!(
+                            "Failed to persist evicted id {} to RocksDB: {}",
+                            evicted,
+                            e
+                        );
+                        false
+                    }
+                };
+            trace!("Evicting {} from LRU (persisted={})", evicted, persisted);
+        }
+        self.lru_cache.put(event_id).await;
+    }
+
     /// Get statistics about the deduplication engine
     pub async fn get_stats(&self) -> DedupeStats {
         DedupeStats {
             bloom_filter_size: 0, // Bloom filter doesn't expose size
+            bloom_bits: self.bloom.num_bits(),
+            bloom_hashes: self.bloom.num_hashes(),
             lru_cache_size: self.lru_cache.len().await,
             hot_set_size: self.hot_set.len(),
             rocksdb_approximate_count: self.rocksdb.approximate_count().await,
@@ -171,7 +254,108 @@ impl DeduplicationEngine {
 #[derive(Debug, Clone)]
 pub struct DedupeStats {
     pub bloom_filter_size: usize,
+    pub bloom_bits: usize,
+    pub bloom_hashes: u32,
     pub lru_cache_size: usize,
     pub hot_set_size: usize,
     pub rocksdb_approximate_count: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::subscription::{SignalInsert, SubscriptionService};
+    use nostr_sdk::{EventBuilder, Keys, Kind};
+
+    #[tokio::test]
+    async fn ids_evicted_from_lru_are_persisted() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(RocksDBStore::new(dir.path()).unwrap());
+        let engine = DeduplicationEngine::new_with_params(store.clone(), 100, 1_000, 0.01, 2);
+        for id in ["a", "b", "c"] {
+            engine.remember(id.to_string()).await;
+        }
+        assert!(!engine.lru_cache.contains("a").await);
+        assert!(store.exists("a").await);
+        assert!(!store.exists("c").await);
+    }
+
+    #[tokio::test]
+    async fn warm_past_its_deadline_finishes_in_the_background() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(RocksDBStore::new(dir.path()).unwrap());
+        let ids: Vec<String> = (0..3)
+            .map(|i| {
+                EventBuilder::new(Kind::Custom(30931), format!("forwarded {}", i))
+                    .sign_with_keys(&Keys::generate())
+                    .unwrap()
+                    .id
+                    .to_hex()
+            })
+            .collect();
+        for id in &ids {
+            store.mark_forward_success(id).await.unwrap();
+        }
+        let warmed =
+            |engine: &DeduplicationEngine| ids.iter().all(|id| engine.hot_set.contains(id));
+
+        let engine = Arc::new(DeduplicationEngine::new_with_params(
+            store.clone(),
+            100,
+            1_000,
+            0.01,
+            100,
+        ));
+        let remaining = Gauge::new("warm_remaining", "ids left to warm").unwrap();
+        engine
+            .warm_from_db(10, Duration::from_nanos(1), Some(remaining.clone()))
+            .await;
+        // Nothing fit in the deadline, and the background task has not had a turn yet
+        assert_eq!(remaining.get(), 3.0);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while remaining.get() > 0.0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("background warm did not finish");
+        assert!(warmed(&engine));
+
+        // A zero deadline warms everything before returning
+        let engine = Arc::new(DeduplicationEngine::new_with_params(
+            store, 100, 1_000, 0.01, 100,
+        ));
+        let remaining = Gauge::new("warm_remaining", "ids left to warm").unwrap();
+        engine
+            .warm_from_db(10, Duration::ZERO, Some(remaining.clone()))
+            .await;
+        assert_eq!(remaining.get(), 0.0);
+        assert!(warmed(&engine));
+    }
+
+    #[tokio::test]
+    async fn persisted_signal_is_a_duplicate_after_warming_from_postgres() {
+        let Some(subs) = SubscriptionService::for_test("dedupe_warm_signals").await else {
+            return;
+        };
+        let event = |content: &str| {
+            EventBuilder::new(Kind::Custom(30931), content)
+                .sign_with_keys(&Keys::generate())
+                .unwrap()
+        };
+        let seen = event("seen");
+        subs.record_signal(SignalInsert::for_test(&seen.id.to_hex()))
+            .await
+            .unwrap();
+
+        // A wiped RocksDB: nothing but the Postgres signals says `seen` was processed
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(RocksDBStore::new(dir.path()).unwrap());
+        let engine = DeduplicationEngine::new_with_params(store, 100, 1_000, 0.01, 100);
+        let ids = subs.recent_signal_event_ids(3600, 100).await.unwrap();
+        engine.warm_from_ids(&ids, "Postgres signals").await;
+
+        assert!(engine.is_duplicate(&seen).await);
+        assert!(!engine.is_duplicate(&event("new")).await);
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::{Context, Result, anyhow};
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use secp256k1::{Message, Secp256k1};
+use sha3::{Digest, Keccak256};
+
+/// Keccak-256 digest as used throughout Ethereum
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+/// EIP-191 `personal_sign` digest of an arbitrary message
+pub fn personal_message_hash(message: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(format!("\x19Ethereum Signed Message:\n{}", message.len()).as_bytes());
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+/// Recover the `0x`-prefixed lowercase eth address that produced a 65-byte `r || s || v` signature
+pub fn recover_address(digest: &[u8; 32], signature: &str) -> Result<String> {
+    let raw = hex::decode(signature.trim().trim_start_matches("0x"))
+        .context("Signature is not valid hex")?;
+    if raw.len() != 65 {
+        return Err(anyhow!("Signature must be 65 bytes, got {}", raw.len()));
+    }
+
+    // Accept both raw (0/1) and legacy Ethereum (27/28) recovery ids
+    let v = match raw[64] {
+        v @ 0..=1 => v,
+        v @ 27..=28 => v - 27,
+        v => return Err(anyhow!("Unsupported signature recovery id {}", v)),
+    };
+    let rec_id = RecoveryId::from_i32(v as i32).context("Invalid recovery id")?;
+    let sig = RecoverableSignature::from_compact(&raw[..64], rec_id)
+        .context("Invalid compact signature")?;
+
+    let secp = Secp256k1::verification_only();
+    let pubkey = secp
+        .recover_ecdsa(&Message::from_digest(*digest), &sig)
+        .context("Failed to recover signer")?;
+
+    // Address is the last 20 bytes of keccak(uncompressed pubkey without the 0x04 prefix)
+    let hash = keccak256(&pubkey.serialize_uncompressed()[1..]);
+    Ok(format!("0x{}", hex::encode(&hash[12..])))
+}
+
+/// Check a `personal_sign` signature over `message` against an expected eth address
+pub fn verify_personal_signature(eth_address: &str, message: &str, signature: &str) -> bool {
+    match recover_address(&personal_message_hash(message.as_bytes()), signature) {
+        Ok(recovered) => recovered.eq_ignore_ascii_case(eth_address),
+        Err(_) => false,
+    }
+}
+
+/// EIP-712 domain separator for `EIP712Domain(string name,string version,uint256 chainId)`
+pub fn eip712_domain_separator(name: &str, version: &str, chain_id: u64) -> [u8; 32] {
+    let mut chain = [0u8; 32];
+    chain[24..].copy_from_slice(&chain_id.to_be_bytes());
+    let mut data = Vec::with_capacity(32 * 4);
+    data.extend_from_slice(&keccak256(
+        b"EIP712Domain(string name,string version,uint256 chainId)",
+    ));
+    data.extend_from_slice(&keccak256(name.as_bytes()));
+    data.extend_from_slice(&keccak256(version.as_bytes()));
+    data.extend_from_slice(&chain);
+    keccak256(&data)
+}
+
+/// EIP-712 `hashStruct` of a struct whose members are all `string`, given its type string
+/// (e.g. `Mail(string from,string body)`) and member values in declaration order
+pub fn eip712_string_struct_hash(type_string: &str, values: &[&str]) -> [u8; 32] {
+    let mut data = Vec::with_capacity(32 * (values.len() + 1));
+    data.extend_from_slice(&keccak256(type_string.as_bytes()));
+    for value in values {
+        data.extend_from_slice(&keccak256(value.as_bytes()));
+    }
+    keccak256(&data)
+}
+
+/// Final EIP-712 digest signed by `eth_signTypedData_v4`
+pub fn eip712_digest(domain_separator: &[u8; 32], struct_hash: &[u8; 32]) -> [u8; 32] {
+    let mut data = Vec::with_capacity(2 + 64);
+    data.extend_from_slice(b"\x19\x01");
+    data.extend_from_slice(domain_separator);
+    data.extend_from_slice(struct_hash);
+    keccak256(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::SecretKey;
+
+    // web3.js `eth.accounts` documentation key and its address
+    const KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const ADDRESS: &str = "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+
+    fn sign(digest: &[u8; 32]) -> String {
+        let secp = Secp256k1::new();
+        let key = SecretKey::from_slice(&hex::decode(KEY).unwrap()).unwrap();
+        let (rec_id, compact) = secp
+            .sign_ecdsa_recoverable(&Message::from_digest(*digest), &key)
+            .serialize_compact();
+        let mut raw = compact.to_vec();
+        raw.push(rec_id.to_i32() as u8 + 27);
+        format!("0x{}", hex::encode(raw))
+    }
+
+    #[test]
+    fn keccak_of_empty_input() {
+        assert_eq!(
+            hex::encode(keccak256(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+    }
+
+    #[test]
+    fn recovers_known_address() {
+        let digest = personal_message_hash(b"register");
+        assert_eq!(recover_address(&digest, &sign(&digest)).unwrap(), ADDRESS);
+    }
+
+    #[test]
+    fn personal_signature_checks_signer_and_message() {
+        let signature = sign(&personal_message_hash(b"register"));
+        assert!(verify_personal_signature(
+            &ADDRESS.to_uppercase().replace("0X", "0x"),
+            "register",
+            &signature
+        ));
+        assert!(!verify_personal_signature(ADDRESS, "tampered", &signature));
+        assert!(!verify_personal_signature(
+            "0x0000000000000000000000000000000000000000",
+            "register",
+            &signature
+        ));
+    }
+
+    #[test]
+    fn accepts_raw_recovery_ids_and_rejects_malformed() {
+        let digest = personal_message_hash(b"register");
+        let legacy = sign(&digest);
+        let mut raw = hex::decode(&legacy[2..]).unwrap();
+        raw[64] -= 27;
+        assert_eq!(
+            recover_address(&digest, &hex::encode(&raw)).unwrap(),
+            ADDRESS
+        );
+        raw[64] = 5;
+        assert!(recover_address(&digest, &hex::encode(&raw)).is_err());
+        assert!(recover_address(&digest, "0x1234").is_err());
+        assert!(recover_address(&digest, "not hex").is_err());
+    }
+
+    #[test]
+    fn eip712_digest_recovers_signer() {
+        let domain = eip712_domain_separator("Moltrade", "1", 1);
+        let hash = eip712_string_struct_hash("Trade(string oid,string symbol)", &["o1", "BTC"]);
+        let digest = eip712_digest(&domain, &hash);
+        assert_eq!(recover_address(&digest, &sign(&digest)).unwrap(), ADDRESS);
+        // Any change to the domain changes what was signed
+        let other = eip712_digest(&eip712_domain_separator("Moltrade", "1", 2), &hash);
+        assert_ne!(recover_address(&other, &sign(&digest)).unwrap(), ADDRESS);
+    }
+}
@@ -1,34 +1,62 @@
 use anyhow::Result;
-use flume::{Receiver, Sender};
+use dashmap::DashMap;
+use flume::{Receiver, Sender, TrySendError};
 use nostr_sdk::Event;
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
+use crate::api::event_tail::EventTail;
 use crate::api::metrics::Metrics;
+use crate::api::rate_limit::TokenBucket;
+use crate::api::timeseries::EventTimeseries;
+use crate::config::{FutureTimestampPolicy, KindsConfig, LatencyMode, PauseMode, RequiredTag};
 use crate::core::dedupe_engine::DeduplicationEngine;
-use crate::core::subscription::{FanoutMessage, SignalInsert, SubscriptionService};
+use crate::core::eth_signature::verify_personal_signature;
+use crate::core::ingestion::{IngestionGate, PauseBuffer};
+use crate::core::payloads::{
+    AgentRegisterPayload, FieldMap, TradeSignalPayload, payload_from_tags,
+};
+use crate::core::relay_pool::AllowedKinds;
+use crate::core::subscription::{
+    FanoutEncryption, FanoutMessage, SignalInsert, SubscriptionRow, SubscriptionService,
+    TradeRowKey, TradeStatus,
+};
+use crate::storage::replay_buffer::ReplayBuffer;
 use chrono::{DateTime, TimeZone, Utc};
+use futures::StreamExt;
 use nostr_sdk::Kind;
-use nostr_sdk::nips::nip04;
+use nostr_sdk::nips::{nip04, nip44};
 use nostr_sdk::prelude::{Client, EventBuilder, Keys, PublicKey, Tag, Timestamp};
-use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::str::FromStr;
 
-const KIND_TRADE_SIGNAL: u16 = 30931;
-const KIND_COPYTRADE_INTENT: u16 = 30932;
-const KIND_HEARTBEAT: u16 = 30933;
-const KIND_EXECUTION_REPORT: u16 = 30934;
-const KIND_AGENT_REGISTER: u16 = 30935;
+/// How often idle per-sender state (rate-limit buckets, high-water marks) is pruned, off the
+/// per-event path
+const SENDER_STATE_PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+/// High-water marks older than this are dropped by the periodic prune
+const HIGH_WATER_RETAIN: Duration = Duration::from_secs(24 * 60 * 60);
+/// Log the first fanout drop and then every this many
+const FANOUT_DROP_LOG_EVERY: usize = 1000;
 const STALE_AFTER: Duration = Duration::from_secs(10 * 60);
+const SIGNAL_PERSIST_ATTEMPTS: u32 = 4;
+const SIGNAL_PERSIST_BACKOFF: Duration = Duration::from_millis(100);
 
 /// Wrapper for Event to enable sorting by timestamp
 #[derive(Clone)]
 struct EventWrapper {
     event: Event,
+    /// Ordering key: `created_at`, clamped to receive time when too far in the future
     timestamp: u64,
+    received_at: u64,
+    /// When the event entered the pending queue; bounds per-event flush latency
+    enqueued_at: Instant,
+    /// Estimated memory held while pending, see `estimated_bytes`
+    bytes: usize,
 }
 
 impl PartialEq for EventWrapper {
@@ -57,14 +85,66 @@ pub struct EventRouter {
     batch_size: usize,
     max_latency: Duration,
     downstream_tx: Sender<Event>,
-    allowed_kinds: Option<Vec<u16>>,
+    allowed_kinds: AllowedKinds,
     fanout_tx: Option<Sender<FanoutMessage>>,
+    /// Set once the fanout receiver is gone; later fanout sends are skipped
+    fanout_closed: Arc<AtomicBool>,
+    /// Payloads dropped on a full fanout channel, for rate-limiting the warning
+    fanout_drops: Arc<AtomicUsize>,
     subscription_service: Option<Arc<SubscriptionService>>,
     nostr_keys: Option<Keys>,
+    /// Previous platform keys, most recent first, tried when `nostr_keys` cannot decrypt
+    retired_keys: Vec<Keys>,
     nostr_client: Option<Arc<Client>>,
     pending_events: Arc<RwLock<Vec<EventWrapper>>>,
+    /// Sum of `bytes` over `pending_events`
+    pending_bytes: AtomicUsize,
+    max_pending_bytes: usize,
     heartbeat_seen: Option<Arc<RwLock<HashMap<String, Instant>>>>,
     metrics: Option<Arc<Metrics>>,
+    require_agent_signature: bool,
+    /// Agent-register events older than this are ignored; None disables the check
+    agent_register_max_age: Option<Duration>,
+    max_content_bytes: usize,
+    /// Per-pubkey (burst, per second) limit; None disables it
+    pubkey_limit: Option<(u32, f64)>,
+    /// Token bucket and last event time per sending pubkey
+    pubkey_buckets: DashMap<PublicKey, (TokenBucket, Instant)>,
+    required_tags: Vec<RequiredTag>,
+    strict_monotonic: bool,
+    /// Newest accepted created_at per pubkey, for `strict_monotonic`
+    high_water: DashMap<PublicKey, u64>,
+    future_skew: Duration,
+    future_policy: FutureTimestampPolicy,
+    stale_from_receive_time: bool,
+    tag_payloads: bool,
+    /// Vendor JSON keys accepted for trade payload fields
+    field_map: FieldMap,
+    log_payloads: bool,
+    dedupe_kinds: Option<Vec<u16>>,
+    dedupe_window: Option<Duration>,
+    replay_buffer: Option<ReplayBuffer>,
+    event_tail: Option<EventTail>,
+    kinds: KindsConfig,
+    latency_mode: LatencyMode,
+    persist_all_events: bool,
+    /// Hands signals to the `SignalBatcher` instead of inserting them one by one
+    signal_batch_tx: Option<Sender<SignalInsert>>,
+    publish_concurrency: usize,
+    /// Follower inbox relays in use -> whether this router added them to the publisher client
+    /// (and so removes them once no subscription lists them)
+    follower_relays: DashMap<String, bool>,
+    /// Most follower inbox relays connected at once; zero publishes everything to platform relays
+    max_follower_relays: usize,
+    /// Per-minute processed-event counts for `/api/metrics/timeseries`
+    timeseries: Option<EventTimeseries>,
+    /// Operator pause switch checked for every inbound event
+    ingestion: Option<IngestionGate>,
+    /// Consecutive failed deliveries that suspend nostr publishing to a follower; zero disables
+    suspend_after_failures: u32,
+    suspend_cooldown: Duration,
+    /// (bot, follower) -> consecutive deliveries that reached none of the follower's keys
+    delivery_failures: DashMap<(String, String), u32>,
 }
 
 impl EventRouter {
@@ -74,7 +154,7 @@ impl EventRouter {
         batch_size: usize,
         max_latency: Duration,
         downstream_tx: Sender<Event>,
-        allowed_kinds: Option<Vec<u16>>,
+        allowed_kinds: AllowedKinds,
         fanout_tx: Option<Sender<FanoutMessage>>,
         subscription_service: Option<Arc<SubscriptionService>>,
         nostr_keys: Option<Keys>,
@@ -91,12 +171,47 @@ impl EventRouter {
             downstream_tx,
             allowed_kinds,
             fanout_tx,
+            fanout_closed: Arc::new(AtomicBool::new(false)),
+            fanout_drops: Arc::new(AtomicUsize::new(0)),
             subscription_service,
             nostr_keys,
+            retired_keys: Vec::new(),
             nostr_client,
             pending_events: Arc::new(RwLock::new(Vec::new())),
+            pending_bytes: AtomicUsize::new(0),
+            max_pending_bytes: 0,
             heartbeat_seen,
             metrics: None,
+            require_agent_signature: false,
+            agent_register_max_age: None,
+            max_content_bytes: 0,
+            pubkey_limit: None,
+            pubkey_buckets: DashMap::new(),
+            required_tags: Vec::new(),
+            strict_monotonic: false,
+            high_water: DashMap::new(),
+            future_skew: Duration::from_secs(300),
+            future_policy: FutureTimestampPolicy::Clamp,
+            stale_from_receive_time: false,
+            tag_payloads: false,
+            field_map: FieldMap::default(),
+            log_payloads: false,
+            dedupe_kinds: None,
+            dedupe_window: None,
+            replay_buffer: None,
+            event_tail: None,
+            kinds: KindsConfig::default(),
+            latency_mode: LatencyMode::Batch,
+            persist_all_events: false,
+            signal_batch_tx: None,
+            publish_concurrency: 16,
+            follower_relays: DashMap::new(),
+            max_follower_relays: 100,
+            timeseries: None,
+            ingestion: None,
+            suspend_after_failures: 0,
+            suspend_cooldown: Duration::ZERO,
+            delivery_failures: DashMap::new(),
         }
     }
 
@@ -106,13 +221,296 @@ impl EventRouter {
         self
     }
 
+    /// Only accept agent registrations signed by the claimed eth address
+    pub fn with_agent_signature_required(mut self, required: bool) -> Self {
+        self.require_agent_signature = required;
+        self
+    }
+
+    /// Ignore agent-register events created more than `max_age` ago; zero disables the check
+    pub fn with_agent_register_max_age(mut self, max_age: Duration) -> Self {
+        self.agent_register_max_age = (!max_age.is_zero()).then_some(max_age);
+        self
+    }
+
+    /// Drop events whose content is larger than `max_bytes` before any decryption; zero disables
+    pub fn with_max_content_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_content_bytes = max_bytes;
+        self
+    }
+
+    /// Let each pubkey send `burst` events at once, then `per_sec`; zero for either disables the limit
+    pub fn with_pubkey_rate_limit(mut self, burst: u32, per_sec: f64) -> Self {
+        self.pubkey_limit = (burst > 0 && per_sec > 0.0).then_some((burst, per_sec));
+        self
+    }
+
+    /// Only route events carrying every one of these tags
+    pub fn with_required_tags(mut self, tags: Vec<RequiredTag>) -> Self {
+        self.required_tags = tags
+            .into_iter()
+            .filter(|t| !t.name.trim().is_empty())
+            .collect();
+        self
+    }
+
+    /// Drop events that are not strictly newer than the last one accepted from the same pubkey
+    pub fn with_strict_monotonic(mut self, enabled: bool) -> Self {
+        self.strict_monotonic = enabled;
+        self
+    }
+
+    /// How to treat events whose `created_at` is more than `skew` ahead of receive time
+    pub fn with_future_timestamp_policy(
+        mut self,
+        skew: Duration,
+        policy: FutureTimestampPolicy,
+    ) -> Self {
+        self.future_skew = skew;
+        self.future_policy = policy;
+        self
+    }
+
+    /// Judge staleness by when the relayer received an event instead of its claimed `created_at`
+    pub fn with_receive_time_staleness(mut self, enabled: bool) -> Self {
+        self.stale_from_receive_time = enabled;
+        self
+    }
+
+    /// Flush early once pending events are estimated to hold `max` bytes; zero disables the cap
+    pub fn with_max_pending_bytes(mut self, max: usize) -> Self {
+        self.max_pending_bytes = max;
+        self
+    }
+
+    /// Also decrypt inbound events with these previous platform keys, tried most recent first
+    pub fn with_retired_keys(mut self, keys: Vec<Keys>) -> Self {
+        self.retired_keys = keys;
+        self
+    }
+
+    /// Take the payload of empty-content events from their tags instead of decrypting
+    pub fn with_tag_payloads(mut self, enabled: bool) -> Self {
+        self.tag_payloads = enabled;
+        self
+    }
+
+    /// Read trade payload fields under the vendor keys in `field_map` as well
+    pub fn with_field_map(mut self, field_map: FieldMap) -> Self {
+        self.field_map = field_map;
+        self
+    }
+
+    /// Include decrypted payload text in debug logs instead of only its length and hash
+    pub fn with_log_payloads(mut self, enabled: bool) -> Self {
+        self.log_payloads = enabled;
+        self
+    }
+
+    /// Restrict id dedupe to `kinds`; events of other kinds are always processed. Empty dedupes every kind
+    pub fn with_dedupe_kinds(mut self, kinds: Vec<u16>) -> Self {
+        self.dedupe_kinds = Some(kinds).filter(|k| !k.is_empty());
+        self
+    }
+
+    /// Treat deduped events older than `window` (by created_at) as duplicates. The id layers
+    /// only remember so much, so this keeps old ids that were forgotten from being reprocessed. Zero disables
+    pub fn with_dedupe_window(mut self, window: Duration) -> Self {
+        self.dedupe_window = Some(window).filter(|w| !w.is_zero());
+        self
+    }
+
+    /// Record routed events so WebSocket clients can request a replay on connect
+    pub fn with_replay_buffer(mut self, buffer: ReplayBuffer) -> Self {
+        self.replay_buffer = Some(buffer);
+        self
+    }
+
+    /// Map copytrade roles (trade signal, heartbeat, ...) to the kinds this deployment uses
+    pub fn with_kinds(mut self, kinds: KindsConfig) -> Self {
+        self.kinds = kinds;
+        self
+    }
+
+    /// Choose whether `processing_latency` is observed per flushed batch or per routed event
+    pub fn with_latency_mode(mut self, mode: LatencyMode) -> Self {
+        self.latency_mode = mode;
+        self
+    }
+
+    /// Write an audit row to `raw_events` for every routed event (requires the subscription service)
+    pub fn with_persist_all_events(mut self, enabled: bool) -> Self {
+        self.persist_all_events = enabled;
+        self
+    }
+
+    /// Queue signal rows for a `SignalBatcher`; falls back to a direct insert when the queue is full
+    pub fn with_signal_batch(mut self, tx: Sender<SignalInsert>) -> Self {
+        self.signal_batch_tx = Some(tx);
+        self
+    }
+
+    /// Publish to at most `limit` follower keys at once when fanning out over nostr
+    pub fn with_publish_concurrency(mut self, limit: usize) -> Self {
+        self.publish_concurrency = limit.max(1);
+        self
+    }
+
+    /// Suspend nostr publishing to a follower after `after` consecutive deliveries reach none of
+    /// its keys, trying it again once `cooldown` has passed; zero `after` disables suspension
+    pub fn with_delivery_suspension(mut self, after: u32, cooldown: Duration) -> Self {
+        self.suspend_after_failures = after;
+        self.suspend_cooldown = cooldown;
+        self
+    }
+
+    /// Connect at most `max` follower inbox relays; followers beyond it get the platform relays
+    pub fn with_max_follower_relays(mut self, max: usize) -> Self {
+        self.max_follower_relays = max;
+        self
+    }
+
+    /// Hold or drop inbound events while `gate` is paused, per its mode
+    pub fn with_ingestion_gate(mut self, gate: IngestionGate) -> Self {
+        self.ingestion = Some(gate);
+        self
+    }
+
+    /// Count processed events per minute
+    pub fn with_timeseries(mut self, timeseries: EventTimeseries) -> Self {
+        self.timeseries = Some(timeseries);
+        self
+    }
+
+    /// Mirror sampled metadata of every inbound event, before filtering and dedupe
+    pub fn with_event_tail(mut self, tail: EventTail) -> Self {
+        self.event_tail = Some(tail);
+        self
+    }
+
+    async fn send_downstream(&self, event: Event) {
+        if let Some(buffer) = &self.replay_buffer {
+            buffer.push(event.clone()).await;
+        }
+        if let Err(e) = self.downstream_tx.send_async(event).await {
+            error!("Failed to send event to downstream: {}", e);
+        }
+    }
+
+    /// Take a token from the sender's bucket; false when it is over its rate
+    fn pubkey_allowed(&self, pubkey: &PublicKey) -> bool {
+        let Some((burst, per_sec)) = self.pubkey_limit else {
+            return true;
+        };
+        let now = Instant::now();
+        let mut entry = self
+            .pubkey_buckets
+            .entry(*pubkey)
+            .or_insert_with(|| (TokenBucket::new(burst, per_sec), now));
+        entry.1 = now;
+        entry.0.try_acquire()
+    }
+
+    /// Drop per-sender state nobody needs any more; runs every `SENDER_STATE_PRUNE_INTERVAL`
+    fn prune_sender_state(&self) {
+        if let Some((burst, per_sec)) = self.pubkey_limit {
+            // A bucket idle long enough to refill completely is the same as a fresh one
+            let refill = Duration::from_secs_f64(burst as f64 / per_sec);
+            let now = Instant::now();
+            self.pubkey_buckets
+                .retain(|_, (_, last)| now.duration_since(*last) < refill);
+        }
+        if self.strict_monotonic {
+            let cutoff = Timestamp::now()
+                .as_secs()
+                .saturating_sub(HIGH_WATER_RETAIN.as_secs());
+            prune_marks(&self.high_water, cutoff);
+        }
+    }
+
+    /// Raise the sender's high-water mark to `timestamp`; false when the event is not newer than it
+    fn advance_high_water(&self, event: &Event, timestamp: u64) -> bool {
+        !self.strict_monotonic || advance_mark(&self.high_water, event.pubkey, timestamp)
+    }
+
+    /// Whether the event carries every required tag (name, and value when one is given)
+    fn has_required_tags(&self, event: &Event) -> bool {
+        carries_required_tags(
+            &self.required_tags,
+            event.tags.iter().map(|tag| tag.as_slice()),
+        )
+    }
+
+    fn should_dedupe(&self, kind: u16) -> bool {
+        match &self.dedupe_kinds {
+            Some(kinds) => kinds.contains(&kind),
+            None => true,
+        }
+    }
+
+    /// The fanout sender, unless its receiver has been dropped
+    fn live_fanout_tx(&self) -> Option<&Sender<FanoutMessage>> {
+        let tx = self.fanout_tx.as_ref()?;
+        if self.fanout_closed.load(Ordering::Relaxed) {
+            return None;
+        }
+        if tx.is_disconnected() {
+            self.disable_fanout();
+            return None;
+        }
+        Some(tx)
+    }
+
+    /// Stop WebSocket fanout after its channel closed, warning only on the first call
+    fn disable_fanout(&self) {
+        if self.fanout_closed.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        warn!("Fanout channel closed; disabling WebSocket fanout until restart");
+        if let Some(m) = &self.metrics {
+            m.fanout_disabled.set(1.0);
+        }
+    }
+
+    /// Queue a fanout message without blocking; drops it when the channel is full
+    fn send_fanout(&self, fanout_tx: &Sender<FanoutMessage>, msg: FanoutMessage) {
+        match fanout_tx.try_send(msg) {
+            Ok(()) => {}
+            Err(TrySendError::Full(msg)) => {
+                // A full channel drops in bursts; `fanout_dropped_total` counts every one
+                let drops = self.fanout_drops.fetch_add(1, Ordering::Relaxed) + 1;
+                if drops == 1 || drops.is_multiple_of(FANOUT_DROP_LOG_EVERY) {
+                    warn!(
+                        "Fanout channel full, dropping payload for {} ({} dropped so far)",
+                        msg.target_pubkey, drops
+                    );
+                }
+                if let Some(m) = &self.metrics {
+                    m.fanout_dropped.inc();
+                }
+            }
+            Err(TrySendError::Disconnected(_)) => self.disable_fanout(),
+        }
+    }
+
     /// Process incoming event stream, deduplicate, and route to downstream
     pub async fn process_stream(self, input: Receiver<Event>) -> Result<()> {
-        let mut last_flush = Instant::now();
+        // Enqueue time of the oldest pending event; it must be flushed within max_latency
+        let mut oldest_pending: Option<Instant> = None;
+        // Events held while ingestion is paused in buffer mode, replayed on resume
+        let mut paused_buffer = PauseBuffer::new(
+            self.ingestion
+                .as_ref()
+                .map_or(0, IngestionGate::buffer_capacity),
+        );
+        let mut pause_rx = self.ingestion.as_ref().map(IngestionGate::subscribe);
+        let mut prune_tick = tokio::time::interval(SENDER_STATE_PRUNE_INTERVAL);
+        prune_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
         loop {
-            // Use timeout to periodically flush even if no new events arrive
-            let timeout = tokio::time::sleep(self.max_latency);
+            // Wake when the oldest pending event reaches max_latency, however full the batch is
+            let deadline = oldest_pending.unwrap_or_else(Instant::now) + self.max_latency;
+            let timeout = tokio::time::sleep_until(deadline.into());
             tokio::pin!(timeout);
 
             tokio::select! {
@@ -120,64 +518,220 @@ impl EventRouter {
                 result = input.recv_async() => {
                     match result {
                         Ok(event) => {
-                            // Kind filtering (drop events not in allowlist if configured)
-                            if let Some(allowed) = &self.allowed_kinds {
-                                if !allowed.contains(&event.kind.as_u16()) {
-                                    continue;
-                                }
-                            }
-                            // Deduplication check
-                            if !self.dedupe_engine.is_duplicate(&event).await {
-                                // Add to pending events (will be sorted before flushing)
-                                let timestamp = event.created_at.as_secs();
-                                let wrapper = EventWrapper {
-                                    event,
-                                    timestamp,
-                                };
-
-                                let mut pending = self.pending_events.write().await;
-                                pending.push(wrapper);
-                                if let Some(m) = &self.metrics {
-                                    m.events_in_queue.set(pending.len() as f64);
-                                }
-
-                                // If we have enough events, flush a batch
-                                if pending.len() >= self.batch_size {
-                                    drop(pending);
-                                    self.flush_batch().await?;
-                                    last_flush = Instant::now();
+                            if let Some(gate) = self.ingestion.as_ref().filter(|g| g.is_paused()) {
+                                if gate.mode() == PauseMode::Drop {
+                                    if let Some(m) = &self.metrics {
+                                        m.paused_events_dropped.inc();
+                                    }
+                                } else {
+                                    self.hold_paused(&mut paused_buffer, event);
                                 }
+                                continue;
                             }
+                            self.accept_event(event, &mut oldest_pending).await?;
                         }
                         Err(_) => {
+                            if !paused_buffer.is_empty() {
+                                warn!(
+                                    "Event stream closed while paused, discarding {} buffered events",
+                                    paused_buffer.len()
+                                );
+                            }
                             info!("Event stream closed, flushing remaining events");
                             self.flush_all().await?;
                             break;
                         }
                     }
                 }
-                // Timeout - flush if we have events and enough time has passed
-                _ = timeout => {
-                    let pending = self.pending_events.read().await;
-                    if !pending.is_empty() && last_flush.elapsed() >= self.max_latency {
-                        drop(pending);
-                        let start = Instant::now();
-                        self.flush_batch().await?;
+                // Pause state changed; replay what was held once ingestion resumes
+                Some(changed) = async {
+                    match pause_rx.as_mut() {
+                        Some(rx) => Some(rx.changed().await),
+                        None => None,
+                    }
+                } => {
+                    if changed.is_err() {
+                        pause_rx = None;
+                    } else if !self.ingestion.as_ref().is_some_and(IngestionGate::is_paused) {
+                        info!("Ingestion resumed, replaying {} buffered events", paused_buffer.len());
+                        let held: Vec<Event> = paused_buffer.drain().collect();
                         if let Some(m) = &self.metrics {
-                            let elapsed = start.elapsed().as_secs_f64();
-                            m.processing_latency.observe(elapsed);
+                            m.paused_buffer_events.set(0.0);
+                        }
+                        for event in held {
+                            self.accept_event(event, &mut oldest_pending).await?;
                         }
-                        last_flush = Instant::now();
                     }
                 }
+                _ = prune_tick.tick() => {
+                    self.prune_sender_state();
+                    self.prune_follower_relays().await;
+                }
+                // Timeout - flush until no pending event has waited max_latency
+                _ = timeout => {
+                    while oldest_pending.is_some_and(|t| t.elapsed() >= self.max_latency) {
+                        self.flush_batch().await?;
+                        oldest_pending = self.oldest_enqueued().await;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Filter, dedupe and enqueue one inbound event, flushing when the batch or byte cap is hit
+    async fn accept_event(&self, event: Event, oldest_pending: &mut Option<Instant>) -> Result<()> {
+        if let Some(tail) = &self.event_tail {
+            tail.publish(&event);
+        }
+        // Kind filtering (drop events not in allowlist if configured)
+        if !self.allowed_kinds.allows(event.kind.as_u16()) {
+            return Ok(());
+        }
+        if !self.has_required_tags(&event) {
+            debug!("Skip event {} without required tags", event.id.to_hex());
+            if let Some(m) = &self.metrics {
+                m.missing_tag_events.inc();
+            }
+            return Ok(());
+        }
+        if !self.pubkey_allowed(&event.pubkey) {
+            debug!(
+                "Rate limited event {} from {}",
+                event.id.to_hex(),
+                event.pubkey.to_hex()
+            );
+            if let Some(m) = &self.metrics {
+                m.rate_limited_events.inc();
+            }
+            return Ok(());
+        }
+        if self.max_content_bytes > 0 && event.content.len() > self.max_content_bytes {
+            warn!(
+                "Dropping oversize event {} ({} bytes > {})",
+                event.id.to_hex(),
+                event.content.len(),
+                self.max_content_bytes
+            );
+            if let Some(m) = &self.metrics {
+                m.oversize_events_dropped.inc();
+            }
+            return Ok(());
+        }
+        // Clients control created_at; never trust it further ahead than the allowed skew
+        let received_at = Timestamp::now().as_secs();
+        let mut timestamp = event.created_at.as_secs();
+        if timestamp > received_at + self.future_skew.as_secs() {
+            if let Some(m) = &self.metrics {
+                m.future_dated_events.inc();
+            }
+            match self.future_policy {
+                FutureTimestampPolicy::Drop => {
+                    warn!(
+                        "Dropping future-dated event {} (created_at={}, now={})",
+                        event.id.to_hex(),
+                        timestamp,
+                        received_at
+                    );
+                    return Ok(());
+                }
+                FutureTimestampPolicy::Clamp => timestamp = received_at,
+            }
+        }
+        // Deduplication check; kinds outside dedupe_kinds bypass it entirely.
+        // Events outside the window are skipped without touching the id layers
+        let duplicate = self.should_dedupe(event.kind.as_u16())
+            && (self.outside_dedupe_window(&event, timestamp, received_at)
+                || self.dedupe_engine.is_duplicate(&event).await);
+        if let Some(m) = self.metrics.as_ref().filter(|_| duplicate) {
+            m.record_duplicate(event.kind.as_u16());
+        }
+        if !duplicate && !self.advance_high_water(&event, timestamp) {
+            warn!(
+                "Dropping replayed event {} from {} (created_at={} not newer than last accepted)",
+                event.id.to_hex(),
+                event.pubkey.to_hex(),
+                timestamp
+            );
+            if let Some(m) = &self.metrics {
+                m.replayed_events.inc();
             }
+            return Ok(());
         }
+        if !duplicate {
+            // Add to pending events (will be sorted before flushing)
+            let enqueued_at = Instant::now();
+            let bytes = estimated_bytes(&event);
+            let wrapper = EventWrapper {
+                event,
+                timestamp,
+                received_at,
+                enqueued_at,
+                bytes,
+            };
 
+            let mut pending = self.pending_events.write().await;
+            pending.push(wrapper);
+            let pending_bytes = self.pending_bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+            if let Some(m) = &self.metrics {
+                m.events_in_queue.set(pending.len() as f64);
+                m.pending_events_bytes.set(pending_bytes as f64);
+            }
+            oldest_pending.get_or_insert(enqueued_at);
+
+            // If we have enough events, flush a batch
+            if pending.len() >= self.batch_size {
+                drop(pending);
+                self.flush_batch().await?;
+                *oldest_pending = self.oldest_enqueued().await;
+            } else if self.over_pending_bytes() {
+                drop(pending);
+                debug!(
+                    "Pending events over {} bytes, flushing early",
+                    self.max_pending_bytes
+                );
+                while self.over_pending_bytes() && oldest_pending.is_some() {
+                    self.flush_batch().await?;
+                    *oldest_pending = self.oldest_enqueued().await;
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Hold an event that arrived while paused in buffer mode, evicting the oldest when full
+    fn hold_paused(&self, buffer: &mut PauseBuffer<Event>, event: Event) {
+        let evicted = buffer.push(event);
+        if let Some(m) = &self.metrics {
+            if evicted {
+                m.paused_buffer_evicted.inc();
+            }
+            m.paused_buffer_events.set(buffer.len() as f64);
+        }
+    }
+
+    fn over_pending_bytes(&self) -> bool {
+        self.max_pending_bytes > 0
+            && self.pending_bytes.load(Ordering::Relaxed) >= self.max_pending_bytes
+    }
+
+    /// Account for events leaving the pending queue
+    fn release_pending_bytes(&self, bytes: usize) {
+        let remaining = self.pending_bytes.fetch_sub(bytes, Ordering::Relaxed) - bytes;
+        if let Some(m) = &self.metrics {
+            m.pending_events_bytes.set(remaining as f64);
+        }
+    }
+
+    async fn oldest_enqueued(&self) -> Option<Instant> {
+        let pending = self.pending_events.read().await;
+        pending.iter().map(|w| w.enqueued_at).min()
+    }
+
     /// Flush a batch of events sorted by timestamp
     async fn flush_batch(&self) -> Result<()> {
+        let start = Instant::now();
         let mut pending = self.pending_events.write().await;
         let batch_size = self.batch_size.min(pending.len());
 
@@ -189,40 +743,49 @@ impl EventRouter {
         pending.sort();
 
         // Take the oldest events (first batch_size events)
-        let batch: Vec<Event> = pending
-            .drain(0..batch_size)
-            .map(|wrapper| wrapper.event)
-            .collect();
+        let batch: Vec<EventWrapper> = pending.drain(0..batch_size).collect();
 
         drop(pending);
+        self.release_pending_bytes(batch.iter().map(|w| w.bytes).sum());
 
         // Send events to downstream in timestamp order
-        for event in batch {
-            if self.is_stale(&event) {
+        for wrapper in batch {
+            let age_secs = self.age_secs(&wrapper);
+            let enqueued_at = wrapper.enqueued_at;
+            let received_at = wrapper.received_at;
+            let event = wrapper.event;
+            if age_secs > STALE_AFTER.as_secs() {
                 debug!(
                     "Skip stale event id={} kind={} age_secs={}",
                     event.id.to_hex(),
                     event.kind.as_u16(),
-                    Timestamp::now()
-                        .as_secs()
-                        .saturating_sub(event.created_at.as_secs())
+                    age_secs
                 );
                 continue;
             }
             self.maybe_update_last_seen(&event).await;
+            self.maybe_persist_raw(&event, received_at).await;
             if let Err(e) = self.handle_copytrade_fanout(&event).await {
                 error!("Fanout processing failed: {}", e);
             }
-            if let Err(e) = self.downstream_tx.send_async(event).await {
-                error!("Failed to send event to downstream: {}", e);
+            self.send_downstream(event).await;
+            if let Some(timeseries) = &self.timeseries {
+                timeseries.record();
             }
             if let Some(m) = &self.metrics {
                 m.events_processed.inc();
+                if self.latency_mode == LatencyMode::Event {
+                    m.processing_latency
+                        .observe(enqueued_at.elapsed().as_secs_f64());
+                }
             }
         }
 
         debug!("Flushed batch of {} events", batch_size);
         if let Some(m) = &self.metrics {
+            if self.latency_mode == LatencyMode::Batch {
+                m.processing_latency.observe(start.elapsed().as_secs_f64());
+            }
             let remaining = self.pending_events.read().await.len();
             m.events_in_queue.set(remaining as f64);
         }
@@ -237,11 +800,20 @@ impl EventRouter {
         // Sort by timestamp before flushing
         pending.sort();
 
-        let events: Vec<Event> = pending.drain(..).map(|wrapper| wrapper.event).collect();
+        let mut bytes = 0;
+        let events: Vec<Event> = pending
+            .drain(..)
+            .map(|wrapper| {
+                bytes += wrapper.bytes;
+                wrapper.event
+            })
+            .collect();
+        self.release_pending_bytes(bytes);
 
         for event in events {
-            if let Err(e) = self.downstream_tx.send_async(event).await {
-                error!("Failed to send event to downstream: {}", e);
+            self.send_downstream(event).await;
+            if let Some(timeseries) = &self.timeseries {
+                timeseries.record();
             }
             if let Some(m) = &self.metrics {
                 m.events_processed.inc();
@@ -257,19 +829,36 @@ impl EventRouter {
 
     async fn handle_copytrade_fanout(&self, event: &Event) -> Result<()> {
         // Short-circuit only heartbeats: execution reports must be processed for DB writes
-        if event.kind.as_u16() == KIND_HEARTBEAT {
+        if event.kind.as_u16() == self.kinds.heartbeat {
             return Ok(());
         }
 
         // Agent registration is plaintext and upserts the bot record
-        if event.kind.as_u16() == KIND_AGENT_REGISTER {
+        if event.kind.as_u16() == self.kinds.agent_register {
             let subs = match &self.subscription_service {
                 Some(s) => s,
                 None => return Ok(()),
             };
 
-            let parsed: Value = match serde_json::from_str(&event.content) {
-                Ok(v) => v,
+            let created_at = event.created_at.as_secs();
+            if let Some(max_age) = self.agent_register_max_age {
+                let now = Timestamp::now().as_secs();
+                if older_than_window(max_age, created_at, now) {
+                    warn!(
+                        "Ignoring agent register {} created {}s ago (max age {}s)",
+                        event.id.to_hex(),
+                        now - created_at,
+                        max_age.as_secs()
+                    );
+                    if let Some(m) = &self.metrics {
+                        m.agent_register_ignored.inc();
+                    }
+                    return Ok(());
+                }
+            }
+
+            let parsed = match AgentRegisterPayload::parse(&event.content) {
+                Ok(p) => p,
                 Err(e) => {
                     error!(
                         "Agent register decode failed for {}: {}",
@@ -280,43 +869,59 @@ impl EventRouter {
                 }
             };
 
-            let nostr_pubkey = parsed
-                .get("nostr_pubkey")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| event.pubkey.to_hex());
-            let bot_pubkey = parsed
-                .get("bot_pubkey")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| event.pubkey.to_hex());
-            let eth_address = parsed
-                .get("eth_address")
-                .or_else(|| parsed.get("account"))
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-            let name = parsed
-                .get("name")
-                .and_then(|v| v.as_str())
-                .unwrap_or("agent")
-                .to_string();
+            let nostr_pubkey = parsed.nostr_pubkey.unwrap_or_else(|| event.pubkey.to_hex());
+            let bot_pubkey = parsed.bot_pubkey.unwrap_or_else(|| event.pubkey.to_hex());
+            let eth_address = parsed.eth_address.unwrap_or_default();
+            let name = parsed.name.unwrap_or_else(|| "agent".to_string());
 
             if eth_address.is_empty() {
                 error!("Agent register missing eth_address for {}", bot_pubkey);
                 return Ok(());
             }
 
-            if let Err(e) = subs
-                .register_bot(&bot_pubkey, &nostr_pubkey, &eth_address, &name)
+            if self.require_agent_signature {
+                let signature = parsed.signature.as_deref().unwrap_or("");
+                if !verify_personal_signature(&eth_address, &bot_pubkey, signature) {
+                    warn!(
+                        "Agent register rejected: signature does not match eth={} for bot_pubkey={}",
+                        eth_address, bot_pubkey
+                    );
+                    if let Some(m) = &self.metrics {
+                        m.agent_register_rejected.inc();
+                    }
+                    return Ok(());
+                }
+            }
+
+            let registered_at = Utc
+                .timestamp_opt(created_at as i64, 0)
+                .single()
+                .unwrap_or_else(Utc::now);
+            match subs
+                .register_bot(
+                    &bot_pubkey,
+                    &nostr_pubkey,
+                    &eth_address,
+                    &name,
+                    registered_at,
+                )
                 .await
             {
-                error!("Agent register upsert failed for {}: {}", bot_pubkey, e);
-            } else {
-                info!(
+                Ok(true) => info!(
                     "Registered bot via nostr: bot_pubkey={} eth={}",
                     bot_pubkey, eth_address
-                );
+                ),
+                Ok(false) => {
+                    warn!(
+                        "Ignoring agent register {} for {}: not newer than its last registration",
+                        event.id.to_hex(),
+                        bot_pubkey
+                    );
+                    if let Some(m) = &self.metrics {
+                        m.agent_register_ignored.inc();
+                    }
+                }
+                Err(e) => error!("Agent register upsert failed for {}: {}", bot_pubkey, e),
             }
 
             return Ok(());
@@ -338,37 +943,45 @@ impl EventRouter {
             return Ok(());
         }
 
-        // Decrypt content using platform key and sender pubkey
-        let plaintext = match nip04::decrypt(nostr_keys.secret_key(), &event.pubkey, &event.content)
-        {
-            Ok(p) => p,
-            Err(e) => {
-                error!("Failed to decrypt event {}: {}", event.id.to_hex(), e);
-                return Ok(());
+        // Tag-only events carry the payload in plain tags; everything else is encrypted to the platform key
+        let plaintext = if self.tag_payloads && event.content.trim().is_empty() {
+            match payload_from_tags(&event.tags) {
+                Some(p) => p,
+                None => {
+                    debug!("Skip empty event {} with no trade tags", event.id.to_hex());
+                    return Ok(());
+                }
+            }
+        } else {
+            match nip04::decrypt(nostr_keys.secret_key(), &event.pubkey, &event.content) {
+                Ok(p) => p,
+                Err(e) => match self.decrypt_with_retired_keys(event) {
+                    Some(p) => p,
+                    None => {
+                        error!("Failed to decrypt event {}: {}", event.id.to_hex(), e);
+                        return Ok(());
+                    }
+                },
             }
         };
 
-        if event.kind.as_u16() == KIND_TRADE_SIGNAL {
+        if event.kind.as_u16() == self.kinds.trade_signal {
             return self
                 .process_trade_signal(event, &plaintext, subs, nostr_keys)
                 .await;
         }
 
-        let preview = if plaintext.len() > 256 {
-            format!("{}...", &plaintext[..256])
-        } else {
-            plaintext.clone()
-        };
         debug!(
             "Decrypted nostr event id={} kind={} from={} preview={}",
             event.id.to_hex(),
             event.kind.as_u16(),
             event.pubkey.to_hex(),
-            preview,
+            payload_preview(&plaintext, self.log_payloads),
         );
 
         // Extract agent eth address from JSON payload
-        let agent_eth = extract_agent_eth(&plaintext)
+        let agent_eth = TradeSignalPayload::parse_with(&plaintext, &self.field_map)
+            .and_then(|p| p.agent_eth().map(str::to_string))
             .ok_or_else(|| anyhow::anyhow!("agent eth address missing"))?;
 
         // Find leader bot by eth address
@@ -386,13 +999,24 @@ impl EventRouter {
         let event_id = event.id.to_hex();
         self.maybe_record_trade(subs, &bot.bot_pubkey, &plaintext, &event_id)
             .await;
-        let followers = subs.list_subscriptions(&bot.bot_pubkey).await?;
+        if bot.paused {
+            debug!("Bot {} is paused, skipping fanout", bot.bot_pubkey);
+            return Ok(());
+        }
+        let kind = event.kind.as_u16();
+        let followers: Vec<_> = subs
+            .cached_subscriptions(&bot.bot_pubkey)
+            .await?
+            .iter()
+            .filter(|f| f.wants_kind(kind))
+            .cloned()
+            .collect();
         if followers.is_empty() {
             return Ok(());
         }
 
         // Fanout over WebSocket (plaintext)
-        if let Some(fanout_tx) = &self.fanout_tx {
+        if let Some(fanout_tx) = self.live_fanout_tx() {
             for follower in &followers {
                 let msg = FanoutMessage {
                     target_pubkey: follower.follower_pubkey.clone(),
@@ -401,43 +1025,24 @@ impl EventRouter {
                     original_event_id: event.id.to_hex(),
                     payload: plaintext.clone(),
                 };
-                if let Err(e) = fanout_tx.send_async(msg).await {
-                    error!("Failed to send fanout ws payload: {}", e);
-                }
+                self.send_fanout(fanout_tx, msg);
             }
         }
 
         // Publish encrypted nostr events to followers if client exists
         if let Some(client) = &self.nostr_client {
-            for follower in followers {
-                let follower_pk_str = follower.shared_secret.as_str();
-                let follower_pk = match PublicKey::from_str(follower_pk_str) {
-                    Ok(pk) => pk,
-                    Err(e) => {
-                        error!(
-                            "Invalid follower shared_secret pubkey {}: {}",
-                            follower_pk_str, e
-                        );
-                        continue;
-                    }
-                };
-
-                let encrypted =
-                    match nip04::encrypt(nostr_keys.secret_key(), &follower_pk, &plaintext) {
-                        Ok(ct) => ct,
-                        Err(e) => {
-                            error!("Encrypt for follower {} failed: {}", follower_pk_str, e);
-                            continue;
-                        }
-                    };
-
-                let mut builder = EventBuilder::new(Kind::Custom(event.kind.as_u16()), encrypted);
-                builder = builder.tag(Tag::public_key(follower_pk));
-
-                if let Err(e) = client.send_event_builder(builder).await {
-                    error!("Publish to follower {} failed: {}", follower_pk_str, e);
-                }
-            }
+            self.publish_to_followers(
+                client,
+                nostr_keys,
+                &bot.bot_pubkey,
+                event,
+                &followers,
+                &plaintext,
+            )
+            .await;
+        } else if let Some(m) = &self.metrics {
+            // WebSocket-only mode: followers only receive the /fanout stream
+            m.nostr_publish_skipped.inc_by(followers.len() as u64);
         }
 
         Ok(())
@@ -452,8 +1057,8 @@ impl EventRouter {
         subs: &SubscriptionService,
         nostr_keys: &Keys,
     ) -> Result<()> {
-        let meta = extract_signal_meta(plaintext);
-        let agent_eth = meta.agent_eth_address.clone();
+        let meta = TradeSignalPayload::parse_with(plaintext, &self.field_map).unwrap_or_default();
+        let agent_eth = meta.agent_eth().map(str::to_string);
         let event_created_at = to_event_datetime(event);
         let leader_pubkey = event.pubkey.to_hex();
 
@@ -494,8 +1099,14 @@ impl EventRouter {
             event_created_at,
         };
 
-        if let Err(e) = subs.record_signal(signal_insert).await {
-            error!("Failed to record trade signal {}: {}", event.id.to_hex(), e);
+        match &self.signal_batch_tx {
+            Some(tx) => match tx.try_send(signal_insert) {
+                Ok(()) => {}
+                Err(TrySendError::Full(signal) | TrySendError::Disconnected(signal)) => {
+                    self.record_signal_with_retry(subs, signal).await
+                }
+            },
+            None => self.record_signal_with_retry(subs, signal_insert).await,
         }
 
         let bot = match bot {
@@ -506,12 +1117,23 @@ impl EventRouter {
         let event_id = event.id.to_hex();
         self.maybe_record_trade(subs, &bot.bot_pubkey, plaintext, &event_id)
             .await;
-        let followers = subs.list_subscriptions(&bot.bot_pubkey).await?;
+        if bot.paused {
+            debug!("Bot {} is paused, skipping fanout", bot.bot_pubkey);
+            return Ok(());
+        }
+        let kind = event.kind.as_u16();
+        let followers: Vec<_> = subs
+            .cached_subscriptions(&bot.bot_pubkey)
+            .await?
+            .iter()
+            .filter(|f| f.wants_kind(kind))
+            .cloned()
+            .collect();
         if followers.is_empty() {
             return Ok(());
         }
 
-        if let Some(fanout_tx) = &self.fanout_tx {
+        if let Some(fanout_tx) = self.live_fanout_tx() {
             for follower in &followers {
                 let msg = FanoutMessage {
                     target_pubkey: follower.follower_pubkey.clone(),
@@ -520,60 +1142,110 @@ impl EventRouter {
                     original_event_id: event.id.to_hex(),
                     payload: plaintext.to_string(),
                 };
-                if let Err(e) = fanout_tx.send_async(msg).await {
-                    error!("Failed to send fanout ws payload: {}", e);
-                }
+                self.send_fanout(fanout_tx, msg);
             }
         }
 
         if let Some(client) = &self.nostr_client {
-            for follower in followers {
-                let follower_pk_str = follower.shared_secret.as_str();
-                let follower_pk = match PublicKey::from_str(follower_pk_str) {
-                    Ok(pk) => pk,
-                    Err(e) => {
-                        error!(
-                            "Invalid follower shared_secret pubkey {}: {}",
-                            follower_pk_str, e
-                        );
-                        continue;
-                    }
-                };
-
-                let encrypted =
-                    match nip04::encrypt(nostr_keys.secret_key(), &follower_pk, plaintext) {
-                        Ok(ct) => ct,
-                        Err(e) => {
-                            error!("Encrypt for follower {} failed: {}", follower_pk_str, e);
-                            continue;
-                        }
-                    };
-
-                let mut builder = EventBuilder::new(Kind::Custom(event.kind.as_u16()), encrypted);
-                builder = builder.tag(Tag::public_key(follower_pk));
+            self.publish_to_followers(
+                client,
+                nostr_keys,
+                &bot.bot_pubkey,
+                event,
+                &followers,
+                plaintext,
+            )
+            .await;
+        } else if let Some(m) = &self.metrics {
+            // WebSocket-only mode: followers only receive the /fanout stream
+            m.nostr_publish_skipped.inc_by(followers.len() as u64);
+        }
 
-                if let Err(e) = client.send_event_builder(builder).await {
-                    error!("Publish to follower {} failed: {}", follower_pk_str, e);
+        Ok(())
+    }
+}
+
+impl EventRouter {
+    /// The event is already marked seen by dedupe, so retry transient failures before giving up
+    async fn record_signal_with_retry(&self, subs: &SubscriptionService, signal: SignalInsert) {
+        let mut backoff = SIGNAL_PERSIST_BACKOFF;
+        for attempt in 1..=SIGNAL_PERSIST_ATTEMPTS {
+            match subs.record_signal(signal.clone()).await {
+                Ok(()) => return,
+                Err(e) if attempt < SIGNAL_PERSIST_ATTEMPTS => {
+                    warn!(
+                        "Failed to record trade signal {} (attempt {}/{}), retrying in {:?}: {}",
+                        signal.event_id, attempt, SIGNAL_PERSIST_ATTEMPTS, backoff, e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to record trade signal {} after {} attempts: {}",
+                        signal.event_id, SIGNAL_PERSIST_ATTEMPTS, e
+                    );
+                    if let Some(m) = &self.metrics {
+                        m.signal_persist_failures.inc();
+                    }
                 }
             }
         }
+    }
 
-        Ok(())
+    fn outside_dedupe_window(&self, event: &Event, timestamp: u64, received_at: u64) -> bool {
+        let Some(window) = self.dedupe_window else {
+            return false;
+        };
+        let outside = older_than_window(window, timestamp, received_at);
+        if outside {
+            debug!(
+                "Skip event id={} kind={} outside dedupe window (created_at={}, now={})",
+                event.id.to_hex(),
+                event.kind.as_u16(),
+                timestamp,
+                received_at
+            );
+        }
+        outside
     }
-}
 
-impl EventRouter {
-    fn is_stale(&self, event: &Event) -> bool {
-        let now = Timestamp::now().as_secs();
-        let created = event.created_at.as_secs();
-        now.saturating_sub(created) > STALE_AFTER.as_secs()
+    fn age_secs(&self, wrapper: &EventWrapper) -> u64 {
+        let reference = if self.stale_from_receive_time {
+            wrapper.received_at
+        } else {
+            wrapper.timestamp
+        };
+        Timestamp::now().as_secs().saturating_sub(reference)
+    }
+
+    async fn maybe_persist_raw(&self, event: &Event, received_at: u64) {
+        if !self.persist_all_events {
+            return;
+        }
+        let Some(subs) = &self.subscription_service else {
+            return;
+        };
+
+        if let Err(e) = subs
+            .record_raw_event(
+                &event.id.to_hex(),
+                event.kind.as_u16(),
+                &event.pubkey.to_hex(),
+                event.created_at.as_secs(),
+                event.content.len(),
+                received_at,
+            )
+            .await
+        {
+            error!("Failed to persist raw event {}: {}", event.id.to_hex(), e);
+        }
     }
 
     async fn maybe_update_last_seen(&self, event: &Event) {
-        const HEARTBEAT_KIND: u16 = KIND_HEARTBEAT;
         const MIN_INTERVAL: Duration = Duration::from_secs(15 * 60);
 
-        if event.kind.as_u16() != HEARTBEAT_KIND {
+        if event.kind.as_u16() != self.kinds.heartbeat {
             return;
         }
 
@@ -609,38 +1281,242 @@ impl EventRouter {
     }
 }
 
-#[derive(Debug)]
-struct TradeMeta {
-    tx_hash: Option<String>,
-    oid: Option<String>,
-    symbol: Option<String>,
-    side: Option<String>,
-    size: Option<f64>,
-    price: Option<f64>,
-    status: Option<String>,
-    pnl: Option<f64>,
-    pnl_usd: Option<f64>,
-    follower_pubkey: Option<String>,
-    role: String,
-    is_test: bool,
-}
+impl EventRouter {
+    /// Publish an encrypted copy to every follower key with bounded concurrency, then report
+    /// the follower keys that were not reached. During a rotation overlap both the current
+    /// and previous key receive the payload. Suspended followers are skipped until their cooldown
+    async fn publish_to_followers(
+        &self,
+        client: &Client,
+        nostr_keys: &Keys,
+        bot_pubkey: &str,
+        event: &Event,
+        followers: &[SubscriptionRow],
+        plaintext: &str,
+    ) {
+        let mut failures: Vec<(usize, String, String)> = Vec::new();
+        let mut builders = Vec::new();
+        let mut new_relays = Vec::new();
+        for (idx, follower) in followers.iter().enumerate() {
+            if self.delivery_suspended(follower) {
+                debug!(
+                    "Publishing to follower {} of bot {} is suspended, skipping",
+                    follower.follower_pubkey, bot_pubkey
+                );
+                continue;
+            }
+            let relays = route_follower_relays(
+                follower.relays.as_deref(),
+                &self.follower_relays,
+                self.max_follower_relays,
+                &mut new_relays,
+            );
+            for follower_pk_str in follower.secrets() {
+                let follower_pk = match PublicKey::from_str(follower_pk_str) {
+                    Ok(pk) => pk,
+                    Err(e) => {
+                        failures.push((
+                            idx,
+                            follower_pk_str.to_string(),
+                            format!("invalid shared_secret pubkey: {}", e),
+                        ));
+                        continue;
+                    }
+                };
+                match encrypt_for_follower(nostr_keys, &follower_pk, plaintext, follower.encryption)
+                {
+                    Ok(ct) => builders.push((
+                        idx,
+                        follower_pk_str.to_string(),
+                        EventBuilder::new(Kind::Custom(event.kind.as_u16()), ct)
+                            .tag(Tag::public_key(follower_pk)),
+                        relays.clone(),
+                    )),
+                    Err(e) => failures.push((
+                        idx,
+                        follower_pk_str.to_string(),
+                        format!("encrypt failed: {}", e),
+                    )),
+                }
+            }
+        }
 
-#[derive(Debug, Default)]
-struct SignalMeta {
-    agent_eth_address: Option<String>,
-    follower_pubkey: Option<String>,
-    role: Option<String>,
-    symbol: Option<String>,
-    side: Option<String>,
-    size: Option<f64>,
-    price: Option<f64>,
-    status: Option<String>,
-    tx_hash: Option<String>,
-    pnl: Option<f64>,
-    pnl_usd: Option<f64>,
-}
+        // Keys tried per follower, to tell a follower nobody reached from one partly reached
+        let mut attempted: HashMap<usize, usize> = HashMap::new();
+        for idx in failures
+            .iter()
+            .map(|f| f.0)
+            .chain(builders.iter().map(|b| b.0))
+        {
+            *attempted.entry(idx).or_default() += 1;
+        }
+        let total = failures.len() + builders.len();
+        // Connected before any send, so no follower's publish races another's relay setup
+        self.add_follower_relays(client, new_relays).await;
+        let publish_failures: Vec<(usize, String, String)> = futures::stream::iter(builders)
+            .map(|(idx, follower_pk, builder, relays)| async move {
+                let sent = match relays {
+                    Some(relays) => client.send_event_builder_to(relays, builder).await,
+                    None => client.send_event_builder(builder).await,
+                };
+                sent.err()
+                    .map(|e| (idx, follower_pk, format!("publish failed: {}", e)))
+            })
+            .buffer_unordered(self.publish_concurrency)
+            .filter_map(|failure| async move { failure })
+            .collect()
+            .await;
+        failures.extend(publish_failures);
+
+        if self.suspend_after_failures > 0 {
+            for (idx, keys) in attempted {
+                let failed = failures.iter().filter(|f| f.0 == idx).count();
+                self.track_delivery(bot_pubkey, &followers[idx], failed < keys)
+                    .await;
+            }
+        }
+
+        if failures.is_empty() {
+            return;
+        }
+        for (_, follower_pk, reason) in &failures {
+            error!("Follower {} not reached: {}", follower_pk, reason);
+        }
+        warn!(
+            "Event {} reached {}/{} follower keys",
+            event.id.to_hex(),
+            total - failures.len(),
+            total
+        );
+    }
+
+    /// Suspended for repeated delivery failures and still inside the cooldown
+    fn delivery_suspended(&self, follower: &SubscriptionRow) -> bool {
+        if self.suspend_after_failures == 0 {
+            return false;
+        }
+        let cooldown = chrono::Duration::from_std(self.suspend_cooldown).unwrap_or_default();
+        follower
+            .suspended_at
+            .is_some_and(|at| Utc::now() < at + cooldown)
+    }
+
+    /// Count consecutive deliveries that reached none of a follower's keys and suspend it at the
+    /// threshold. A follower that fails its first try after a cooldown is suspended again at
+    /// once; one that is reached has its count reset and any suspension lifted
+    async fn track_delivery(&self, bot_pubkey: &str, follower: &SubscriptionRow, reached: bool) {
+        let Some(subs) = &self.subscription_service else {
+            return;
+        };
+        let key = (bot_pubkey.to_string(), follower.follower_pubkey.clone());
+        if reached {
+            self.delivery_failures.remove(&key);
+            if follower.suspended_at.is_some() {
+                info!(
+                    "Follower {} of bot {} reached again, resuming publishing",
+                    follower.follower_pubkey, bot_pubkey
+                );
+                if let Err(e) = subs
+                    .set_subscription_suspended(bot_pubkey, &follower.follower_pubkey, false)
+                    .await
+                {
+                    warn!(
+                        "Failed to resume follower {}: {}",
+                        follower.follower_pubkey, e
+                    );
+                }
+            }
+            return;
+        }
+
+        let Some(failures) = self.count_failed_delivery(key, follower.suspended_at.is_some())
+        else {
+            return;
+        };
+        warn!(
+            "Suspending publishing to follower {} of bot {} after {} failed deliveries, retrying in {:?}",
+            follower.follower_pubkey, bot_pubkey, failures, self.suspend_cooldown
+        );
+        match subs
+            .set_subscription_suspended(bot_pubkey, &follower.follower_pubkey, true)
+            .await
+        {
+            Ok(()) => {
+                if let Some(m) = &self.metrics {
+                    m.followers_suspended.inc();
+                }
+            }
+            Err(e) => warn!(
+                "Failed to suspend follower {}: {}",
+                follower.follower_pubkey, e
+            ),
+        }
+    }
+
+    /// Count one more failed delivery; the count when the follower is due to be suspended
+    fn count_failed_delivery(&self, key: (String, String), suspended: bool) -> Option<u32> {
+        let failures = {
+            let mut count = self.delivery_failures.entry(key).or_insert(0);
+            *count += 1;
+            *count
+        };
+        (failures >= self.suspend_after_failures || suspended).then_some(failures)
+    }
+
+    /// Add the follower inbox relays routing just reserved to the publisher client. They are
+    /// added as read relays so that platform-wide publishes, which go to write relays, never
+    /// reach them. A URL the client already had (e.g. a platform relay) is left to the client
+    async fn add_follower_relays(&self, client: &Client, urls: Vec<String>) {
+        for url in urls {
+            match client.add_read_relay(url.as_str()).await {
+                Ok(true) => {
+                    if let Err(e) = client.connect_relay(url.as_str()).await {
+                        warn!("Failed to connect follower relay {}: {}", url, e);
+                    }
+                    self.follower_relays.insert(url, true);
+                }
+                Ok(false) => {
+                    self.follower_relays.insert(url, false);
+                }
+                Err(e) => {
+                    warn!("Failed to add follower relay {}: {}", url, e);
+                    self.follower_relays.remove(&url);
+                }
+            }
+        }
+    }
+
+    /// Disconnect follower inbox relays that no subscription lists any more
+    async fn prune_follower_relays(&self) {
+        if self.follower_relays.is_empty() {
+            return;
+        }
+        let (Some(client), Some(subs)) = (&self.nostr_client, &self.subscription_service) else {
+            return;
+        };
+        let listed = match subs.follower_relay_urls().await {
+            Ok(listed) => listed,
+            Err(e) => {
+                warn!("Failed to load follower relays for pruning: {}", e);
+                return;
+            }
+        };
+        let unused: Vec<String> = self
+            .follower_relays
+            .iter()
+            .filter(|entry| !listed.contains(entry.key()))
+            .map(|entry| entry.key().clone())
+            .collect();
+        for url in unused {
+            if let Some((_, true)) = self.follower_relays.remove(&url) {
+                match client.remove_relay(url.as_str()).await {
+                    Ok(()) => info!("Removed follower relay {}: no subscription lists it", url),
+                    Err(e) => warn!("Failed to remove follower relay {}: {}", url, e),
+                }
+            }
+        }
+    }
 
-impl EventRouter {
     async fn maybe_record_trade(
         &self,
         subs: &SubscriptionService,
@@ -648,37 +1524,50 @@ impl EventRouter {
         plaintext: &str,
         event_id: &str,
     ) {
-        let meta = match extract_trade_meta(plaintext) {
-            Some(m) => m,
-            None => return,
+        let meta = match TradeSignalPayload::parse_with(plaintext, &self.field_map) {
+            Some(m) if m.tx_hash.is_some() || m.oid.is_some() => m,
+            _ => return,
         };
 
         let oid_fallback = meta.oid.clone().or_else(|| Some(event_id.to_string()));
+        let role = meta.role.as_deref().unwrap_or("leader");
 
         if let Err(e) = subs
             .record_trade_tx(
                 bot_pubkey,
                 meta.follower_pubkey.as_deref(),
-                &meta.role,
+                role,
                 meta.symbol.as_deref().unwrap_or(""),
                 meta.side.as_deref().unwrap_or(""),
                 meta.size.unwrap_or(0.0),
                 meta.price.unwrap_or(0.0),
                 meta.tx_hash.as_deref(),
                 oid_fallback.as_deref(),
-                meta.is_test,
+                meta.is_test(),
             )
             .await
         {
             error!("Failed to record trade tx/oid: {}", e);
         }
 
-        if meta.status.is_some() || meta.pnl.is_some() || meta.pnl_usd.is_some() {
+        // Venue statuses such as `submitted` or `simulated` leave the settlement status alone
+        let status = meta.status.as_deref().and_then(TradeStatus::parse);
+        if let (Some(raw), None) = (meta.status.as_deref(), status) {
+            debug!(
+                "Execution report {} status {:?} is not a settlement status; keeping the trade's status",
+                event_id, raw
+            );
+        }
+        if status.is_some() || meta.pnl.is_some() || meta.pnl_usd.is_some() {
             if let Err(e) = subs
                 .update_trade_settlement(
-                    meta.tx_hash.as_deref(),
-                    oid_fallback.as_deref(),
-                    meta.status.as_deref().unwrap_or("pending"),
+                    TradeRowKey {
+                        tx_hash: meta.tx_hash.as_deref(),
+                        oid: oid_fallback.as_deref(),
+                        role,
+                        follower_pubkey: meta.follower_pubkey.as_deref(),
+                    },
+                    status,
                     meta.pnl,
                     meta.pnl_usd,
                 )
@@ -690,139 +1579,1054 @@ impl EventRouter {
     }
 }
 
-fn extract_trade_meta(plaintext: &str) -> Option<TradeMeta> {
-    let parsed: Value = serde_json::from_str(plaintext).ok()?;
-    let tx_hash = parsed
-        .get("tx_hash")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let oid = parsed
-        .get("oid")
-        .or_else(|| parsed.get("order_id"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let symbol = parsed
-        .get("symbol")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let side = parsed
-        .get("side")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let size = parsed.get("size").and_then(|v| v.as_f64());
-    let price = parsed.get("price").and_then(|v| v.as_f64());
-
-    let status = parsed
-        .get("status")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let is_test = parsed
-        .get("test_mode")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false)
-        || parsed
-            .get("status")
-            .and_then(|v| v.as_str())
-            .map(|s| s.eq_ignore_ascii_case("simulated"))
-            .unwrap_or(false);
-    let pnl = parsed.get("pnl").and_then(|v| v.as_f64());
-    let pnl_usd = parsed.get("pnl_usd").and_then(|v| v.as_f64());
-    let follower_pubkey = parsed
-        .get("follower_pubkey")
-        .or_else(|| parsed.get("follower"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let role = parsed
-        .get("role")
-        .and_then(|v| v.as_str())
-        .unwrap_or("leader")
-        .to_string();
-
-    if tx_hash.is_none() && oid.is_none() {
-        return None;
-    }
-
-    Some(TradeMeta {
-        tx_hash,
-        oid,
-        symbol,
-        side,
-        size,
-        price,
-        status,
-        pnl,
-        pnl_usd,
-        follower_pubkey,
-        role,
-        is_test,
-    })
+/// Relays to publish a follower's copy to, or None for the platform relays. Only allowed URLs
+/// (see [`follower_relay_allowed`]) are used. Relays already in `in_use` are always kept; new
+/// ones are reserved there and pushed to `new_relays` while fewer than `max` are in use
+fn route_follower_relays(
+    wanted: Option<&[String]>,
+    in_use: &DashMap<String, bool>,
+    max: usize,
+    new_relays: &mut Vec<String>,
+) -> Option<Vec<String>> {
+    let mut relays = Vec::new();
+    for url in wanted.unwrap_or_default() {
+        if !follower_relay_allowed(url) {
+            continue;
+        }
+        if in_use.contains_key(url) {
+            relays.push(url.clone());
+        } else if in_use.len() < max {
+            in_use.insert(url.clone(), false);
+            new_relays.push(url.clone());
+            relays.push(url.clone());
+        } else {
+            warn!(
+                "Follower relay limit of {} reached, not connecting {}",
+                max, url
+            );
+        }
+    }
+    (!relays.is_empty()).then_some(relays)
 }
 
-fn extract_signal_meta(plaintext: &str) -> SignalMeta {
-    let parsed: Value = match serde_json::from_str(plaintext) {
-        Ok(v) => v,
-        Err(_) => return SignalMeta::default(),
+/// Whether follower copies may be published to `url`: a `wss://` URL whose host is a public
+/// name or address. Loopback, private, link-local and other internal hosts are refused, since
+/// subscriptions are unauthenticated and must not point the publisher at internal services
+pub(crate) fn follower_relay_allowed(url: &str) -> bool {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    if parsed.scheme() != "wss" {
+        return false;
+    }
+    let Some(host) = parsed.host_str() else {
+        return false;
     };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match host.parse::<IpAddr>() {
+        Ok(ip) => public_ip(ip),
+        Err(_) => public_host_name(host),
+    }
+}
 
-    let agent_eth_address = parsed
-        .get("agent_eth_address")
-        .or_else(|| parsed.get("agent"))
-        .or_else(|| parsed.get("account"))
-        .or_else(|| parsed.get("eth_address"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-
-    let follower_pubkey = parsed
-        .get("follower_pubkey")
-        .or_else(|| parsed.get("follower"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-
-    let role = parsed
-        .get("role")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let symbol = parsed
-        .get("symbol")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let side = parsed
-        .get("side")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let size = parsed.get("size").and_then(|v| v.as_f64());
-    let price = parsed.get("price").and_then(|v| v.as_f64());
-    let status = parsed
-        .get("status")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let tx_hash = parsed
-        .get("tx_hash")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let pnl = parsed.get("pnl").and_then(|v| v.as_f64());
-    let pnl_usd = parsed.get("pnl_usd").and_then(|v| v.as_f64());
-
-    SignalMeta {
-        agent_eth_address,
-        follower_pubkey,
-        role,
-        symbol,
-        side,
-        size,
-        price,
-        status,
-        tx_hash,
-        pnl,
-        pnl_usd,
+fn public_ip(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                // Unique local fc00::/7 and link-local fe80::/10
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
     }
 }
 
-fn extract_agent_eth(plaintext: &str) -> Option<String> {
-    extract_signal_meta(plaintext).agent_eth_address
+/// Rejects single-label names and the suffixes reserved for local networks
+fn public_host_name(host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    const LOCAL_SUFFIXES: [&str; 5] = [".localhost", ".local", ".internal", ".lan", ".home.arpa"];
+    host.contains('.') && !LOCAL_SUFFIXES.iter().any(|suffix| host.ends_with(suffix))
+}
+
+/// Encrypt a fanout payload with the scheme the follower's client supports
+fn encrypt_for_follower(
+    nostr_keys: &Keys,
+    follower_pk: &PublicKey,
+    plaintext: &str,
+    scheme: FanoutEncryption,
+) -> Result<String> {
+    Ok(match scheme {
+        FanoutEncryption::Nip04 => nip04::encrypt(nostr_keys.secret_key(), follower_pk, plaintext)?,
+        FanoutEncryption::Nip44 => nip44::encrypt(
+            nostr_keys.secret_key(),
+            follower_pk,
+            plaintext,
+            nip44::Version::V2,
+        )?,
+    })
+}
+
+/// Whether `timestamp` is more than `window` before `received_at` (both unix seconds)
+fn older_than_window(window: Duration, timestamp: u64, received_at: u64) -> bool {
+    timestamp.saturating_add(window.as_secs()) < received_at
+}
+
+/// Raise `key`'s mark to `timestamp`; false when `timestamp` is not strictly newer
+fn advance_mark<K: Eq + std::hash::Hash>(marks: &DashMap<K, u64>, key: K, timestamp: u64) -> bool {
+    let mut last = marks.entry(key).or_insert(0);
+    if timestamp <= *last {
+        return false;
+    }
+    *last = timestamp;
+    true
+}
+
+/// Forget marks older than `cutoff`
+fn prune_marks<K: Eq + std::hash::Hash>(marks: &DashMap<K, u64>, cutoff: u64) {
+    marks.retain(|_, last| *last >= cutoff);
+}
+
+/// Rough memory held by a pending event: the wrapper itself plus its content and tag strings
+fn estimated_bytes(event: &Event) -> usize {
+    let tags: usize = event
+        .tags
+        .iter()
+        .map(|tag| tag.as_slice().iter().map(String::len).sum::<usize>())
+        .sum();
+    std::mem::size_of::<EventWrapper>() + event.content.len() + tags
 }
 
 fn to_event_datetime(event: &Event) -> DateTime<Utc> {
     let secs = event.created_at.as_secs() as i64;
     Utc.timestamp_opt(secs, 0).single().unwrap_or_else(Utc::now)
 }
+
+impl EventRouter {
+    /// Try the retired platform keys in order, stopping at the first that decrypts
+    fn decrypt_with_retired_keys(&self, event: &Event) -> Option<String> {
+        let (index, plaintext) = first_decryption(&self.retired_keys, |keys| {
+            nip04::decrypt(keys.secret_key(), &event.pubkey, &event.content).ok()
+        })?;
+        debug!(
+            "Decrypted event {} with retired platform key #{} ({})",
+            event.id.to_hex(),
+            index + 1,
+            self.retired_keys[index].public_key().to_hex()
+        );
+        if let Some(m) = &self.metrics {
+            m.retired_key_decryptions.inc();
+        }
+        Some(plaintext)
+    }
+}
+
+fn carries_required_tags<'a>(
+    required: &[RequiredTag],
+    tags: impl Iterator<Item = &'a [String]> + Clone,
+) -> bool {
+    required.iter().all(|required| {
+        tags.clone().any(|tag| match tag {
+            [name, rest @ ..] if *name == required.name => match &required.value {
+                Some(value) => rest.first() == Some(value),
+                None => true,
+            },
+            _ => false,
+        })
+    })
+}
+
+/// Index and plaintext of the first key, in order, that `decrypt` succeeds with
+fn first_decryption<K, T>(
+    keys: &[K],
+    mut decrypt: impl FnMut(&K) -> Option<T>,
+) -> Option<(usize, T)> {
+    keys.iter()
+        .enumerate()
+        .find_map(|(index, key)| decrypt(key).map(|p| (index, p)))
+}
+
+/// Debug-log form of a decrypted payload: the first 256 bytes when `log_payloads` is set,
+/// otherwise only its length and a short sha256 so the trade details never reach the logs
+fn payload_preview(plaintext: &str, log_payloads: bool) -> String {
+    if !log_payloads {
+        let digest = Sha256::digest(plaintext.as_bytes());
+        return format!(
+            "<redacted len={} sha256={}>",
+            plaintext.len(),
+            hex::encode(&digest[..8])
+        );
+    }
+    if plaintext.len() <= 256 {
+        return plaintext.to_string();
+    }
+    let mut end = 256;
+    while !plaintext.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...", &plaintext[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fake_relay::FakeRelay;
+    use crate::core::relay_transport::RelayTransport;
+    use crate::storage::rocksdb_store::RocksDBStore;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    /// A router over a throwaway RocksDB; keep the `TempDir` alive while using it
+    fn test_router() -> (EventRouter, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let store = Arc::new(RocksDBStore::new(dir.path()).unwrap());
+        let dedupe = DeduplicationEngine::new_with_params(store, 100, 1_000, 0.01, 100);
+        let router = EventRouter::new(
+            Arc::new(dedupe),
+            10,
+            Duration::from_millis(10),
+            flume::unbounded().0,
+            AllowedKinds::new(None),
+            None,
+            None,
+            None,
+            None,
+        );
+        (router, dir)
+    }
+
+    #[test]
+    fn dedupe_kinds_limit_which_kinds_are_deduped() {
+        let (router, _dir) = test_router();
+        let router = router.with_dedupe_kinds(vec![30931, 30932]);
+        assert!(router.should_dedupe(30931));
+        assert!(!router.should_dedupe(30933));
+
+        // An empty list dedupes every kind
+        let (router, _dir) = test_router();
+        let router = router.with_dedupe_kinds(Vec::new());
+        assert!(router.should_dedupe(30933));
+    }
+
+    #[test]
+    fn dedupe_window_compares_created_at_with_receive_time() {
+        let window = Duration::from_secs(60);
+        assert!(!older_than_window(window, 1_000, 1_060));
+        assert!(older_than_window(window, 1_000, 1_061));
+        // Future-dated events are never outside the window
+        assert!(!older_than_window(window, 2_000, 1_000));
+        assert!(!older_than_window(window, u64::MAX, 1_000));
+
+        let (router, _dir) = test_router();
+        let router = router.with_dedupe_window(Duration::ZERO);
+        assert_eq!(router.dedupe_window, None);
+    }
+
+    #[test]
+    fn marks_only_advance_strictly() {
+        let marks = DashMap::new();
+        assert!(advance_mark(&marks, "a", 100));
+        assert!(!advance_mark(&marks, "a", 100));
+        assert!(!advance_mark(&marks, "a", 40));
+        assert!(advance_mark(&marks, "a", 101));
+        // Senders are tracked independently
+        assert!(advance_mark(&marks, "b", 50));
+    }
+
+    #[test]
+    fn pruning_forgets_only_old_marks() {
+        let marks = DashMap::new();
+        advance_mark(&marks, "old", 10);
+        advance_mark(&marks, "new", 500);
+        prune_marks(&marks, 100);
+        assert!(!marks.contains_key("old"));
+        assert_eq!(marks.get("new").map(|v| *v), Some(500));
+        // A pruned sender starts over
+        assert!(advance_mark(&marks, "old", 10));
+    }
+
+    #[test]
+    fn retired_keys_are_tried_in_order() {
+        let keys = ["newest", "older", "oldest"];
+        let mut tried = Vec::new();
+        let found = first_decryption(&keys, |key| {
+            tried.push(*key);
+            (*key != "newest").then(|| format!("plaintext via {}", key))
+        });
+        assert_eq!(found, Some((1, "plaintext via older".to_string())));
+        // Stops at the first key that works
+        assert_eq!(tried, ["newest", "older"]);
+    }
+
+    #[test]
+    fn no_retired_key_decrypts() {
+        assert_eq!(first_decryption(&["a", "b"], |_| None::<String>), None);
+        assert_eq!(first_decryption(&[] as &[&str], |_| Some(())), None);
+    }
+
+    fn required(name: &str, value: Option<&str>) -> RequiredTag {
+        RequiredTag {
+            name: name.to_string(),
+            value: value.map(str::to_string),
+        }
+    }
+
+    fn carries(required: &[RequiredTag], tags: &[&[&str]]) -> bool {
+        let tags: Vec<Vec<String>> = tags
+            .iter()
+            .map(|tag| tag.iter().map(|s| s.to_string()).collect())
+            .collect();
+        carries_required_tags(required, tags.iter().map(Vec::as_slice))
+    }
+
+    #[test]
+    fn required_tags_match_name_and_first_value() {
+        let platform = [required("t", Some("moltrade"))];
+        assert!(carries(&platform, &[&["p", "abc"], &["t", "moltrade"]]));
+        assert!(!carries(&platform, &[&["t", "other", "moltrade"]]));
+        assert!(!carries(&platform, &[&["t"]]));
+        assert!(!carries(&platform, &[]));
+
+        let any_value = [required("d", None)];
+        assert!(carries(&any_value, &[&["d", "anything"]]));
+        assert!(carries(&any_value, &[&["d"]]));
+    }
+
+    #[test]
+    fn every_required_tag_must_be_present() {
+        let both = [required("t", Some("moltrade")), required("d", None)];
+        assert!(carries(&both, &[&["t", "moltrade"], &["d", "x"]]));
+        assert!(!carries(&both, &[&["t", "moltrade"]]));
+        // No requirement keeps every event
+        assert!(carries(&[], &[]));
+    }
+
+    #[test]
+    fn blank_required_tag_names_are_ignored() {
+        let (router, _dir) = test_router();
+        let router = router.with_required_tags(vec![required(" ", None), required("t", None)]);
+        assert_eq!(router.required_tags.len(), 1);
+        assert_eq!(router.required_tags[0].name, "t");
+    }
+
+    #[test]
+    fn agent_register_max_age_zero_disables_the_check() {
+        let (router, _dir) = test_router();
+        let router = router.with_agent_register_max_age(Duration::ZERO);
+        assert_eq!(router.agent_register_max_age, None);
+        let (router, _dir) = test_router();
+        let router = router.with_agent_register_max_age(Duration::from_secs(3600));
+        let max_age = router.agent_register_max_age.unwrap();
+        // A registration replayed a day later is ignored; one at the limit is not
+        assert!(older_than_window(max_age, 1_000, 1_000 + 86_400));
+        assert!(!older_than_window(max_age, 1_000, 1_000 + 3_600));
+    }
+
+    fn follower(suspended_at: Option<DateTime<Utc>>) -> SubscriptionRow {
+        SubscriptionRow {
+            follower_pubkey: "follower".to_string(),
+            shared_secret: String::new(),
+            previous_secret: None,
+            encryption: FanoutEncryption::Nip04,
+            kinds: None,
+            relays: None,
+            suspended_at,
+        }
+    }
+
+    #[test]
+    fn follower_is_suspended_after_consecutive_failures() {
+        let (router, _dir) = test_router();
+        let router = router.with_delivery_suspension(3, Duration::from_secs(3600));
+        let key = || ("bot".to_string(), "follower".to_string());
+        assert_eq!(router.count_failed_delivery(key(), false), None);
+        assert_eq!(router.count_failed_delivery(key(), false), None);
+        assert_eq!(router.count_failed_delivery(key(), false), Some(3));
+
+        // A reached follower starts counting again
+        router.delivery_failures.remove(&key());
+        assert_eq!(router.count_failed_delivery(key(), false), None);
+        // One failing its retry after a cooldown is suspended again at once
+        assert_eq!(router.count_failed_delivery(key(), true), Some(2));
+    }
+
+    #[test]
+    fn suspension_lasts_for_the_cooldown() {
+        let (router, _dir) = test_router();
+        let router = router.with_delivery_suspension(3, Duration::from_secs(3600));
+        assert!(!router.delivery_suspended(&follower(None)));
+        let recent = Utc::now() - chrono::Duration::minutes(5);
+        assert!(router.delivery_suspended(&follower(Some(recent))));
+        let expired = Utc::now() - chrono::Duration::hours(2);
+        assert!(!router.delivery_suspended(&follower(Some(expired))));
+
+        let (disabled, _dir) = test_router();
+        assert!(!disabled.delivery_suspended(&follower(Some(recent))));
+    }
+
+    #[test]
+    fn pending_bytes_cap_triggers_an_early_flush() {
+        let (router, _dir) = test_router();
+        let router = router.with_max_pending_bytes(4096);
+        router.pending_bytes.fetch_add(4000, Ordering::Relaxed);
+        assert!(!router.over_pending_bytes());
+        router.pending_bytes.fetch_add(96, Ordering::Relaxed);
+        assert!(router.over_pending_bytes());
+        router.release_pending_bytes(4096);
+        assert!(!router.over_pending_bytes());
+        assert_eq!(router.pending_bytes.load(Ordering::Relaxed), 0);
+
+        // Without a cap the estimate is only reported
+        let (uncapped, _dir) = test_router();
+        uncapped
+            .pending_bytes
+            .fetch_add(usize::MAX / 2, Ordering::Relaxed);
+        assert!(!uncapped.over_pending_bytes());
+    }
+
+    #[test]
+    fn payload_preview_redacts_by_default() {
+        let payload = r#"{"symbol":"ETH","side":"buy"}"#;
+        let redacted = payload_preview(payload, false);
+        assert!(!redacted.contains("ETH"), "{}", redacted);
+        assert!(redacted.starts_with(&format!("<redacted len={} sha256=", payload.len())));
+        // Same payload, same digest, so log lines can still be correlated
+        assert_eq!(redacted, payload_preview(payload, false));
+        assert_eq!(payload_preview(payload, true), payload);
+    }
+
+    #[test]
+    fn payload_preview_truncates_on_a_char_boundary() {
+        let long = format!("{}é{}", "a".repeat(255), "b".repeat(100));
+        let preview = payload_preview(&long, true);
+        assert_eq!(preview, format!("{}...", "a".repeat(255)));
+    }
+
+    fn fanout_message(target: &str) -> FanoutMessage {
+        FanoutMessage {
+            target_pubkey: target.to_string(),
+            bot_pubkey: "bot".to_string(),
+            kind: 30931,
+            original_event_id: "event".to_string(),
+            payload: String::new(),
+        }
+    }
+
+    #[test]
+    fn closed_fanout_channel_disables_fanout() {
+        let (tx, rx) = flume::unbounded();
+        let (mut router, _dir) = test_router();
+        router.fanout_tx = Some(tx);
+        let fanout_tx = router.live_fanout_tx().unwrap().clone();
+        router.send_fanout(&fanout_tx, fanout_message("f1"));
+        assert_eq!(rx.len(), 1);
+
+        drop(rx);
+        router.send_fanout(&fanout_tx, fanout_message("f2"));
+        assert!(router.fanout_closed.load(Ordering::Relaxed));
+        assert!(router.live_fanout_tx().is_none());
+    }
+
+    #[test]
+    fn dropped_fanout_receiver_is_noticed_before_sending() {
+        let (tx, rx) = flume::unbounded::<FanoutMessage>();
+        let (mut router, _dir) = test_router();
+        router.fanout_tx = Some(tx);
+        drop(rx);
+        assert!(router.live_fanout_tx().is_none());
+        assert!(router.fanout_closed.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn full_fanout_channel_drops_and_counts() {
+        let (tx, rx) = flume::bounded(1);
+        let (router, _dir) = test_router();
+        router.send_fanout(&tx, fanout_message("f1"));
+        router.send_fanout(&tx, fanout_message("f2"));
+        router.send_fanout(&tx, fanout_message("f3"));
+        assert_eq!(rx.len(), 1);
+        assert_eq!(router.fanout_drops.load(Ordering::Relaxed), 2);
+        // A full channel is not a closed one
+        assert!(!router.fanout_closed.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn followers_with_inbox_relays_are_published_there() {
+        let in_use = DashMap::new();
+        let mut new_relays = Vec::new();
+        let inbox = vec![
+            "wss://inbox.example.com".to_string(),
+            "wss://127.0.0.1:7777".to_string(),
+        ];
+        assert_eq!(
+            route_follower_relays(Some(&inbox), &in_use, 10, &mut new_relays),
+            Some(vec!["wss://inbox.example.com".to_string()])
+        );
+        assert_eq!(new_relays, vec!["wss://inbox.example.com".to_string()]);
+
+        // Followers without a list, or with nothing usable, get the platform relays
+        assert_eq!(
+            route_follower_relays(None, &in_use, 10, &mut new_relays),
+            None
+        );
+        let internal = vec!["wss://10.0.0.1".to_string()];
+        assert_eq!(
+            route_follower_relays(Some(&internal), &in_use, 10, &mut new_relays),
+            None
+        );
+
+        // A relay already in use is not connected twice
+        new_relays.clear();
+        assert_eq!(
+            route_follower_relays(Some(&inbox[..1]), &in_use, 10, &mut new_relays),
+            Some(vec!["wss://inbox.example.com".to_string()])
+        );
+        assert!(new_relays.is_empty());
+    }
+
+    #[test]
+    fn follower_relays_stop_at_the_cap() {
+        let in_use = DashMap::new();
+        let mut new_relays = Vec::new();
+        let first = vec!["wss://one.example.com".to_string()];
+        let second = vec![
+            "wss://one.example.com".to_string(),
+            "wss://two.example.com".to_string(),
+        ];
+        route_follower_relays(Some(&first), &in_use, 1, &mut new_relays);
+        // Relays already connected keep working at the cap; new ones are not added
+        assert_eq!(
+            route_follower_relays(Some(&second), &in_use, 1, &mut new_relays),
+            Some(first.clone())
+        );
+        assert_eq!(new_relays, first);
+        assert_eq!(
+            route_follower_relays(Some(&second[1..]), &in_use, 1, &mut new_relays),
+            None
+        );
+        // Zero sends every follower to the platform relays
+        assert_eq!(
+            route_follower_relays(Some(&first), &DashMap::new(), 0, &mut new_relays),
+            None
+        );
+    }
+
+    /// A router fanning out tag-only trade signals for `subs` over WebSocket
+    fn copytrade_router(
+        subs: Arc<SubscriptionService>,
+    ) -> (EventRouter, flume::Receiver<FanoutMessage>, TempDir) {
+        let (router, dir) = test_router();
+        let (tx, rx) = flume::unbounded();
+        let mut router = router.with_tag_payloads(true);
+        router.fanout_tx = Some(tx);
+        router.subscription_service = Some(subs);
+        router.nostr_keys = Some(Keys::generate());
+        (router, rx, dir)
+    }
+
+    /// A tag-only trade signal from the agent `register_test_bot` creates for `bot`
+    fn trade_signal(bot: &str, symbol: &str) -> Event {
+        trade_signal_of_kind(30931, bot, symbol)
+    }
+
+    fn trade_signal_of_kind(kind: u16, bot: &str, symbol: &str) -> Event {
+        let agent = format!("0x{:0>40}", bot);
+        EventBuilder::new(Kind::Custom(kind), "")
+            .tag(Tag::parse(["agent", agent.as_str()]).unwrap())
+            .tag(Tag::parse(["symbol", symbol]).unwrap())
+            .tag(Tag::parse(["side", "buy"]).unwrap())
+            .tag(Tag::parse(["size", "1"]).unwrap())
+            .tag(Tag::parse(["price", "100"]).unwrap())
+            .sign_with_keys(&Keys::generate())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn paused_bot_signals_are_recorded_but_not_fanned_out() {
+        let Some(subs) = SubscriptionService::for_test("router_paused_bot").await else {
+            return;
+        };
+        subs.register_test_bot("bot1").await;
+        subs.add_subscription("bot1", "follower1", "secret", None, None, None)
+            .await
+            .unwrap();
+        let subs = Arc::new(subs);
+        let (router, fanout_rx, _dir) = copytrade_router(subs.clone());
+
+        router
+            .handle_copytrade_fanout(&trade_signal("bot1", "BTC"))
+            .await
+            .unwrap();
+        assert_eq!(fanout_rx.try_recv().unwrap().target_pubkey, "follower1");
+
+        assert!(subs.set_bot_paused("bot1", true).await.unwrap());
+        router
+            .handle_copytrade_fanout(&trade_signal("bot1", "ETH"))
+            .await
+            .unwrap();
+        assert!(fanout_rx.is_empty());
+        let recorded = subs.search_signals("ETH", Some("bot1"), 10).await.unwrap();
+        assert_eq!(recorded.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn followers_only_get_the_kinds_they_opted_into() {
+        let Some(subs) = SubscriptionService::for_test("router_wants_kind").await else {
+            return;
+        };
+        subs.register_test_bot("bot1").await;
+        for (follower, kinds) in [
+            ("every_kind", None),
+            ("intents_only", Some(&[30932][..])),
+            ("signals_only", Some(&[30931][..])),
+        ] {
+            subs.add_subscription("bot1", follower, "secret", None, kinds, None)
+                .await
+                .unwrap();
+        }
+        let (router, fanout_rx, _dir) = copytrade_router(Arc::new(subs));
+
+        router
+            .handle_copytrade_fanout(&trade_signal("bot1", "BTC"))
+            .await
+            .unwrap();
+        let mut targets: Vec<_> = fanout_rx.drain().map(|m| m.target_pubkey).collect();
+        targets.sort();
+        assert_eq!(targets, ["every_kind", "signals_only"]);
+    }
+
+    #[tokio::test]
+    async fn remapped_kinds_are_routed_by_their_configured_role() {
+        let Some(subs) = SubscriptionService::for_test("router_remapped_kinds").await else {
+            return;
+        };
+        subs.register_test_bot("bot1").await;
+        subs.add_subscription("bot1", "follower1", "secret", None, None, None)
+            .await
+            .unwrap();
+        let subs = Arc::new(subs);
+        let (router, fanout_rx, _dir) = copytrade_router(subs.clone());
+        let router = router.with_kinds(KindsConfig {
+            trade_signal: 31931,
+            heartbeat: 30931,
+            ..KindsConfig::default()
+        });
+
+        router
+            .handle_copytrade_fanout(&trade_signal_of_kind(31931, "bot1", "BTC"))
+            .await
+            .unwrap();
+        assert_eq!(fanout_rx.try_recv().unwrap().target_pubkey, "follower1");
+        assert_eq!(
+            subs.search_signals("BTC", Some("bot1"), 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // The default trade signal kind is a heartbeat here, so it is neither recorded nor sent
+        router
+            .handle_copytrade_fanout(&trade_signal_of_kind(30931, "bot1", "ETH"))
+            .await
+            .unwrap();
+        assert!(fanout_rx.is_empty());
+        assert!(
+            subs.search_signals("ETH", Some("bot1"), 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn publishes_run_concurrently_and_only_unreached_followers_count_as_failed() {
+        let Some(subs) = SubscriptionService::for_test("router_publish_followers").await else {
+            return;
+        };
+        subs.register_test_bot("bot1").await;
+        let reached = Keys::generate().public_key().to_hex();
+        let rejected = Keys::generate().public_key().to_hex();
+        for (follower, key) in [
+            ("reached", reached.as_str()),
+            ("rejected", rejected.as_str()),
+            ("invalid", "not-a-key"),
+        ] {
+            subs.add_subscription("bot1", follower, key, None, None, None)
+                .await
+                .unwrap();
+        }
+        let subs = Arc::new(subs);
+        let (router, _fanout_rx, _dir) = copytrade_router(subs.clone());
+        let router = router
+            .with_publish_concurrency(2)
+            .with_delivery_suspension(1, Duration::from_secs(3600));
+
+        let relay = FakeRelay::start().await;
+        let client = RelayTransport::default().client(Keys::generate());
+        client.add_relay(&relay.url).await.unwrap();
+        client.try_connect(Duration::from_secs(5)).await;
+        let followers = subs.list_subscriptions("bot1").await.unwrap();
+        let signal = trade_signal("bot1", "BTC");
+        let keys = router.nostr_keys.clone().unwrap();
+        let publish =
+            router.publish_to_followers(&client, &keys, "bot1", &signal, &followers, "{}");
+        // Both publishes must be in flight before the relay acknowledges either of them
+        let relay_side = async {
+            let events = [relay.next("EVENT").await, relay.next("EVENT").await];
+            for event in events {
+                let accepted = event[1]["tags"][0][1] == reached.as_str();
+                relay.send(json!(["OK", event[1]["id"], accepted, "blocked: test"]));
+            }
+        };
+        tokio::join!(publish, relay_side);
+
+        let suspended: HashMap<_, _> = subs
+            .list_subscriptions("bot1")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.follower_pubkey, f.suspended_at.is_some()))
+            .collect();
+        assert_eq!(
+            suspended,
+            HashMap::from([
+                ("reached".to_string(), false),
+                ("rejected".to_string(), true),
+                ("invalid".to_string(), true),
+            ])
+        );
+    }
+
+    fn test_metrics() -> Arc<Metrics> {
+        Arc::new(Metrics::with_registry(&prometheus::Registry::new()).unwrap())
+    }
+
+    /// A signed event of `kind` from a fresh key
+    fn signed_event(kind: u16, content: &str, created_at: Timestamp) -> Event {
+        EventBuilder::new(Kind::Custom(kind), content)
+            .custom_created_at(created_at)
+            .sign_with_keys(&Keys::generate())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn oversize_content_is_dropped_before_queueing() {
+        let metrics = test_metrics();
+        let (router, _dir) = test_router();
+        let router = router
+            .with_max_content_bytes(100)
+            .with_metrics(metrics.clone());
+        let mut oldest = None;
+
+        let oversize = signed_event(30931, &"x".repeat(101), Timestamp::now());
+        router.accept_event(oversize, &mut oldest).await.unwrap();
+        assert!(router.pending_events.read().await.is_empty());
+        assert_eq!(metrics.oversize_events_dropped.get(), 1);
+
+        let at_limit = signed_event(30931, &"x".repeat(100), Timestamp::now());
+        router.accept_event(at_limit, &mut oldest).await.unwrap();
+        assert_eq!(router.pending_events.read().await.len(), 1);
+        assert_eq!(metrics.oversize_events_dropped.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn processing_latency_is_sampled_per_batch_or_per_event() {
+        for (mode, samples) in [(LatencyMode::Batch, 1), (LatencyMode::Event, 3)] {
+            let metrics = test_metrics();
+            let (router, _dir) = test_router();
+            let router = router.with_latency_mode(mode).with_metrics(metrics.clone());
+            let mut oldest = None;
+            for i in 0..3 {
+                let event = signed_event(30931, &format!("event {}", i), Timestamp::now());
+                router.accept_event(event, &mut oldest).await.unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            router.flush_batch().await.unwrap();
+
+            let latency = &metrics.processing_latency;
+            assert_eq!(latency.get_sample_count(), samples, "{:?}", mode);
+            // Only per-event samples include the time spent waiting in the queue
+            let waited = latency.get_sample_sum() >= 0.05 * samples as f64;
+            assert_eq!(waited, mode == LatencyMode::Event, "{:?}", mode);
+        }
+    }
+
+    #[tokio::test]
+    async fn heartbeats_are_persisted_when_all_events_are() {
+        let Some(subs) = SubscriptionService::for_test("router_persist_all").await else {
+            return;
+        };
+        let subs = Arc::new(subs);
+        let (mut router, _dir) = test_router();
+        router.subscription_service = Some(subs.clone());
+        let router = router.with_persist_all_events(true);
+        let mut oldest = None;
+
+        let heartbeat = signed_event(30933, "alive", Timestamp::now());
+        router
+            .accept_event(heartbeat.clone(), &mut oldest)
+            .await
+            .unwrap();
+        router.flush_batch().await.unwrap();
+
+        let rows = subs
+            .query_test_count(&format!(
+                "SELECT COUNT(*) FROM raw_events
+                 WHERE event_id = '{}' AND kind = 30933 AND pubkey = '{}' AND content_length = 5",
+                heartbeat.id.to_hex(),
+                heartbeat.pubkey.to_hex()
+            ))
+            .await;
+        assert_eq!(rows, 1);
+    }
+
+    #[tokio::test]
+    async fn duplicates_are_counted_per_kind() {
+        let metrics = test_metrics();
+        let (router, _dir) = test_router();
+        let router = router.with_metrics(metrics.clone());
+        let mut oldest = None;
+
+        let signal = signed_event(30931, "signal", Timestamp::now());
+        let intent = signed_event(30932, "intent", Timestamp::now());
+        for event in [&signal, &signal, &signal, &intent, &intent] {
+            router
+                .accept_event(event.clone(), &mut oldest)
+                .await
+                .unwrap();
+        }
+        assert_eq!(router.pending_events.read().await.len(), 2);
+        let counts: Vec<_> = metrics.duplicates_by_kind().into_iter().collect();
+        assert_eq!(counts, [("30931".to_string(), 2), ("30932".to_string(), 1)]);
+    }
+
+    #[tokio::test]
+    async fn bursty_pubkeys_are_throttled_without_affecting_others() {
+        let metrics = test_metrics();
+        let (router, _dir) = test_router();
+        let router = router
+            .with_pubkey_rate_limit(2, 0.001)
+            .with_metrics(metrics.clone());
+        let mut oldest = None;
+        let bursty = Keys::generate();
+        let quiet = Keys::generate();
+        let event = |keys: &Keys, i: u32| {
+            EventBuilder::new(Kind::Custom(30931), format!("event {}", i))
+                .sign_with_keys(keys)
+                .unwrap()
+        };
+
+        for i in 0..5 {
+            router
+                .accept_event(event(&bursty, i), &mut oldest)
+                .await
+                .unwrap();
+        }
+        router
+            .accept_event(event(&quiet, 5), &mut oldest)
+            .await
+            .unwrap();
+
+        let accepted: Vec<_> = router
+            .pending_events
+            .read()
+            .await
+            .iter()
+            .map(|w| w.event.pubkey)
+            .collect();
+        let from = |keys: &Keys| {
+            accepted
+                .iter()
+                .filter(|pk| **pk == keys.public_key())
+                .count()
+        };
+        assert_eq!((from(&bursty), from(&quiet)), (2, 1));
+        assert_eq!(metrics.rate_limited_events.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn future_dated_events_are_dropped_or_clamped() {
+        let skew = Duration::from_secs(60);
+        let ahead = Timestamp::now() + Duration::from_secs(3600);
+        let mut oldest = None;
+
+        let metrics = test_metrics();
+        let (router, _dir) = test_router();
+        let router = router
+            .with_future_timestamp_policy(skew, FutureTimestampPolicy::Drop)
+            .with_metrics(metrics.clone());
+        router
+            .accept_event(signed_event(30931, "", ahead), &mut oldest)
+            .await
+            .unwrap();
+        assert!(router.pending_events.read().await.is_empty());
+        assert_eq!(metrics.future_dated_events.get(), 1);
+        // Within the skew is not future-dated
+        let near = Timestamp::now() + Duration::from_secs(30);
+        router
+            .accept_event(signed_event(30931, "", near), &mut oldest)
+            .await
+            .unwrap();
+        assert_eq!(
+            router.pending_events.read().await[0].timestamp,
+            near.as_secs()
+        );
+
+        let (router, _dir) = test_router();
+        let router = router.with_future_timestamp_policy(skew, FutureTimestampPolicy::Clamp);
+        router
+            .accept_event(signed_event(30931, "", ahead), &mut oldest)
+            .await
+            .unwrap();
+        let pending = router.pending_events.read().await;
+        assert_eq!(pending[0].timestamp, pending[0].received_at);
+    }
+
+    #[tokio::test]
+    async fn staleness_can_follow_the_receive_time() {
+        let an_hour_ago = Timestamp::now() - Duration::from_secs(3600);
+        for (receive_time, min_age, max_age) in [(false, 3600, 3700), (true, 0, 100)] {
+            let mut oldest = None;
+            let (router, _dir) = test_router();
+            let router = router.with_receive_time_staleness(receive_time);
+            router
+                .accept_event(signed_event(30931, "", an_hour_ago), &mut oldest)
+                .await
+                .unwrap();
+            let age = router.age_secs(&router.pending_events.read().await[0]);
+            assert!((min_age..max_age).contains(&age), "{}", age);
+        }
+    }
+
+    #[test]
+    fn each_follower_is_encrypted_with_its_own_scheme() {
+        let platform = Keys::generate();
+        let (nip04_follower, nip44_follower) = (Keys::generate(), Keys::generate());
+        let payload = r#"{"symbol":"ETH","side":"buy"}"#;
+
+        let to_nip04 = encrypt_for_follower(
+            &platform,
+            &nip04_follower.public_key(),
+            payload,
+            FanoutEncryption::Nip04,
+        )
+        .unwrap();
+        let to_nip44 = encrypt_for_follower(
+            &platform,
+            &nip44_follower.public_key(),
+            payload,
+            FanoutEncryption::Nip44,
+        )
+        .unwrap();
+
+        let platform_pk = platform.public_key();
+        let opened = nip04::decrypt(nip04_follower.secret_key(), &platform_pk, &to_nip04);
+        assert_eq!(opened.unwrap(), payload);
+        let opened = nip44::decrypt(nip44_follower.secret_key(), &platform_pk, &to_nip44);
+        assert_eq!(opened.unwrap(), payload);
+        // Each copy is only readable by its follower, and only with its scheme
+        assert!(nip44::decrypt(nip04_follower.secret_key(), &platform_pk, &to_nip44).is_err());
+        assert!(nip44::decrypt(nip44_follower.secret_key(), &platform_pk, &to_nip04).is_err());
+    }
+
+    #[tokio::test]
+    async fn signal_insert_is_retried_after_a_failure() {
+        let Some(subs) = SubscriptionService::for_test("router_signal_retry").await else {
+            return;
+        };
+        // Fail the first insert only: a sequence keeps counting when the insert rolls back
+        subs.execute_test_sql(
+            "CREATE SEQUENCE signal_attempts;
+             CREATE FUNCTION fail_first_signal() RETURNS trigger AS $$
+             BEGIN
+                 IF nextval('signal_attempts') = 1 THEN
+                     RAISE EXCEPTION 'injected failure';
+                 END IF;
+                 RETURN NEW;
+             END $$ LANGUAGE plpgsql;
+             CREATE TRIGGER fail_first_signal BEFORE INSERT ON signals
+                 FOR EACH ROW EXECUTE FUNCTION fail_first_signal();",
+        )
+        .await;
+        let metrics = test_metrics();
+        let (router, _dir) = test_router();
+        let router = router.with_metrics(metrics.clone());
+
+        router
+            .record_signal_with_retry(&subs, SignalInsert::for_test("retried"))
+            .await;
+        let recorded = subs.recent_signal_event_ids(3600, 10).await.unwrap();
+        assert_eq!(recorded, ["retried"]);
+        assert_eq!(metrics.signal_persist_failures.get(), 0);
+    }
+
+    #[tokio::test]
+    async fn websocket_only_fanout_counts_skipped_publishes() {
+        let Some(subs) = SubscriptionService::for_test("router_publish_skipped").await else {
+            return;
+        };
+        subs.register_test_bot("bot1").await;
+        for follower in ["follower1", "follower2"] {
+            subs.add_subscription("bot1", follower, "secret", None, None, None)
+                .await
+                .unwrap();
+        }
+        let metrics = test_metrics();
+        let (router, fanout_rx, _dir) = copytrade_router(Arc::new(subs));
+        let router = router.with_metrics(metrics.clone());
+
+        router
+            .handle_copytrade_fanout(&trade_signal("bot1", "BTC"))
+            .await
+            .unwrap();
+        assert_eq!(fanout_rx.len(), 2);
+        assert_eq!(metrics.nostr_publish_skipped.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn first_event_waits_no_longer_than_max_latency() {
+        let (mut router, _dir) = test_router();
+        let (downstream_tx, downstream) = flume::unbounded();
+        router.downstream_tx = downstream_tx;
+        router.max_latency = Duration::from_millis(100);
+        let (input, input_rx) = flume::unbounded();
+        tokio::spawn(router.process_stream(input_rx));
+
+        let sent = Instant::now();
+        input
+            .send(signed_event(30931, "first", Timestamp::now()))
+            .unwrap();
+        // A trickle too slow to fill the batch must not keep pushing the deadline back
+        tokio::spawn(async move {
+            for i in 0..8 {
+                tokio::time::sleep(Duration::from_millis(30)).await;
+                let event = signed_event(30931, &format!("trickle {}", i), Timestamp::now());
+                if input.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let first = tokio::time::timeout(Duration::from_secs(2), downstream.recv_async())
+            .await
+            .unwrap()
+            .unwrap();
+        let waited = sent.elapsed();
+        assert_eq!(first.content, "first");
+        assert!(waited >= Duration::from_millis(100), "{:?}", waited);
+        assert!(waited < Duration::from_millis(200), "{:?}", waited);
+    }
+}
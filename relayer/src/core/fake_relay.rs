@@ -0,0 +1,71 @@
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+
+/// A relay on localhost for a single client: hands over each message the client sends and
+/// sends whatever the test queues
+pub struct FakeRelay {
+    pub url: String,
+    received: flume::Receiver<Value>,
+    outbound: flume::Sender<Value>,
+}
+
+impl FakeRelay {
+    pub async fn start() -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (received_tx, received) = flume::unbounded();
+        let (outbound, outbound_rx) = flume::unbounded::<Value>();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let (mut sink, mut stream) = ws.split();
+            loop {
+                tokio::select! {
+                    msg = stream.next() => match msg {
+                        Some(Ok(Message::Text(text))) => {
+                            if let Ok(value) = serde_json::from_str(text.as_str()) {
+                                let _ = received_tx.send(value);
+                            }
+                        }
+                        Some(Ok(_)) => {}
+                        _ => break,
+                    },
+                    out = outbound_rx.recv_async() => match out {
+                        Ok(value) => {
+                            if sink.send(Message::text(value.to_string())).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(_) => break,
+                    },
+                }
+            }
+        });
+        Self {
+            url,
+            received,
+            outbound,
+        }
+    }
+
+    pub fn send(&self, message: Value) {
+        self.outbound.send(message).unwrap();
+    }
+
+    /// Next client message of type `kind` (e.g. "REQ"), skipping any other
+    pub async fn next(&self, kind: &str) -> Value {
+        let wait = async {
+            loop {
+                let message = self.received.recv_async().await.unwrap();
+                if message[0] == kind {
+                    return message;
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), wait)
+            .await
+            .unwrap_or_else(|_| panic!("relay got no {}", kind))
+    }
+}
@@ -0,0 +1,152 @@
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+use crate::config::PauseMode;
+
+/// Operator switch that pauses event ingestion without disconnecting relays; shared by the
+/// event router and the admin endpoints
+#[derive(Clone)]
+pub struct IngestionGate {
+    paused: Arc<watch::Sender<bool>>,
+    paused_since: Arc<Mutex<Option<DateTime<Utc>>>>,
+    mode: PauseMode,
+    buffer_capacity: usize,
+}
+
+impl IngestionGate {
+    pub fn new(mode: PauseMode) -> Self {
+        let (paused, _) = watch::channel(false);
+        Self {
+            paused: Arc::new(paused),
+            paused_since: Arc::new(Mutex::new(None)),
+            mode,
+            buffer_capacity: 10_000,
+        }
+    }
+
+    /// Hold at most `capacity` events while paused in buffer mode
+    pub fn with_buffer_capacity(mut self, capacity: usize) -> Self {
+        self.buffer_capacity = capacity;
+        self
+    }
+
+    pub fn buffer_capacity(&self) -> usize {
+        self.buffer_capacity
+    }
+
+    /// What the router does with events that arrive while paused
+    pub fn mode(&self) -> PauseMode {
+        self.mode
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    pub fn paused_since(&self) -> Option<DateTime<Utc>> {
+        *self.paused_since.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Pause or resume ingestion; false if it was already in that state
+    pub fn set_paused(&self, paused: bool) -> bool {
+        let changed = self.paused.send_if_modified(|current| {
+            let changed = *current != paused;
+            *current = paused;
+            changed
+        });
+        if changed {
+            *self.paused_since.lock().unwrap_or_else(|e| e.into_inner()) = paused.then(Utc::now);
+        }
+        changed
+    }
+
+    /// Watch pause state changes; the router replays its buffer when this flips to false
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.paused.subscribe()
+    }
+}
+
+/// Events held while ingestion is paused in buffer mode. Bounded: once full, the oldest event
+/// is evicted to make room
+pub struct PauseBuffer<T> {
+    queue: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T> PauseBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            queue: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Hold `item`; true when an older event (or `item` itself at zero capacity) was dropped
+    pub fn push(&mut self, item: T) -> bool {
+        if self.capacity == 0 {
+            return true;
+        }
+        let evicted = self.queue.len() >= self.capacity;
+        if evicted {
+            self.queue.pop_front();
+        }
+        self.queue.push_back(item);
+        evicted
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Take everything held, oldest first
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.queue.drain(..)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pause_and_resume_toggle_state_once() {
+        let gate = IngestionGate::new(PauseMode::Buffer);
+        let rx = gate.subscribe();
+        assert!(!gate.is_paused());
+
+        assert!(gate.set_paused(true));
+        assert!(!gate.set_paused(true));
+        assert!(gate.is_paused());
+        assert!(*rx.borrow());
+        assert!(gate.paused_since().is_some());
+
+        assert!(gate.set_paused(false));
+        assert!(!gate.is_paused());
+        assert!(!*rx.borrow());
+        assert!(gate.paused_since().is_none());
+    }
+
+    #[test]
+    fn buffer_drops_oldest_when_full() {
+        let mut buffer = PauseBuffer::new(2);
+        assert!(!buffer.push(1));
+        assert!(!buffer.push(2));
+        assert!(buffer.push(3));
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer.drain().collect::<Vec<_>>(), vec![2, 3]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn zero_capacity_buffer_holds_nothing() {
+        let mut buffer = PauseBuffer::new(0);
+        assert!(buffer.push(1));
+        assert!(buffer.is_empty());
+    }
+}
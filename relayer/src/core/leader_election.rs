@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use deadpool_postgres::ClientWrapper;
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+use crate::core::subscription::SubscriptionService;
+
+/// Whether this instance currently holds the leader advisory lock
+#[derive(Clone, Default)]
+pub struct Leadership(Arc<AtomicBool>);
+
+impl Leadership {
+    pub fn is_leader(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn set(&self, leader: bool) {
+        self.0.store(leader, Ordering::Relaxed);
+    }
+}
+
+/// True when leader election is on and this instance does not hold the lock, so singleton
+/// jobs must skip their work
+pub fn is_follower(leadership: Option<&Leadership>) -> bool {
+    leadership.is_some_and(|l| !l.is_leader())
+}
+
+/// Postgres advisory-lock leader election so only one instance runs singleton background jobs
+pub struct LeaderElection {
+    svc: Arc<SubscriptionService>,
+    lock_key: i64,
+    interval: Duration,
+    leadership: Leadership,
+}
+
+impl LeaderElection {
+    pub fn new(svc: Arc<SubscriptionService>, lock_key: i64, interval: Duration) -> Self {
+        Self {
+            svc,
+            lock_key,
+            interval,
+            leadership: Leadership::default(),
+        }
+    }
+
+    pub fn leadership(&self) -> Leadership {
+        self.leadership.clone()
+    }
+
+    pub async fn run(self) {
+        // The lock is session-scoped: it lives exactly as long as this connection
+        let mut held: Option<ClientWrapper> = None;
+        loop {
+            match held.as_ref() {
+                Some(client) => {
+                    if let Err(e) = client.simple_query("SELECT 1").await {
+                        warn!(
+                            "Lost leader lock {} (connection check failed: {}); stepping down",
+                            self.lock_key, e
+                        );
+                        held = None;
+                        self.leadership.set(false);
+                    }
+                }
+                None => match self.svc.try_advisory_lock(self.lock_key).await {
+                    Ok(Some(client)) => {
+                        info!("Acquired leader lock {}; running as leader", self.lock_key);
+                        held = Some(client);
+                        self.leadership.set(true);
+                    }
+                    Ok(None) => debug!(
+                        "Leader lock {} held by another instance; running as follower",
+                        self.lock_key
+                    ),
+                    Err(e) => warn!("Failed to try leader lock {}: {}", self.lock_key, e),
+                },
+            }
+            sleep(self.interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_non_leaders_skip_singleton_jobs() {
+        // Without election every instance runs the jobs
+        assert!(!is_follower(None));
+
+        let leadership = Leadership::default();
+        let seen_by_worker = leadership.clone();
+        assert!(is_follower(Some(&seen_by_worker)));
+        leadership.set(true);
+        assert!(!is_follower(Some(&seen_by_worker)));
+        leadership.set(false);
+        assert!(is_follower(Some(&seen_by_worker)));
+    }
+}
@@ -1,5 +1,15 @@
 pub mod dedupe_engine;
+pub mod eth_signature;
 pub mod event_router;
+#[cfg(test)]
+pub mod fake_relay;
+pub mod ingestion;
+pub mod leader_election;
+pub mod payloads;
+pub mod presence_monitor;
+pub mod pubkey;
 pub mod relay_pool;
+pub mod relay_transport;
 pub mod settlement_worker;
+pub mod signal_batcher;
 pub mod subscription;
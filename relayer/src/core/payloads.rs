@@ -0,0 +1,405 @@
+use crate::core::pubkey::canonical_pubkey;
+use anyhow::{Result, bail};
+use nostr_sdk::Tags;
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Tag names read by [`payload_from_tags`]; each takes the tag's first value
+const STRING_TAGS: &[&str] = &[
+    "agent_eth_address",
+    "agent",
+    "account",
+    "eth_address",
+    "follower_pubkey",
+    "follower",
+    "role",
+    "symbol",
+    "side",
+    "status",
+    "tx_hash",
+    "oid",
+    "order_id",
+];
+const NUMBER_TAGS: &[&str] = &["size", "price", "pnl", "pnl_usd"];
+const BOOL_TAGS: &[&str] = &["test_mode"];
+
+/// Field names of [`TradeSignalPayload`], with the synonyms it already accepts
+const TRADE_FIELDS: &[(&str, &[&str])] = &[
+    ("agent_eth_address", &["agent"]),
+    ("account", &[]),
+    ("eth_address", &[]),
+    ("follower_pubkey", &["follower"]),
+    ("role", &[]),
+    ("symbol", &[]),
+    ("side", &[]),
+    ("size", &[]),
+    ("price", &[]),
+    ("status", &[]),
+    ("tx_hash", &[]),
+    ("oid", &["order_id"]),
+    ("pnl", &[]),
+    ("pnl_usd", &[]),
+    ("test_mode", &[]),
+];
+
+/// Decrypted trade signal / execution report payload published by agents
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TradeSignalPayload {
+    #[serde(alias = "agent", deserialize_with = "lenient_string")]
+    pub agent_eth_address: Option<String>,
+    /// Older agents only send the trading account; kept separate so it never clashes with `agent`
+    #[serde(deserialize_with = "lenient_string")]
+    pub account: Option<String>,
+    #[serde(deserialize_with = "lenient_string")]
+    pub eth_address: Option<String>,
+    #[serde(alias = "follower", deserialize_with = "lenient_pubkey")]
+    pub follower_pubkey: Option<String>,
+    #[serde(deserialize_with = "lenient_string")]
+    pub role: Option<String>,
+    #[serde(deserialize_with = "lenient_string")]
+    pub symbol: Option<String>,
+    #[serde(deserialize_with = "lenient_string")]
+    pub side: Option<String>,
+    #[serde(deserialize_with = "lenient_f64")]
+    pub size: Option<f64>,
+    #[serde(deserialize_with = "lenient_f64")]
+    pub price: Option<f64>,
+    #[serde(deserialize_with = "lenient_string")]
+    pub status: Option<String>,
+    #[serde(deserialize_with = "lenient_string")]
+    pub tx_hash: Option<String>,
+    #[serde(alias = "order_id", deserialize_with = "lenient_string")]
+    pub oid: Option<String>,
+    #[serde(deserialize_with = "lenient_f64")]
+    pub pnl: Option<f64>,
+    #[serde(deserialize_with = "lenient_f64")]
+    pub pnl_usd: Option<f64>,
+    #[serde(deserialize_with = "lenient_bool")]
+    pub test_mode: Option<bool>,
+}
+
+impl TradeSignalPayload {
+    /// Parse a JSON payload; returns None when it is not a JSON object
+    pub fn parse(plaintext: &str) -> Option<Self> {
+        serde_json::from_str(plaintext).ok()
+    }
+
+    /// Like [`Self::parse`], also reading fields under the vendor keys in `field_map`
+    pub fn parse_with(plaintext: &str, field_map: &FieldMap) -> Option<Self> {
+        if field_map.is_empty() {
+            return Self::parse(plaintext);
+        }
+        let Value::Object(mut fields) = serde_json::from_str(plaintext).ok()? else {
+            return None;
+        };
+        field_map.apply(&mut fields);
+        serde_json::from_value(Value::Object(fields)).ok()
+    }
+
+    /// Agent eth address, preferring the explicit field over the legacy synonyms
+    pub fn agent_eth(&self) -> Option<&str> {
+        self.agent_eth_address
+            .as_deref()
+            .or(self.account.as_deref())
+            .or(self.eth_address.as_deref())
+    }
+
+    /// Test trades are flagged explicitly or reported with a `simulated` status
+    pub fn is_test(&self) -> bool {
+        self.test_mode.unwrap_or(false)
+            || self
+                .status
+                .as_deref()
+                .map(|s| s.eq_ignore_ascii_case("simulated"))
+                .unwrap_or(false)
+    }
+}
+
+/// Vendor JSON keys accepted for trade payload fields (`filters.field_map`), so agents that send
+/// e.g. `px`/`sz` need no code change
+#[derive(Debug, Clone, Default)]
+pub struct FieldMap(Arc<Vec<MappedField>>);
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct MappedField {
+    name: &'static str,
+    synonyms: &'static [&'static str],
+    keys: Vec<String>,
+}
+
+impl FieldMap {
+    /// Fails on a field name [`TradeSignalPayload`] does not have
+    pub fn new(map: &HashMap<String, Vec<String>>) -> Result<Self> {
+        let mut entries = Vec::with_capacity(map.len());
+        for (field, keys) in map {
+            let Some(&(name, synonyms)) = TRADE_FIELDS.iter().find(|(name, _)| name == field)
+            else {
+                bail!(
+                    "filters.field_map: unknown payload field `{}` (expected one of {})",
+                    field,
+                    TRADE_FIELDS
+                        .iter()
+                        .map(|(name, _)| *name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            };
+            entries.push(MappedField {
+                name,
+                synonyms,
+                keys: keys.clone(),
+            });
+        }
+        entries.sort();
+        Ok(Self(Arc::new(entries)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Copy the first mapped key present into each field the payload does not already set under
+    /// its own name or a built-in synonym
+    fn apply(&self, fields: &mut Map<String, Value>) {
+        for field in self.0.iter() {
+            if fields.contains_key(field.name)
+                || field.synonyms.iter().any(|s| fields.contains_key(*s))
+            {
+                continue;
+            }
+            if let Some(value) = field.keys.iter().find_map(|key| fields.get(key)).cloned() {
+                fields.insert(field.name.to_string(), value);
+            }
+        }
+    }
+}
+
+/// Build the JSON payload a tag-only event stands for, using the same field names as the
+/// encrypted payload. None when no recognized tag is present
+pub fn payload_from_tags(tags: &Tags) -> Option<String> {
+    let mut fields = Map::new();
+    for tag in tags.iter() {
+        let [name, value, ..] = tag.as_slice() else {
+            continue;
+        };
+        let name = name.as_str();
+        let value = if STRING_TAGS.contains(&name) {
+            Value::String(value.clone())
+        } else if NUMBER_TAGS.contains(&name) {
+            match value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+            {
+                Some(n) => Value::Number(n),
+                None => continue,
+            }
+        } else if BOOL_TAGS.contains(&name) {
+            match value.trim().parse::<bool>() {
+                Ok(b) => Value::Bool(b),
+                Err(_) => continue,
+            }
+        } else {
+            continue;
+        };
+        // First occurrence wins, as with duplicate JSON keys in serde
+        fields.entry(name).or_insert(value);
+    }
+    (!fields.is_empty()).then(|| Value::Object(fields).to_string())
+}
+
+/// Plaintext agent registration payload (kind 30935)
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AgentRegisterPayload {
+    #[serde(deserialize_with = "lenient_string")]
+    pub nostr_pubkey: Option<String>,
+    #[serde(deserialize_with = "lenient_string")]
+    pub bot_pubkey: Option<String>,
+    #[serde(alias = "account", deserialize_with = "lenient_string")]
+    pub eth_address: Option<String>,
+    #[serde(deserialize_with = "lenient_string")]
+    pub name: Option<String>,
+    #[serde(deserialize_with = "lenient_string")]
+    pub signature: Option<String>,
+}
+
+impl AgentRegisterPayload {
+    pub fn parse(content: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(content)
+    }
+}
+
+// Wrong-typed optional fields are treated as absent instead of failing the whole payload
+
+fn lenient_string<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    Ok(match Value::deserialize(d)? {
+        Value::String(s) => Some(s),
+        _ => None,
+    })
+}
+
+fn lenient_pubkey<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    Ok(lenient_string(d)?.map(|s| canonical_pubkey(&s)))
+}
+
+fn lenient_f64<'de, D: Deserializer<'de>>(d: D) -> Result<Option<f64>, D::Error> {
+    Ok(Value::deserialize(d)?.as_f64())
+}
+
+fn lenient_bool<'de, D: Deserializer<'de>>(d: D) -> Result<Option<bool>, D::Error> {
+    Ok(Value::deserialize(d)?.as_bool())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trade_payload_reads_aliases_and_ignores_wrong_types() {
+        let payload = TradeSignalPayload::parse(
+            r#"{"agent":"0xabc","follower":" f1 ","order_id":"o1","size":"big","price":2.5,"test_mode":"yes"}"#,
+        )
+        .unwrap();
+        assert_eq!(payload.agent_eth_address.as_deref(), Some("0xabc"));
+        assert_eq!(payload.follower_pubkey.as_deref(), Some("f1"));
+        assert_eq!(payload.oid.as_deref(), Some("o1"));
+        assert_eq!(payload.size, None);
+        assert_eq!(payload.price, Some(2.5));
+        assert_eq!(payload.test_mode, None);
+        assert!(TradeSignalPayload::parse("\"text\"").is_none());
+        assert!(TradeSignalPayload::parse("not json").is_none());
+    }
+
+    #[test]
+    fn agent_eth_prefers_explicit_field() {
+        let payload =
+            TradeSignalPayload::parse(r#"{"account":"0x1","eth_address":"0x2"}"#).unwrap();
+        assert_eq!(payload.agent_eth(), Some("0x1"));
+        let payload = TradeSignalPayload::parse(
+            r#"{"agent_eth_address":"0x0","account":"0x1","eth_address":"0x2"}"#,
+        )
+        .unwrap();
+        assert_eq!(payload.agent_eth(), Some("0x0"));
+    }
+
+    #[test]
+    fn test_trades_are_flagged_or_simulated() {
+        assert!(
+            TradeSignalPayload::parse(r#"{"test_mode":true}"#)
+                .unwrap()
+                .is_test()
+        );
+        assert!(
+            TradeSignalPayload::parse(r#"{"status":"Simulated"}"#)
+                .unwrap()
+                .is_test()
+        );
+        assert!(
+            !TradeSignalPayload::parse(r#"{"status":"filled"}"#)
+                .unwrap()
+                .is_test()
+        );
+    }
+
+    #[test]
+    fn register_payload_accepts_account_alias() {
+        let payload =
+            AgentRegisterPayload::parse(r#"{"account":"0xabc","name":7,"bot_pubkey":"b"}"#)
+                .unwrap();
+        assert_eq!(payload.eth_address.as_deref(), Some("0xabc"));
+        assert_eq!(payload.name, None);
+        assert_eq!(payload.bot_pubkey.as_deref(), Some("b"));
+    }
+
+    fn field_map(entries: &[(&str, &[&str])]) -> Result<FieldMap> {
+        FieldMap::new(
+            &entries
+                .iter()
+                .map(|(field, keys)| {
+                    (
+                        field.to_string(),
+                        keys.iter().map(|k| k.to_string()).collect(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn vendor_keys_fill_missing_fields() {
+        let map = field_map(&[("price", &["px"]), ("size", &["sz", "qty"])]).unwrap();
+        let payload =
+            TradeSignalPayload::parse_with(r#"{"px":2500,"qty":3,"sz":1}"#, &map).unwrap();
+        assert_eq!(payload.price, Some(2500.0));
+        // First listed key present wins
+        assert_eq!(payload.size, Some(1.0));
+    }
+
+    #[test]
+    fn own_name_and_builtin_synonyms_take_precedence() {
+        let map = field_map(&[("price", &["px"]), ("oid", &["client_oid"])]).unwrap();
+        let payload = TradeSignalPayload::parse_with(
+            r#"{"price":1,"px":2,"order_id":"a","client_oid":"b"}"#,
+            &map,
+        )
+        .unwrap();
+        assert_eq!(payload.price, Some(1.0));
+        assert_eq!(payload.oid.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn unknown_field_is_rejected() {
+        assert!(field_map(&[("leverage", &["lev"])]).is_err());
+        assert!(field_map(&[]).unwrap().is_empty());
+    }
+
+    fn tags(list: &[&[&str]]) -> Tags {
+        Tags::new(
+            list.iter()
+                .map(|tag| nostr_sdk::Tag::parse(tag.iter().copied()).unwrap())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn tag_payload_reads_trade_fields() {
+        let plaintext = payload_from_tags(&tags(&[
+            &["agent", "0xabc"],
+            &["symbol", "ETH"],
+            &["side", "buy"],
+            &["size", "1"],
+            &["price", " 2500.5 "],
+            &["test_mode", "false"],
+        ]))
+        .unwrap();
+        let payload = TradeSignalPayload::parse(&plaintext).unwrap();
+        assert_eq!(payload.agent_eth(), Some("0xabc"));
+        assert_eq!(payload.symbol.as_deref(), Some("ETH"));
+        assert_eq!(payload.side.as_deref(), Some("buy"));
+        assert_eq!(payload.size, Some(1.0));
+        assert_eq!(payload.price, Some(2500.5));
+        assert!(!payload.is_test());
+    }
+
+    #[test]
+    fn tag_payload_skips_unknown_and_malformed_tags() {
+        assert_eq!(
+            payload_from_tags(&tags(&[
+                &["t", "moltrade"],
+                &["symbol"],
+                &["size", "big"],
+                &["test_mode", "yes"],
+            ])),
+            None
+        );
+        // The first occurrence of a tag wins
+        let plaintext =
+            payload_from_tags(&tags(&[&["symbol", "ETH"], &["symbol", "BTC"]])).unwrap();
+        assert_eq!(plaintext, r#"{"symbol":"ETH"}"#);
+    }
+}
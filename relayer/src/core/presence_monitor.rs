@@ -0,0 +1,180 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use serde_json::json;
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+use crate::core::leader_election::{Leadership, is_follower};
+use crate::core::subscription::SubscriptionService;
+
+const WEBHOOK_ATTEMPTS: u32 = 3;
+const WEBHOOK_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Periodically flips `bots.online` based on heartbeat staleness and notifies a webhook on transitions
+#[derive(Clone)]
+pub struct PresenceMonitor {
+    svc: Arc<SubscriptionService>,
+    client: reqwest::Client,
+    interval: Duration,
+    offline_after: Duration,
+    webhook_url: Option<String>,
+    leadership: Option<Leadership>,
+}
+
+impl PresenceMonitor {
+    pub fn new(
+        svc: Arc<SubscriptionService>,
+        interval: Duration,
+        offline_after: Duration,
+        webhook_url: Option<String>,
+    ) -> Self {
+        Self {
+            svc,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            interval,
+            offline_after,
+            webhook_url: webhook_url.filter(|u| !u.trim().is_empty()),
+            leadership: None,
+        }
+    }
+
+    /// Only scan (and notify) while this instance holds the leader lock
+    pub fn with_leadership(mut self, leadership: Leadership) -> Self {
+        self.leadership = Some(leadership);
+        self
+    }
+
+    pub async fn run(self) {
+        loop {
+            if is_follower(self.leadership.as_ref()) {
+                debug!("presence: not leader, skipping scan");
+            } else if let Err(e) = self.tick().await {
+                warn!("presence scan failed: {}", e);
+            }
+            sleep(self.interval).await;
+        }
+    }
+
+    async fn tick(&self) -> Result<()> {
+        let transitions = self
+            .svc
+            .update_bot_presence(self.offline_after.as_secs() as i64)
+            .await?;
+
+        if transitions.is_empty() {
+            debug!("presence: no transitions");
+            return Ok(());
+        }
+
+        for (bot_pubkey, online) in transitions {
+            let state = if online { "online" } else { "offline" };
+            info!("presence: bot {} is now {}", bot_pubkey, state);
+            let Some(url) = &self.webhook_url else {
+                continue;
+            };
+            if let Err(e) = self.notify(url, &bot_pubkey, state).await {
+                warn!(
+                    "presence: webhook for bot {} ({}) failed: {}",
+                    bot_pubkey, state, e
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// POST the transition, retrying with backoff on transport errors and non-2xx responses
+    async fn notify(&self, url: &str, bot_pubkey: &str, state: &str) -> Result<()> {
+        let body = json!({
+            "bot_pubkey": bot_pubkey,
+            "state": state,
+            "at": Utc::now().to_rfc3339(),
+        });
+
+        let mut backoff = WEBHOOK_BACKOFF;
+        let mut last_err = anyhow!("webhook not attempted");
+        for attempt in 1..=WEBHOOK_ATTEMPTS {
+            match self.client.post(url).json(&body).send().await {
+                Ok(resp) if resp.status().is_success() => return Ok(()),
+                Ok(resp) => last_err = anyhow!("webhook returned {}", resp.status()),
+                Err(e) => last_err = e.into(),
+            }
+            if attempt < WEBHOOK_ATTEMPTS {
+                sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+        Err(last_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Webhook receiver collecting every body POSTed to it
+    async fn webhook() -> (String, Arc<Mutex<Vec<serde_json::Value>>>) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let received = calls.clone();
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::post(move |axum::Json(body): axum::Json<serde_json::Value>| {
+                let received = received.clone();
+                async move { received.lock().unwrap().push(body) }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/", addr), calls)
+    }
+
+    #[tokio::test]
+    async fn each_transition_notifies_the_webhook_once() {
+        let Some(svc) = SubscriptionService::for_test("presence_webhook").await else {
+            return;
+        };
+        svc.register_test_bot("bot1").await;
+        let svc = Arc::new(svc);
+        let (url, calls) = webhook().await;
+        let monitor = PresenceMonitor::new(
+            svc.clone(),
+            Duration::from_secs(30),
+            Duration::from_secs(60),
+            Some(url),
+        );
+        let states = || {
+            let calls = calls.lock().unwrap();
+            calls
+                .iter()
+                .map(|c| (c["bot_pubkey"].clone(), c["state"].clone()))
+                .collect::<Vec<_>>()
+        };
+
+        svc.update_bot_last_seen("bot1").await.unwrap();
+        monitor.tick().await.unwrap();
+        assert_eq!(states(), [("bot1".into(), "online".into())]);
+        // No change, no call
+        monitor.tick().await.unwrap();
+        assert_eq!(states().len(), 1);
+
+        svc.execute_test_sql("UPDATE bots SET last_seen_at = now() - interval '1 hour'")
+            .await;
+        monitor.tick().await.unwrap();
+        monitor.tick().await.unwrap();
+        assert_eq!(
+            states(),
+            [
+                ("bot1".into(), "online".into()),
+                ("bot1".into(), "offline".into())
+            ]
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use nostr_sdk::prelude::PublicKey;
+
+/// Canonical lowercase hex form of a nostr pubkey given as hex or `npub`.
+/// Anything that is not a valid pubkey is only trimmed, so other identifiers pass through
+pub fn canonical_pubkey(raw: &str) -> String {
+    let raw = raw.trim();
+    PublicKey::parse(raw)
+        .map(|pk| pk.to_hex())
+        .unwrap_or_else(|_| raw.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // NIP-19 example key
+    const HEX: &str = "7e7e9c42a91bfef19fa929e5fda1b72e0ebc1a4c1141673e2794234d86addf4e";
+    const NPUB: &str = "npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg";
+
+    #[test]
+    fn hex_and_npub_name_the_same_pubkey() {
+        assert_eq!(canonical_pubkey(NPUB), HEX);
+        assert_eq!(canonical_pubkey(&format!(" {} ", HEX)), HEX);
+        assert_eq!(canonical_pubkey(&HEX.to_uppercase()), HEX);
+    }
+
+    #[test]
+    fn other_identifiers_are_only_trimmed() {
+        assert_eq!(canonical_pubkey(" follower-1 "), "follower-1");
+        assert_eq!(canonical_pubkey("0xAbC"), "0xAbC");
+    }
+}
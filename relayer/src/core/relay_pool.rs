@@ -1,11 +1,14 @@
 use crate::api::metrics::Metrics;
+use crate::core::relay_transport::RelayTransport;
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 use flume::{Receiver, Sender};
-use nostr_sdk::{Client, Event, Filter, Keys, Kind, RelayPoolNotification};
+use nostr_sdk::{Client, Event, Filter, Keys, Kind, RelayMessage, RelayPoolNotification};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::sync::Arc as StdArc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tracing::{error, info, warn};
 
@@ -18,6 +21,56 @@ pub enum RelayStatus {
     Error(String),
 }
 
+/// Most recent NOTICE a relay sent us
+#[derive(Debug, Clone)]
+pub struct RelayNotice {
+    pub message: String,
+    pub received_at: DateTime<Utc>,
+}
+
+/// First wait before resubscribing after a rate-limit NOTICE; doubles while notices keep coming
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Kinds relays are subscribed to, shared with the event router; `None` means every kind
+#[derive(Clone, Default)]
+pub struct AllowedKinds(Arc<std::sync::RwLock<Option<Vec<u16>>>>);
+
+impl AllowedKinds {
+    pub fn new(kinds: Option<Vec<u16>>) -> Self {
+        Self(Arc::new(std::sync::RwLock::new(normalize_kinds(kinds))))
+    }
+
+    pub fn get(&self) -> Option<Vec<u16>> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn allows(&self, kind: u16) -> bool {
+        match &*self.0.read().unwrap_or_else(|e| e.into_inner()) {
+            Some(kinds) => kinds.contains(&kind),
+            None => true,
+        }
+    }
+
+    /// Swap in a new set (empty means every kind); false if it was already in place
+    fn replace(&self, kinds: Option<Vec<u16>>) -> bool {
+        let kinds = normalize_kinds(kinds);
+        let mut current = self.0.write().unwrap_or_else(|e| e.into_inner());
+        if *current == kinds {
+            return false;
+        }
+        *current = kinds;
+        true
+    }
+
+    fn filter(&self) -> Filter {
+        match self.get() {
+            Some(kinds) => Filter::new().kinds(kinds.into_iter().map(Kind::Custom)),
+            None => Filter::new(),
+        }
+    }
+}
+
 /// Connection state for a single relay
 #[derive(Clone)]
 pub struct RelayConnection {
@@ -25,6 +78,8 @@ pub struct RelayConnection {
     client: Arc<Client>,
     status: Arc<RwLock<RelayStatus>>,
     event_tx: Sender<Event>,
+    filter: Arc<RwLock<Filter>>,
+    last_notice: Arc<RwLock<Option<RelayNotice>>>,
 }
 
 /// Pool of relay connections with health checking and load balancing
@@ -33,8 +88,17 @@ pub struct RelayPool {
     health_check_interval: Duration,
     max_connections: usize,
     event_tx: Sender<Event>,
-    allowed_kinds: Option<Vec<u16>>,
+    allowed_kinds: AllowedKinds,
     metrics: Option<StdArc<Metrics>>,
+    idle_resubscribe: Option<Duration>,
+    backfill_window: Option<Duration>,
+    transport: RelayTransport,
+}
+
+/// Historical events buffered after a (re)subscribe, forwarded together on EOSE or window expiry
+struct Backfill {
+    deadline: Instant,
+    events: Vec<Event>,
 }
 
 impl RelayPool {
@@ -50,8 +114,11 @@ impl RelayPool {
             health_check_interval,
             max_connections,
             event_tx: tx,
-            allowed_kinds,
+            allowed_kinds: AllowedKinds::new(allowed_kinds),
             metrics: None,
+            idle_resubscribe: None,
+            backfill_window: None,
+            transport: RelayTransport::default(),
         };
         (pool, rx)
     }
@@ -61,6 +128,55 @@ impl RelayPool {
         self
     }
 
+    /// Resubscribe when a relay has been silent for `idle`; zero disables the watchdog
+    pub fn with_idle_resubscribe(mut self, idle: Duration) -> Self {
+        self.idle_resubscribe = (!idle.is_zero()).then_some(idle);
+        self
+    }
+
+    /// Buffer events for up to `window` after each (re)subscribe and forward them as one
+    /// sorted, de-duplicated batch on EOSE; zero disables coalescing
+    pub fn with_backfill_coalescing(mut self, window: Duration) -> Self {
+        self.backfill_window = (!window.is_zero()).then_some(window);
+        self
+    }
+
+    /// Connect through `transport`, e.g. to accept self-signed certificates on some relays
+    pub fn with_transport(mut self, transport: RelayTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Handle on the kinds relays are subscribed to, for filters that must follow runtime changes
+    pub fn allowed_kinds(&self) -> AllowedKinds {
+        self.allowed_kinds.clone()
+    }
+
+    /// Change the kinds relays are subscribed to (`None` or empty means every kind) and
+    /// resubscribe every connected relay with the new filter. Returns how many were resubscribed;
+    /// zero when the set did not change
+    pub async fn set_allowed_kinds(&self, kinds: Option<Vec<u16>>) -> usize {
+        if !self.allowed_kinds.replace(kinds) {
+            return 0;
+        }
+        let filter = self.allowed_kinds.filter();
+        let connections: Vec<RelayConnection> = self
+            .connections
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        for connection in &connections {
+            *connection.filter.write().await = filter.clone();
+            Self::resubscribe(connection, &self.metrics).await;
+        }
+        info!(
+            "Allowed kinds now {:?}, resubscribed {} relays",
+            self.allowed_kinds.get(),
+            connections.len()
+        );
+        connections.len()
+    }
+
     /// Connect to a relay and subscribe to events
     pub async fn connect_and_subscribe(&self, relay_url: String) -> Result<()> {
         if self.connections.len() >= self.max_connections {
@@ -75,7 +191,7 @@ impl RelayPool {
 
         info!("Connecting to relay: {}", relay_url);
 
-        let client = Client::new(Keys::generate());
+        let client = self.transport.client(Keys::generate());
 
         // Add relay to client
         client
@@ -91,15 +207,9 @@ impl RelayPool {
         let event_tx = self.event_tx.clone();
 
         // Subscribe using allowed kinds if provided, otherwise subscribe to all events
-        let filter = match &self.allowed_kinds {
-            Some(kinds) if !kinds.is_empty() => {
-                let kinds: Vec<Kind> = kinds.iter().map(|k| Kind::Custom(*k)).collect();
-                Filter::new().kinds(kinds)
-            }
-            _ => Filter::new(),
-        };
+        let filter = self.allowed_kinds.filter();
         client
-            .subscribe(filter, None)
+            .subscribe(filter.clone(), None)
             .await
             .context("Failed to subscribe to relay")?;
 
@@ -108,13 +218,21 @@ impl RelayPool {
             client: Arc::new(client),
             status: status.clone(),
             event_tx: event_tx.clone(),
+            filter: Arc::new(RwLock::new(filter)),
+            last_notice: Arc::new(RwLock::new(None)),
         };
 
         self.connections
             .insert(relay_url.clone(), connection.clone());
 
         // Spawn task to handle events from this relay
-        tokio::spawn(Self::handle_relay_events(connection, event_tx));
+        tokio::spawn(Self::handle_relay_events(
+            connection,
+            event_tx,
+            self.metrics.clone(),
+            self.idle_resubscribe,
+            self.backfill_window,
+        ));
 
         info!(
             "Successfully connected and subscribed to relay: {}",
@@ -124,17 +242,130 @@ impl RelayPool {
     }
 
     /// Handle events from a single relay connection
-    async fn handle_relay_events(connection: RelayConnection, event_tx: Sender<Event>) {
+    async fn handle_relay_events(
+        connection: RelayConnection,
+        event_tx: Sender<Event>,
+        metrics: Option<StdArc<Metrics>>,
+        idle_resubscribe: Option<Duration>,
+        backfill_window: Option<Duration>,
+    ) {
         let mut notifications = connection.client.notifications();
+        let start_backfill = || {
+            backfill_window.map(|w| Backfill {
+                deadline: Instant::now() + w,
+                events: Vec::new(),
+            })
+        };
+        let mut backfill = start_backfill();
+        // Resubscribes wait until this passes after a rate-limit NOTICE
+        let mut rate_limited_until: Option<Instant> = None;
+        let mut rate_limit_backoff = RATE_LIMIT_BACKOFF;
+
+        loop {
+            // Wake for whichever comes first: the backfill window closing or the idle watchdog
+            let backfill_left = backfill
+                .as_ref()
+                .map(|b| b.deadline.saturating_duration_since(Instant::now()));
+            let wait = match (backfill_left, idle_resubscribe) {
+                (Some(b), Some(i)) => Some(b.min(i)),
+                (b, i) => b.or(i),
+            };
+            let received = match wait {
+                Some(wait) => match tokio::time::timeout(wait, notifications.recv()).await {
+                    Ok(received) => received,
+                    Err(_)
+                        if backfill
+                            .as_ref()
+                            .is_some_and(|b| Instant::now() >= b.deadline) =>
+                    {
+                        if !Self::flush_backfill(&connection.url, backfill.take(), &event_tx).await
+                        {
+                            break;
+                        }
+                        continue;
+                    }
+                    Err(_) => {
+                        // Socket may be up while the relay silently dropped our subscription
+                        warn!(
+                            "No notifications from {} for {:?}, resubscribing",
+                            connection.url, wait
+                        );
+                        if !Self::flush_backfill(&connection.url, backfill.take(), &event_tx).await
+                        {
+                            break;
+                        }
+                        Self::wait_rate_limit(&connection.url, rate_limited_until).await;
+                        Self::resubscribe(&connection, &metrics).await;
+                        backfill = start_backfill();
+                        continue;
+                    }
+                },
+                None => notifications.recv().await,
+            };
+            let notification = match received {
+                Ok(n) => n,
+                Err(_) => break,
+            };
 
-        while let Ok(notification) = notifications.recv().await {
             match notification {
                 RelayPoolNotification::Event { event, .. } => {
+                    if let Some(b) = backfill.as_mut() {
+                        b.events.push(*event);
+                        continue;
+                    }
                     if let Err(e) = event_tx.send_async(*event).await {
                         error!("Failed to send event to pipeline: {}", e);
                         break;
                     }
                 }
+                RelayPoolNotification::Message {
+                    message: RelayMessage::EndOfStoredEvents(_),
+                    ..
+                } => {
+                    // Relay finished sending stored events; release the coalesced batch now
+                    let forwarded =
+                        Self::flush_backfill(&connection.url, backfill.take(), &event_tx).await;
+                    if !forwarded {
+                        break;
+                    }
+                }
+                RelayPoolNotification::Message {
+                    message: RelayMessage::Closed { message, .. },
+                    ..
+                } => {
+                    warn!(
+                        "Relay {} closed our subscription ({}), resubscribing",
+                        connection.url, message
+                    );
+                    if !Self::flush_backfill(&connection.url, backfill.take(), &event_tx).await {
+                        break;
+                    }
+                    Self::wait_rate_limit(&connection.url, rate_limited_until).await;
+                    Self::resubscribe(&connection, &metrics).await;
+                    backfill = start_backfill();
+                }
+                RelayPoolNotification::Message {
+                    message: RelayMessage::Notice(message),
+                    ..
+                } => {
+                    warn!("NOTICE from relay {}: {}", connection.url, message);
+                    if is_rate_limit_notice(&message) {
+                        // Back off harder while the relay keeps complaining, start over once it stops
+                        rate_limit_backoff = next_rate_limit_backoff(
+                            rate_limit_backoff,
+                            rate_limited_until,
+                            Instant::now(),
+                        );
+                        rate_limited_until = Some(Instant::now() + rate_limit_backoff);
+                        if let Some(m) = &metrics {
+                            m.relay_rate_limited.inc();
+                        }
+                    }
+                    *connection.last_notice.write().await = Some(RelayNotice {
+                        message: message.to_string(),
+                        received_at: Utc::now(),
+                    });
+                }
                 RelayPoolNotification::Message { message, .. } => {
                     // Handle other message types if needed
                     info!("Received message from {}: {:?}", connection.url, message);
@@ -147,6 +378,62 @@ impl RelayPool {
         *connection.status.write().await = RelayStatus::Disconnected;
     }
 
+    /// Hold off a resubscribe while a rate-limit NOTICE backoff is running
+    async fn wait_rate_limit(url: &str, until: Option<Instant>) {
+        let Some(until) = until.filter(|u| *u > Instant::now()) else {
+            return;
+        };
+        info!(
+            "Relay {} rate-limited us, waiting {:?} before resubscribing",
+            url,
+            until - Instant::now()
+        );
+        tokio::time::sleep_until(until.into()).await;
+    }
+
+    /// Forward buffered backfill events oldest first, once per id; false if the pipeline is gone
+    async fn flush_backfill(
+        url: &str,
+        backfill: Option<Backfill>,
+        event_tx: &Sender<Event>,
+    ) -> bool {
+        let Some(backfill) = backfill else {
+            return true;
+        };
+        let mut events = backfill.events;
+        events.sort_by_key(|e| e.created_at);
+        let mut seen = HashSet::with_capacity(events.len());
+        events.retain(|e| seen.insert(e.id));
+        if !events.is_empty() {
+            info!(
+                "Forwarding {} coalesced backfill events from {}",
+                events.len(),
+                url
+            );
+        }
+        for event in events {
+            if let Err(e) = event_tx.send_async(event).await {
+                error!("Failed to send event to pipeline: {}", e);
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Drop any lingering subscriptions and subscribe again with the current filter
+    async fn resubscribe(connection: &RelayConnection, metrics: &Option<StdArc<Metrics>>) {
+        connection.client.unsubscribe_all().await;
+        let filter = connection.filter.read().await.clone();
+        match connection.client.subscribe(filter, None).await {
+            Ok(_) => {
+                if let Some(m) = metrics {
+                    m.resubscribe_total.inc();
+                }
+            }
+            Err(e) => error!("Failed to resubscribe to {}: {}", connection.url, e),
+        }
+    }
+
     /// Connect to multiple relays in parallel
     pub async fn subscribe_all(&self, relay_urls: Vec<String>) -> Result<()> {
         let tasks: Vec<_> = relay_urls
@@ -214,6 +501,17 @@ impl RelayPool {
         statuses
     }
 
+    /// Most recent NOTICE per relay, for relays that sent one
+    pub async fn last_notices(&self) -> HashMap<String, RelayNotice> {
+        let mut notices = HashMap::new();
+        for entry in self.connections.iter() {
+            if let Some(notice) = entry.value().last_notice.read().await.clone() {
+                notices.insert(entry.key().clone(), notice);
+            }
+        }
+        notices
+    }
+
     /// Disconnect and remove a relay
     pub async fn disconnect_relay(&self, relay_url: &str) -> Result<()> {
         if let Some((_, connection)) = self.connections.remove(relay_url) {
@@ -245,6 +543,160 @@ impl Clone for RelayPool {
             event_tx: self.event_tx.clone(),
             allowed_kinds: self.allowed_kinds.clone(),
             metrics: self.metrics.clone(),
+            idle_resubscribe: self.idle_resubscribe,
+            backfill_window: self.backfill_window,
+            transport: self.transport.clone(),
+        }
+    }
+}
+
+/// NIP-01 machine-readable `rate-limited:` prefix, or free-text notices that say the same
+fn is_rate_limit_notice(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    message.starts_with("rate-limited")
+        || message.contains("rate limit")
+        || message.contains("too many")
+}
+
+/// Wait after a rate-limit NOTICE: double the current one while the previous wait is still
+/// running, else start over at [`RATE_LIMIT_BACKOFF`]
+fn next_rate_limit_backoff(
+    current: Duration,
+    limited_until: Option<Instant>,
+    now: Instant,
+) -> Duration {
+    match limited_until {
+        Some(until) if now < until => (current * 2).min(MAX_RATE_LIMIT_BACKOFF),
+        _ => RATE_LIMIT_BACKOFF,
+    }
+}
+
+/// Sorted, de-duplicated kinds; empty means no restriction
+fn normalize_kinds(kinds: Option<Vec<u16>>) -> Option<Vec<u16>> {
+    let mut kinds = kinds.filter(|k| !k.is_empty())?;
+    kinds.sort_unstable();
+    kinds.dedup();
+    Some(kinds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fake_relay::FakeRelay;
+    use nostr_sdk::{EventBuilder, Timestamp};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn closed_subscription_is_resubscribed() {
+        let relay = FakeRelay::start().await;
+        let (pool, _events) = RelayPool::new(Duration::from_secs(30), 10, None);
+        pool.connect_and_subscribe(relay.url.clone()).await.unwrap();
+
+        let req = relay.next("REQ").await;
+        relay.send(json!([
+            "CLOSED",
+            req[1],
+            "error: shutting down idle subscriptions"
+        ]));
+        let again = relay.next("REQ").await;
+        assert_eq!(again[2], req[2]);
+    }
+
+    #[tokio::test]
+    async fn backfill_is_forwarded_sorted_and_deduplicated_on_eose() {
+        let relay = FakeRelay::start().await;
+        let (pool, events) = RelayPool::new(Duration::from_secs(30), 10, None);
+        let pool = pool.with_backfill_coalescing(Duration::from_secs(30));
+        pool.connect_and_subscribe(relay.url.clone()).await.unwrap();
+
+        let req = relay.next("REQ").await;
+        let keys = Keys::generate();
+        let stored: Vec<Event> = [300, 100, 200]
+            .into_iter()
+            .map(|at| {
+                EventBuilder::new(Kind::Custom(30931), format!("at {}", at))
+                    .custom_created_at(Timestamp::from(at))
+                    .sign_with_keys(&keys)
+                    .unwrap()
+            })
+            .collect();
+        // Relays may send the same stored event twice, e.g. when two filters match it
+        for event in stored.iter().chain([&stored[1]]) {
+            relay.send(json!(["EVENT", req[1], event]));
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(events.is_empty(), "backfill must be held until EOSE");
+
+        relay.send(json!(["EOSE", req[1]]));
+        let mut forwarded = Vec::new();
+        for _ in 0..3 {
+            let event = tokio::time::timeout(Duration::from_secs(5), events.recv_async())
+                .await
+                .expect("backfill was not forwarded")
+                .unwrap();
+            forwarded.push(event.created_at.as_secs());
         }
+        assert_eq!(forwarded, vec![100, 200, 300]);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn rate_limit_notices_are_recognized() {
+        assert!(is_rate_limit_notice("rate-limited: slow down"));
+        assert!(is_rate_limit_notice("You hit the Rate Limit"));
+        assert!(is_rate_limit_notice("too many concurrent REQs"));
+        assert!(!is_rate_limit_notice("invalid: bad filter"));
+        // The machine-readable prefix only counts at the start
+        assert!(!is_rate_limit_notice("blocked: not rate-limited"));
+    }
+
+    #[test]
+    fn rate_limit_backoff_doubles_while_notices_keep_coming() {
+        let now = Instant::now();
+        let running = Some(now + Duration::from_secs(1));
+        assert_eq!(
+            next_rate_limit_backoff(RATE_LIMIT_BACKOFF, None, now),
+            RATE_LIMIT_BACKOFF
+        );
+        assert_eq!(
+            next_rate_limit_backoff(Duration::from_secs(5), running, now),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            next_rate_limit_backoff(Duration::from_secs(200), running, now),
+            MAX_RATE_LIMIT_BACKOFF
+        );
+        // Once a wait has passed the next notice starts over
+        let passed = Some(now - Duration::from_secs(1));
+        assert_eq!(
+            next_rate_limit_backoff(Duration::from_secs(80), passed, now),
+            RATE_LIMIT_BACKOFF
+        );
+    }
+
+    #[test]
+    fn allowed_kinds_are_normalized() {
+        let kinds = AllowedKinds::new(Some(vec![30933, 30931, 30933]));
+        assert_eq!(kinds.get(), Some(vec![30931, 30933]));
+        assert!(kinds.allows(30931));
+        assert!(!kinds.allows(30932));
+
+        let every = AllowedKinds::new(Some(Vec::new()));
+        assert_eq!(every.get(), None);
+        assert!(every.allows(30932));
+    }
+
+    #[test]
+    fn replacing_allowed_kinds_reports_a_change() {
+        let kinds = AllowedKinds::new(None);
+        let router_view = kinds.clone();
+        assert!(kinds.replace(Some(vec![30932, 30931])));
+        assert!(!router_view.allows(30933));
+        // The same set in another order is not a change, so relays are not resubscribed
+        assert!(!kinds.replace(Some(vec![30931, 30932, 30931])));
+        assert!(kinds.replace(Some(Vec::new())));
+        assert!(router_view.allows(30933));
+        assert!(!kinds.replace(None));
     }
 }
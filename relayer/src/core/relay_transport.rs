@@ -0,0 +1,204 @@
+use std::collections::HashSet;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_wsocket::{ConnectionMode, Message};
+use futures::{SinkExt, StreamExt, future};
+use nostr_sdk::pool::transport::error::TransportError;
+use nostr_sdk::pool::transport::websocket::{
+    DefaultWebsocketTransport, WebSocketSink, WebSocketStream, WebSocketTransport,
+};
+use nostr_sdk::util::BoxedFuture;
+use nostr_sdk::{Client, Keys, Url};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::WebPkiSupportedAlgorithms;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use tokio_tungstenite::Connector;
+use tracing::warn;
+
+/// Matches every relay in `relay.insecure_tls_relays`
+const ALL_RELAYS: &str = "*";
+
+/// WebSocket transport for the relayer's nostr clients. Relays listed in
+/// `relay.insecure_tls_relays` are connected to WITHOUT certificate verification, for private
+/// relays with self-signed certificates; every other relay goes through the default transport
+#[derive(Debug, Clone, Default)]
+pub struct RelayTransport {
+    insecure: Arc<HashSet<String>>,
+    /// Built only when some relay skips verification
+    insecure_tls: Option<Arc<ClientConfig>>,
+}
+
+impl RelayTransport {
+    pub fn new(insecure_relays: &[String]) -> Result<Self> {
+        let insecure: HashSet<String> = insecure_relays
+            .iter()
+            .map(|url| normalize_url(url))
+            .filter(|url| !url.is_empty())
+            .collect();
+        if insecure.is_empty() {
+            return Ok(Self::default());
+        }
+        for url in &insecure {
+            warn!(
+                "TLS certificate verification is DISABLED for relay {} (relay.insecure_tls_relays)",
+                url
+            );
+        }
+        Ok(Self {
+            insecure: Arc::new(insecure),
+            insecure_tls: Some(Arc::new(insecure_tls_config()?)),
+        })
+    }
+
+    /// Whether connections to `url` skip certificate verification
+    pub fn skips_verification(&self, url: &str) -> bool {
+        self.insecure.contains(ALL_RELAYS) || self.insecure.contains(&normalize_url(url))
+    }
+
+    /// A nostr client signing with `keys` that connects through this transport
+    pub fn client(&self, keys: Keys) -> Client {
+        Client::builder()
+            .signer(keys)
+            .websocket_transport(self.clone())
+            .build()
+    }
+}
+
+impl WebSocketTransport for RelayTransport {
+    fn support_ping(&self) -> bool {
+        true
+    }
+
+    fn connect<'a>(
+        &'a self,
+        url: &'a Url,
+        mode: &'a ConnectionMode,
+        timeout: Duration,
+    ) -> BoxedFuture<'a, Result<(WebSocketSink, WebSocketStream), TransportError>> {
+        Box::pin(async move {
+            match &self.insecure_tls {
+                Some(tls) if self.skips_verification(url.as_str()) => {
+                    connect_insecure(tls.clone(), url, mode, timeout).await
+                }
+                _ => DefaultWebsocketTransport.connect(url, mode, timeout).await,
+            }
+        })
+    }
+}
+
+async fn connect_insecure(
+    tls: Arc<ClientConfig>,
+    url: &Url,
+    mode: &ConnectionMode,
+    timeout: Duration,
+) -> Result<(WebSocketSink, WebSocketStream), TransportError> {
+    if !matches!(mode, ConnectionMode::Direct) {
+        return Err(TransportError::backend(io::Error::other(
+            "insecure TLS relays only support direct connections",
+        )));
+    }
+    let connect = tokio_tungstenite::connect_async_tls_with_config(
+        url.as_str(),
+        None,
+        false,
+        Some(Connector::Rustls(tls)),
+    );
+    let (socket, _) = tokio::time::timeout(timeout, connect)
+        .await
+        .map_err(TransportError::backend)?
+        .map_err(TransportError::backend)?;
+
+    let (tx, rx) = socket.split();
+    let sink: WebSocketSink = Box::new(
+        tx.sink_map_err(TransportError::backend)
+            .with(|msg: Message| future::ready(Ok(msg.into()))),
+    );
+    let stream: WebSocketStream =
+        Box::new(rx.map(|msg| msg.map(Message::from).map_err(TransportError::backend)));
+    Ok((sink, stream))
+}
+
+fn normalize_url(url: &str) -> String {
+    url.trim().trim_end_matches('/').to_ascii_lowercase()
+}
+
+fn insecure_tls_config() -> Result<ClientConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = AcceptAnyCertificate(provider.signature_verification_algorithms);
+    Ok(ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .context("Failed to build TLS config for insecure relays")?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth())
+}
+
+/// Accepts any server certificate, still checking handshake signatures so the session is
+/// encrypted to whoever holds the presented key
+#[derive(Debug)]
+struct AcceptAnyCertificate(WebPkiSupportedAlgorithms);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_listed_relays_skip_verification() {
+        let strict = RelayTransport::new(&[]).unwrap();
+        assert!(!strict.skips_verification("wss://relay.internal"));
+        assert!(strict.insecure_tls.is_none());
+
+        let transport =
+            RelayTransport::new(&[" WSS://Relay.Internal/ ".to_string(), " ".to_string()]).unwrap();
+        assert!(transport.skips_verification("wss://relay.internal"));
+        assert!(transport.skips_verification("wss://relay.internal/"));
+        assert!(!transport.skips_verification("wss://relay.damus.io"));
+        assert!(transport.insecure_tls.is_some());
+    }
+
+    #[test]
+    fn wildcard_skips_verification_everywhere() {
+        let transport = RelayTransport::new(&["*".to_string()]).unwrap();
+        assert!(transport.skips_verification("wss://relay.damus.io"));
+        assert!(transport.skips_verification("ws://localhost:7777"));
+    }
+}
@@ -1,15 +1,114 @@
-use std::sync::Arc;
-use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
 use reqwest::StatusCode;
-use tokio::time::sleep;
+use rust_decimal::prelude::{Decimal, FromPrimitive};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use tokio::time::sleep_until;
 use tracing::{debug, error, info, warn};
 
+use crate::api::metrics::Metrics;
 use crate::config::SettlementCreditConfig;
-use crate::core::subscription::SubscriptionService;
+use crate::core::leader_election::{Leadership, is_follower};
+use crate::core::subscription::{CreditAward, PendingTrade, SubscriptionService, TradeStatus};
 
-#[derive(Clone, Debug)]
+const USER_AGENT: &str = concat!("moltrade-relayer/", env!("CARGO_PKG_VERSION"));
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// A single award above this is taken as a malformed trade and rejected, cap or no cap
+const MAX_SANE_CREDIT: f64 = 1e12;
+
+/// What the explorer says about a trade's tx hash
+#[derive(Debug, PartialEq, Eq)]
+enum TxStatus {
+    Confirmed,
+    /// Found, but with fewer than `min_confirmations` confirmations so far
+    Confirming(u64),
+    /// Rejected outright, e.g. 400 for a malformed hash
+    Failed(StatusCode),
+    /// Not found yet, or no tx hash to check
+    Unknown,
+    /// 5xx / 429 / 408: the explorer could not answer right now
+    Unavailable(StatusCode),
+    /// Malformed hash, rejected without asking the explorer
+    Invalid,
+}
+
+/// Outcome counts for one settlement pass
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TickSummary {
+    /// Pending trades examined (at most `batch_limit`)
+    pub trades: usize,
+    pub confirmed: usize,
+    pub failed: usize,
+    /// Left pending: not found yet, or explorer unavailable within the grace period
+    pub pending: usize,
+    /// Explorer or award errors. Explorer errors leave the trade pending; a failed award on a
+    /// confirmed tx leaves it confirmed without credit, and on a hash-less trade leaves it pending
+    pub errors: usize,
+    /// Confirmed trades whose missing PnL was filled from the PnL lookup
+    pub pnl_backfilled: usize,
+}
+
+/// Realized PnL reported by the venue lookup
+#[derive(Debug, Deserialize)]
+struct VenuePnl {
+    #[serde(default)]
+    pnl: Option<f64>,
+    #[serde(default)]
+    pnl_usd: Option<f64>,
+}
+
+type TickReply = oneshot::Sender<Result<TickSummary>>;
+
+/// A triggered tick was refused because another instance holds the settlement lock
+#[derive(Debug)]
+pub struct NotLeader;
+
+impl std::fmt::Display for NotLeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("this instance is not the settlement leader")
+    }
+}
+
+impl std::error::Error for NotLeader {}
+
+/// Lets the API run a pass on the worker's own task and see when the next one is due
+#[derive(Clone)]
+pub struct SettlementHandle {
+    trigger: flume::Sender<TickReply>,
+    next_tick: Arc<Mutex<Option<DateTime<Utc>>>>,
+    interval: Duration,
+}
+
+impl SettlementHandle {
+    /// Run a pass now, after any pass already in progress; the schedule is unchanged
+    pub async fn tick_now(&self) -> Result<TickSummary> {
+        let (reply, rx) = oneshot::channel();
+        self.trigger
+            .send_async(reply)
+            .await
+            .map_err(|_| anyhow!("settlement worker is not running"))?;
+        rx.await
+            .map_err(|_| anyhow!("settlement worker stopped during the tick"))?
+    }
+
+    /// When the next scheduled pass starts; None until the worker has started
+    pub fn next_tick_at(&self) -> Option<DateTime<Utc>> {
+        *self.next_tick.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+#[derive(Clone)]
 pub struct SettlementWorker {
     svc: Arc<SubscriptionService>,
     client: reqwest::Client,
@@ -17,117 +116,303 @@ pub struct SettlementWorker {
     interval: Duration,
     batch_limit: i64,
     credit_cfg: Option<SettlementCreditConfig>,
+    metrics: Option<Arc<Metrics>>,
+    leadership: Option<Leadership>,
+    unavailable_grace: Duration,
+    /// tx_hash -> when the explorer first answered "unavailable" for it, reset by any other answer
+    unavailable_since: Arc<Mutex<HashMap<String, Instant>>>,
+    tx_hash_prefix: String,
+    tx_hash_hex_len: usize,
+    /// Confirmations a found tx needs before it counts as confirmed; zero trusts the first 200
+    min_confirmations: u64,
+    /// Base URL queried as `{pnl_base}/{tx_hash|oid}` for realized PnL; None disables the backfill
+    pnl_base: Option<String>,
+    pnl_window: Duration,
+    trigger_tx: flume::Sender<TickReply>,
+    trigger_rx: flume::Receiver<TickReply>,
+    next_tick: Arc<Mutex<Option<DateTime<Utc>>>>,
 }
 
 impl SettlementWorker {
+    /// Fails only when the explorer HTTP client cannot be built
     pub fn new(
         svc: Arc<SubscriptionService>,
         base_url: String,
         interval: Duration,
         batch_limit: i64,
         credit_cfg: Option<SettlementCreditConfig>,
-    ) -> Self {
-        Self {
+    ) -> Result<Self> {
+        let (trigger_tx, trigger_rx) = flume::unbounded();
+        Ok(Self {
             svc,
-            client: reqwest::Client::new(),
+            client: explorer_client(
+                DEFAULT_CONNECT_TIMEOUT,
+                DEFAULT_REQUEST_TIMEOUT,
+                DEFAULT_POOL_IDLE_TIMEOUT,
+            )?,
             base_url,
             interval,
             batch_limit,
             credit_cfg,
+            metrics: None,
+            leadership: None,
+            unavailable_grace: Duration::ZERO,
+            unavailable_since: Arc::new(Mutex::new(HashMap::new())),
+            tx_hash_prefix: String::new(),
+            tx_hash_hex_len: 0,
+            min_confirmations: 0,
+            pnl_base: None,
+            pnl_window: Duration::ZERO,
+            trigger_tx,
+            trigger_rx,
+            next_tick: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Handle for triggering passes out of band and reading the schedule
+    pub fn handle(&self) -> SettlementHandle {
+        SettlementHandle {
+            trigger: self.trigger_tx.clone(),
+            next_tick: self.next_tick.clone(),
+            interval: self.interval,
         }
     }
 
+    /// Fail trades whose tx hash is not `hex_len` hex digits (after an optional `prefix`)
+    /// without querying the explorer; zero `hex_len` sends every hash
+    pub fn with_tx_hash_format(mut self, prefix: String, hex_len: usize) -> Self {
+        self.tx_hash_prefix = prefix;
+        self.tx_hash_hex_len = hex_len;
+        self
+    }
+
+    /// Only confirm a found tx once the explorer reports at least `min` confirmations; answers
+    /// without a `confirmations` count are confirmed as before. Zero confirms on the first 200
+    pub fn with_min_confirmations(mut self, min: u64) -> Self {
+        self.min_confirmations = min;
+        self
+    }
+
+    /// Each pass, look up realized PnL at `{base}/{tx_hash|oid}` for confirmed trades created
+    /// within `window` that still lack it; an empty `base` disables the backfill
+    pub fn with_pnl_backfill(mut self, base: String, window: Duration) -> Self {
+        let base = base.trim().trim_end_matches('/').to_string();
+        self.pnl_base = (!base.is_empty()).then_some(base);
+        self.pnl_window = window;
+        self
+    }
+
+    /// Keep retrying trades the explorer reports as temporarily unavailable for this long
+    /// before marking them failed; zero fails them on the first such answer
+    pub fn with_unavailable_grace(mut self, grace: Duration) -> Self {
+        self.unavailable_grace = grace;
+        self
+    }
+
+    /// Attach metrics collection
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Only settle while this instance holds the leader lock
+    pub fn with_leadership(mut self, leadership: Leadership) -> Self {
+        self.leadership = Some(leadership);
+        self
+    }
+
+    /// Rebuild the explorer client with explicit timeouts
+    pub fn with_http_timeouts(
+        mut self,
+        connect: Duration,
+        request: Duration,
+        pool_idle: Duration,
+    ) -> Result<Self> {
+        self.client = explorer_client(connect, request, pool_idle)?;
+        Ok(self)
+    }
+
     pub async fn run(self) {
+        let mut next = tokio::time::Instant::now();
         loop {
-            if let Err(e) = self.tick().await {
-                warn!("settlement tick failed: {}", e);
+            tokio::select! {
+                _ = sleep_until(next) => {
+                    if self.is_follower() {
+                        debug!("settlement: not leader, skipping tick");
+                        // Every instance reports the backlog, not only the one settling it
+                        self.update_pending_gauge().await;
+                    } else if let Err(e) = self.tick().await {
+                        warn!("settlement tick failed: {}", e);
+                    }
+                    next = tokio::time::Instant::now() + self.interval;
+                    *self.next_tick.lock().unwrap_or_else(|e| e.into_inner()) =
+                        chrono::Duration::from_std(self.interval).ok().map(|d| Utc::now() + d);
+                }
+                Ok(reply) = self.trigger_rx.recv_async() => {
+                    let result = if self.is_follower() {
+                        Err(NotLeader.into())
+                    } else {
+                        info!("settlement: running triggered tick");
+                        self.tick().await
+                    };
+                    let _ = reply.send(result);
+                }
+            }
+        }
+    }
+
+    fn is_follower(&self) -> bool {
+        is_follower(self.leadership.as_ref())
+    }
+
+    /// Set the `pending_trades` gauge with a dedicated COUNT, so it is not capped at batch_limit
+    async fn update_pending_gauge(&self) {
+        if let Some(m) = &self.metrics {
+            match self.svc.count_pending_trades().await {
+                Ok(count) => m.pending_trades.set(count as f64),
+                Err(e) => warn!("settlement: failed to count pending trades: {}", e),
             }
-            sleep(self.interval).await;
         }
     }
 
-    async fn tick(&self) -> Result<()> {
+    async fn tick(&self) -> Result<TickSummary> {
+        self.update_pending_gauge().await;
+
+        let pnl_backfilled = self.backfill_pnl().await;
         let trades = self.svc.list_pending_trades(self.batch_limit).await?;
+        self.prune_unavailable(&trades);
+        let mut summary = TickSummary {
+            trades: trades.len(),
+            pnl_backfilled,
+            ..Default::default()
+        };
 
         if trades.is_empty() {
             debug!("settlement: no pending trades");
-            return Ok(());
+            return Ok(summary);
         }
 
         for t in trades {
             // Early-phase behavior: award credits as soon as we have an oid (tx hash may be absent for Hyperliquid).
             // If tx_hash is present we still attempt verification; otherwise we short-circuit to credit award.
-            match self.verify_tx_opt(t.tx_hash.as_deref()).await {
-                Ok(Some(true)) => {
+            let status = self.verify_tx_opt(t.tx_hash.as_deref()).await;
+            if let (Some(tx), Ok(s)) = (t.tx_hash.as_deref(), &status) {
+                self.track_unavailable(tx, matches!(s, TxStatus::Unavailable(_)));
+            }
+            // Unavailable past the grace period: the trade is given up on rather than rejected
+            let stale = matches!(status, Ok(TxStatus::Unavailable(_)));
+            match status {
+                Ok(TxStatus::Unavailable(code)) if !self.grace_expired(t.tx_hash.as_deref()) => {
+                    summary.pending += 1;
+                    debug!(
+                        "settlement: explorer unavailable ({}) for tx {:?}; retrying within grace period",
+                        code, t.tx_hash
+                    );
+                }
+                Ok(TxStatus::Confirming(confirmations)) => {
+                    summary.pending += 1;
+                    debug!(
+                        "settlement: tx {:?} has {}/{} confirmations",
+                        t.tx_hash, confirmations, self.min_confirmations
+                    );
+                }
+                Ok(TxStatus::Confirmed) => {
                     self.svc
-                        .update_trade_settlement(
-                            t.tx_hash.as_deref(),
-                            t.oid.as_deref(),
-                            "confirmed",
-                            None,
-                            None,
-                        )
+                        .set_trade_status(t.id, TradeStatus::Confirmed)
                         .await?;
-                    if let Some(credit) = self.compute_credit(&t) {
+                    self.record_outcome("confirmed");
+                    if let Some(credit) = self
+                        .credit_cfg
+                        .as_ref()
+                        .and_then(|c| compute_credit(c, &t, self.metrics.as_deref()))
+                    {
                         let recipient = t.follower_pubkey.as_deref().unwrap_or(&t.bot_pubkey);
                         if let Err(e) = self
                             .svc
-                            .award_credits(&t.bot_pubkey, recipient, credit)
+                            .award_credits(
+                                &t.bot_pubkey,
+                                recipient,
+                                credit,
+                                &award_reason("settlement", &t),
+                                trade_ref(&t),
+                            )
                             .await
                         {
                             log_award_error(&e, &t.bot_pubkey, recipient);
+                            summary.errors += 1;
                             continue;
                         }
+                        self.record_outcome("credited");
                     }
+                    summary.confirmed += 1;
                     info!(
                         "settlement: confirmed tx_hash={:?} oid={:?}",
                         t.tx_hash, t.oid
                     );
                 }
-                Ok(Some(false)) => {
-                    self.svc
-                        .update_trade_settlement(
-                            t.tx_hash.as_deref(),
-                            t.oid.as_deref(),
-                            "failed",
-                            None,
-                            None,
-                        )
-                        .await?;
+                Ok(TxStatus::Failed(code) | TxStatus::Unavailable(code)) => {
+                    if let Some(tx) = t.tx_hash.as_deref() {
+                        self.track_unavailable(tx, false);
+                    }
+                    self.svc.set_trade_status(t.id, TradeStatus::Failed).await?;
+                    self.record_outcome(if stale { "stale" } else { "failed" });
+                    summary.failed += 1;
                     warn!(
-                        "settlement: marked failed tx_hash={:?} oid={:?}",
+                        "settlement: marked failed tx_hash={:?} oid={:?} (explorer returned {})",
+                        t.tx_hash, t.oid, code
+                    );
+                }
+                Ok(TxStatus::Invalid) => {
+                    self.svc.set_trade_status(t.id, TradeStatus::Failed).await?;
+                    if let Some(m) = &self.metrics {
+                        m.settlement_invalid_tx_hash.inc();
+                    }
+                    self.record_outcome("failed");
+                    summary.failed += 1;
+                    warn!(
+                        "settlement: marked failed tx_hash={:?} oid={:?} (malformed tx hash)",
                         t.tx_hash, t.oid
                     );
                 }
-                Ok(None) => {
+                Ok(TxStatus::Unknown) => {
                     // If no tx hash, treat pending entry as immediately credit-eligible.
                     if t.tx_hash.is_none() {
-                        if let Some(credit) = self.compute_credit(&t) {
+                        if let Some(credit) = self
+                            .credit_cfg
+                            .as_ref()
+                            .and_then(|c| compute_credit(c, &t, self.metrics.as_deref()))
+                        {
                             let recipient = t.follower_pubkey.as_deref().unwrap_or(&t.bot_pubkey);
                             if let Err(e) = self
                                 .svc
-                                .award_credits(&t.bot_pubkey, recipient, credit)
+                                .award_credits(
+                                    &t.bot_pubkey,
+                                    recipient,
+                                    credit,
+                                    &award_reason("settlement", &t),
+                                    trade_ref(&t),
+                                )
                                 .await
                             {
                                 log_award_error(&e, &t.bot_pubkey, recipient);
+                                summary.errors += 1;
                                 continue;
                             }
+                            self.record_outcome("credited");
                         }
                         self.svc
-                            .update_trade_settlement(
-                                t.tx_hash.as_deref(),
-                                t.oid.as_deref(),
-                                "confirmed",
-                                None,
-                                None,
-                            )
+                            .set_trade_status(t.id, TradeStatus::Confirmed)
                             .await?;
+                        self.record_outcome("confirmed");
+                        summary.confirmed += 1;
                         info!("settlement: credited pending trade with oid={:?}", t.oid);
                     } else {
+                        summary.pending += 1;
                         debug!("settlement: tx {:?} not yet found", t.tx_hash);
                     }
                 }
                 Err(e) => {
+                    summary.errors += 1;
                     error!(
                         "settlement: verify tx_hash={:?} oid={:?} error: {}",
                         t.tx_hash, t.oid, e
@@ -136,57 +421,324 @@ impl SettlementWorker {
             }
         }
 
-        Ok(())
+        Ok(summary)
+    }
+
+    /// Count a settlement outcome in `settlement_outcomes_total`
+    fn record_outcome(&self, outcome: &str) {
+        if let Some(m) = &self.metrics {
+            m.settlement_outcomes.with_label_values(&[outcome]).inc();
+        }
     }
 
-    /// Naive verifier: HTTP GET the explorer endpoint; 200 -> confirmed, 404 -> unknown
-    async fn verify_tx_opt(&self, tx_hash: Option<&str>) -> Result<Option<bool>> {
+    /// Naive verifier: HTTP GET the explorer endpoint; 200 -> confirmed (or confirming while a
+    /// reported `confirmations` count is below the minimum), 404 -> unknown,
+    /// 5xx/429/408 -> unavailable, any other 4xx -> failed
+    async fn verify_tx_opt(&self, tx_hash: Option<&str>) -> Result<TxStatus> {
         let tx = match tx_hash {
             Some(v) if !v.is_empty() => v,
-            _ => return Ok(None),
+            _ => return Ok(TxStatus::Unknown),
         };
+        if !is_valid_tx_hash(tx, &self.tx_hash_prefix, self.tx_hash_hex_len) {
+            return Ok(TxStatus::Invalid);
+        }
         let url = format!("{}/{}", self.base_url.trim_end_matches('/'), tx);
         let resp = self.client.get(&url).send().await?;
         match resp.status() {
-            StatusCode::OK => Ok(Some(true)),
-            StatusCode::NOT_FOUND => Ok(None),
-            s if s.is_client_error() || s.is_server_error() => Ok(Some(false)),
-            _ => Ok(None),
+            StatusCode::OK if self.min_confirmations == 0 => Ok(TxStatus::Confirmed),
+            StatusCode::OK => {
+                let body: serde_json::Value = resp.json().await.unwrap_or_default();
+                Ok(found_status(&body, self.min_confirmations))
+            }
+            StatusCode::NOT_FOUND => Ok(TxStatus::Unknown),
+            s if s.is_server_error()
+                || s == StatusCode::TOO_MANY_REQUESTS
+                || s == StatusCode::REQUEST_TIMEOUT =>
+            {
+                Ok(TxStatus::Unavailable(s))
+            }
+            s if s.is_client_error() => Ok(TxStatus::Failed(s)),
+            _ => Ok(TxStatus::Unknown),
         }
     }
 
-    fn compute_credit(&self, trade: &crate::core::subscription::PendingTrade) -> Option<f64> {
-        let cfg = match self.credit_cfg.as_ref() {
-            Some(c) if c.enable => c,
-            _ => return None,
+    /// Fill PnL for confirmed trades that were settled before it was known, crediting any
+    /// profit-multiplier bonus they missed; returns how many trades were filled
+    async fn backfill_pnl(&self) -> usize {
+        let Some(base) = self.pnl_base.as_deref() else {
+            return 0;
         };
-
-        let base_rate = if trade.role == "leader" {
-            cfg.leader_rate
-        } else {
-            cfg.follower_rate
+        let trades = match self
+            .svc
+            .list_trades_missing_pnl(self.pnl_window.as_secs_f64(), self.batch_limit)
+            .await
+        {
+            Ok(t) => t,
+            Err(e) => {
+                warn!("settlement: failed to list trades missing pnl: {}", e);
+                return 0;
+            }
         };
 
-        let mut credit = (trade.size * trade.price * base_rate).max(cfg.min_credit);
-        if let Some(pnl) = trade.pnl_usd {
-            if pnl > 0.0 {
-                credit *= cfg.profit_multiplier;
+        let mut filled = 0;
+        for t in trades {
+            let Some(reference) = trade_ref(&t) else {
+                continue;
+            };
+            let pnl = match self.fetch_pnl(base, reference).await {
+                Ok(Some(p)) => p,
+                Ok(None) => continue,
+                Err(e) => {
+                    debug!("settlement: pnl lookup for {} failed: {}", reference, e);
+                    continue;
+                }
+            };
+            let Some(pnl_usd) = pnl.pnl_usd else {
+                continue;
+            };
+            let bonus = self
+                .credit_cfg
+                .as_ref()
+                .and_then(|cfg| pnl_bonus(cfg, &t, pnl_usd, self.metrics.as_deref()));
+            match self
+                .svc
+                .backfill_trade_pnl(t.id, pnl.pnl, pnl_usd, bonus.as_ref())
+                .await
+            {
+                Ok(true) => {
+                    filled += 1;
+                    info!(
+                        "settlement: backfilled pnl_usd={} for {} (bonus={:?})",
+                        pnl_usd,
+                        reference,
+                        bonus.as_ref().map(|b| b.amount)
+                    );
+                }
+                Ok(false) => {}
+                Err(e) => warn!(
+                    "settlement: failed to backfill pnl for {}: {}",
+                    reference, e
+                ),
             }
         }
+        filled
+    }
 
-        if trade.is_test {
-            credit *= cfg.test_multiplier;
+    /// 200 with a JSON body carries the PnL; 404 means the venue has none yet
+    async fn fetch_pnl(&self, base: &str, reference: &str) -> Result<Option<VenuePnl>> {
+        let resp = self
+            .client
+            .get(format!("{}/{}", base, reference))
+            .send()
+            .await?;
+        match resp.status() {
+            StatusCode::OK => Ok(Some(resp.json().await?)),
+            StatusCode::NOT_FOUND => Ok(None),
+            s => Err(anyhow!("pnl lookup returned {}", s)),
         }
+    }
 
-        if credit.is_finite() && credit > 0.0 {
-            Some(credit)
+    /// Start the grace clock on the first unavailable answer for `tx`; clear it on any other answer
+    fn track_unavailable(&self, tx: &str, unavailable: bool) {
+        let mut since = self
+            .unavailable_since
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if unavailable {
+            since.entry(tx.to_string()).or_insert_with(Instant::now);
         } else {
-            None
+            since.remove(tx);
+        }
+    }
+
+    /// Forget grace clocks of trades no longer pending, e.g. settled by hand. A trade still
+    /// pending stays in the oldest-first batch, so only settled ones drop out
+    fn prune_unavailable(&self, pending: &[PendingTrade]) {
+        let pending: HashSet<&str> = pending
+            .iter()
+            .filter_map(|t| t.tx_hash.as_deref())
+            .collect();
+        self.unavailable_since
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|tx, _| pending.contains(tx.as_str()));
+    }
+
+    fn grace_expired(&self, tx_hash: Option<&str>) -> bool {
+        let since = self
+            .unavailable_since
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        tx_hash
+            .and_then(|tx| since.get(tx))
+            .is_none_or(|first| first.elapsed() >= self.unavailable_grace)
+    }
+}
+
+/// Status of a tx the explorer found: confirming while it reports fewer than `min_confirmations`,
+/// confirmed otherwise (including when it reports no count at all)
+fn found_status(body: &serde_json::Value, min_confirmations: u64) -> TxStatus {
+    match confirmations(body) {
+        Some(n) if n < min_confirmations => TxStatus::Confirming(n),
+        _ => TxStatus::Confirmed,
+    }
+}
+
+/// `confirmations` from an explorer answer, top level or under `result`, as a number or a
+/// decimal or `0x` hex string; None when the chain does not report one
+fn confirmations(body: &serde_json::Value) -> Option<u64> {
+    let value = body
+        .get("confirmations")
+        .or_else(|| body.get("result").and_then(|r| r.get("confirmations")))?;
+    match value {
+        serde_json::Value::Number(n) => n.as_u64(),
+        serde_json::Value::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => s.parse().ok(),
+        },
+        _ => None,
+    }
+}
+
+/// `hex_len` hex digits, optionally preceded by `prefix`; any hash passes when `hex_len` is zero
+fn is_valid_tx_hash(tx: &str, prefix: &str, hex_len: usize) -> bool {
+    if hex_len == 0 {
+        return true;
+    }
+    let digits = tx.strip_prefix(prefix).unwrap_or(tx);
+    digits.len() == hex_len && digits.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Ledger reason for a trade-driven award, e.g. `settlement 0xabc...`
+pub fn award_reason(source: &str, trade: &PendingTrade) -> String {
+    format!("{} {}", source, trade_ref(trade).unwrap_or("-"))
+}
+
+/// How the ledger refers to a trade: its tx hash, else its oid
+pub fn trade_ref(trade: &PendingTrade) -> Option<&str> {
+    trade.tx_hash.as_deref().or(trade.oid.as_deref())
+}
+
+/// Credits earned for a confirmed trade under `cfg`, capped at `max_award`; None when crediting
+/// is disabled, the amount is not positive, or the trade yields a non-finite or implausible amount
+pub fn compute_credit(
+    cfg: &SettlementCreditConfig,
+    trade: &PendingTrade,
+    metrics: Option<&Metrics>,
+) -> Option<Decimal> {
+    if !cfg.enable {
+        return None;
+    }
+
+    let base_rate = if trade.role == "leader" {
+        cfg.leader_rate
+    } else {
+        cfg.follower_rate
+    };
+
+    // Checked before `max`, which would turn a NaN notional into min_credit
+    let notional_credit = trade.size * trade_price(cfg, trade) * base_rate;
+    let mut credit = notional_credit.max(cfg.min_credit);
+    if let Some(pnl) = trade.pnl_usd {
+        if pnl > 0.0 {
+            credit *= cfg.profit_multiplier;
         }
     }
+
+    if trade.is_test {
+        credit *= cfg.test_multiplier;
+    }
+
+    if !notional_credit.is_finite() || !credit.is_finite() || credit > MAX_SANE_CREDIT {
+        warn!(
+            "settlement: rejecting implausible credit {} for trade {:?} (size={}, price={})",
+            credit,
+            trade_ref(trade),
+            trade.size,
+            trade.price
+        );
+        if let Some(m) = metrics {
+            m.credit_awards_rejected.inc();
+        }
+        return None;
+    }
+    if cfg.max_award > 0.0 && credit > cfg.max_award {
+        warn!(
+            "settlement: capping credit {} to max_award {} for trade {:?}",
+            credit,
+            cfg.max_award,
+            trade_ref(trade)
+        );
+        if let Some(m) = metrics {
+            m.credit_awards_capped.inc();
+        }
+        credit = cfg.max_award;
+    }
+
+    // Rates are f64 config; round once here so balances are summed exactly as NUMERIC
+    Decimal::from_f64(credit)
+        .map(|c| c.round_dp(cfg.precision))
+        .filter(|c| *c > Decimal::ZERO)
+}
+
+/// Extra credit a trade earns now that its PnL is known, over what it was paid at confirmation
+fn pnl_bonus(
+    cfg: &SettlementCreditConfig,
+    trade: &PendingTrade,
+    pnl_usd: f64,
+    metrics: Option<&Metrics>,
+) -> Option<CreditAward> {
+    // Already counted in the metrics when the trade was confirmed
+    let paid = compute_credit(cfg, trade, None).unwrap_or(Decimal::ZERO);
+    let earned = compute_credit(
+        cfg,
+        &PendingTrade {
+            pnl_usd: Some(pnl_usd),
+            ..trade.clone()
+        },
+        metrics,
+    )?;
+    let amount = earned - paid;
+    (amount > Decimal::ZERO).then(|| CreditAward {
+        bot_pubkey: trade.bot_pubkey.clone(),
+        follower_pubkey: trade
+            .follower_pubkey
+            .clone()
+            .unwrap_or_else(|| trade.bot_pubkey.clone()),
+        amount,
+        reason: award_reason("pnl-backfill", trade),
+        trade_ref: trade_ref(trade).map(str::to_string),
+    })
+}
+
+/// The trade's own price, or the configured reference price for its symbol when it was recorded as 0
+fn trade_price(cfg: &SettlementCreditConfig, trade: &PendingTrade) -> f64 {
+    if trade.price > 0.0 {
+        return trade.price;
+    }
+    cfg.reference_prices
+        .iter()
+        .find(|(symbol, _)| symbol.eq_ignore_ascii_case(&trade.symbol))
+        .map(|(_, price)| *price)
+        .unwrap_or(trade.price)
 }
 
-fn log_award_error(err: &anyhow::Error, bot_pubkey: &str, follower: &str) {
+/// Explorer HTTP client; without timeouts a hung explorer would stall a tick indefinitely
+fn explorer_client(
+    connect: Duration,
+    request: Duration,
+    pool_idle: Duration,
+) -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(connect)
+        .read_timeout(request)
+        .timeout(request)
+        .pool_idle_timeout(pool_idle)
+        .build()?)
+}
+
+pub fn log_award_error(err: &anyhow::Error, bot_pubkey: &str, follower: &str) {
     if let Some(db_err) = err.downcast_ref::<tokio_postgres::Error>() {
         if let Some(code) = db_err.code() {
             warn!(
@@ -202,3 +754,350 @@ fn log_award_error(err: &anyhow::Error, bot_pubkey: &str, follower: &str) {
         bot_pubkey, follower, err
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::Registry;
+    use serde_json::json;
+    use std::collections::VecDeque;
+
+    fn credit_cfg(overrides: serde_json::Value) -> SettlementCreditConfig {
+        serde_json::from_value(overrides).unwrap()
+    }
+
+    fn trade(role: &str, symbol: &str, size: f64, price: f64) -> PendingTrade {
+        PendingTrade {
+            id: 1,
+            tx_hash: None,
+            oid: Some("o1".to_string()),
+            bot_pubkey: "bot".to_string(),
+            follower_pubkey: Some("follower".to_string()),
+            role: role.to_string(),
+            size,
+            price,
+            pnl_usd: None,
+            is_test: false,
+            symbol: symbol.to_string(),
+        }
+    }
+
+    #[test]
+    fn tx_hash_format_is_checked_when_configured() {
+        let hash = format!("0x{}", "ab".repeat(32));
+        assert!(is_valid_tx_hash(&hash, "0x", 64));
+        assert!(is_valid_tx_hash(&hash[2..], "0x", 64));
+        assert!(!is_valid_tx_hash(&hash[..60], "0x", 64));
+        assert!(!is_valid_tx_hash(
+            &format!("0x{}", "zz".repeat(32)),
+            "0x",
+            64
+        ));
+        // Zero length accepts any venue's format
+        assert!(is_valid_tx_hash("not-a-hash", "0x", 0));
+    }
+
+    #[test]
+    fn confirmations_are_read_in_every_supported_shape() {
+        assert_eq!(confirmations(&json!({"confirmations": 3})), Some(3));
+        assert_eq!(confirmations(&json!({"confirmations": "12"})), Some(12));
+        assert_eq!(
+            confirmations(&json!({"result": {"confirmations": "0x1a"}})),
+            Some(26)
+        );
+        assert_eq!(confirmations(&json!({"confirmations": "many"})), None);
+        assert_eq!(confirmations(&json!({"confirmations": -1})), None);
+        assert_eq!(confirmations(&json!({"status": "ok"})), None);
+    }
+
+    #[test]
+    fn found_tx_waits_for_min_confirmations() {
+        assert_eq!(
+            found_status(&json!({"confirmations": 1}), 3),
+            TxStatus::Confirming(1)
+        );
+        assert_eq!(
+            found_status(&json!({"confirmations": 3}), 3),
+            TxStatus::Confirmed
+        );
+        // Chains that report no count confirm on the first 200
+        assert_eq!(found_status(&json!({}), 3), TxStatus::Confirmed);
+    }
+
+    #[test]
+    fn zero_price_trades_use_reference_price_for_symbol() {
+        let cfg = credit_cfg(json!({"reference_prices": {"BTC": 60000.0}}));
+        // 0.01 BTC at 60000 and follower_rate 0.001
+        assert_eq!(
+            compute_credit(&cfg, &trade("follower", "btc", 0.01, 0.0), None),
+            Some(Decimal::new(6, 1))
+        );
+        // A recorded price wins over the reference
+        assert_eq!(
+            compute_credit(&cfg, &trade("follower", "BTC", 0.01, 100_000.0), None),
+            Some(Decimal::new(1, 0))
+        );
+        // Unknown symbols still fall back to min_credit
+        assert_eq!(
+            compute_credit(&cfg, &trade("follower", "ETH", 1.0, 0.0), None),
+            Some(Decimal::new(5, 1))
+        );
+    }
+
+    #[test]
+    fn awards_are_capped_at_max_award() {
+        let cfg = credit_cfg(json!({"max_award": 100.0}));
+        assert_eq!(
+            compute_credit(&cfg, &trade("leader", "ETH", 1e9, 2500.0), None),
+            Some(Decimal::new(100, 0))
+        );
+        // Below the cap the award is untouched: 10 * 2500 * 0.002
+        assert_eq!(
+            compute_credit(&cfg, &trade("leader", "ETH", 10.0, 2500.0), None),
+            Some(Decimal::new(50, 0))
+        );
+    }
+
+    #[test]
+    fn implausible_amounts_earn_nothing() {
+        let cfg = credit_cfg(json!({}));
+        assert_eq!(
+            compute_credit(&cfg, &trade("leader", "ETH", f64::NAN, 2500.0), None),
+            None
+        );
+        assert_eq!(
+            compute_credit(&cfg, &trade("leader", "ETH", 1.0, f64::INFINITY), None),
+            None
+        );
+        assert_eq!(
+            compute_credit(&cfg, &trade("leader", "ETH", 1e20, 1e3), None),
+            None
+        );
+        // The cap does not rescue a malformed trade
+        let capped = credit_cfg(json!({"max_award": 100.0}));
+        assert_eq!(
+            compute_credit(&capped, &trade("leader", "ETH", 1e20, 1e3), None),
+            None
+        );
+    }
+
+    #[test]
+    fn venue_pnl_needs_only_pnl_usd() {
+        let pnl: VenuePnl = serde_json::from_value(json!({"pnl_usd": 12.5})).unwrap();
+        assert_eq!((pnl.pnl, pnl.pnl_usd), (None, Some(12.5)));
+        let pnl: VenuePnl = serde_json::from_value(json!({"pnl_usd": 12.5, "pnl": 0.004})).unwrap();
+        assert_eq!((pnl.pnl, pnl.pnl_usd), (Some(0.004), Some(12.5)));
+    }
+
+    #[test]
+    fn pnl_backfill_pays_only_the_missed_profit_bonus() {
+        let cfg = credit_cfg(json!({"profit_multiplier": 1.5}));
+        // 1000 notional at the 0.002 leader rate earned 2 at confirmation
+        let t = trade("leader", "ETH", 1.0, 1000.0);
+        let bonus = pnl_bonus(&cfg, &t, 10.0, None).unwrap();
+        assert_eq!(bonus.amount, Decimal::new(1, 0));
+        assert_eq!(bonus.follower_pubkey, "follower");
+        assert_eq!(bonus.reason, "pnl-backfill o1");
+        assert_eq!(bonus.trade_ref.as_deref(), Some("o1"));
+
+        // A loss or break-even earns nothing extra
+        assert!(pnl_bonus(&cfg, &t, -5.0, None).is_none());
+        assert!(pnl_bonus(&cfg, &t, 0.0, None).is_none());
+        // Neither does a trade that already had its PnL
+        let settled = PendingTrade {
+            pnl_usd: Some(10.0),
+            ..t.clone()
+        };
+        assert!(pnl_bonus(&cfg, &settled, 10.0, None).is_none());
+    }
+
+    fn handle() -> (SettlementHandle, flume::Receiver<TickReply>) {
+        let (trigger, rx) = flume::unbounded();
+        let handle = SettlementHandle {
+            trigger,
+            next_tick: Arc::new(Mutex::new(None)),
+            interval: Duration::from_secs(30),
+        };
+        (handle, rx)
+    }
+
+    #[tokio::test]
+    async fn tick_now_returns_the_worker_summary() {
+        let (handle, rx) = handle();
+        tokio::spawn(async move {
+            let reply = rx.recv_async().await.unwrap();
+            let _ = reply.send(Ok(TickSummary {
+                trades: 1,
+                confirmed: 1,
+                ..TickSummary::default()
+            }));
+        });
+        let summary = handle.tick_now().await.unwrap();
+        assert_eq!((summary.trades, summary.confirmed), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn tick_now_fails_without_a_worker() {
+        let (handle, rx) = handle();
+        drop(rx);
+        let err = handle.tick_now().await.unwrap_err().to_string();
+        assert_eq!(err, "settlement worker is not running");
+        assert_eq!(handle.next_tick_at(), None);
+    }
+
+    /// Explorer answering each lookup with the next of `statuses`, repeating the last one
+    async fn explorer(statuses: Vec<StatusCode>) -> String {
+        let answers = Arc::new(Mutex::new(VecDeque::from(statuses)));
+        let app = axum::Router::new().route(
+            "/{tx}",
+            axum::routing::get(move || {
+                let answers = answers.clone();
+                async move {
+                    let mut answers = answers.lock().unwrap();
+                    if answers.len() > 1 {
+                        answers.pop_front().unwrap()
+                    } else {
+                        answers[0]
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    /// Worker over a fresh test database holding one pending trade with tx hash `0xabc`
+    async fn worker_with_trade(name: &str, statuses: Vec<StatusCode>) -> Option<SettlementWorker> {
+        let svc = SubscriptionService::for_test(name).await?;
+        svc.register_test_bot("bot1").await;
+        svc.record_trade_tx(
+            "bot1",
+            None,
+            "leader",
+            "ETH",
+            "buy",
+            1.0,
+            1000.0,
+            Some("0xabc"),
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+        let base = explorer(statuses).await;
+        let worker =
+            SettlementWorker::new(Arc::new(svc), base, Duration::from_secs(30), 10, None).unwrap();
+        Some(worker.with_unavailable_grace(Duration::from_secs(60)))
+    }
+
+    fn grace_clocks(worker: &SettlementWorker) -> usize {
+        worker.unavailable_since.lock().unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn pending_trades_gauge_is_set_by_every_instance() {
+        let statuses = vec![StatusCode::OK];
+        let Some(leader) = worker_with_trade("settle_gauge", statuses.clone()).await else {
+            return;
+        };
+        let metrics = Arc::new(Metrics::with_registry(&Registry::new()).unwrap());
+        let leader = leader.with_metrics(metrics.clone());
+        // Counted before the batch is settled, so it catches up on the next tick
+        leader.tick().await.unwrap();
+        assert_eq!(metrics.pending_trades.get(), 1.0);
+        leader.tick().await.unwrap();
+        assert_eq!(metrics.pending_trades.get(), 0.0);
+
+        let Some(follower) = worker_with_trade("settle_gauge_follower", statuses).await else {
+            return;
+        };
+        let svc = follower.svc.clone();
+        let metrics = Arc::new(Metrics::with_registry(&Registry::new()).unwrap());
+        let follower = follower
+            .with_metrics(metrics.clone())
+            .with_leadership(Leadership::default());
+        let run = tokio::spawn(follower.run());
+        let reported = async {
+            while metrics.pending_trades.get() != 1.0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        let reported = tokio::time::timeout(Duration::from_secs(5), reported).await;
+        run.abort();
+        assert!(reported.is_ok(), "follower never set the gauge");
+        assert_eq!(svc.list_pending_trades(10).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn unavailable_explorer_is_retried_within_the_grace_period() {
+        let statuses = vec![StatusCode::SERVICE_UNAVAILABLE, StatusCode::OK];
+        let Some(worker) = worker_with_trade("settle_grace", statuses).await else {
+            return;
+        };
+        let first = worker.tick().await.unwrap();
+        assert_eq!((first.pending, first.failed), (1, 0));
+        assert_eq!(grace_clocks(&worker), 1);
+
+        let second = worker.tick().await.unwrap();
+        assert_eq!(second.confirmed, 1);
+        assert_eq!(grace_clocks(&worker), 0);
+        assert!(worker.svc.list_pending_trades(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejected_tx_fails_without_waiting_for_grace() {
+        let statuses = vec![StatusCode::BAD_REQUEST];
+        let Some(worker) = worker_with_trade("settle_rejected", statuses).await else {
+            return;
+        };
+        let summary = worker.tick().await.unwrap();
+        assert_eq!((summary.pending, summary.failed), (0, 1));
+        assert_eq!(grace_clocks(&worker), 0);
+    }
+
+    #[tokio::test]
+    async fn grace_clock_is_dropped_once_the_trade_is_settled_elsewhere() {
+        let statuses = vec![StatusCode::SERVICE_UNAVAILABLE];
+        let Some(worker) = worker_with_trade("settle_prune", statuses).await else {
+            return;
+        };
+        worker.tick().await.unwrap();
+        assert_eq!(grace_clocks(&worker), 1);
+
+        let pending = worker.svc.list_pending_trades(10).await.unwrap();
+        worker
+            .svc
+            .set_trade_status(pending[0].id, TradeStatus::Confirmed)
+            .await
+            .unwrap();
+        let summary = worker.tick().await.unwrap();
+        assert_eq!(summary.trades, 0);
+        assert_eq!(grace_clocks(&worker), 0);
+    }
+
+    #[tokio::test]
+    async fn explorer_request_times_out_instead_of_hanging() {
+        // Accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/0xabc", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+
+        let client = explorer_client(
+            Duration::from_secs(1),
+            Duration::from_millis(200),
+            DEFAULT_POOL_IDLE_TIMEOUT,
+        )
+        .unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(5), client.get(url).send())
+            .await
+            .expect("explorer request hung past its timeout");
+        assert!(result.unwrap_err().is_timeout());
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use flume::Receiver;
+use tokio::time::{Instant, sleep, timeout_at};
+use tracing::{debug, error, info, warn};
+
+use crate::api::metrics::Metrics;
+use crate::core::subscription::{SignalInsert, SubscriptionService};
+
+const FLUSH_ATTEMPTS: u32 = 4;
+const FLUSH_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Collects signals from the router and writes them with multi-row INSERTs,
+/// flushing once `max_rows` are buffered or the oldest has waited `max_delay`
+pub struct SignalBatcher {
+    svc: Arc<SubscriptionService>,
+    rx: Receiver<SignalInsert>,
+    max_rows: usize,
+    max_delay: Duration,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl SignalBatcher {
+    pub fn new(
+        svc: Arc<SubscriptionService>,
+        rx: Receiver<SignalInsert>,
+        max_rows: usize,
+        max_delay: Duration,
+    ) -> Self {
+        Self {
+            svc,
+            rx,
+            max_rows: max_rows.max(1),
+            max_delay,
+            metrics: None,
+        }
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Runs until every sender is dropped, then flushes what is left
+    pub async fn run(self) {
+        let mut batch = Vec::with_capacity(self.max_rows);
+        loop {
+            let open = fill_batch(&self.rx, &mut batch, self.max_rows, self.max_delay).await;
+            if !batch.is_empty() {
+                self.flush(&mut batch).await;
+            }
+            if !open {
+                break;
+            }
+        }
+        info!("Signal batcher stopped");
+    }
+
+    async fn flush(&self, batch: &mut Vec<SignalInsert>) {
+        let mut backoff = FLUSH_BACKOFF;
+        for attempt in 1..=FLUSH_ATTEMPTS {
+            match self.svc.record_signals(batch).await {
+                Ok(inserted) => {
+                    debug!(
+                        "Recorded {} of {} batched signals ({} already present)",
+                        inserted,
+                        batch.len(),
+                        batch.len() as u64 - inserted
+                    );
+                    break;
+                }
+                Err(e) if attempt < FLUSH_ATTEMPTS => {
+                    warn!(
+                        "Failed to record {} batched signals (attempt {}/{}), retrying in {:?}: {}",
+                        batch.len(),
+                        attempt,
+                        FLUSH_ATTEMPTS,
+                        backoff,
+                        e
+                    );
+                    sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to record {} batched signals after {} attempts: {}",
+                        batch.len(),
+                        FLUSH_ATTEMPTS,
+                        e
+                    );
+                    if let Some(m) = &self.metrics {
+                        m.signal_persist_failures.inc_by(batch.len() as u64);
+                    }
+                }
+            }
+        }
+        batch.clear();
+    }
+}
+
+/// Wait for one item, then keep adding until `max_rows` are held or `max_delay` has passed
+/// since the first. Returns false once every sender is gone
+async fn fill_batch<T>(
+    rx: &Receiver<T>,
+    batch: &mut Vec<T>,
+    max_rows: usize,
+    max_delay: Duration,
+) -> bool {
+    let Ok(first) = rx.recv_async().await else {
+        return false;
+    };
+    batch.push(first);
+    let deadline = Instant::now() + max_delay;
+    while batch.len() < max_rows {
+        match timeout_at(deadline, rx.recv_async()).await {
+            Ok(Ok(item)) => batch.push(item),
+            Ok(Err(_)) => return false,
+            Err(_) => break,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn full_batch_flushes_without_waiting() {
+        let (tx, rx) = flume::unbounded();
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        let mut batch = Vec::new();
+        let started = Instant::now();
+        assert!(fill_batch(&rx, &mut batch, 2, Duration::from_secs(60)).await);
+        assert_eq!(batch, [0, 1]);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn partial_batch_flushes_after_delay() {
+        let (tx, rx) = flume::unbounded();
+        tx.send(1).unwrap();
+        let mut batch = Vec::new();
+        assert!(fill_batch(&rx, &mut batch, 50, Duration::from_millis(20)).await);
+        assert_eq!(batch, [1]);
+    }
+
+    #[tokio::test]
+    async fn closed_channel_returns_what_is_left() {
+        let (tx, rx) = flume::unbounded();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        drop(tx);
+        let mut batch = Vec::new();
+        assert!(!fill_batch(&rx, &mut batch, 50, Duration::from_secs(60)).await);
+        assert_eq!(batch, [1, 2]);
+
+        batch.clear();
+        assert!(!fill_batch(&rx, &mut batch, 50, Duration::from_secs(60)).await);
+        assert!(batch.is_empty());
+    }
+}
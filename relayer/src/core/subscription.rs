@@ -2,26 +2,121 @@ use anyhow::{Context, Result, anyhow};
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as BASE64;
 use chacha20poly1305::aead::{Aead, KeyInit};
-use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
 use chrono::{DateTime, Utc};
-use deadpool_postgres::{Config as PgConfig, Pool, Runtime};
+use dashmap::DashMap;
+use deadpool_postgres::{
+    ClientWrapper, Config as PgConfig, ManagerConfig, Object, Pool, RecyclingMethod, Runtime,
+    Transaction,
+};
+use futures::{Stream, StreamExt};
 use nostr_sdk::prelude::{Client, EventBuilder, Keys};
 use nostr_sdk::{Event, Kind};
 use rand::RngCore;
 use rand::rng;
+use rust_decimal::Decimal;
 use serde::Serialize;
 use serde_json::json;
 use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio_postgres::types::ToSql;
-use tokio_postgres::{NoTls, Row};
+use tokio_postgres::{NoTls, Row, RowStream};
 use tracing::{info, warn};
 
+use crate::core::pubkey::canonical_pubkey;
+
+/// Columns bound per row by `record_signals`
+const SIGNAL_COLUMNS: usize = 17;
+/// Rows per INSERT statement, keeping bind parameters well under Postgres' 65535 limit
+const SIGNAL_INSERT_CHUNK: usize = 1000;
+/// Upper bound on the wait between startup connection attempts
+const MAX_CONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
 /// Row shape for subscriptions
 #[derive(Debug, Clone)]
 pub struct SubscriptionRow {
     pub follower_pubkey: String,
     pub shared_secret: String,
+    /// Secret replaced by a rotation, present only while its overlap window is open
+    pub previous_secret: Option<String>,
+    pub encryption: FanoutEncryption,
+    /// Kinds this follower wants fanned out; None means all
+    pub kinds: Option<Vec<u16>>,
+    /// Inbox relays (NIP-65) to publish this follower's events to; None means the platform relays
+    pub relays: Option<Vec<String>>,
+    /// Set while nostr publishing to this follower is suspended after repeated delivery failures
+    pub suspended_at: Option<DateTime<Utc>>,
+}
+
+/// Scheme used to encrypt nostr fanout payloads for a follower
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FanoutEncryption {
+    Nip04,
+    Nip44,
+}
+
+impl FanoutEncryption {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "nip04" => Some(Self::Nip04),
+            "nip44" => Some(Self::Nip44),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Nip04 => "nip04",
+            Self::Nip44 => "nip44",
+        }
+    }
+}
+
+/// Settlement state of a recorded trade, stored as text in `trade_executions.status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeStatus {
+    Pending,
+    Confirmed,
+    Failed,
+}
+
+impl TradeStatus {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "pending" => Some(Self::Pending),
+            "confirmed" => Some(Self::Confirmed),
+            "failed" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Confirmed => "confirmed",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+impl std::fmt::Display for TradeStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl SubscriptionRow {
+    /// Secrets that should currently receive fanout
+    pub fn secrets(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.shared_secret.as_str()).chain(self.previous_secret.as_deref())
+    }
+
+    /// Whether the follower's kind preferences allow fanning out `kind`
+    pub fn wants_kind(&self, kind: u16) -> bool {
+        self.kinds.as_ref().is_none_or(|k| k.contains(&kind))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -29,10 +124,12 @@ pub struct BotRecord {
     pub bot_pubkey: String,
     pub nostr_pubkey: String,
     pub eth_address: String,
+    pub paused: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct PendingTrade {
+    pub id: i64,
     pub tx_hash: Option<String>,
     pub oid: Option<String>,
     pub bot_pubkey: String,
@@ -42,13 +139,130 @@ pub struct PendingTrade {
     pub price: f64,
     pub pnl_usd: Option<f64>,
     pub is_test: bool,
+    pub symbol: String,
+}
+
+/// A follower of a bot with its balance and latest signal
+#[derive(Debug, Clone)]
+pub struct FollowerDetail {
+    pub follower_pubkey: String,
+    pub subscribed_at: DateTime<Utc>,
+    pub credits: Decimal,
+    /// Newest signal from the bot, of a kind the follower wants, since it subscribed
+    pub last_signal_at: Option<DateTime<Utc>>,
+    /// When nostr publishing to the follower was last suspended, while it still is
+    pub suspended_at: Option<DateTime<Utc>>,
+}
+
+/// Outcome of a registration through the REST API, where the bot's API key is the only proof
+/// of ownership
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyedRegistration {
+    /// Stored; `api_key` is set when this registration issued the bot's key
+    Registered { api_key: Option<String> },
+    /// The bot already has a key and the one presented was missing or wrong; nothing changed
+    KeyRejected,
+}
+
+/// Rows removed along with a deregistered bot
+#[derive(Debug, Clone, Serialize)]
+pub struct BotDeregistration {
+    pub subscriptions: i64,
+    pub trades: i64,
+    pub credits: i64,
 }
 
 #[derive(Debug, Clone)]
 pub struct CreditBalance {
     pub bot_pubkey: String,
     pub follower_pubkey: String,
-    pub credits: f64,
+    pub credits: Decimal,
+}
+
+/// One credit grant; `reason` is kept in the ledger
+#[derive(Debug, Clone)]
+pub struct CreditAward {
+    pub bot_pubkey: String,
+    pub follower_pubkey: String,
+    pub amount: Decimal,
+    pub reason: String,
+    /// tx hash or oid of the trade that earned it, if any
+    pub trade_ref: Option<String>,
+}
+
+/// One credit ledger row
+#[derive(Debug, Clone)]
+pub struct CreditLedgerEntry {
+    pub amount: Decimal,
+    pub reason: String,
+    pub trade_ref: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One trade row, keyed like the unique indexes on `trade_executions`: tx hash or oid, plus
+/// role and follower
+#[derive(Debug, Clone, Copy)]
+pub struct TradeRowKey<'a> {
+    pub tx_hash: Option<&'a str>,
+    pub oid: Option<&'a str>,
+    pub role: &'a str,
+    pub follower_pubkey: Option<&'a str>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TradeRecord {
+    pub id: i64,
+    pub bot_pubkey: String,
+    pub follower_pubkey: Option<String>,
+    pub role: String,
+    pub symbol: String,
+    pub side: String,
+    pub size: f64,
+    pub price: f64,
+    pub tx_hash: Option<String>,
+    pub oid: Option<String>,
+    pub status: String,
+    pub pnl: Option<f64>,
+    pub pnl_usd: Option<f64>,
+    pub is_test: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SignalRecord {
+    pub event_id: String,
+    pub kind: i32,
+    pub bot_pubkey: Option<String>,
+    pub leader_pubkey: String,
+    pub follower_pubkey: Option<String>,
+    pub agent_eth_address: Option<String>,
+    pub role: Option<String>,
+    pub symbol: Option<String>,
+    pub side: Option<String>,
+    pub size: Option<f64>,
+    pub price: Option<f64>,
+    pub status: Option<String>,
+    pub tx_hash: Option<String>,
+    pub pnl: Option<f64>,
+    pub pnl_usd: Option<f64>,
+    pub event_created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolPnl {
+    pub symbol: String,
+    pub pnl_usd: f64,
+    pub trade_count: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolPosition {
+    pub symbol: String,
+    /// Buys and longs add their size, sells and shorts subtract it
+    pub net_size: f64,
+    pub trade_count: i64,
+    /// Trades left out of `net_size` for a missing or non-positive size or an unknown side
+    pub skipped_trades: i64,
 }
 
 #[derive(Debug, Clone)]
@@ -86,27 +300,140 @@ pub struct FanoutMessage {
 #[derive(Clone, Debug)]
 pub struct SubscriptionService {
     pool: Pool,
+    /// bot_pubkey -> (loaded at, followers); dropped on any subscription change for that bot
+    follower_cache: DashMap<String, (Instant, Arc<Vec<SubscriptionRow>>)>,
+    follower_cache_ttl: Duration,
+    /// New followers refused beyond this many per bot; zero means unlimited
+    max_followers_per_bot: u64,
 }
 
 impl SubscriptionService {
     /// Build a Postgres pool and ensure schema
     pub async fn new(dsn: &str, max_connections: usize) -> Result<Self> {
-        let mut cfg = PgConfig::new();
-        cfg.url = Some(dsn.to_string());
-        cfg.pool = Some(deadpool_postgres::PoolConfig {
-            max_size: max_connections,
-            ..Default::default()
-        });
-
-        let pool = cfg
+        let pool = pool_config(dsn, max_connections)
             .create_pool(Some(Runtime::Tokio1), NoTls)
             .context("Failed to create Postgres pool")?;
 
-        let svc = Self { pool };
+        let svc = Self {
+            pool,
+            follower_cache: DashMap::new(),
+            follower_cache_ttl: Duration::ZERO,
+            max_followers_per_bot: 0,
+        };
         svc.init_schema().await?;
+        svc.normalize_follower_pubkeys().await?;
         Ok(svc)
     }
 
+    /// [`Self::new`], retried up to `retries` more times while Postgres is unreachable.
+    /// The delay starts at `delay` and doubles after each failure, up to 30s
+    pub async fn connect_with_retry(
+        dsn: &str,
+        max_connections: usize,
+        retries: u32,
+        delay: Duration,
+    ) -> Result<Self> {
+        retry_with_backoff(retries, delay, || Self::new(dsn, max_connections)).await
+    }
+
+    /// Refuse new followers once a bot has `max` of them; zero disables the cap
+    pub fn with_max_followers_per_bot(mut self, max: u64) -> Self {
+        self.max_followers_per_bot = max;
+        self
+    }
+
+    /// Serve fanout follower lists from memory for up to `ttl`; zero queries on every event
+    pub fn with_follower_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.follower_cache_ttl = ttl;
+        self
+    }
+
+    /// Followers of a bot for fanout, cached per bot. Changes made through this service
+    /// invalidate the entry immediately; changes made by other instances show up within the TTL
+    pub async fn cached_subscriptions(
+        &self,
+        bot_pubkey: &str,
+    ) -> Result<Arc<Vec<SubscriptionRow>>> {
+        if self.follower_cache_ttl.is_zero() {
+            return Ok(Arc::new(self.list_subscriptions(bot_pubkey).await?));
+        }
+        let fresh = self
+            .follower_cache
+            .get(bot_pubkey)
+            .filter(|entry| entry.0.elapsed() < self.follower_cache_ttl)
+            .map(|entry| entry.1.clone());
+        if let Some(followers) = fresh {
+            return Ok(followers);
+        }
+
+        let followers = Arc::new(self.list_subscriptions(bot_pubkey).await?);
+        self.follower_cache
+            .insert(bot_pubkey.to_string(), (Instant::now(), followers.clone()));
+        Ok(followers)
+    }
+
+    /// Drop a bot's cached followers so the next fanout reloads them
+    pub fn invalidate_followers(&self, bot_pubkey: &str) {
+        self.follower_cache.remove(bot_pubkey);
+    }
+
+    /// Try to take session-level advisory lock `key`; on success the connection holding it is
+    /// detached from the pool and returned, and the lock is released when it is dropped
+    pub async fn try_advisory_lock(&self, key: i64) -> Result<Option<ClientWrapper>> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let row = client
+            .query_one("SELECT pg_try_advisory_lock($1)", &[&key])
+            .await
+            .context("Failed to try advisory lock")?;
+        let acquired: bool = row.get(0);
+        Ok(acquired.then(|| Object::take(client)))
+    }
+
+    /// Rewrite subscriptions stored with an npub follower key to hex, matching what the API now
+    /// stores. A row whose hex twin already exists is left alone and reported
+    async fn normalize_follower_pubkeys(&self) -> Result<()> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let rows = client
+            .query(
+                "SELECT bot_pubkey, follower_pubkey FROM subscriptions WHERE follower_pubkey LIKE 'npub1%'",
+                &[],
+            )
+            .await
+            .context("Failed to scan follower pubkeys")?;
+
+        for row in rows {
+            let bot_pubkey: String = row.get(0);
+            let stored: String = row.get(1);
+            let canonical = canonical_pubkey(&stored);
+            if canonical == stored {
+                continue;
+            }
+            let updated = client
+                .execute(
+                    "UPDATE subscriptions SET follower_pubkey = $3
+                     WHERE bot_pubkey = $1 AND follower_pubkey = $2
+                       AND NOT EXISTS (
+                           SELECT 1 FROM subscriptions WHERE bot_pubkey = $1 AND follower_pubkey = $3
+                       )",
+                    &[&bot_pubkey, &stored, &canonical],
+                )
+                .await
+                .context("Failed to normalize follower pubkey")?;
+            if updated == 0 {
+                warn!(
+                    "Subscription {}/{} duplicates hex follower {}; left unchanged",
+                    bot_pubkey, stored, canonical
+                );
+            } else {
+                info!(
+                    "Normalized follower pubkey {} -> {} for bot {}",
+                    stored, canonical, bot_pubkey
+                );
+            }
+        }
+        Ok(())
+    }
+
     /// Initialize tables if they do not exist
     async fn init_schema(&self) -> Result<()> {
         let client = self.pool.get().await.context("Failed to get PG client")?;
@@ -123,6 +450,10 @@ impl SubscriptionService {
                 ALTER TABLE bots ADD COLUMN IF NOT EXISTS nostr_pubkey TEXT NOT NULL DEFAULT '';
                 ALTER TABLE bots ADD COLUMN IF NOT EXISTS eth_address TEXT NOT NULL DEFAULT '';
                 ALTER TABLE bots ADD COLUMN IF NOT EXISTS last_seen_at TIMESTAMPTZ NOT NULL DEFAULT now();
+                ALTER TABLE bots ADD COLUMN IF NOT EXISTS paused BOOLEAN NOT NULL DEFAULT false;
+                ALTER TABLE bots ADD COLUMN IF NOT EXISTS online BOOLEAN NOT NULL DEFAULT false;
+                ALTER TABLE bots ADD COLUMN IF NOT EXISTS api_key_hash TEXT NULL;
+                ALTER TABLE bots ADD COLUMN IF NOT EXISTS registered_at TIMESTAMPTZ NULL;
                 CREATE TABLE IF NOT EXISTS subscriptions (
                     id BIGSERIAL PRIMARY KEY,
                     bot_pubkey TEXT NOT NULL REFERENCES bots(bot_pubkey) ON DELETE CASCADE,
@@ -131,6 +462,12 @@ impl SubscriptionService {
                     created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
                     UNIQUE(bot_pubkey, follower_pubkey)
                 );
+                ALTER TABLE subscriptions ADD COLUMN IF NOT EXISTS previous_secret TEXT NULL;
+                ALTER TABLE subscriptions ADD COLUMN IF NOT EXISTS previous_secret_expires_at TIMESTAMPTZ NULL;
+                ALTER TABLE subscriptions ADD COLUMN IF NOT EXISTS encryption TEXT NOT NULL DEFAULT 'nip04';
+                ALTER TABLE subscriptions ADD COLUMN IF NOT EXISTS kinds INTEGER[] NULL;
+                ALTER TABLE subscriptions ADD COLUMN IF NOT EXISTS relays TEXT[] NULL;
+                ALTER TABLE subscriptions ADD COLUMN IF NOT EXISTS suspended_at TIMESTAMPTZ NULL;
                 CREATE TABLE IF NOT EXISTS platform_state (
                     id TEXT PRIMARY KEY,
                     pubkey TEXT NOT NULL,
@@ -145,7 +482,7 @@ impl SubscriptionService {
                     side TEXT NOT NULL,
                     size DOUBLE PRECISION NOT NULL,
                     price DOUBLE PRECISION NOT NULL,
-                    tx_hash TEXT NOT NULL UNIQUE,
+                    tx_hash TEXT NOT NULL,
                     status TEXT NOT NULL DEFAULT 'pending',
                     pnl DOUBLE PRECISION NULL,
                     pnl_usd DOUBLE PRECISION NULL,
@@ -157,7 +494,16 @@ impl SubscriptionService {
                 ALTER TABLE trade_executions ALTER COLUMN pnl TYPE DOUBLE PRECISION USING pnl::double precision;
                 ALTER TABLE trade_executions ALTER COLUMN pnl_usd TYPE DOUBLE PRECISION USING pnl_usd::double precision;
                 ALTER TABLE trade_executions ALTER COLUMN tx_hash DROP NOT NULL;
-                ALTER TABLE trade_executions ADD COLUMN IF NOT EXISTS oid TEXT UNIQUE;
+                ALTER TABLE trade_executions ADD COLUMN IF NOT EXISTS oid TEXT;
+                -- Leader and follower executions may share a tx hash or oid; one row per (ref, role, follower)
+                ALTER TABLE trade_executions DROP CONSTRAINT IF EXISTS trade_executions_tx_hash_key;
+                ALTER TABLE trade_executions DROP CONSTRAINT IF EXISTS trade_executions_oid_key;
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_trade_executions_tx_hash
+                    ON trade_executions (tx_hash, role, COALESCE(follower_pubkey, ''))
+                    WHERE tx_hash IS NOT NULL;
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_trade_executions_oid
+                    ON trade_executions (oid, role, COALESCE(follower_pubkey, ''))
+                    WHERE oid IS NOT NULL;
                 ALTER TABLE trade_executions ADD COLUMN IF NOT EXISTS is_test BOOLEAN NOT NULL DEFAULT false;
                 CREATE TABLE IF NOT EXISTS credits (
                     bot_pubkey TEXT NOT NULL REFERENCES bots(bot_pubkey) ON DELETE CASCADE,
@@ -166,8 +512,18 @@ impl SubscriptionService {
                     updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
                     PRIMARY KEY (bot_pubkey, follower_pubkey)
                 );
-                ALTER TABLE credits ALTER COLUMN credits TYPE DOUBLE PRECISION USING credits::double precision;
-                ALTER TABLE credits ALTER COLUMN credits SET DEFAULT 0.0;
+                ALTER TABLE credits ALTER COLUMN credits TYPE NUMERIC USING credits::numeric;
+                ALTER TABLE credits ALTER COLUMN credits SET DEFAULT 0;
+                CREATE TABLE IF NOT EXISTS credit_ledger (
+                    id BIGSERIAL PRIMARY KEY,
+                    bot_pubkey TEXT NOT NULL REFERENCES bots(bot_pubkey) ON DELETE CASCADE,
+                    follower_pubkey TEXT NOT NULL,
+                    amount NUMERIC NOT NULL,
+                    reason TEXT NOT NULL,
+                    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+                );
+                ALTER TABLE credit_ledger ADD COLUMN IF NOT EXISTS trade_ref TEXT NULL;
+                CREATE INDEX IF NOT EXISTS idx_credit_ledger_follower ON credit_ledger(bot_pubkey, follower_pubkey, created_at);
                 CREATE TABLE IF NOT EXISTS signals (
                     id BIGSERIAL PRIMARY KEY,
                     event_id TEXT NOT NULL UNIQUE,
@@ -188,52 +544,203 @@ impl SubscriptionService {
                     raw_content TEXT NOT NULL,
                     event_created_at TIMESTAMPTZ NOT NULL,
                     inserted_at TIMESTAMPTZ NOT NULL DEFAULT now()
-                );",
+                );
+                ALTER TABLE signals ADD COLUMN IF NOT EXISTS search_tsv tsvector GENERATED ALWAYS AS (
+                    to_tsvector('simple',
+                        raw_content || ' ' || coalesce(symbol, '') || ' ' || coalesce(side, '') || ' ' ||
+                        coalesce(status, '') || ' ' || coalesce(tx_hash, '') || ' ' ||
+                        coalesce(agent_eth_address, '') || ' ' || leader_pubkey)
+                ) STORED;
+                CREATE INDEX IF NOT EXISTS idx_signals_search ON signals USING GIN (search_tsv);
+                CREATE INDEX IF NOT EXISTS idx_signals_bot_inserted ON signals(bot_pubkey, inserted_at);
+                CREATE TABLE IF NOT EXISTS raw_events (
+                    event_id TEXT PRIMARY KEY,
+                    kind INTEGER NOT NULL,
+                    pubkey TEXT NOT NULL,
+                    event_created_at TIMESTAMPTZ NOT NULL,
+                    content_length INTEGER NOT NULL,
+                    received_at TIMESTAMPTZ NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS raw_events_kind_received_idx ON raw_events (kind, received_at);",
             )
             .await
             .context("Failed to initialize subscription schema")?;
         Ok(())
     }
 
-    /// Register or upsert a bot
+    /// Register or upsert a bot as of `registered_at`. An existing bot is only updated when
+    /// `registered_at` is newer than its last registration; returns false when it was not.
     pub async fn register_bot(
         &self,
         bot_pubkey: &str,
         nostr_pubkey: &str,
         eth_address: &str,
         name: &str,
-    ) -> Result<()> {
+        registered_at: DateTime<Utc>,
+    ) -> Result<bool> {
         let client = self.pool.get().await.context("Failed to get PG client")?;
-        client
+        let updated = client
             .execute(
-                "INSERT INTO bots (bot_pubkey, nostr_pubkey, eth_address, name) VALUES ($1, $2, $3, $4)
-                 ON CONFLICT (bot_pubkey) DO UPDATE SET name = EXCLUDED.name, nostr_pubkey = EXCLUDED.nostr_pubkey, eth_address = EXCLUDED.eth_address",
-                &[&bot_pubkey, &nostr_pubkey, &eth_address, &name],
+                "INSERT INTO bots (bot_pubkey, nostr_pubkey, eth_address, name, registered_at) VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (bot_pubkey) DO UPDATE SET name = EXCLUDED.name, nostr_pubkey = EXCLUDED.nostr_pubkey, eth_address = EXCLUDED.eth_address, registered_at = EXCLUDED.registered_at
+                 WHERE bots.registered_at IS NULL OR bots.registered_at < EXCLUDED.registered_at",
+                &[&bot_pubkey, &nostr_pubkey, &eth_address, &name, &registered_at],
             )
             .await
             .context("Failed to upsert bot")?;
-        Ok(())
+        Ok(updated > 0)
+    }
+
+    /// Register or update a bot through the REST API. A new bot, or one registered before keys
+    /// existed, is issued an API key; a bot that already has one is only updated when `api_key`
+    /// is that key, so re-registering cannot redirect another bot's eth address
+    pub async fn register_bot_with_key(
+        &self,
+        bot_pubkey: &str,
+        nostr_pubkey: &str,
+        eth_address: &str,
+        name: &str,
+        api_key: Option<&str>,
+    ) -> Result<KeyedRegistration> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let stored: Option<String> = client
+            .query_opt(
+                "SELECT api_key_hash FROM bots WHERE bot_pubkey = $1",
+                &[&bot_pubkey],
+            )
+            .await
+            .context("Failed to query bot api key")?
+            .and_then(|row| row.get(0));
+        let authorized = match &stored {
+            Some(hash) => api_key.is_some_and(|key| api_key_matches(hash, key)),
+            None => true,
+        };
+        if !authorized {
+            return Ok(KeyedRegistration::KeyRejected);
+        }
+
+        // The update only applies while the stored hash is still the one checked above, so a
+        // concurrent first registration cannot slip in and take the key
+        let new_key = generate_api_key();
+        let updated = client
+            .execute(
+                "INSERT INTO bots (bot_pubkey, nostr_pubkey, eth_address, name, registered_at, api_key_hash)
+                 VALUES ($1, $2, $3, $4, now(), $5)
+                 ON CONFLICT (bot_pubkey) DO UPDATE SET name = EXCLUDED.name, nostr_pubkey = EXCLUDED.nostr_pubkey, eth_address = EXCLUDED.eth_address, registered_at = EXCLUDED.registered_at,
+                     api_key_hash = COALESCE(bots.api_key_hash, EXCLUDED.api_key_hash)
+                 WHERE bots.api_key_hash IS NOT DISTINCT FROM $6",
+                &[&bot_pubkey, &nostr_pubkey, &eth_address, &name, &hash_api_key(&new_key), &stored],
+            )
+            .await
+            .context("Failed to upsert bot")?;
+        Ok(if updated == 0 {
+            KeyedRegistration::KeyRejected
+        } else {
+            KeyedRegistration::Registered {
+                api_key: stored.is_none().then_some(new_key),
+            }
+        })
+    }
+
+    /// Every inbox relay some subscription lists
+    pub async fn follower_relay_urls(&self) -> Result<HashSet<String>> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let rows = client
+            .query(
+                "SELECT DISTINCT unnest(relays) FROM subscriptions WHERE relays IS NOT NULL",
+                &[],
+            )
+            .await
+            .context("Failed to query follower relays")?;
+        Ok(rows.into_iter().map(|row| row.get(0)).collect())
     }
 
-    /// Add or update a subscription for a follower
+    /// Add or update a subscription for a follower.
+    /// `kinds`: None keeps the current preference, an empty list resets it to all kinds.
+    /// `relays`: likewise, with an empty list resetting to the platform relays.
+    /// A new follower is refused (false) once the bot is at `max_followers_per_bot`. The bot
+    /// row is locked while counting, so concurrent saves cannot overshoot the cap.
     pub async fn add_subscription(
         &self,
         bot_pubkey: &str,
         follower_pubkey: &str,
         shared_secret: &str,
-    ) -> Result<()> {
-        let client = self.pool.get().await.context("Failed to get PG client")?;
-        client
-            .execute(
-                "INSERT INTO subscriptions (bot_pubkey, follower_pubkey, shared_secret)
-                 VALUES ($1, $2, $3)
+        encryption: Option<FanoutEncryption>,
+        kinds: Option<&[u16]>,
+        relays: Option<&[String]>,
+    ) -> Result<bool> {
+        let mut client = self.pool.get().await.context("Failed to get PG client")?;
+        let tx = client
+            .transaction()
+            .await
+            .context("Failed to start subscription transaction")?;
+        if self.max_followers_per_bot > 0 {
+            // Re-saving an existing follower never counts against the cap
+            let row = tx
+                .query_opt(
+                    "SELECT (SELECT COUNT(*) FROM subscriptions WHERE bot_pubkey = $1),
+                            EXISTS (SELECT 1 FROM subscriptions WHERE bot_pubkey = $1 AND follower_pubkey = $2)
+                     FROM bots WHERE bot_pubkey = $1 FOR UPDATE",
+                    &[&bot_pubkey, &follower_pubkey],
+                )
+                .await
+                .context("Failed to count subscriptions")?;
+            if let Some(row) = row {
+                let count: i64 = row.get(0);
+                let exists: bool = row.get(1);
+                if !exists && count.max(0) as u64 >= self.max_followers_per_bot {
+                    return Ok(false);
+                }
+            }
+        }
+        // Re-saving without a scheme keeps whatever the follower already uses
+        let encryption = encryption.map(|e| e.as_str());
+        let kinds: Option<Vec<i32>> = kinds.map(|k| k.iter().map(|&k| k as i32).collect());
+        tx.execute(
+                "INSERT INTO subscriptions (bot_pubkey, follower_pubkey, shared_secret, encryption, kinds, relays)
+                 VALUES ($1, $2, $3, COALESCE($4, 'nip04'),
+                         CASE WHEN cardinality($5::INTEGER[]) > 0 THEN $5::INTEGER[] END,
+                         CASE WHEN cardinality($6::TEXT[]) > 0 THEN $6::TEXT[] END)
                  ON CONFLICT (bot_pubkey, follower_pubkey) DO UPDATE
-                 SET shared_secret = EXCLUDED.shared_secret",
-                &[&bot_pubkey, &follower_pubkey, &shared_secret],
+                 SET shared_secret = EXCLUDED.shared_secret,
+                     suspended_at = NULL,
+                     encryption = COALESCE($4, subscriptions.encryption),
+                     kinds = CASE WHEN $5::INTEGER[] IS NULL THEN subscriptions.kinds ELSE EXCLUDED.kinds END,
+                     relays = CASE WHEN $6::TEXT[] IS NULL THEN subscriptions.relays ELSE EXCLUDED.relays END",
+                &[&bot_pubkey, &follower_pubkey, &shared_secret, &encryption, &kinds, &relays],
             )
             .await
             .context("Failed to upsert subscription")?;
-        Ok(())
+        tx.commit().await.context("Failed to commit subscription")?;
+        self.invalidate_followers(bot_pubkey);
+        Ok(true)
+    }
+
+    /// Replace a follower's secret, keeping the old one live for `overlap_secs`.
+    /// Returns false when the subscription does not exist.
+    pub async fn rotate_subscription_secret(
+        &self,
+        bot_pubkey: &str,
+        follower_pubkey: &str,
+        new_secret: &str,
+        overlap_secs: i64,
+    ) -> Result<bool> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let updated = client
+            .execute(
+                "UPDATE subscriptions
+                 SET previous_secret = CASE WHEN shared_secret <> $3 THEN shared_secret ELSE previous_secret END,
+                     previous_secret_expires_at = CASE WHEN shared_secret <> $3
+                         THEN now() + $4::BIGINT * interval '1 second'
+                         ELSE previous_secret_expires_at END,
+                     shared_secret = $3
+                 WHERE bot_pubkey = $1 AND follower_pubkey = $2",
+                &[&bot_pubkey, &follower_pubkey, &new_secret, &overlap_secs],
+            )
+            .await
+            .context("Failed to rotate subscription secret")?;
+        self.invalidate_followers(bot_pubkey);
+        Ok(updated > 0)
     }
 
     /// List subscriptions for a bot
@@ -241,7 +748,10 @@ impl SubscriptionService {
         let client = self.pool.get().await.context("Failed to get PG client")?;
         let rows = client
             .query(
-                "SELECT follower_pubkey, shared_secret FROM subscriptions WHERE bot_pubkey = $1",
+                "SELECT follower_pubkey, shared_secret,
+                        CASE WHEN previous_secret_expires_at > now() THEN previous_secret END,
+                        encryption, kinds, relays, suspended_at
+                 FROM subscriptions WHERE bot_pubkey = $1",
                 &[&bot_pubkey],
             )
             .await
@@ -252,10 +762,88 @@ impl SubscriptionService {
             .map(|row| SubscriptionRow {
                 follower_pubkey: row.get(0),
                 shared_secret: row.get(1),
+                previous_secret: row.get(2),
+                encryption: FanoutEncryption::parse(row.get(3)).unwrap_or(FanoutEncryption::Nip04),
+                kinds: row
+                    .get::<_, Option<Vec<i32>>>(4)
+                    .map(|k| k.into_iter().map(|k| k as u16).collect()),
+                relays: row.get(5),
+                suspended_at: row.get(6),
+            })
+            .collect())
+    }
+
+    /// Followers of a bot joined with their credits and latest matching signal, oldest subscription first
+    pub async fn follower_details(&self, bot_pubkey: &str) -> Result<Vec<FollowerDetail>> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let rows = client
+            .query(
+                "SELECT s.follower_pubkey, s.created_at, COALESCE(c.credits, 0), last.inserted_at,
+                        s.suspended_at
+                 FROM subscriptions s
+                 LEFT JOIN credits c
+                   ON c.bot_pubkey = s.bot_pubkey AND c.follower_pubkey = s.follower_pubkey
+                 LEFT JOIN LATERAL (
+                     SELECT sig.inserted_at
+                     FROM signals sig
+                     WHERE sig.bot_pubkey = s.bot_pubkey
+                       AND sig.inserted_at >= s.created_at
+                       AND (s.kinds IS NULL OR sig.kind = ANY(s.kinds))
+                     ORDER BY sig.inserted_at DESC
+                     LIMIT 1
+                 ) last ON true
+                 WHERE s.bot_pubkey = $1
+                 ORDER BY s.created_at ASC",
+                &[&bot_pubkey],
+            )
+            .await
+            .context("Failed to query follower details")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| FollowerDetail {
+                follower_pubkey: row.get(0),
+                subscribed_at: row.get(1),
+                credits: row.get(2),
+                last_signal_at: row.get(3),
+                suspended_at: row.get(4),
             })
             .collect())
     }
 
+    /// Suspend nostr publishing to a follower (stamping `suspended_at` now) or lift the suspension
+    pub async fn set_subscription_suspended(
+        &self,
+        bot_pubkey: &str,
+        follower_pubkey: &str,
+        suspended: bool,
+    ) -> Result<()> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        client
+            .execute(
+                "UPDATE subscriptions SET suspended_at = CASE WHEN $3 THEN now() END
+                 WHERE bot_pubkey = $1 AND follower_pubkey = $2",
+                &[&bot_pubkey, &follower_pubkey, &suspended],
+            )
+            .await
+            .context("Failed to update subscription suspension")?;
+        self.invalidate_followers(bot_pubkey);
+        Ok(())
+    }
+
+    /// Count followers subscribed to a bot
+    pub async fn count_subscriptions(&self, bot_pubkey: &str) -> Result<i64> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let row = client
+            .query_one(
+                "SELECT COUNT(*) FROM subscriptions WHERE bot_pubkey = $1",
+                &[&bot_pubkey],
+            )
+            .await
+            .context("Failed to count subscriptions")?;
+        Ok(row.get(0))
+    }
+
     /// Produce encrypted fanout messages for all followers of the bot that emitted the event
     pub async fn fanout_for_event(&self, event: &Event) -> Result<Vec<FanoutMessage>> {
         let bot_pubkey = event.pubkey.to_hex();
@@ -284,7 +872,7 @@ impl SubscriptionService {
         let client = self.pool.get().await.context("Failed to get PG client")?;
         let row = client
             .query_opt(
-                "SELECT bot_pubkey, nostr_pubkey, eth_address FROM bots WHERE eth_address = $1",
+                "SELECT bot_pubkey, nostr_pubkey, eth_address, paused FROM bots WHERE eth_address = $1",
                 &[&eth_address],
             )
             .await
@@ -315,6 +903,103 @@ impl SubscriptionService {
         Ok(row.is_some())
     }
 
+    /// Pause or resume fanout for a bot; returns false when the bot is unknown
+    pub async fn set_bot_paused(&self, bot_pubkey: &str, paused: bool) -> Result<bool> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let updated = client
+            .execute(
+                "UPDATE bots SET paused = $2 WHERE bot_pubkey = $1",
+                &[&bot_pubkey, &paused],
+            )
+            .await
+            .context("Failed to update bot paused flag")?;
+        Ok(updated > 0)
+    }
+
+    /// Delete a bot; subscriptions, trades and credits cascade and its signals are kept with
+    /// `bot_pubkey` cleared. Returns the removed dependent rows, or None if there was no such bot
+    pub async fn deregister_bot(&self, bot_pubkey: &str) -> Result<Option<BotDeregistration>> {
+        let mut client = self.pool.get().await.context("Failed to get PG client")?;
+        let tx = client
+            .transaction()
+            .await
+            .context("Failed to start deregistration transaction")?;
+        // Counted in the same transaction as the delete, so they match what the cascade removed
+        let Some(counts) = tx
+            .query_opt(
+                "SELECT (SELECT COUNT(*) FROM subscriptions WHERE bot_pubkey = $1),
+                        (SELECT COUNT(*) FROM trade_executions WHERE bot_pubkey = $1),
+                        (SELECT COUNT(*) FROM credits WHERE bot_pubkey = $1)
+                 FROM bots WHERE bot_pubkey = $1 FOR UPDATE",
+                &[&bot_pubkey],
+            )
+            .await
+            .context("Failed to count bot dependents")?
+        else {
+            return Ok(None);
+        };
+        tx.execute("DELETE FROM bots WHERE bot_pubkey = $1", &[&bot_pubkey])
+            .await
+            .context("Failed to delete bot")?;
+        tx.commit()
+            .await
+            .context("Failed to commit bot deregistration")?;
+        self.invalidate_followers(bot_pubkey);
+        Ok(Some(BotDeregistration {
+            subscriptions: counts.get(0),
+            trades: counts.get(1),
+            credits: counts.get(2),
+        }))
+    }
+
+    /// Issue a new API key for a bot, replacing any current one and storing only its hash.
+    /// None means no such bot
+    pub async fn issue_bot_api_key(&self, bot_pubkey: &str) -> Result<Option<String>> {
+        let key = generate_api_key();
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let updated = client
+            .execute(
+                "UPDATE bots SET api_key_hash = $2 WHERE bot_pubkey = $1",
+                &[&bot_pubkey, &hash_api_key(&key)],
+            )
+            .await
+            .context("Failed to store bot api key")?;
+        Ok((updated > 0).then_some(key))
+    }
+
+    /// Whether `key` is the bot's current API key; false for bots that were never issued one
+    pub async fn verify_bot_api_key(&self, bot_pubkey: &str, key: &str) -> Result<bool> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let row = client
+            .query_opt(
+                "SELECT api_key_hash FROM bots WHERE bot_pubkey = $1",
+                &[&bot_pubkey],
+            )
+            .await
+            .context("Failed to query bot api key")?;
+        let stored: Option<String> = row.and_then(|r| r.get(0));
+        Ok(stored.is_some_and(|hash| api_key_matches(&hash, key)))
+    }
+
+    /// Recompute `online` from heartbeat staleness; returns only the bots whose state changed
+    pub async fn update_bot_presence(
+        &self,
+        offline_after_secs: i64,
+    ) -> Result<Vec<(String, bool)>> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let rows = client
+            .query(
+                "UPDATE bots
+                 SET online = (last_seen_at > now() - $1::BIGINT * interval '1 second')
+                 WHERE online IS DISTINCT FROM (last_seen_at > now() - $1::BIGINT * interval '1 second')
+                 RETURNING bot_pubkey, online",
+                &[&offline_after_secs],
+            )
+            .await
+            .context("Failed to update bot presence")?;
+        Ok(rows.into_iter().map(|r| (r.get(0), r.get(1))).collect())
+    }
+
     pub async fn update_bot_last_seen(&self, bot_pubkey: &str) -> Result<()> {
         let client = self.pool.get().await.context("Failed to get PG client")?;
         client
@@ -414,61 +1099,339 @@ impl SubscriptionService {
         Ok(())
     }
 
-    /// Update trade settlement/PnL once the chain confirms
+    /// Update trade settlement/PnL once the chain confirms; a None `status` only updates PnL.
+    /// Only the row `key` names is touched, so a follower's report never overwrites the leader
+    /// row sharing its tx hash
     pub async fn update_trade_settlement(
         &self,
-        tx_hash: Option<&str>,
-        oid: Option<&str>,
-        status: &str,
+        key: TradeRowKey<'_>,
+        status: Option<TradeStatus>,
         pnl: Option<f64>,
         pnl_usd: Option<f64>,
     ) -> Result<()> {
-        if tx_hash.is_none() && oid.is_none() {
+        if key.tx_hash.is_none() && key.oid.is_none() {
             return Ok(());
         }
         let client = self.pool.get().await.context("Failed to get PG client")?;
+        let status = status.map(|s| s.as_str());
         client
             .execute(
                 "UPDATE trade_executions
-                 SET status = $2,
+                 SET status = COALESCE($2, status),
                      pnl = COALESCE($3, pnl),
                      pnl_usd = COALESCE($4, pnl_usd),
                      updated_at = now()
-                 WHERE ($1 IS NOT NULL AND tx_hash = $1)
-                    OR ($5 IS NOT NULL AND oid = $5)",
-                &[&tx_hash, &status, &pnl, &pnl_usd, &oid],
+                 WHERE (($1::TEXT IS NOT NULL AND tx_hash = $1) OR ($5::TEXT IS NOT NULL AND oid = $5))
+                   AND role = $6
+                   AND COALESCE(follower_pubkey, '') = COALESCE($7, '')",
+                &[
+                    &key.tx_hash,
+                    &status,
+                    &pnl,
+                    &pnl_usd,
+                    &key.oid,
+                    &key.role,
+                    &key.follower_pubkey,
+                ],
             )
             .await
             .context("Failed to update trade settlement")?;
         Ok(())
     }
 
+    /// Settle one trade row. Leader and follower rows can share a tx hash, so the worker
+    /// settles each row it has credited rather than every row for the hash.
+    pub async fn set_trade_status(&self, id: i64, status: TradeStatus) -> Result<()> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        client
+            .execute(
+                "UPDATE trade_executions SET status = $2, updated_at = now() WHERE id = $1",
+                &[&id, &status.as_str()],
+            )
+            .await
+            .context("Failed to update trade status")?;
+        Ok(())
+    }
+
+    /// Set a trade's status regardless of verification, returning the rows that actually changed
+    pub async fn force_trade_settlement(
+        &self,
+        tx_hash: Option<&str>,
+        oid: Option<&str>,
+        status: TradeStatus,
+        pnl_usd: Option<f64>,
+    ) -> Result<Vec<PendingTrade>> {
+        if tx_hash.is_none() && oid.is_none() {
+            return Ok(Vec::new());
+        }
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let status = status.as_str();
+        let rows = client
+            .query(
+                "UPDATE trade_executions
+                 SET status = $2,
+                     pnl_usd = COALESCE($3, pnl_usd),
+                     updated_at = now()
+                 WHERE (($1::TEXT IS NOT NULL AND tx_hash = $1) OR ($4::TEXT IS NOT NULL AND oid = $4))
+                   AND status <> $2
+                 RETURNING id, tx_hash, oid, bot_pubkey, follower_pubkey, role, size, price, pnl_usd, is_test, symbol",
+                &[&tx_hash, &status, &pnl_usd, &oid],
+            )
+            .await
+            .context("Failed to force trade settlement")?;
+
+        Ok(rows.into_iter().map(row_to_pending_trade).collect())
+    }
+
+    /// Total number of trades still awaiting settlement
+    pub async fn count_pending_trades(&self) -> Result<i64> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let row = client
+            .query_one(
+                "SELECT COUNT(*) FROM trade_executions WHERE status = $1",
+                &[&TradeStatus::Pending.as_str()],
+            )
+            .await
+            .context("Failed to count pending trades")?;
+        Ok(row.get(0))
+    }
+
     pub async fn list_pending_trades(&self, limit: i64) -> Result<Vec<PendingTrade>> {
         let client = self.pool.get().await.context("Failed to get PG client")?;
         let rows = client
             .query(
-                "SELECT tx_hash, oid, bot_pubkey, follower_pubkey, role, size, price, pnl_usd, is_test
+                "SELECT id, tx_hash, oid, bot_pubkey, follower_pubkey, role, size, price, pnl_usd, is_test, symbol
                  FROM trade_executions
-                 WHERE status = 'pending'
-                 ORDER BY created_at ASC
+                 WHERE status = $2
+                 ORDER BY created_at ASC, id ASC
                  LIMIT $1",
-                &[&limit],
+                &[&limit, &TradeStatus::Pending.as_str()],
             )
             .await
             .context("Failed to query pending trades")?;
 
+        Ok(rows.into_iter().map(row_to_pending_trade).collect())
+    }
+
+    /// Confirmed trades created within the last `window_secs` whose `pnl_usd` is still unknown, oldest first
+    pub async fn list_trades_missing_pnl(
+        &self,
+        window_secs: f64,
+        limit: i64,
+    ) -> Result<Vec<PendingTrade>> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let rows = client
+            .query(
+                "SELECT id, tx_hash, oid, bot_pubkey, follower_pubkey, role, size, price, pnl_usd, is_test, symbol
+                 FROM trade_executions
+                 WHERE status = $3
+                   AND pnl_usd IS NULL
+                   AND (tx_hash IS NOT NULL OR oid IS NOT NULL)
+                   AND created_at >= now() - make_interval(secs => $1)
+                 ORDER BY created_at ASC, id ASC
+                 LIMIT $2",
+                &[&window_secs, &limit, &TradeStatus::Confirmed.as_str()],
+            )
+            .await
+            .context("Failed to query trades missing pnl")?;
+
+        Ok(rows.into_iter().map(row_to_pending_trade).collect())
+    }
+
+    /// Set a trade's PnL if it is still unknown and pay `bonus` in the same transaction.
+    /// Returns false when the PnL was already set, so a bonus is never paid twice.
+    pub async fn backfill_trade_pnl(
+        &self,
+        id: i64,
+        pnl: Option<f64>,
+        pnl_usd: f64,
+        bonus: Option<&CreditAward>,
+    ) -> Result<bool> {
+        let mut client = self.pool.get().await.context("Failed to get PG client")?;
+        let tx = client
+            .transaction()
+            .await
+            .context("Failed to start pnl backfill transaction")?;
+        let updated = tx
+            .execute(
+                "UPDATE trade_executions
+                 SET pnl_usd = $2,
+                     pnl = COALESCE($3, pnl),
+                     updated_at = now()
+                 WHERE id = $1 AND pnl_usd IS NULL",
+                &[&id, &pnl_usd, &pnl],
+            )
+            .await
+            .context("Failed to backfill trade pnl")?;
+        if updated == 0 {
+            return Ok(false);
+        }
+        if let Some(a) = bonus {
+            apply_credit_award(
+                &tx,
+                &a.bot_pubkey,
+                &a.follower_pubkey,
+                a.amount,
+                &a.reason,
+                a.trade_ref.as_deref(),
+            )
+            .await?;
+        }
+        tx.commit().await.context("Failed to commit pnl backfill")?;
+        Ok(true)
+    }
+
+    /// List trades newest first, optionally bounded to a `created_at` range
+    pub async fn list_trades(
+        &self,
+        bot_pubkey: Option<&str>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<TradeRecord>> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let rows = client
+            .query(TRADES_QUERY, &[&bot_pubkey, &from, &to, &limit])
+            .await
+            .context("Failed to query trades")?;
+
+        Ok(rows.into_iter().map(row_to_trade_record).collect())
+    }
+
+    /// Stream every trade matching the filter without buffering the result set
+    pub async fn stream_trades(
+        &self,
+        bot_pubkey: Option<String>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<impl Stream<Item = Result<TradeRecord>> + Send + 'static> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let params: [&(dyn ToSql + Sync); 4] = [&bot_pubkey, &from, &to, &i64::MAX];
+        let rows = client
+            .query_raw(TRADES_QUERY, params)
+            .await
+            .context("Failed to query trades")?;
+
+        Ok(row_stream(client, rows, row_to_trade_record))
+    }
+
+    /// Stream every signal matching the filter without buffering the result set
+    pub async fn stream_signals(
+        &self,
+        bot_pubkey: Option<String>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<impl Stream<Item = Result<SignalRecord>> + Send + 'static> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let params: [&(dyn ToSql + Sync); 3] = [&bot_pubkey, &from, &to];
+        let rows = client
+            .query_raw(
+                "SELECT event_id, kind, bot_pubkey, leader_pubkey, follower_pubkey, agent_eth_address,
+                        role, symbol, side, size, price, status, tx_hash, pnl, pnl_usd, event_created_at
+                 FROM signals
+                 WHERE ($1::TEXT IS NULL OR bot_pubkey = $1)
+                   AND event_created_at BETWEEN COALESCE($2, '-infinity'::timestamptz)
+                                            AND COALESCE($3, 'infinity'::timestamptz)
+                 ORDER BY event_created_at DESC",
+                params,
+            )
+            .await
+            .context("Failed to query signals")?;
+
+        Ok(row_stream(client, rows, row_to_signal_record))
+    }
+
+    /// Signals whose content or key fields contain every word of `query`, newest first
+    pub async fn search_signals(
+        &self,
+        query: &str,
+        bot_pubkey: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<SignalRecord>> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let rows = client
+            .query(
+                "SELECT event_id, kind, bot_pubkey, leader_pubkey, follower_pubkey, agent_eth_address,
+                        role, symbol, side, size, price, status, tx_hash, pnl, pnl_usd, event_created_at
+                 FROM signals
+                 WHERE search_tsv @@ plainto_tsquery('simple', $1)
+                   AND ($2::TEXT IS NULL OR bot_pubkey = $2)
+                 ORDER BY event_created_at DESC
+                 LIMIT $3",
+                &[&query, &bot_pubkey, &limit],
+            )
+            .await
+            .context("Failed to search signals")?;
+        Ok(rows.into_iter().map(row_to_signal_record).collect())
+    }
+
+    /// Aggregate realized PnL and trade counts per symbol for a bot
+    pub async fn pnl_by_symbol(
+        &self,
+        bot_pubkey: &str,
+        include_test: bool,
+    ) -> Result<Vec<SymbolPnl>> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let rows = client
+            .query(
+                "SELECT symbol, COALESCE(SUM(pnl_usd), 0), COUNT(*)
+                 FROM trade_executions
+                 WHERE bot_pubkey = $1 AND ($2 OR NOT is_test)
+                 GROUP BY symbol
+                 ORDER BY 2 DESC",
+                &[&bot_pubkey, &include_test],
+            )
+            .await
+            .context("Failed to query pnl by symbol")?;
+
         Ok(rows
             .into_iter()
-            .map(|row| PendingTrade {
-                tx_hash: row.get(0),
-                oid: row.get(1),
-                bot_pubkey: row.get(2),
-                follower_pubkey: row.get(3),
-                role: row.get(4),
-                size: row.get(5),
-                price: row.get(6),
-                pnl_usd: row.get(7),
-                is_test: row.get(8),
+            .map(|row| SymbolPnl {
+                symbol: row.get(0),
+                pnl_usd: row.get(1),
+                trade_count: row.get(2),
+            })
+            .collect())
+    }
+
+    /// Net position per symbol from a bot's own (leader) trades, excluding failed ones
+    pub async fn positions(
+        &self,
+        bot_pubkey: &str,
+        include_test: bool,
+    ) -> Result<Vec<SymbolPosition>> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let rows = client
+            .query(
+                "WITH t AS (
+                     SELECT symbol, COALESCE(size, 0) AS size,
+                            CASE lower(side)
+                                WHEN 'buy' THEN 1 WHEN 'long' THEN 1
+                                WHEN 'sell' THEN -1 WHEN 'short' THEN -1
+                            END AS sign
+                     FROM trade_executions
+                     WHERE bot_pubkey = $1 AND role = 'leader' AND status <> 'failed'
+                       AND ($2 OR NOT is_test)
+                 )
+                 SELECT symbol,
+                        COALESCE(SUM(sign * size) FILTER (WHERE sign IS NOT NULL AND size > 0), 0),
+                        COUNT(*) FILTER (WHERE sign IS NOT NULL AND size > 0),
+                        COUNT(*) FILTER (WHERE sign IS NULL OR NOT size > 0)
+                 FROM t
+                 GROUP BY symbol
+                 ORDER BY symbol",
+                &[&bot_pubkey, &include_test],
+            )
+            .await
+            .context("Failed to query positions")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SymbolPosition {
+                symbol: row.get(0),
+                net_size: row.get(1),
+                trade_count: row.get(2),
+                skipped_trades: row.get(3),
             })
             .collect())
     }
@@ -520,32 +1483,178 @@ impl SubscriptionService {
             .collect())
     }
 
-    /// Increase follower credits for a bot after confirmed settlement
+    /// Increase follower credits for a bot and record the grant in the ledger
     pub async fn award_credits(
         &self,
         bot_pubkey: &str,
         follower_pubkey: &str,
-        delta: f64,
+        delta: Decimal,
+        reason: &str,
+        trade_ref: Option<&str>,
+    ) -> Result<()> {
+        let mut client = self.pool.get().await.context("Failed to get PG client")?;
+        let tx = client
+            .transaction()
+            .await
+            .context("Failed to start credit transaction")?;
+        apply_credit_award(&tx, bot_pubkey, follower_pubkey, delta, reason, trade_ref).await?;
+        tx.commit().await.context("Failed to commit credit award")?;
+        Ok(())
+    }
+
+    /// Apply every award or none; returns each row's new balance in input order
+    pub async fn award_credits_batch(&self, awards: &[CreditAward]) -> Result<Vec<Decimal>> {
+        let mut client = self.pool.get().await.context("Failed to get PG client")?;
+        let tx = client
+            .transaction()
+            .await
+            .context("Failed to start credit transaction")?;
+        let mut balances = Vec::with_capacity(awards.len());
+        for a in awards {
+            let balance = apply_credit_award(
+                &tx,
+                &a.bot_pubkey,
+                &a.follower_pubkey,
+                a.amount,
+                &a.reason,
+                a.trade_ref.as_deref(),
+            )
+            .await?;
+            balances.push(balance);
+        }
+        tx.commit()
+            .await
+            .context("Failed to commit credit awards")?;
+        Ok(balances)
+    }
+
+    /// A follower's ledger entries under a bot, newest first
+    pub async fn credit_history(
+        &self,
+        bot_pubkey: &str,
+        follower_pubkey: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<CreditLedgerEntry>> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let rows = client
+            .query(
+                "SELECT amount, reason, trade_ref, created_at
+                 FROM credit_ledger
+                 WHERE bot_pubkey = $1 AND follower_pubkey = $2
+                 ORDER BY created_at DESC, id DESC
+                 LIMIT $3 OFFSET $4",
+                &[&bot_pubkey, &follower_pubkey, &limit, &offset],
+            )
+            .await
+            .context("Failed to query credit history")?;
+        Ok(rows
+            .into_iter()
+            .map(|row| CreditLedgerEntry {
+                amount: row.get(0),
+                reason: row.get(1),
+                trade_ref: row.get(2),
+                created_at: row.get(3),
+            })
+            .collect())
+    }
+
+    /// Event ids of signals inserted within the last `window_secs`, newest first
+    pub async fn recent_signal_event_ids(
+        &self,
+        window_secs: i64,
+        limit: i64,
+    ) -> Result<Vec<String>> {
+        let client = self.pool.get().await.context("Failed to get PG client")?;
+        let rows = client
+            .query(
+                "SELECT event_id FROM signals
+                 WHERE inserted_at > now() - $1::BIGINT * interval '1 second'
+                 ORDER BY inserted_at DESC
+                 LIMIT $2",
+                &[&window_secs, &limit],
+            )
+            .await
+            .context("Failed to load recent signal ids")?;
+        Ok(rows.into_iter().map(|r| r.get(0)).collect())
+    }
+
+    /// Audit row for a processed event of any kind; content itself is not stored
+    pub async fn record_raw_event(
+        &self,
+        event_id: &str,
+        kind: u16,
+        pubkey: &str,
+        created_at: u64,
+        content_length: usize,
+        received_at: u64,
     ) -> Result<()> {
         let client = self.pool.get().await.context("Failed to get PG client")?;
         client
             .execute(
-                "INSERT INTO credits AS c (bot_pubkey, follower_pubkey, credits)
-                 VALUES ($1, $2, $3)
-                 ON CONFLICT (bot_pubkey, follower_pubkey)
-                 DO UPDATE SET credits = c.credits + EXCLUDED.credits, updated_at = now()",
-                &[&bot_pubkey, &follower_pubkey, &delta],
+                "INSERT INTO raw_events (event_id, kind, pubkey, event_created_at, content_length, received_at)
+                 VALUES ($1, $2, $3, to_timestamp($4::BIGINT), $5, to_timestamp($6::BIGINT))
+                 ON CONFLICT (event_id) DO NOTHING",
+                &[
+                    &event_id,
+                    &(kind as i32),
+                    &pubkey,
+                    &(created_at as i64),
+                    &(content_length as i32),
+                    &(received_at as i64),
+                ],
             )
             .await
-            .context("Failed to award credits")?;
+            .context("Failed to insert raw event")?;
         Ok(())
     }
 
     pub async fn record_signal(&self, signal: SignalInsert) -> Result<()> {
+        self.record_signals(std::slice::from_ref(&signal)).await?;
+        Ok(())
+    }
+
+    /// Insert many signals with multi-row INSERTs; rows whose event_id already exists
+    /// (in the table or earlier in the batch) are skipped. Returns the rows inserted.
+    pub async fn record_signals(&self, signals: &[SignalInsert]) -> Result<u64> {
+        if signals.is_empty() {
+            return Ok(0);
+        }
         let client = self.pool.get().await.context("Failed to get PG client")?;
 
-        client
-            .execute(
+        let mut inserted = 0;
+        for chunk in signals.chunks(SIGNAL_INSERT_CHUNK) {
+            let kinds: Vec<i32> = chunk.iter().map(|s| s.kind as i32).collect();
+            let mut values = Vec::with_capacity(chunk.len());
+            let mut params: Vec<&(dyn ToSql + Sync)> =
+                Vec::with_capacity(chunk.len() * SIGNAL_COLUMNS);
+            for (i, (signal, kind)) in chunk.iter().zip(&kinds).enumerate() {
+                let base = i * SIGNAL_COLUMNS;
+                let placeholders: Vec<String> = (1..=SIGNAL_COLUMNS)
+                    .map(|n| format!("${}", base + n))
+                    .collect();
+                values.push(format!("({})", placeholders.join(", ")));
+                params.extend_from_slice(&[
+                    &signal.event_id,
+                    kind,
+                    &signal.bot_pubkey,
+                    &signal.leader_pubkey,
+                    &signal.follower_pubkey,
+                    &signal.agent_eth_address,
+                    &signal.role,
+                    &signal.symbol,
+                    &signal.side,
+                    &signal.size,
+                    &signal.price,
+                    &signal.status,
+                    &signal.tx_hash,
+                    &signal.pnl,
+                    &signal.pnl_usd,
+                    &signal.raw_content,
+                    &signal.event_created_at,
+                ]);
+            }
+            let sql = format!(
                 "INSERT INTO signals (
                     event_id,
                     kind,
@@ -564,34 +1673,177 @@ impl SubscriptionService {
                     pnl_usd,
                     raw_content,
                     event_created_at
-                ) VALUES (
-                    $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17
-                )
+                ) VALUES {}
                 ON CONFLICT (event_id) DO NOTHING",
-                &[
-                    &signal.event_id,
-                    &(signal.kind as i32),
-                    &signal.bot_pubkey,
-                    &signal.leader_pubkey,
-                    &signal.follower_pubkey,
-                    &signal.agent_eth_address,
-                    &signal.role,
-                    &signal.symbol,
-                    &signal.side,
-                    &signal.size,
-                    &signal.price,
-                    &signal.status,
-                    &signal.tx_hash,
-                    &signal.pnl,
-                    &signal.pnl_usd,
-                    &signal.raw_content,
-                    &signal.event_created_at,
-                ],
-            )
-            .await
-            .context("Failed to record signal event")?;
+                values.join(", ")
+            );
+            inserted += client
+                .execute(sql.as_str(), &params)
+                .await
+                .context("Failed to record signal events")?;
+        }
 
-        Ok(())
+        Ok(inserted)
+    }
+}
+
+const TRADES_QUERY: &str =
+    "SELECT id, bot_pubkey, follower_pubkey, role, symbol, side, size, price,
+        tx_hash, oid, status, pnl, pnl_usd, is_test, created_at
+ FROM trade_executions
+ WHERE ($1::TEXT IS NULL OR bot_pubkey = $1)
+   AND created_at BETWEEN COALESCE($2, '-infinity'::timestamptz)
+                      AND COALESCE($3, 'infinity'::timestamptz)
+ ORDER BY created_at DESC
+ LIMIT $4";
+
+/// Upsert the balance and append the matching ledger entry, returning the new balance
+async fn apply_credit_award(
+    tx: &Transaction<'_>,
+    bot_pubkey: &str,
+    follower_pubkey: &str,
+    delta: Decimal,
+    reason: &str,
+    trade_ref: Option<&str>,
+) -> Result<Decimal> {
+    let row = tx
+        .query_one(
+            "INSERT INTO credits AS c (bot_pubkey, follower_pubkey, credits)
+             VALUES ($1, $2, $3)
+             ON CONFLICT (bot_pubkey, follower_pubkey)
+             DO UPDATE SET credits = c.credits + EXCLUDED.credits, updated_at = now()
+             RETURNING credits",
+            &[&bot_pubkey, &follower_pubkey, &delta],
+        )
+        .await
+        .context("Failed to award credits")?;
+    tx.execute(
+        "INSERT INTO credit_ledger (bot_pubkey, follower_pubkey, amount, reason, trade_ref)
+         VALUES ($1, $2, $3, $4, $5)",
+        &[&bot_pubkey, &follower_pubkey, &delta, &reason, &trade_ref],
+    )
+    .await
+    .context("Failed to write credit ledger entry")?;
+    Ok(row.get(0))
+}
+
+fn row_to_pending_trade(row: Row) -> PendingTrade {
+    PendingTrade {
+        id: row.get(0),
+        tx_hash: row.get(1),
+        oid: row.get(2),
+        bot_pubkey: row.get(3),
+        follower_pubkey: row.get(4),
+        role: row.get(5),
+        size: row.get(6),
+        price: row.get(7),
+        pnl_usd: row.get(8),
+        is_test: row.get(9),
+        symbol: row.get(10),
+    }
+}
+
+fn row_to_signal_record(row: Row) -> SignalRecord {
+    SignalRecord {
+        event_id: row.get(0),
+        kind: row.get(1),
+        bot_pubkey: row.get(2),
+        leader_pubkey: row.get(3),
+        follower_pubkey: row.get(4),
+        agent_eth_address: row.get(5),
+        role: row.get(6),
+        symbol: row.get(7),
+        side: row.get(8),
+        size: row.get(9),
+        price: row.get(10),
+        status: row.get(11),
+        tx_hash: row.get(12),
+        pnl: row.get(13),
+        pnl_usd: row.get(14),
+        event_created_at: row.get(15),
+    }
+}
+
+fn row_to_trade_record(row: Row) -> TradeRecord {
+    TradeRecord {
+        id: row.get(0),
+        bot_pubkey: row.get(1),
+        follower_pubkey: row.get(2),
+        role: row.get(3),
+        symbol: row.get(4),
+        side: row.get(5),
+        size: row.get(6),
+        price: row.get(7),
+        tx_hash: row.get(8),
+        oid: row.get(9),
+        status: row.get(10),
+        pnl: row.get(11),
+        pnl_usd: row.get(12),
+        is_test: row.get(13),
+        created_at: row.get(14),
+    }
+}
+
+/// Map a row stream while keeping the pooled client checked out until it is drained
+fn row_stream<T>(
+    client: Object,
+    rows: RowStream,
+    map: fn(Row) -> T,
+) -> impl Stream<Item = Result<T>> + Send + 'static
+where
+    T: Send + 'static,
+{
+    futures::stream::unfold(
+        (client, Box::pin(rows)),
+        move |(client, mut rows)| async move {
+            let next = rows.next().await?;
+            let item = next.map(map).context("Failed to read row");
+            Some((item, (client, rows)))
+        },
+    )
+}
+
+fn pool_config(dsn: &str, max_connections: usize) -> PgConfig {
+    let mut cfg = PgConfig::new();
+    cfg.url = Some(dsn.to_string());
+    cfg.pool = Some(deadpool_postgres::PoolConfig {
+        max_size: max_connections,
+        ..Default::default()
+    });
+    // Run a test query when recycling so connections killed server-side (e.g. after a
+    // failover) are replaced instead of failing the next caller's query
+    cfg.manager = Some(ManagerConfig {
+        recycling_method: RecyclingMethod::Verified,
+    });
+    cfg
+}
+
+/// Run `connect` until it succeeds or `retries` retries have failed, doubling the delay
+/// between attempts up to [`MAX_CONNECT_BACKOFF`]
+async fn retry_with_backoff<T, F, Fut>(retries: u32, delay: Duration, mut connect: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let mut delay = delay;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match connect().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt <= retries => {
+                warn!(
+                    "Postgres not ready (attempt {}/{}), retrying in {:?}: {:#}",
+                    attempt,
+                    retries + 1,
+                    delay,
+                    e
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_CONNECT_BACKOFF);
+            }
+            Err(e) => return Err(e),
+        }
     }
 }
 
@@ -600,24 +1852,46 @@ fn row_to_bot_record(row: Row) -> BotRecord {
         bot_pubkey: row.get(0),
         nostr_pubkey: row.get(1),
         eth_address: row.get(2),
+        paused: row.get(3),
     }
 }
 
+/// Random bot API key, returned to the bot once and never stored in clear
+fn generate_api_key() -> String {
+    let mut bytes = [0u8; 32];
+    rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Whether `key` hashes to the stored hex `hash`, compared in constant time
+fn api_key_matches(hash: &str, key: &str) -> bool {
+    let Ok(stored) = hex::decode(hash) else {
+        return false;
+    };
+    let digest = Sha256::digest(key.as_bytes());
+    stored.len() == digest.len()
+        && stored
+            .iter()
+            .zip(digest.iter())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
 /// Encrypt a payload using a shared secret derived key (ChaCha20-Poly1305)
 fn encrypt_with_secret(content: &str, shared_secret: &str) -> Result<String> {
-    let mut hasher = Sha256::new();
-    hasher.update(shared_secret.as_bytes());
-    let key_bytes = hasher.finalize();
-    let key = Key::from_slice(&key_bytes[..32]);
-    let cipher = ChaCha20Poly1305::new(key);
+    let key_bytes = Sha256::digest(shared_secret.as_bytes());
+    let cipher = ChaCha20Poly1305::new_from_slice(&key_bytes)
+        .map_err(|_| anyhow!("Failed to build cipher"))?;
 
     let mut nonce_bytes = [0u8; 12];
-    let mut rng = rng();
-    rng.fill_bytes(&mut nonce_bytes);
-    let nonce = Nonce::from_slice(&nonce_bytes);
+    rng().fill_bytes(&mut nonce_bytes);
 
     let ciphertext = cipher
-        .encrypt(nonce, content.as_bytes())
+        .encrypt(&Nonce::from(nonce_bytes), content.as_bytes())
         .map_err(|_| anyhow!("Failed to encrypt content"))?;
 
     let mut combined = Vec::with_capacity(nonce_bytes.len() + ciphertext.len());
@@ -626,3 +1900,483 @@ fn encrypt_with_secret(content: &str, shared_secret: &str) -> Result<String> {
 
     Ok(BASE64.encode(combined))
 }
+
+/// Server for the Postgres-backed tests: a key=value DSN (e.g. `host=localhost user=postgres`)
+/// whose user may create databases. Those tests skip when it is unset
+#[cfg(test)]
+const TEST_PG_ENV: &str = "MOLTRADE_TEST_PG";
+
+#[cfg(test)]
+impl SubscriptionService {
+    /// A service on a freshly created `moltrade_test_<name>` database, or None when
+    /// [`TEST_PG_ENV`] is unset. `name` must be unique per test since tests run in parallel
+    pub(crate) async fn for_test(name: &str) -> Option<Self> {
+        let base = std::env::var(TEST_PG_ENV).ok()?;
+        let (admin, connection) = tokio_postgres::connect(&base, NoTls)
+            .await
+            .expect("connect to test Postgres");
+        tokio::spawn(connection);
+        let db = format!("moltrade_test_{name}");
+        admin
+            .batch_execute(&format!("DROP DATABASE IF EXISTS {db} WITH (FORCE)"))
+            .await
+            .expect("drop old test database");
+        admin
+            .batch_execute(&format!("CREATE DATABASE {db}"))
+            .await
+            .expect("create test database");
+        Some(
+            Self::new(&format!("{base} dbname={db}"), 4)
+                .await
+                .expect("open test database"),
+        )
+    }
+
+    /// Register `bot_pubkey` with a placeholder eth address derived from it
+    pub(crate) async fn register_test_bot(&self, bot_pubkey: &str) {
+        let eth_address = format!("0x{:0>40}", &bot_pubkey[..bot_pubkey.len().min(40)]);
+        self.register_bot(bot_pubkey, bot_pubkey, &eth_address, "test bot", Utc::now())
+            .await
+            .expect("register test bot");
+    }
+
+    /// Run raw SQL against the test database, e.g. to backdate seeded rows
+    pub(crate) async fn execute_test_sql(&self, sql: &str) {
+        let client = self.pool.get().await.expect("test PG client");
+        client.batch_execute(sql).await.expect("run test SQL");
+    }
+
+    /// The single BIGINT a test query selects, e.g. a `COUNT(*)`
+    pub(crate) async fn query_test_count(&self, sql: &str) -> i64 {
+        let client = self.pool.get().await.expect("test PG client");
+        client
+            .query_one(sql, &[])
+            .await
+            .expect("run test query")
+            .get(0)
+    }
+}
+
+#[cfg(test)]
+impl SignalInsert {
+    /// A bare leader signal for `event_id`, received now
+    pub(crate) fn for_test(event_id: &str) -> Self {
+        Self {
+            event_id: event_id.to_string(),
+            kind: 30931,
+            bot_pubkey: None,
+            leader_pubkey: "leader".to_string(),
+            follower_pubkey: None,
+            agent_eth_address: None,
+            role: None,
+            symbol: None,
+            side: None,
+            size: None,
+            price: None,
+            status: None,
+            tx_hash: None,
+            pnl: None,
+            pnl_usd: None,
+            raw_content: "{}".to_string(),
+            event_created_at: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trade_status_round_trips_through_its_text_form() {
+        for status in [
+            TradeStatus::Pending,
+            TradeStatus::Confirmed,
+            TradeStatus::Failed,
+        ] {
+            assert_eq!(TradeStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(
+            TradeStatus::parse(" Confirmed "),
+            Some(TradeStatus::Confirmed)
+        );
+        for invalid in ["", "settled", "pending!", "fail"] {
+            assert_eq!(TradeStatus::parse(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn pooled_connections_are_verified_on_recycle() {
+        let cfg = pool_config("postgres://localhost/moltrade", 7);
+        assert_eq!(
+            cfg.manager.map(|m| m.recycling_method),
+            Some(RecyclingMethod::Verified)
+        );
+        assert_eq!(cfg.pool.map(|p| p.max_size), Some(7));
+    }
+
+    #[tokio::test]
+    async fn connect_retries_until_success() {
+        let mut attempts = 0;
+        let result = retry_with_backoff(3, Duration::from_millis(1), || {
+            attempts += 1;
+            let attempt = attempts;
+            async move {
+                if attempt < 3 {
+                    Err(anyhow!("connection refused"))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn connect_gives_up_after_retries() {
+        let mut attempts = 0;
+        let result: Result<()> = retry_with_backoff(2, Duration::from_millis(1), || {
+            attempts += 1;
+            async { Err(anyhow!("connection refused")) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+
+        attempts = 0;
+        let result: Result<()> = retry_with_backoff(0, Duration::from_secs(60), || {
+            attempts += 1;
+            async { Err(anyhow!("connection refused")) }
+        })
+        .await;
+        // Zero retries fails on the first error without sleeping
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    fn split(encoded: &str) -> (Vec<u8>, Vec<u8>) {
+        let raw = BASE64.decode(encoded).unwrap();
+        (raw[..12].to_vec(), raw[12..].to_vec())
+    }
+
+    #[test]
+    fn random_nonce_differs_between_calls() {
+        let a = encrypt_with_secret("hello", "s").unwrap();
+        let b = encrypt_with_secret("hello", "s").unwrap();
+        assert_ne!(split(&a).0, split(&b).0);
+    }
+
+    #[test]
+    fn payload_decrypts_with_secret_key() {
+        let secret = "follower-secret";
+        let (nonce, ciphertext) = split(&encrypt_with_secret("hello", secret).unwrap());
+        let nonce: [u8; 12] = nonce.try_into().unwrap();
+        let cipher = ChaCha20Poly1305::new_from_slice(&Sha256::digest(secret.as_bytes())).unwrap();
+        let plain = cipher
+            .decrypt(&Nonce::from(nonce), ciphertext.as_slice())
+            .unwrap();
+        assert_eq!(plain, b"hello");
+    }
+
+    #[tokio::test]
+    async fn leader_and_follower_rows_share_a_tx_hash() {
+        let Some(svc) = SubscriptionService::for_test("shared_tx_hash").await else {
+            return;
+        };
+        svc.register_test_bot("bot1").await;
+        let follower = "ab".repeat(32);
+        for (role, follower_pubkey) in [("leader", None), ("follower", Some(follower.as_str()))] {
+            svc.record_trade_tx(
+                "bot1",
+                follower_pubkey,
+                role,
+                "BTC",
+                "buy",
+                1.0,
+                100.0,
+                Some("0xshared"),
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+        }
+
+        svc.update_trade_settlement(
+            TradeRowKey {
+                tx_hash: Some("0xshared"),
+                oid: None,
+                role: "follower",
+                follower_pubkey: Some(&follower),
+            },
+            Some(TradeStatus::Failed),
+            Some(-3.0),
+            Some(-3.0),
+        )
+        .await
+        .unwrap();
+
+        let trades = svc.list_trades(Some("bot1"), None, None, 10).await.unwrap();
+        assert_eq!(trades.len(), 2);
+        let leader = trades.iter().find(|t| t.role == "leader").unwrap();
+        let follower_row = trades.iter().find(|t| t.role == "follower").unwrap();
+        assert_eq!(
+            follower_row.follower_pubkey.as_deref(),
+            Some(follower.as_str())
+        );
+        assert_eq!(follower_row.status, "failed");
+        assert_eq!(follower_row.pnl_usd, Some(-3.0));
+        assert_eq!(leader.status, "pending");
+        assert_eq!(leader.pnl, None);
+        assert_eq!(leader.pnl_usd, None);
+    }
+
+    #[test]
+    fn api_key_matches_only_its_hash() {
+        let hash = hash_api_key("secret-key");
+        assert!(api_key_matches(&hash, "secret-key"));
+        assert!(!api_key_matches(&hash, "secret-kez"));
+        assert!(!api_key_matches(&hash, ""));
+        assert!(!api_key_matches("not hex", "secret-key"));
+        assert!(!api_key_matches(&hash[..10], "secret-key"));
+    }
+
+    #[tokio::test]
+    async fn re_registering_a_bot_needs_its_api_key() {
+        let Some(svc) = SubscriptionService::for_test("bot_api_key").await else {
+            return;
+        };
+        let first = svc
+            .register_bot_with_key("bot1", "nostr1", "0xaaa", "bot", None)
+            .await
+            .unwrap();
+        let KeyedRegistration::Registered { api_key: Some(key) } = first else {
+            panic!("first registration should issue a key, got {:?}", first);
+        };
+        assert!(svc.verify_bot_api_key("bot1", &key).await.unwrap());
+
+        // Missing and wrong keys leave the bot untouched
+        for presented in [None, Some("wrong")] {
+            let outcome = svc
+                .register_bot_with_key("bot1", "nostr1", "0xevil", "bot", presented)
+                .await
+                .unwrap();
+            assert_eq!(outcome, KeyedRegistration::KeyRejected);
+        }
+        assert_eq!(
+            svc.get_bot_eth_address("bot1").await.unwrap().as_deref(),
+            Some("0xaaa")
+        );
+        assert!(!svc.verify_bot_api_key("bot1", "wrong").await.unwrap());
+
+        let outcome = svc
+            .register_bot_with_key("bot1", "nostr1", "0xbbb", "bot", Some(&key))
+            .await
+            .unwrap();
+        assert_eq!(outcome, KeyedRegistration::Registered { api_key: None });
+        assert_eq!(
+            svc.get_bot_eth_address("bot1").await.unwrap().as_deref(),
+            Some("0xbbb")
+        );
+        assert!(svc.verify_bot_api_key("bot1", &key).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn follower_relay_urls_track_subscriptions() {
+        let Some(svc) = SubscriptionService::for_test("follower_relays").await else {
+            return;
+        };
+        svc.register_test_bot("bot1").await;
+        let relays = vec![
+            "wss://a.example.com".to_string(),
+            "wss://b.example.com".to_string(),
+        ];
+        for follower in ["f1", "f2"] {
+            svc.add_subscription("bot1", follower, "secret", None, None, Some(&relays))
+                .await
+                .unwrap();
+        }
+        assert_eq!(svc.follower_relay_urls().await.unwrap().len(), 2);
+
+        // A relay stays listed while any subscription still names it
+        let only_a = &relays[..1];
+        svc.add_subscription("bot1", "f1", "secret", None, None, Some(only_a))
+            .await
+            .unwrap();
+        assert_eq!(svc.follower_relay_urls().await.unwrap().len(), 2);
+        svc.add_subscription("bot1", "f2", "secret", None, None, Some(&[]))
+            .await
+            .unwrap();
+        assert_eq!(
+            svc.follower_relay_urls().await.unwrap(),
+            HashSet::from(["wss://a.example.com".to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn follower_cap_refuses_only_new_followers() {
+        let Some(svc) = SubscriptionService::for_test("follower_cap").await else {
+            return;
+        };
+        let svc = svc.with_max_followers_per_bot(2);
+        svc.register_test_bot("bot1").await;
+        for follower in ["f1", "f2"] {
+            assert!(
+                svc.add_subscription("bot1", follower, "secret", None, None, None)
+                    .await
+                    .unwrap()
+            );
+        }
+
+        assert!(
+            !svc.add_subscription("bot1", "f3", "secret", None, None, None)
+                .await
+                .unwrap()
+        );
+        // An existing follower can still re-save while the bot is at the cap
+        assert!(
+            svc.add_subscription("bot1", "f2", "rotated", None, None, None)
+                .await
+                .unwrap()
+        );
+        assert_eq!(svc.list_subscriptions("bot1").await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn rotated_secret_overlaps_with_the_previous_one() {
+        let Some(svc) = SubscriptionService::for_test("secret_rotation").await else {
+            return;
+        };
+        svc.register_test_bot("bot1").await;
+        svc.add_subscription("bot1", "f1", "old", None, None, None)
+            .await
+            .unwrap();
+        let secrets = || async {
+            let subs = svc.list_subscriptions("bot1").await.unwrap();
+            subs[0].secrets().map(str::to_string).collect::<Vec<_>>()
+        };
+
+        assert!(
+            svc.rotate_subscription_secret("bot1", "f1", "new", 3600)
+                .await
+                .unwrap()
+        );
+        assert_eq!(secrets().await, ["new", "old"]);
+        // Re-sending the current secret does not push the old one out early
+        svc.rotate_subscription_secret("bot1", "f1", "new", 3600)
+            .await
+            .unwrap();
+        assert_eq!(secrets().await, ["new", "old"]);
+
+        svc.execute_test_sql(
+            "UPDATE subscriptions SET previous_secret_expires_at = now() - interval '1 second'",
+        )
+        .await;
+        assert_eq!(secrets().await, ["new"]);
+        assert!(
+            !svc.rotate_subscription_secret("bot1", "f2", "new", 3600)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn subscription_changes_invalidate_the_follower_cache() {
+        let Some(svc) = SubscriptionService::for_test("follower_cache").await else {
+            return;
+        };
+        let svc = svc.with_follower_cache_ttl(Duration::from_secs(3600));
+        svc.register_test_bot("bot1").await;
+        let followers = || async {
+            let subs = svc.cached_subscriptions("bot1").await.unwrap();
+            subs.iter()
+                .map(|s| s.follower_pubkey.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(followers().await.is_empty());
+        svc.add_subscription("bot1", "f1", "secret", None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(followers().await, ["f1"]);
+
+        // Writes that bypass the service are only seen once the entry expires
+        svc.execute_test_sql("DELETE FROM subscriptions").await;
+        assert_eq!(followers().await, ["f1"]);
+        svc.invalidate_followers("bot1");
+        assert!(followers().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn deregistering_a_bot_removes_its_rows_once() {
+        let Some(svc) = SubscriptionService::for_test("deregister_bot").await else {
+            return;
+        };
+        for bot in ["bot1", "bot2"] {
+            svc.register_test_bot(bot).await;
+            svc.add_subscription(bot, "f1", "secret", None, None, None)
+                .await
+                .unwrap();
+        }
+        svc.record_trade_tx(
+            "bot1",
+            None,
+            "leader",
+            "ETH",
+            "buy",
+            1.0,
+            1.0,
+            None,
+            Some("o1"),
+            false,
+        )
+        .await
+        .unwrap();
+        svc.award_credits("bot1", "f1", Decimal::ONE, "test", None)
+            .await
+            .unwrap();
+        let mut signal = SignalInsert::for_test("signal1");
+        signal.bot_pubkey = Some("bot1".to_string());
+        svc.record_signal(signal).await.unwrap();
+
+        let removed = svc.deregister_bot("bot1").await.unwrap().unwrap();
+        assert_eq!(
+            (removed.subscriptions, removed.trades, removed.credits),
+            (1, 1, 1)
+        );
+        for table in [
+            "subscriptions",
+            "trade_executions",
+            "credits",
+            "credit_ledger",
+        ] {
+            let sql = format!("SELECT COUNT(*) FROM {} WHERE bot_pubkey = 'bot1'", table);
+            assert_eq!(svc.query_test_count(&sql).await, 0, "{}", table);
+        }
+        // Signals stay for the audit trail, detached from the bot
+        let sql = "SELECT COUNT(*) FROM signals WHERE event_id = 'signal1' AND bot_pubkey IS NULL";
+        assert_eq!(svc.query_test_count(sql).await, 1);
+        assert_eq!(svc.count_subscriptions("bot2").await.unwrap(), 1);
+
+        assert!(svc.deregister_bot("bot1").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn small_credit_awards_sum_exactly() {
+        let Some(svc) = SubscriptionService::for_test("credit_sum").await else {
+            return;
+        };
+        svc.register_test_bot("bot1").await;
+        // 0.1 has no exact f64 form, so ten f64 additions would land just short of 1
+        let tenth = Decimal::new(1, 1);
+        for _ in 0..10 {
+            svc.award_credits("bot1", "f1", tenth, "test", None)
+                .await
+                .unwrap();
+        }
+        let balances = svc.list_credits(Some("bot1"), Some("f1")).await.unwrap();
+        assert_eq!(balances.len(), 1);
+        assert_eq!(balances[0].credits, Decimal::ONE);
+    }
+}
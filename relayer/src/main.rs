@@ -1,36 +1,71 @@
 mod api;
 mod config;
 mod core;
+mod selftest;
+mod shutdown;
 mod storage;
 
-use anyhow::{Context, Result};
-use api::{metrics::Metrics, rest_api, websocket};
-use clap::Parser;
+use anyhow::{Context, Result, bail};
+use api::{
+    admin_auth::IpAllowlist,
+    error_log::ErrorLog,
+    event_tail::EventTail,
+    metrics::Metrics,
+    rate_limit::TokenBucket,
+    rest_api::{self, SupportedKinds},
+    timeseries::EventTimeseries,
+    websocket,
+};
+use axum::Extension;
+use clap::{Parser, Subcommand};
 use config::AppConfig;
 use core::{
-    dedupe_engine::DeduplicationEngine, event_router::EventRouter, relay_pool::RelayPool,
-    settlement_worker::SettlementWorker, subscription::FanoutMessage,
+    dedupe_engine::DeduplicationEngine,
+    event_router::EventRouter,
+    ingestion::IngestionGate,
+    leader_election::LeaderElection,
+    payloads::FieldMap,
+    presence_monitor::PresenceMonitor,
+    relay_pool::RelayPool,
+    relay_transport::RelayTransport,
+    settlement_worker::{SettlementHandle, SettlementWorker},
+    signal_batcher::SignalBatcher,
+    subscription::FanoutMessage,
     subscription::SubscriptionService,
 };
 use flume::Receiver;
 use nostr_sdk::Event;
 use nostr_sdk::ToBech32;
 use nostr_sdk::prelude::{Client, Keys};
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
-use storage::rocksdb_store::RocksDBStore;
-use tokio::signal;
+use storage::{replay_buffer::ReplayBuffer, rocksdb_store::RocksDBStore};
 use tracing::{error, info, warn};
-use tracing_subscriber;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 
 #[derive(Parser, Debug)]
 #[command(name = "moltrade-relayer")]
 #[command(about = "Moltrade Relayer service", version)]
 struct Cli {
     /// Path to configuration TOML file
-    #[arg(long)]
+    #[arg(long, global = true)]
     config: Option<std::path::PathBuf>,
+
+    /// Without a config file or RELAY_URLS, connect to public example relays instead of refusing to start
+    #[arg(long, global = true)]
+    allow_default_relays: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check Postgres, RocksDB and relay connectivity (publish + read back), then exit
+    Selftest,
 }
 
 #[tokio::main]
@@ -42,10 +77,17 @@ async fn main() -> Result<()> {
     let (cfg, cfg_path) = load_config(&cli)?;
 
     // Initialize tracing - prefer config log level if provided, else env, else default
-    init_tracing(&cfg);
+    let error_log = init_tracing(&cfg);
+
+    if let Some(Command::Selftest) = cli.command {
+        return selftest::run(&cfg, cli.allow_default_relays).await;
+    }
 
     info!("Starting Moltrade Relayer...");
 
+    // Resolve relays before opening anything, so a missing relay list fails fast
+    let relay_urls = bootstrap_relays(&cfg, cli.allow_default_relays)?;
+
     // Initialize metrics
     let metrics = Arc::new(Metrics::new().context("Failed to initialize metrics")?);
 
@@ -54,7 +96,7 @@ async fn main() -> Result<()> {
     info!("RocksDB storage initialized");
 
     // Initialize deduplication engine
-    let dedupe_engine = init_dedupe_engine(&cfg, rocksdb.clone(), metrics.clone());
+    let dedupe_engine = init_dedupe_engine(&cfg, rocksdb.clone());
     info!("Deduplication engine initialized");
 
     // Warm dedup engine from RocksDB successful-forward index to avoid duplicate downstream sends after restart
@@ -62,32 +104,58 @@ async fn main() -> Result<()> {
         .as_ref()
         .map(|c| c.deduplication.hotset_size)
         .unwrap_or(10_000);
-    dedupe_engine.warm_from_db(warm_limit).await;
+    let warm_deadline = cfg
+        .as_ref()
+        .map(|c| c.deduplication.warm_deadline_secs)
+        .unwrap_or(30);
+    dedupe_engine
+        .warm_from_db(
+            warm_limit,
+            Duration::from_secs(warm_deadline),
+            Some(metrics.dedupe_warm_remaining.clone()),
+        )
+        .await;
 
     // Initialize relay pool
     let (health_check_interval, max_connections) = relay_settings(&cfg);
-    let allowed_kinds = resolve_allowed_kinds(&cfg);
     let nostr_keys = load_nostr_keys(&cfg, cfg_path.as_deref())?;
+    let retired_keys = load_retired_keys(&cfg)?;
     let platform_pubkey = nostr_keys.as_ref().map(|k| {
         k.public_key()
             .to_bech32()
             .unwrap_or_else(|_| k.public_key().to_hex())
     });
-    let nostr_client = init_nostr_publisher(&cfg, nostr_keys.as_ref()).await?;
+    let transport = relay_transport(&cfg)?;
+    let nostr_client =
+        init_nostr_publisher(&cfg, nostr_keys.as_ref(), &transport, &relay_urls).await?;
     let (relay_pool, relay_event_rx) = RelayPool::new(
         health_check_interval,
         max_connections,
-        allowed_kinds.clone(),
+        resolve_allowed_kinds(&cfg),
+    );
+    let resubscribe_idle = Duration::from_secs(
+        cfg.as_ref()
+            .map(|c| c.relay.resubscribe_idle_secs)
+            .unwrap_or(600),
+    );
+    let relay_pool = Arc::new(
+        relay_pool
+            .with_metrics(metrics.clone())
+            .with_idle_resubscribe(resubscribe_idle)
+            .with_backfill_coalescing(Duration::from_millis(
+                cfg.as_ref()
+                    .map(|c| c.relay.backfill_coalesce_ms)
+                    .unwrap_or(500),
+            ))
+            .with_transport(transport),
     );
-    let relay_pool = Arc::new(relay_pool.with_metrics(metrics.clone()));
     info!("Relay pool initialized");
 
     // Start health checks
     relay_pool.start_health_checks().await;
     info!("Health checks started");
 
-    // Connect to relays (example - load from config file or environment)
-    let relay_urls = bootstrap_relays(&cfg).await?;
+    // Connect to relays
     info!("Loading {} relay URLs", relay_urls.len());
 
     relay_pool
@@ -102,7 +170,44 @@ async fn main() -> Result<()> {
     // Optional Postgres-backed subscription service for fanout
     let subscription_service = init_subscription_service(&cfg).await?;
 
+    // Seed dedupe from persisted signals so a wiped RocksDB does not cause reprocessing
+    if let Some(subs) = subscription_service.as_ref() {
+        let window_secs = cfg
+            .as_ref()
+            .map(|c| c.deduplication.warm_from_signals_secs)
+            .unwrap_or(0);
+        if window_secs > 0 {
+            match subs
+                .recent_signal_event_ids(window_secs as i64, warm_limit as i64)
+                .await
+            {
+                Ok(ids) => dedupe_engine.warm_from_ids(&ids, "Postgres signals").await,
+                Err(e) => warn!("Failed to warm dedupe from signals: {}", e),
+            }
+        }
+    }
+
+    // Optional leader election so multiple instances do not double-settle or double-notify
+    let leadership = match (
+        subscription_service.clone(),
+        cfg.as_ref().and_then(|c| c.coordination.clone()),
+    ) {
+        (Some(subs), Some(coord)) if coord.enable => {
+            let election =
+                LeaderElection::new(subs, coord.lock_key, Duration::from_secs(coord.check_secs));
+            let leadership = election.leadership();
+            tokio::spawn(async move { election.run().await });
+            info!(
+                "Leader election enabled (lock_key={}, check={}s)",
+                coord.lock_key, coord.check_secs
+            );
+            Some(leadership)
+        }
+        _ => None,
+    };
+
     // Start settlement worker (Hyperliquid tx hash polling)
+    let mut settlement_handle: Option<SettlementHandle> = None;
     if let Some(subs) = subscription_service.clone() {
         let settlement_cfg = cfg.as_ref().and_then(|c| c.settlement.clone());
         let base_url = settlement_cfg
@@ -118,7 +223,69 @@ async fn main() -> Result<()> {
             Duration::from_secs(interval_secs),
             batch_limit,
             credit_cfg,
+        )
+        .context("Failed to build settlement HTTP client")?
+        .with_metrics(metrics.clone())
+        .with_http_timeouts(
+            Duration::from_secs(
+                settlement_cfg
+                    .as_ref()
+                    .map(|s| s.connect_timeout_secs)
+                    .unwrap_or(5),
+            ),
+            Duration::from_secs(
+                settlement_cfg
+                    .as_ref()
+                    .map(|s| s.request_timeout_secs)
+                    .unwrap_or(15),
+            ),
+            Duration::from_secs(
+                settlement_cfg
+                    .as_ref()
+                    .map(|s| s.pool_idle_timeout_secs)
+                    .unwrap_or(90),
+            ),
+        )
+        .context("Failed to build settlement HTTP client")?
+        .with_unavailable_grace(Duration::from_secs(
+            settlement_cfg
+                .as_ref()
+                .map(|s| s.unavailable_grace_secs)
+                .unwrap_or(3600),
+        ))
+        .with_tx_hash_format(
+            settlement_cfg
+                .as_ref()
+                .map(|s| s.tx_hash_prefix.clone())
+                .unwrap_or_else(|| "0x".to_string()),
+            settlement_cfg
+                .as_ref()
+                .map(|s| s.tx_hash_hex_len)
+                .unwrap_or(64),
+        )
+        .with_min_confirmations(
+            settlement_cfg
+                .as_ref()
+                .map(|s| s.min_confirmations)
+                .unwrap_or(0),
+        )
+        .with_pnl_backfill(
+            settlement_cfg
+                .as_ref()
+                .map(|s| s.pnl_lookup_base.clone())
+                .unwrap_or_default(),
+            Duration::from_secs(
+                settlement_cfg
+                    .as_ref()
+                    .map(|s| s.pnl_backfill_window_secs)
+                    .unwrap_or(86_400),
+            ),
         );
+        let worker = match leadership.clone() {
+            Some(l) => worker.with_leadership(l),
+            None => worker,
+        };
+        settlement_handle = Some(worker.handle());
         tokio::spawn(async move { worker.run().await });
         info!(
             "Settlement worker started (interval={}s, batch={}, credit_cfg={})",
@@ -132,6 +299,31 @@ async fn main() -> Result<()> {
         );
     }
 
+    // Track bot online/offline from heartbeat staleness
+    if let Some(subs) = subscription_service.clone() {
+        let presence_cfg = cfg
+            .as_ref()
+            .and_then(|c| c.presence.clone())
+            .unwrap_or_default();
+        let monitor = PresenceMonitor::new(
+            subs,
+            Duration::from_secs(presence_cfg.scan_secs),
+            Duration::from_secs(presence_cfg.offline_after_secs),
+            presence_cfg.webhook_url.clone(),
+        );
+        let monitor = match leadership.clone() {
+            Some(l) => monitor.with_leadership(l),
+            None => monitor,
+        };
+        tokio::spawn(async move { monitor.run().await });
+        info!(
+            "Presence monitor started (scan={}s, offline_after={}s, webhook={})",
+            presence_cfg.scan_secs,
+            presence_cfg.offline_after_secs,
+            presence_cfg.webhook_url.is_some()
+        );
+    }
+
     if let (Some(subs), Some(pk)) = (subscription_service.as_ref(), platform_pubkey.as_ref()) {
         if let Err(e) = subs
             .ensure_platform_pubkey(pk, nostr_client.clone(), nostr_keys.as_ref())
@@ -141,27 +333,164 @@ async fn main() -> Result<()> {
         }
     }
 
+    // Multi-row signal inserts (only if configured)
+    let pg_cfg = cfg.as_ref().and_then(|c| c.postgres.as_ref());
+    let (signal_batch_tx, signal_batcher_handle) = match (
+        subscription_service.clone(),
+        pg_cfg.filter(|p| p.signal_batch_size > 0),
+    ) {
+        (Some(subs), Some(pg)) => {
+            let (tx, rx) = flume::bounded(pg.signal_batch_size * 4);
+            let batcher = SignalBatcher::new(
+                subs,
+                rx,
+                pg.signal_batch_size,
+                Duration::from_millis(pg.signal_batch_ms),
+            )
+            .with_metrics(metrics.clone());
+            info!(
+                "Signal batching enabled (rows={}, max_delay={}ms)",
+                pg.signal_batch_size, pg.signal_batch_ms
+            );
+            (Some(tx), Some(tokio::spawn(batcher.run())))
+        }
+        _ => (None, None),
+    };
+
     // Fanout channel (only if subscription service is enabled)
     let (fanout_tx, fanout_rx) = if subscription_service.is_some() {
-        let (tx, rx) = flume::unbounded();
+        let capacity = cfg
+            .as_ref()
+            .map(|c| c.output.fanout_capacity)
+            .unwrap_or(10_000);
+        let (tx, rx) = flume::bounded(capacity);
         (Some(tx), Some(rx))
     } else {
         (None, None)
     };
 
+    // Recent events replayed to WebSocket clients on request
+    let replay_buffer = ReplayBuffer::with_capacity(
+        cfg.as_ref()
+            .map(|c| {
+                if c.output.websocket_enabled {
+                    c.output.replay_buffer_size
+                } else {
+                    0
+                }
+            })
+            .unwrap_or(1000),
+    );
+
+    let event_tail = EventTail::new(
+        cfg.as_ref()
+            .map(|c| c.monitoring.tail_max_per_sec)
+            .unwrap_or(20),
+    );
+    let timeseries = EventTimeseries::new(
+        cfg.as_ref()
+            .map(|c| c.monitoring.timeseries_minutes)
+            .unwrap_or(60),
+    );
+
+    let ingestion_gate = IngestionGate::new(
+        cfg.as_ref()
+            .map(|c| c.output.pause_mode)
+            .unwrap_or_default(),
+    )
+    .with_buffer_capacity(
+        cfg.as_ref()
+            .map(|c| c.output.pause_buffer_capacity)
+            .unwrap_or(10_000),
+    );
+
     // Initialize event router
+    let filters = cfg.as_ref().map(|c| c.filters.clone()).unwrap_or_default();
     let event_router = EventRouter::new(
         dedupe_engine.clone(),
         cfg.as_ref().map(|c| c.output.batch_size).unwrap_or(100), // batch size
         Duration::from_millis(cfg.as_ref().map(|c| c.output.max_latency_ms).unwrap_or(100) as u64), // max latency
         downstream_tx.clone(),
-        allowed_kinds,
-        fanout_tx,
+        relay_pool.allowed_kinds(),
+        fanout_tx.clone(),
         subscription_service.clone(),
         nostr_keys,
         nostr_client.clone(),
     )
-    .with_metrics(metrics.clone());
+    .with_metrics(metrics.clone())
+    .with_agent_signature_required(filters.require_agent_signature)
+    .with_agent_register_max_age(Duration::from_secs(filters.agent_register_max_age_secs))
+    .with_max_content_bytes(filters.max_content_bytes)
+    .with_pubkey_rate_limit(filters.pubkey_burst, filters.pubkey_per_sec)
+    .with_required_tags(filters.required_tags.clone())
+    .with_strict_monotonic(filters.strict_monotonic)
+    .with_future_timestamp_policy(
+        Duration::from_secs(filters.future_skew_secs),
+        filters.future_timestamp_policy,
+    )
+    .with_receive_time_staleness(filters.stale_from_receive_time)
+    .with_tag_payloads(filters.tag_payloads)
+    .with_field_map(FieldMap::new(&filters.field_map)?)
+    .with_retired_keys(retired_keys)
+    .with_max_pending_bytes(
+        cfg.as_ref()
+            .map(|c| c.output.max_pending_bytes)
+            .unwrap_or(0),
+    )
+    .with_log_payloads(cfg.as_ref().is_some_and(|c| c.monitoring.log_payloads))
+    .with_publish_concurrency(
+        cfg.as_ref()
+            .and_then(|c| c.nostr.as_ref())
+            .map(|n| n.publish_concurrency)
+            .unwrap_or(16),
+    )
+    .with_max_follower_relays(
+        cfg.as_ref()
+            .and_then(|c| c.nostr.as_ref())
+            .map(|n| n.max_follower_relays)
+            .unwrap_or(100),
+    )
+    .with_delivery_suspension(
+        cfg.as_ref()
+            .and_then(|c| c.nostr.as_ref())
+            .map(|n| n.suspend_after_failures)
+            .unwrap_or(10),
+        Duration::from_secs(
+            cfg.as_ref()
+                .and_then(|c| c.nostr.as_ref())
+                .map(|n| n.suspend_cooldown_secs)
+                .unwrap_or(3600),
+        ),
+    )
+    .with_replay_buffer(replay_buffer.clone())
+    .with_event_tail(event_tail.clone())
+    .with_timeseries(timeseries.clone())
+    .with_ingestion_gate(ingestion_gate.clone())
+    .with_kinds(cfg.as_ref().map(|c| c.kinds).unwrap_or_default())
+    .with_persist_all_events(
+        cfg.as_ref()
+            .map(|c| c.output.persist_all_events)
+            .unwrap_or(false),
+    )
+    .with_latency_mode(
+        cfg.as_ref()
+            .map(|c| c.output.latency_mode)
+            .unwrap_or_default(),
+    )
+    .with_dedupe_kinds(
+        cfg.as_ref()
+            .map(|c| c.deduplication.dedupe_kinds.clone())
+            .unwrap_or_default(),
+    )
+    .with_dedupe_window(Duration::from_secs(
+        cfg.as_ref()
+            .map(|c| c.deduplication.window_secs)
+            .unwrap_or(0),
+    ));
+    let event_router = match signal_batch_tx {
+        Some(tx) => event_router.with_signal_batch(tx),
+        None => event_router,
+    };
 
     // Spawn event router task
     let router_handle = tokio::spawn(async move {
@@ -170,23 +499,38 @@ async fn main() -> Result<()> {
         }
     });
 
+    let ws_connections =
+        websocket::ConnectionRegistry::new(Some(metrics.fanout_connections.clone()));
+
     // Create REST API router
-    let subscription_daily_limit = cfg
+    let subscriptions_cfg = cfg
         .as_ref()
-        .and_then(|c| c.subscriptions.as_ref())
-        .map(|s| s.daily_limit)
-        .unwrap_or(1000);
+        .and_then(|c| c.subscriptions.clone())
+        .unwrap_or_default();
+    let admin_allowlist = IpAllowlist::parse(
+        cfg.as_ref()
+            .map(|c| c.output.admin_allowed_ips.as_slice())
+            .unwrap_or_default(),
+    )
+    .context("Invalid output.admin_allowed_ips")?;
     let rest_router = rest_api::create_router(
         relay_pool.clone(),
         dedupe_engine.clone(),
         metrics.clone(),
         subscription_service.clone(),
         platform_pubkey.clone(),
-        cfg.as_ref()
-            .and_then(|c| c.settlement.as_ref())
-            .and_then(|s| s.token.clone()),
-        subscription_daily_limit,
-    );
+        cfg.as_ref().and_then(|c| c.settlement.clone()),
+        subscriptions_cfg,
+    )
+    .layer(Extension(admin_allowlist))
+    .layer(Extension(error_log))
+    .layer(Extension(event_tail))
+    .layer(Extension(timeseries))
+    .layer(Extension(ingestion_gate))
+    .layer(Extension(supported_kinds(&cfg)))
+    .layer(Extension(relay_mutation_limiter(&cfg)))
+    .layer(Extension(settlement_handle))
+    .layer(Extension(ws_connections.clone()));
 
     // Build HTTP server (WebSocket streaming optional)
     let websocket_enabled = cfg
@@ -194,7 +538,15 @@ async fn main() -> Result<()> {
         .map(|c| c.output.websocket_enabled)
         .unwrap_or(true);
 
-    let app = build_app(rest_router, downstream_rx, fanout_rx, websocket_enabled);
+    let app = build_app(
+        rest_router,
+        downstream_rx,
+        fanout_rx,
+        replay_buffer,
+        fanout_options(&cfg),
+        ws_connections,
+        websocket_enabled,
+    );
 
     // Start HTTP server
     let addr = match &cfg {
@@ -208,10 +560,14 @@ async fn main() -> Result<()> {
             .await
             .context("Failed to bind to address")
             .unwrap();
-        axum::serve(listener, app)
-            .await
-            .context("Failed to start server")
-            .unwrap();
+        // Peer addresses feed the admin IP allowlist
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .context("Failed to start server")
+        .unwrap();
     });
 
     info!("Moltrade Relayer started successfully");
@@ -222,37 +578,111 @@ async fn main() -> Result<()> {
     // Periodically update memory usage gauge
     spawn_memory_metrics(metrics.clone());
     // Wait for shutdown signal
-    signal::ctrl_c()
-        .await
-        .context("Failed to listen for shutdown signal")?;
+    shutdown::shutdown_signal()?.await?;
     info!("Shutdown signal received, gracefully shutting down...");
+    log_shutdown_summary(&metrics, &downstream_tx, fanout_tx.as_ref());
 
     // Cancel tasks
     router_handle.abort();
     server_handle.abort();
+    // Aborting the router drops the batch sender, so the batcher writes what it holds and exits
+    if let Some(handle) = signal_batcher_handle
+        && tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .is_err()
+    {
+        warn!("Timed out flushing batched signals");
+    }
 
     info!("Shutdown complete");
     Ok(())
 }
 
-/// Load relay URLs from environment or config file
-/// In production, this should load from a config file or database
-async fn load_relay_urls() -> Result<Vec<String>> {
-    // Example: load from environment variable
-    if let Ok(urls) = std::env::var("RELAY_URLS") {
-        return Ok(urls
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect());
+/// What is still buffered when the process stops
+#[derive(Debug, PartialEq)]
+struct ShutdownSummary {
+    pending_events: u64,
+    downstream_queue: usize,
+    fanout_queue: usize,
+    pending_trades: u64,
+}
+
+impl ShutdownSummary {
+    fn collect(
+        metrics: &Metrics,
+        downstream_tx: &flume::Sender<Event>,
+        fanout_tx: Option<&flume::Sender<FanoutMessage>>,
+    ) -> Self {
+        Self {
+            pending_events: metrics.events_in_queue.get() as u64,
+            downstream_queue: downstream_tx.len(),
+            fanout_queue: fanout_tx.map_or(0, |tx| tx.len()),
+            pending_trades: metrics.pending_trades.get() as u64,
+        }
     }
+}
+
+/// Log what is still buffered when the process stops; these events are dropped on exit,
+/// while pending trades stay in Postgres for the next run
+fn log_shutdown_summary(
+    metrics: &Metrics,
+    downstream_tx: &flume::Sender<Event>,
+    fanout_tx: Option<&flume::Sender<FanoutMessage>>,
+) {
+    let summary = ShutdownSummary::collect(metrics, downstream_tx, fanout_tx);
+    info!(
+        pending_events = summary.pending_events,
+        downstream_queue = summary.downstream_queue,
+        fanout_queue = summary.fanout_queue,
+        pending_trades = summary.pending_trades,
+        "Shutdown summary (buffered events and queued messages are dropped)"
+    );
+}
+
+/// Public relays used without a config file or `RELAY_URLS`, only with `--allow-default-relays`
+const DEFAULT_RELAYS: [&str; 3] = [
+    "wss://relay.damus.io",
+    "wss://nos.lol",
+    "wss://relay.snort.social",
+];
 
-    // Default example relays (replace with actual relay URLs)
-    Ok(vec![
-        "wss://relay.damus.io".to_string(),
-        "wss://nos.lol".to_string(),
-        "wss://relay.snort.social".to_string(),
-    ])
+/// Relays from `RELAY_URLS` (comma-separated), else the public defaults when allowed
+fn load_relay_urls(allow_default_relays: bool) -> Result<Vec<String>> {
+    relay_urls_from(
+        &std::env::var("RELAY_URLS").unwrap_or_default(),
+        allow_default_relays,
+    )
+}
+
+fn relay_urls_from(relay_urls: &str, allow_default_relays: bool) -> Result<Vec<String>> {
+    relays_or_defaults(relay_urls.split(','), allow_default_relays)
+}
+
+/// The non-blank relays given, else the public defaults when allowed
+fn relays_or_defaults<'a>(
+    relays: impl IntoIterator<Item = &'a str>,
+    allow_default_relays: bool,
+) -> Result<Vec<String>> {
+    let urls: Vec<String> = relays
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if !urls.is_empty() {
+        return Ok(urls);
+    }
+
+    if !allow_default_relays {
+        bail!(
+            "No relays configured: pass --config with relay.bootstrap_relays, set RELAY_URLS, or use --allow-default-relays to connect to public example relays ({})",
+            DEFAULT_RELAYS.join(", ")
+        );
+    }
+    warn!(
+        "No relays configured, connecting to public example relays because of --allow-default-relays: {}",
+        DEFAULT_RELAYS.join(", ")
+    );
+    Ok(DEFAULT_RELAYS.iter().map(|url| url.to_string()).collect())
 }
 
 fn load_config(cli: &Cli) -> Result<(Option<AppConfig>, Option<PathBuf>)> {
@@ -262,18 +692,27 @@ fn load_config(cli: &Cli) -> Result<(Option<AppConfig>, Option<PathBuf>)> {
     }
 }
 
-fn init_tracing(cfg: &Option<AppConfig>) {
+fn init_tracing(cfg: &Option<AppConfig>) -> ErrorLog {
     let default_level = cfg
         .as_ref()
         .map(|c| c.monitoring.log_level.clone())
         .unwrap_or_else(|| "info".to_string());
+    let error_log = ErrorLog::new(
+        cfg.as_ref()
+            .map(|c| c.monitoring.error_buffer_size)
+            .unwrap_or(200),
+    );
 
-    tracing_subscriber::fmt()
-        .with_env_filter(
+    tracing_subscriber::registry()
+        .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| format!("moltrade_relayer={}", default_level).into()),
         )
+        .with(tracing_subscriber::fmt::layer())
+        .with(error_log.clone())
         .init();
+
+    error_log
 }
 
 fn init_rocksdb(cfg: &Option<AppConfig>) -> Result<Arc<RocksDBStore>> {
@@ -289,19 +728,16 @@ fn init_rocksdb(cfg: &Option<AppConfig>) -> Result<Arc<RocksDBStore>> {
 fn init_dedupe_engine(
     cfg: &Option<AppConfig>,
     rocksdb: Arc<RocksDBStore>,
-    metrics: Arc<Metrics>,
 ) -> Arc<DeduplicationEngine> {
     match cfg {
-        Some(c) => Arc::new(
-            DeduplicationEngine::new_with_params(
-                rocksdb.clone(),
-                c.deduplication.hotset_size,
-                c.deduplication.bloom_capacity,
-                c.deduplication.lru_size,
-            )
-            .with_metrics(metrics),
-        ),
-        None => Arc::new(DeduplicationEngine::new(rocksdb).with_metrics(metrics)),
+        Some(c) => Arc::new(DeduplicationEngine::new_with_params(
+            rocksdb.clone(),
+            c.deduplication.hotset_size,
+            c.deduplication.bloom_capacity,
+            c.deduplication.bloom_fp_rate,
+            c.deduplication.lru_size,
+        )),
+        None => Arc::new(DeduplicationEngine::new(rocksdb)),
     }
 }
 
@@ -321,18 +757,61 @@ fn resolve_allowed_kinds(cfg: &Option<AppConfig>) -> Option<Vec<u16>> {
         .filter(|kinds| !kinds.is_empty())
 }
 
+/// WebSocket transport for every nostr client, honouring `relay.insecure_tls_relays`
+fn relay_transport(cfg: &Option<AppConfig>) -> Result<RelayTransport> {
+    match cfg {
+        Some(c) => RelayTransport::new(&c.relay.insecure_tls_relays),
+        None => Ok(RelayTransport::default()),
+    }
+}
+
+/// Shared limiter for the relay add/remove endpoints
+fn relay_mutation_limiter(cfg: &Option<AppConfig>) -> TokenBucket {
+    match cfg {
+        Some(c) => TokenBucket::new(c.relay.mutation_burst, c.relay.mutation_per_sec),
+        None => TokenBucket::new(10, 0.5),
+    }
+}
+
+/// Copytrade role kinds; `/api/platform` narrows them to the relay pool's current allowed kinds
+fn supported_kinds(cfg: &Option<AppConfig>) -> SupportedKinds {
+    SupportedKinds(
+        cfg.as_ref()
+            .map(|c| c.kinds)
+            .unwrap_or_default()
+            .all()
+            .to_vec(),
+    )
+}
+
+fn fanout_options(cfg: &Option<AppConfig>) -> websocket::FanoutOptions {
+    let Some(output) = cfg.as_ref().map(|c| &c.output) else {
+        return websocket::FanoutOptions::default();
+    };
+    let subprotocol = output.fanout_subprotocol.trim();
+    websocket::FanoutOptions {
+        subprotocol: (!subprotocol.is_empty()).then(|| subprotocol.to_string()),
+        auth_timeout: output
+            .fanout_auth
+            .then(|| Duration::from_secs(output.fanout_auth_timeout_secs.max(1))),
+        hub_capacity: output.fanout_capacity,
+    }
+}
+
 fn load_nostr_keys(cfg: &Option<AppConfig>, cfg_path: Option<&Path>) -> Result<Option<Keys>> {
     if let Some(nostr) = cfg.as_ref().and_then(|c| c.nostr.as_ref()) {
-        let secret = nostr.secret_key.trim();
-
-        if secret.is_empty() {
+        let Some(secret) = nostr.resolve_secret_key()? else {
             let keys = Keys::generate();
             persist_generated_secret(cfg_path, &keys)?;
             return Ok(Some(keys));
-        }
+        };
 
-        match Keys::parse(secret) {
+        match Keys::parse(&secret) {
             Ok(keys) => Ok(Some(keys)),
+            // Never overwrite a key kept outside the config with a generated one
+            Err(e) if nostr.secret_key.trim().is_empty() => {
+                Err(anyhow::anyhow!("Invalid nostr secret key: {}", e))
+            }
             Err(_) => {
                 warn!("Invalid nostr secret key in config; generating a new one.");
                 let keys = Keys::generate();
@@ -345,6 +824,33 @@ fn load_nostr_keys(cfg: &Option<AppConfig>, cfg_path: Option<&Path>) -> Result<O
     }
 }
 
+/// Retired platform keys to keep decrypting with, capped at `nostr.max_retired_keys` (oldest dropped)
+fn load_retired_keys(cfg: &Option<AppConfig>) -> Result<Vec<Keys>> {
+    let Some(nostr) = cfg.as_ref().and_then(|c| c.nostr.as_ref()) else {
+        return Ok(Vec::new());
+    };
+    parse_retired_keys(&nostr.retired_secret_keys, nostr.max_retired_keys)
+}
+
+fn parse_retired_keys(retired: &[String], max_retired_keys: usize) -> Result<Vec<Keys>> {
+    if retired.len() > max_retired_keys {
+        warn!(
+            "{} retired nostr keys configured; using the {} most recent (nostr.max_retired_keys)",
+            retired.len(),
+            max_retired_keys
+        );
+    }
+    retired
+        .iter()
+        .take(max_retired_keys)
+        .enumerate()
+        .map(|(i, secret)| {
+            Keys::parse(secret.trim())
+                .map_err(|e| anyhow::anyhow!("Invalid nostr.retired_secret_keys[{}]: {}", i, e))
+        })
+        .collect()
+}
+
 fn persist_generated_secret(cfg_path: Option<&Path>, keys: &Keys) -> Result<()> {
     let nsec = keys
         .secret_key()
@@ -385,18 +891,27 @@ fn persist_generated_secret(cfg_path: Option<&Path>, keys: &Keys) -> Result<()>
 async fn init_nostr_publisher(
     cfg: &Option<AppConfig>,
     keys: Option<&Keys>,
+    transport: &RelayTransport,
+    relays: &[String],
 ) -> Result<Option<Arc<Client>>> {
     let keys = match keys {
         Some(k) => k,
         None => return Ok(None),
     };
 
-    let client = Client::new(keys.clone());
-    let relays = match cfg {
-        Some(c) => c.relay.bootstrap_relays.clone(),
-        None => load_relay_urls().await?,
-    };
+    let publish = cfg
+        .as_ref()
+        .and_then(|c| c.nostr.as_ref())
+        .map(|n| n.publish_to_followers)
+        .unwrap_or(true);
+    if !publish {
+        warn!(
+            "nostr.publish_to_followers = false: follower fanout is WebSocket-only (/fanout); skipped publishes are counted in nostr_publish_skipped_total"
+        );
+        return Ok(None);
+    }
 
+    let client = transport.client(keys.clone());
     for url in relays {
         client.add_relay(url).await.ok();
     }
@@ -404,10 +919,13 @@ async fn init_nostr_publisher(
     Ok(Some(Arc::new(client)))
 }
 
-async fn bootstrap_relays(cfg: &Option<AppConfig>) -> Result<Vec<String>> {
+fn bootstrap_relays(cfg: &Option<AppConfig>, allow_default_relays: bool) -> Result<Vec<String>> {
     match cfg {
-        Some(c) => Ok(c.relay.bootstrap_relays.clone()),
-        None => load_relay_urls().await,
+        Some(c) => relays_or_defaults(
+            c.relay.bootstrap_relays.iter().map(String::as_str),
+            allow_default_relays,
+        ),
+        None => load_relay_urls(allow_default_relays),
     }
 }
 
@@ -415,26 +933,83 @@ async fn init_subscription_service(
     cfg: &Option<AppConfig>,
 ) -> Result<Option<Arc<SubscriptionService>>> {
     if let Some(pg) = cfg.as_ref().and_then(|c| c.postgres.as_ref()) {
-        let svc = SubscriptionService::new(&pg.dsn, pg.max_connections)
-            .await
-            .context("Failed to initialize subscription service")?;
+        let follower_cache_secs = cfg
+            .as_ref()
+            .and_then(|c| c.subscriptions.as_ref())
+            .map(|s| s.follower_cache_secs)
+            .unwrap_or(30);
+        let max_followers_per_bot = cfg
+            .as_ref()
+            .and_then(|c| c.subscriptions.as_ref())
+            .map_or(0, |s| s.max_followers_per_bot);
+        let svc = SubscriptionService::connect_with_retry(
+            &pg.dsn,
+            pg.max_connections,
+            pg.connect_retries,
+            Duration::from_secs(pg.retry_delay_secs),
+        )
+        .await
+        .context("Failed to initialize subscription service")?
+        .with_follower_cache_ttl(Duration::from_secs(follower_cache_secs))
+        .with_max_followers_per_bot(max_followers_per_bot);
         Ok(Some(Arc::new(svc)))
     } else {
+        warn_subscriptions_disabled(cfg);
         Ok(None)
     }
 }
 
+/// Spell out what silently stops working when Postgres is not configured
+fn warn_subscriptions_disabled(cfg: &Option<AppConfig>) {
+    let mut disabled = vec![
+        "bot registration and subscription REST endpoints (503)",
+        "agent register events (kind 30935)",
+        "trade signal persistence and follower fanout",
+        "bot heartbeat last_seen tracking",
+    ];
+    if cfg.as_ref().and_then(|c| c.settlement.as_ref()).is_some() {
+        disabled.push("settlement worker and credit issuance ([settlement] is configured)");
+    }
+    warn!(
+        "No [postgres] configured; subscription service disabled: {}",
+        disabled.join(", ")
+    );
+
+    let copytrade_kinds = cfg.as_ref().map(|c| c.kinds).unwrap_or_default().all();
+    let accepted: Vec<u16> = match resolve_allowed_kinds(cfg) {
+        Some(kinds) => kinds
+            .into_iter()
+            .filter(|k| copytrade_kinds.contains(k))
+            .collect(),
+        None => copytrade_kinds.to_vec(),
+    };
+    if !accepted.is_empty() {
+        warn!(
+            "!!! Copytrade kinds {:?} are accepted but WILL NOT be processed without [postgres]; configure Postgres or remove them from filters.allowed_kinds !!!",
+            accepted
+        );
+    }
+}
+
 fn build_app(
     rest_router: axum::Router,
     downstream_rx: Receiver<Event>,
     fanout_rx: Option<Receiver<FanoutMessage>>,
+    replay_buffer: ReplayBuffer,
+    fanout_options: websocket::FanoutOptions,
+    ws_connections: websocket::ConnectionRegistry,
     websocket_enabled: bool,
 ) -> axum::Router {
     if websocket_enabled {
         let downstream_rx_arc = Arc::new(downstream_rx);
         let fanout_rx_arc = fanout_rx.map(Arc::new);
-        let ws_router =
-            websocket::create_websocket_router(downstream_rx_arc.clone(), fanout_rx_arc);
+        let ws_router = websocket::create_websocket_router(
+            downstream_rx_arc.clone(),
+            fanout_rx_arc,
+            replay_buffer,
+            fanout_options,
+            ws_connections,
+        );
         axum::Router::new().merge(rest_router).merge(ws_router)
     } else {
         let mut warned = false;
@@ -471,3 +1046,88 @@ fn spawn_memory_metrics(metrics: Arc<Metrics>) {
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostr_sdk::{EventBuilder, Kind};
+
+    #[test]
+    fn shutdown_summary_counts_what_is_still_buffered() {
+        let metrics = Metrics::with_registry(&prometheus::Registry::new()).unwrap();
+        metrics.events_in_queue.set(4.0);
+        metrics.pending_trades.set(2.0);
+        let (downstream_tx, _downstream_rx) = flume::unbounded();
+        let event = EventBuilder::new(Kind::Custom(30931), "")
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        downstream_tx.send(event).unwrap();
+        let (fanout_tx, _fanout_rx) = flume::unbounded();
+        for follower in ["f1", "f2", "f3"] {
+            fanout_tx
+                .send(FanoutMessage {
+                    target_pubkey: follower.to_string(),
+                    bot_pubkey: "bot".to_string(),
+                    kind: 30931,
+                    original_event_id: "id".to_string(),
+                    payload: "{}".to_string(),
+                })
+                .unwrap();
+        }
+
+        let summary = ShutdownSummary::collect(&metrics, &downstream_tx, Some(&fanout_tx));
+        assert_eq!(
+            summary,
+            ShutdownSummary {
+                pending_events: 4,
+                downstream_queue: 1,
+                fanout_queue: 3,
+                pending_trades: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn relay_urls_are_split_and_trimmed() {
+        let urls = relay_urls_from(" wss://a.example ,,wss://b.example,", false).unwrap();
+        assert_eq!(urls, ["wss://a.example", "wss://b.example"]);
+    }
+
+    #[test]
+    fn missing_relays_need_allow_default_relays() {
+        for unset in ["", " , "] {
+            let err = relay_urls_from(unset, false).unwrap_err().to_string();
+            assert!(err.starts_with("No relays configured"), "{}", err);
+            assert_eq!(relay_urls_from(unset, true).unwrap(), DEFAULT_RELAYS);
+        }
+
+        let mut cfg: AppConfig = toml::from_str(include_str!("../config.template.toml")).unwrap();
+        cfg.relay.bootstrap_relays = vec![" ".to_string()];
+        let cfg = Some(cfg);
+        let err = bootstrap_relays(&cfg, false).unwrap_err().to_string();
+        assert!(err.starts_with("No relays configured"), "{}", err);
+        assert_eq!(bootstrap_relays(&cfg, true).unwrap(), DEFAULT_RELAYS);
+    }
+
+    #[test]
+    fn retired_keys_past_the_cap_are_ignored() {
+        let retired = vec!["not a key".to_string(), "nor this".to_string()];
+        // Entries past the cap are never parsed, so they cannot fail startup
+        assert!(parse_retired_keys(&retired, 0).unwrap().is_empty());
+        let err = parse_retired_keys(&retired, 1).unwrap_err().to_string();
+        assert!(err.contains("nostr.retired_secret_keys[0]"), "{}", err);
+    }
+
+    #[test]
+    fn retired_keys_keep_the_most_recent() {
+        let newest = Keys::generate();
+        let older = Keys::generate();
+        let retired = vec![
+            newest.secret_key().to_secret_hex(),
+            older.secret_key().to_secret_hex(),
+        ];
+        let keys = parse_retired_keys(&retired, 1).unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].public_key(), newest.public_key());
+    }
+}
@@ -0,0 +1,175 @@
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use nostr_sdk::prelude::{Client, EventBuilder, Filter, Keys, Kind, Tag, Timestamp};
+
+use crate::config::AppConfig;
+use crate::core::subscription::SubscriptionService;
+use crate::{bootstrap_relays, init_rocksdb, relay_transport};
+
+/// Budget for each component check, so an unreachable dependency fails instead of hanging
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Addressable kind for the probe event; the fixed `d` tag makes reruns replace the previous probe
+const PROBE_KIND: u16 = 39991;
+const PROBE_TTL: Duration = Duration::from_secs(600);
+
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Pass(String),
+    Fail(String),
+    Skip(String),
+}
+
+/// Check every external dependency once, print a per-component report and fail if any check failed
+pub async fn run(cfg: &Option<AppConfig>, allow_default_relays: bool) -> Result<()> {
+    let checks = [
+        ("postgres", check(check_postgres(cfg)).await),
+        ("rocksdb", check(check_rocksdb(cfg)).await),
+        ("relay", check(check_relay(cfg, allow_default_relays)).await),
+    ];
+
+    let mut failed = 0;
+    for (component, outcome) in &checks {
+        match outcome {
+            Outcome::Pass(detail) => println!("PASS {:<9} {}", component, detail),
+            Outcome::Skip(detail) => println!("SKIP {:<9} {}", component, detail),
+            Outcome::Fail(detail) => {
+                failed += 1;
+                println!("FAIL {:<9} {}", component, detail);
+            }
+        }
+    }
+
+    if failed > 0 {
+        bail!("selftest failed: {} of {} checks", failed, checks.len());
+    }
+    println!("selftest passed");
+    Ok(())
+}
+
+async fn check(fut: impl Future<Output = Result<Option<String>>>) -> Outcome {
+    check_within(CHECK_TIMEOUT, fut).await
+}
+
+async fn check_within(
+    timeout: Duration,
+    fut: impl Future<Output = Result<Option<String>>>,
+) -> Outcome {
+    match tokio::time::timeout(timeout, fut).await {
+        Ok(Ok(Some(detail))) => Outcome::Pass(detail),
+        Ok(Ok(None)) => Outcome::Skip("not configured".to_string()),
+        Ok(Err(e)) => Outcome::Fail(format!("{:#}", e)),
+        Err(_) => Outcome::Fail(format!("timed out after {:?}", timeout)),
+    }
+}
+
+/// Connect and run schema init, exactly as startup does
+async fn check_postgres(cfg: &Option<AppConfig>) -> Result<Option<String>> {
+    let Some(pg) = cfg.as_ref().and_then(|c| c.postgres.as_ref()) else {
+        return Ok(None);
+    };
+    SubscriptionService::new(&pg.dsn, pg.max_connections).await?;
+    Ok(Some("connected, schema up to date".to_string()))
+}
+
+async fn check_rocksdb(cfg: &Option<AppConfig>) -> Result<Option<String>> {
+    let store = init_rocksdb(cfg)?;
+    Ok(Some(format!(
+        "opened (~{} keys)",
+        store.approximate_count().await
+    )))
+}
+
+/// Publish a short-lived probe event to the bootstrap relays and read it back
+async fn check_relay(
+    cfg: &Option<AppConfig>,
+    allow_default_relays: bool,
+) -> Result<Option<String>> {
+    let relays = bootstrap_relays(cfg, allow_default_relays)?;
+    if relays.is_empty() {
+        bail!("no bootstrap relays configured");
+    }
+
+    // Use the configured identity when there is one, but never generate-and-persist a key here
+    let keys = cfg
+        .as_ref()
+        .and_then(|c| c.nostr.as_ref())
+        .and_then(|n| n.resolve_secret_key().ok().flatten())
+        .and_then(|secret| Keys::parse(&secret).ok())
+        .unwrap_or_else(Keys::generate);
+    let client = relay_transport(cfg)?.client(keys);
+    for url in &relays {
+        client
+            .add_relay(url.as_str())
+            .await
+            .with_context(|| format!("invalid relay url {}", url))?;
+    }
+
+    let result = probe_relays(&client).await;
+    client.disconnect().await;
+    result.map(Some)
+}
+
+async fn probe_relays(client: &Client) -> Result<String> {
+    let connected = client.try_connect(CHECK_TIMEOUT / 2).await;
+    if connected.success.is_empty() {
+        bail!("could not connect to any relay: {:?}", connected.failed);
+    }
+
+    let builder = EventBuilder::new(Kind::Custom(PROBE_KIND), "moltrade-relayer selftest")
+        .tag(Tag::identifier("moltrade-selftest"))
+        .tag(Tag::expiration(Timestamp::now() + PROBE_TTL));
+    let sent = client
+        .send_event_builder(builder)
+        .await
+        .context("failed to publish probe event")?;
+    if sent.success.is_empty() {
+        bail!("no relay accepted the probe event: {:?}", sent.failed);
+    }
+
+    let probe_id = *sent.id();
+    let found = client
+        .fetch_events(Filter::new().id(probe_id), CHECK_TIMEOUT / 4)
+        .await
+        .context("failed to read back probe event")?
+        .into_iter()
+        .any(|e| e.id == probe_id);
+    if !found {
+        return Err(anyhow!(
+            "probe event {} accepted but not returned by any relay",
+            probe_id.to_hex()
+        ));
+    }
+
+    Ok(format!(
+        "{} connected, probe {} published and read back",
+        connected.success.len(),
+        probe_id.to_hex()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn check_outcomes() {
+        let pass = check(async { Ok(Some("ok".to_string())) }).await;
+        assert_eq!(pass, Outcome::Pass("ok".to_string()));
+        let skip = check(async { Ok(None) }).await;
+        assert_eq!(skip, Outcome::Skip("not configured".to_string()));
+        let fail = check(async { Err(anyhow!("refused").context("connect")) }).await;
+        assert_eq!(fail, Outcome::Fail("connect: refused".to_string()));
+    }
+
+    #[tokio::test]
+    async fn unanswered_check_fails() {
+        let outcome = check_within(
+            Duration::from_millis(20),
+            std::future::pending::<Result<Option<String>>>(),
+        )
+        .await;
+        assert_eq!(outcome, Outcome::Fail("timed out after 20ms".to_string()));
+    }
+}
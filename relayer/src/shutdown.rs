@@ -0,0 +1,49 @@
+use std::future::Future;
+
+use anyhow::{Context, Result};
+use tokio::signal;
+use tracing::info;
+
+/// Resolves on SIGINT, or SIGTERM on unix (sent by Kubernetes/Docker on stop). The SIGTERM
+/// handler is installed before this returns, so a signal sent right after is not missed
+pub fn shutdown_signal() -> Result<impl Future<Output = Result<()>>> {
+    #[cfg(unix)]
+    let mut sigterm = signal::unix::signal(signal::unix::SignalKind::terminate())
+        .context("Failed to install SIGTERM handler")?;
+
+    Ok(async move {
+        #[cfg(unix)]
+        tokio::select! {
+            res = signal::ctrl_c() => res.context("Failed to listen for shutdown signal")?,
+            _ = sigterm.recv() => info!("SIGTERM received"),
+        }
+
+        #[cfg(not(unix))]
+        signal::ctrl_c()
+            .await
+            .context("Failed to listen for shutdown signal")?;
+
+        Ok(())
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn sigterm_triggers_shutdown() {
+        let shutdown = shutdown_signal().unwrap();
+        let status = Command::new("kill")
+            .args(["-TERM", &std::process::id().to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        tokio::time::timeout(Duration::from_secs(5), shutdown)
+            .await
+            .expect("SIGTERM did not trigger shutdown")
+            .unwrap();
+    }
+}
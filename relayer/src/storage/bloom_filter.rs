@@ -6,6 +6,8 @@ use tokio::sync::RwLock;
 /// Capacity: 10 million events with ~1% false positive rate
 pub struct BloomFilter {
     filter: Arc<RwLock<BloomFilterLib>>,
+    num_bits: usize,
+    num_hashes: u32,
 }
 
 impl BloomFilter {
@@ -13,6 +15,8 @@ impl BloomFilter {
     pub fn with_capacity(capacity: usize, false_positive_rate: f64) -> Self {
         let filter = BloomFilterLib::with_rate(false_positive_rate as f32, capacity as u32);
         Self {
+            num_bits: filter.num_bits(),
+            num_hashes: filter.num_hashes(),
             filter: Arc::new(RwLock::new(filter)),
         }
     }
@@ -20,10 +24,17 @@ impl BloomFilter {
     /// Create a new Bloom filter with capacity for 10 million items
     pub fn new() -> Self {
         // Create bloom filter with 10M capacity and 1% false positive rate
-        let filter = BloomFilterLib::with_rate(0.01, 10_000_000);
-        Self {
-            filter: Arc::new(RwLock::new(filter)),
-        }
+        Self::with_capacity(10_000_000, 0.01)
+    }
+
+    /// Number of bits backing the filter
+    pub fn num_bits(&self) -> usize {
+        self.num_bits
+    }
+
+    /// Number of hash functions applied per item
+    pub fn num_hashes(&self) -> u32 {
+        self.num_hashes
     }
 
     /// Check if an event ID might exist (fast check, may have false positives)
@@ -41,7 +52,7 @@ impl BloomFilter {
     /// Clear the bloom filter (useful for testing or reset)
     pub async fn clear(&self) {
         let mut filter = self.filter.write().await;
-        *filter = BloomFilterLib::with_rate(0.01, 10_000_000);
+        *filter = BloomFilterLib::with_size(self.num_bits, self.num_hashes);
     }
 }
 
@@ -50,3 +61,30 @@ impl Default for BloomFilter {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lower_false_positive_rate_needs_more_bits_and_hashes() {
+        let loose = BloomFilter::with_capacity(10_000, 0.05);
+        let tight = BloomFilter::with_capacity(10_000, 0.001);
+        assert!(tight.num_bits() > loose.num_bits());
+        assert!(tight.num_hashes() > loose.num_hashes());
+    }
+
+    #[tokio::test]
+    async fn inserted_ids_are_found_until_cleared() {
+        let filter = BloomFilter::with_capacity(1_000, 0.01);
+        let id = [7u8; 32];
+        assert!(!filter.contains(&id).await);
+        filter.insert(&id).await;
+        assert!(filter.contains(&id).await);
+
+        let (bits, hashes) = (filter.num_bits(), filter.num_hashes());
+        filter.clear().await;
+        assert!(!filter.contains(&id).await);
+        assert_eq!((filter.num_bits(), filter.num_hashes()), (bits, hashes));
+    }
+}
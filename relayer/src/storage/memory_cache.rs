@@ -40,6 +40,15 @@ impl MemoryCache {
         cache.put(event_id, ());
     }
 
+    /// The ID that inserting `incoming` would evict, if the cache is full and `incoming` is new
+    pub async fn eviction_candidate(&self, incoming: &str) -> Option<String> {
+        let cache = self.cache.read().await;
+        if cache.len() < cache.cap().get() || cache.contains(incoming) {
+            return None;
+        }
+        cache.peek_lru().map(|(id, _)| id.clone())
+    }
+
     /// Get the current size of the cache
     pub async fn len(&self) -> usize {
         let cache = self.cache.read().await;
@@ -52,3 +61,19 @@ impl Default for MemoryCache {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn eviction_candidate_is_lru_only_when_full_and_new() {
+        let cache = MemoryCache::with_capacity(2);
+        cache.put("a".to_string()).await;
+        assert_eq!(cache.eviction_candidate("b").await, None);
+        cache.put("b".to_string()).await;
+        assert_eq!(cache.eviction_candidate("c").await.as_deref(), Some("a"));
+        // Re-inserting a cached id evicts nothing
+        assert_eq!(cache.eviction_candidate("b").await, None);
+    }
+}
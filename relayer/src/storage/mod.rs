@@ -1,3 +1,4 @@
 pub mod bloom_filter;
 pub mod memory_cache;
+pub mod replay_buffer;
 pub mod rocksdb_store;
@@ -0,0 +1,77 @@
+use nostr_sdk::Event;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Bounded in-memory buffer of recently routed events, replayed to new WebSocket clients
+#[derive(Clone)]
+pub struct ReplayBuffer {
+    events: Arc<RwLock<VecDeque<Event>>>,
+    capacity: usize,
+}
+
+impl ReplayBuffer {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            events: Arc::new(RwLock::new(VecDeque::with_capacity(capacity.min(10_000)))),
+            capacity,
+        }
+    }
+
+    /// Record an event, evicting the oldest once full
+    pub async fn push(&self, event: Event) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut events = self.events.write().await;
+        if events.len() >= self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// The `limit` most recently routed events, ordered by `created_at` (oldest first unless `newest_first`)
+    pub async fn recent(&self, limit: usize, newest_first: bool) -> Vec<Event> {
+        let events = self.events.read().await;
+        let skip = events.len().saturating_sub(limit);
+        let mut out: Vec<Event> = events.iter().skip(skip).cloned().collect();
+        drop(events);
+
+        out.sort_by_key(|e| e.created_at);
+        if newest_first {
+            out.reverse();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostr_sdk::{EventBuilder, Keys, Kind, Timestamp};
+
+    fn event_at(created_at: u64) -> Event {
+        EventBuilder::new(Kind::Custom(30931), "")
+            .custom_created_at(Timestamp::from_secs(created_at))
+            .sign_with_keys(&Keys::generate())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn recent_events_come_in_either_order() {
+        let buffer = ReplayBuffer::with_capacity(3);
+        // Routed out of created_at order; the first is evicted
+        for created_at in [100, 300, 200, 400] {
+            buffer.push(event_at(created_at)).await;
+        }
+        let times = |events: Vec<Event>| {
+            events
+                .iter()
+                .map(|e| e.created_at.as_secs())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(times(buffer.recent(2, false).await), [200, 400]);
+        assert_eq!(times(buffer.recent(2, true).await), [400, 200]);
+        assert_eq!(times(buffer.recent(10, false).await), [200, 300, 400]);
+    }
+}
@@ -42,6 +42,15 @@ impl RocksDBStore {
         key
     }
 
+    #[inline]
+    fn key_seen(event_id: &str) -> Vec<u8> {
+        // Id-only dedupe marker for events whose payload was never stored
+        let mut key = Vec::with_capacity(5 + event_id.len());
+        key.extend_from_slice(b"seen:");
+        key.extend_from_slice(event_id.as_bytes());
+        key
+    }
+
     #[inline]
     fn key_forward_status(event_id: &str) -> Vec<u8> {
         // Forwarding status for quick lookup
@@ -70,10 +79,18 @@ impl RocksDBStore {
         let db = self.db.read().await;
         match db.get(Self::key_event(event_id)) {
             Ok(Some(_)) => true,
-            _ => false,
+            _ => matches!(db.get(Self::key_seen(event_id)), Ok(Some(_))),
         }
     }
 
+    /// Record an event ID as seen without storing its payload
+    pub async fn mark_seen(&self, event_id: &str) -> Result<()> {
+        let db = self.db.write().await;
+        db.put(Self::key_seen(event_id), [])
+            .context("Failed to store seen marker in RocksDB")?;
+        Ok(())
+    }
+
     /// Store an event in the database
     pub async fn store_event(&self, event: &Event) -> Result<()> {
         let event_id = event.id.to_string();
@@ -3,16 +3,20 @@
 Simple REST smoke tests for the relayer.
 Set RELAYER_BASE_URL (default http://localhost:8080) and optionally RELAYER_SETTLEMENT_TOKEN.
 Optional trade exercise: set RELAYER_TEST_TX_HASH plus RELAYER_TEST_BOT_PK; follower/role/symbol/side/size/price optional.
+The bot API key comes from registration; for a bot registered on an earlier run set RELAYER_TEST_BOT_API_KEY.
 """
 import json
 import os
 import sys
+import time
 from typing import Any, Dict
 
 import requests
 
 BASE_URL = os.getenv("RELAYER_BASE_URL", "http://localhost:8080").rstrip("/")
 SETTLEMENT_TOKEN = os.getenv("RELAYER_SETTLEMENT_TOKEN")
+# Set when this host is outside output.admin_allowed_ips, so admin endpoints must refuse it
+EXPECT_ADMIN_FORBIDDEN = os.getenv("RELAYER_EXPECT_ADMIN_FORBIDDEN") == "1"
 TIMEOUT = float(os.getenv("RELAYER_TIMEOUT", "5"))
 
 # Demo bot identities (for eth-based bots, bot_pubkey == eth_address)
@@ -22,6 +26,9 @@ LEADER_NOSTR = os.getenv("RELAYER_LEADER_NOSTR_PUB", "npub1leaderdemo")
 FOLLOWER_NOSTR = os.getenv("RELAYER_FOLLOWER_NOSTR_PUB", "npub1followdemo")
 SUB_SHARED_SECRET = os.getenv("RELAYER_SUB_SHARED_SECRET", "shared_secret_demo")
 
+# API keys returned by /api/bots/register, keyed by bot_pubkey (only issued on first registration)
+BOT_API_KEYS: Dict[str, str] = {}
+
 
 def call(method: str, path: str, *, include_token: bool = False, **kwargs):
     url = f"{BASE_URL}{path}"
@@ -44,6 +51,13 @@ def expect_ok(resp, label: str) -> None:
     print(f"[OK] {label}: status={resp.status_code}")
 
 
+def expect_status(resp, status: int, label: str) -> None:
+    if resp.status_code != status:
+        print(f"[FAIL] {label}: expected {status}, got status={resp.status_code}, body={resp.text}")
+        sys.exit(1)
+    print(f"[OK] {label}: status={resp.status_code}")
+
+
 def smoke_core() -> None:
     expect_ok(call("get", "/health"), "health")
     expect_ok(call("get", "/status"), "status")
@@ -52,6 +66,75 @@ def smoke_core() -> None:
     expect_ok(call("get", "/api/credits"), "credits")
 
 
+def admin_source_check() -> None:
+    resp = call("get", "/api/debug/errors", include_token=True)
+    if EXPECT_ADMIN_FORBIDDEN:
+        expect_status(resp, 403, "admin endpoint from a disallowed source")
+    else:
+        expect_ok(resp, "admin endpoint from an allowed source")
+
+
+def toggle_relay_kinds() -> None:
+    resp = call("get", "/api/relays")
+    expect_ok(resp, "list relays")
+    relays = resp.json()
+    original = relays.get("allowed_kinds")
+    toggled = [30931] if original != [30931] else [30931, 30932]
+
+    resp = call("put", "/api/relays/kinds", include_token=True, json={"kinds": toggled})
+    expect_ok(resp, "toggle relay kinds")
+    body = resp.json()
+    if body.get("allowed_kinds") != toggled or body.get("resubscribed") != relays.get("count"):
+        print(f"[FAIL] toggle relay kinds: expected {toggled} on {relays.get('count')} relays, got {body}")
+        sys.exit(1)
+    resp = call("put", "/api/relays/kinds", include_token=True, json={"kinds": toggled})
+    expect_ok(resp, "toggle relay kinds again")
+    if resp.json().get("resubscribed") != 0:
+        print(f"[FAIL] unchanged relay kinds resubscribed: {resp.json()}")
+        sys.exit(1)
+    expect_ok(call("put", "/api/relays/kinds", include_token=True, json={"kinds": original}), "restore relay kinds")
+
+
+def pause_and_resume_ingestion() -> None:
+    resp = call("post", "/api/admin/pause", include_token=True)
+    expect_ok(resp, "pause ingestion")
+    if resp.json().get("ingestion_paused") is not True:
+        print(f"[FAIL] pause ingestion: {resp.json()}")
+        sys.exit(1)
+    resp = call("get", "/status")
+    expect_ok(resp, "status while paused")
+    if resp.json().get("ingestion_paused") is not True or not resp.json().get("ingestion_paused_since"):
+        print(f"[FAIL] status does not report the pause: {resp.json()}")
+        sys.exit(1)
+
+    resp = call("post", "/api/admin/resume", include_token=True)
+    expect_ok(resp, "resume ingestion")
+    if resp.json().get("ingestion_paused") is not False or resp.json().get("changed") is not True:
+        print(f"[FAIL] resume ingestion: {resp.json()}")
+        sys.exit(1)
+    resp = call("get", "/status")
+    expect_ok(resp, "status after resume")
+    if resp.json().get("ingestion_paused") is not False:
+        print(f"[FAIL] status still reports a pause: {resp.json()}")
+        sys.exit(1)
+
+
+def register_bot(payload: Dict[str, str], label: str) -> None:
+    bot_pk = payload["bot_pubkey"]
+    headers = {"Content-Type": "application/json"}
+    api_key = BOT_API_KEYS.get(bot_pk)
+    if api_key is None and bot_pk == LEADER_ETH:
+        api_key = os.getenv("RELAYER_TEST_BOT_API_KEY")
+    if api_key:
+        headers["X-Bot-Api-Key"] = api_key
+    resp = call("post", "/api/bots/register", headers=headers, data=json.dumps(payload))
+    if resp.status_code == 401 and not api_key:
+        print(f"[SKIP] {label} (bot registered earlier with a key; set RELAYER_TEST_BOT_API_KEY)")
+        return
+    expect_ok(resp, label)
+    remember_api_key(bot_pk, resp)
+
+
 def register_bots_and_subscription() -> None:
     # Register leader bot
     leader_payload = {
@@ -60,13 +143,16 @@ def register_bots_and_subscription() -> None:
         "eth_address": LEADER_ETH,
         "name": "leader-bot",
     }
+    register_bot(leader_payload, "register leader bot")
+
+    # A bot with a key cannot be re-registered without it
     resp = call(
         "post",
         "/api/bots/register",
         headers={"Content-Type": "application/json"},
         data=json.dumps(leader_payload),
     )
-    expect_ok(resp, "register leader bot")
+    expect_status(resp, 401, "re-register leader bot without api key")
 
     # Register follower bot
     follower_payload = {
@@ -75,13 +161,7 @@ def register_bots_and_subscription() -> None:
         "eth_address": FOLLOWER_ETH,
         "name": "follower-bot",
     }
-    resp = call(
-        "post",
-        "/api/bots/register",
-        headers={"Content-Type": "application/json"},
-        data=json.dumps(follower_payload),
-    )
-    expect_ok(resp, "register follower bot")
+    register_bot(follower_payload, "register follower bot")
 
     # Follower subscribes to leader
     sub_payload = {
@@ -97,6 +177,79 @@ def register_bots_and_subscription() -> None:
     )
     expect_ok(resp, "subscription follower->leader")
 
+    resp = call("get", f"/api/bots/{LEADER_ETH}/followers")
+    expect_ok(resp, "follower details")
+    details = {f["follower_pubkey"]: f for f in resp.json()["followers"]}
+    follower = details.get(FOLLOWER_ETH)
+    if follower is None or follower["connected"] or "credits" not in follower:
+        print(f"[FAIL] follower details for {FOLLOWER_ETH}: {follower}")
+        sys.exit(1)
+    print("[OK] follower details list the follower")
+
+
+def batch_reads() -> None:
+    queries = [
+        {"op": "bot_stats", "params": {"bot_pubkey": LEADER_ETH}},
+        {"op": "credits", "params": {"bot_pubkey": LEADER_ETH}},
+        {"op": "relays"},
+    ]
+    resp = call("post", "/api/batch", json=queries)
+    expect_ok(resp, "batch reads")
+    results = resp.json()["results"]
+    if (
+        len(results) != 3
+        or any(r.get("status") != 200 for r in results)
+        or results[0]["body"].get("bot_pubkey") != LEADER_ETH
+        or "credits" not in results[1]["body"]
+        or "relays" not in results[2]["body"]
+    ):
+        print(f"[FAIL] batch reads: {results}")
+        sys.exit(1)
+    print("[OK] batch reads returned three results in order")
+
+    resp = call("post", "/api/batch", json=[{"op": "register_bot"}])
+    expect_ok(resp, "batch with a write op")
+    if resp.json()["results"][0].get("status") != 400:
+        print(f"[FAIL] batch accepted a write op: {resp.json()}")
+        sys.exit(1)
+
+
+def remember_api_key(bot_pubkey: str, resp) -> None:
+    api_key = resp.json().get("api_key")
+    if api_key:
+        BOT_API_KEYS[bot_pubkey] = api_key
+
+
+def net_positions() -> None:
+    api_key = os.getenv("RELAYER_TEST_BOT_API_KEY") or BOT_API_KEYS.get(LEADER_ETH)
+    if not api_key:
+        print("[SKIP] positions (bot registered earlier; set RELAYER_TEST_BOT_API_KEY)")
+        return
+
+    symbol = f"SMOKE-{int(time.time())}"
+    trades = [("buy", 2.0), ("sell", 0.5), ("long", 1.0), ("buy", 0.0)]
+    for i, (side, size) in enumerate(trades):
+        payload = {
+            "bot_pubkey": LEADER_ETH,
+            "role": "leader",
+            "symbol": symbol,
+            "side": side,
+            "size": size,
+            "price": 100.0,
+            "tx_hash": f"0x{symbol.lower()}-{i}",
+        }
+        resp = call("post", "/api/trades/record", include_token=True, headers={"X-Bot-Api-Key": api_key}, json=payload)
+        expect_ok(resp, f"record {side} {size} {symbol}")
+
+    resp = call("get", f"/api/bots/{LEADER_ETH}/positions")
+    expect_ok(resp, "positions")
+    position = next((p for p in resp.json()["positions"] if p["symbol"] == symbol), None)
+    expected = {"symbol": symbol, "net_size": 2.5, "trade_count": 3, "skipped_trades": 1}
+    if position != expected:
+        print(f"[FAIL] positions: expected {expected}, got {position}")
+        sys.exit(1)
+    print(f"[OK] net position for {symbol} is 2.5")
+
 
 def maybe_exercise_trade() -> None:
     tx_hash = os.getenv("RELAYER_TEST_TX_HASH")
@@ -124,14 +277,44 @@ def maybe_exercise_trade() -> None:
         "tx_hash": tx_hash,
     }
 
+    api_key = os.getenv("RELAYER_TEST_BOT_API_KEY") or BOT_API_KEYS.get(bot_pk)
+    if not api_key:
+        print("[SKIP] trade record/settlement (bot registered earlier; set RELAYER_TEST_BOT_API_KEY)")
+        return
+
+    def record(headers: Dict[str, str]):
+        return call(
+            "post",
+            "/api/trades/record",
+            include_token=True,
+            headers={"Content-Type": "application/json", **headers},
+            data=json.dumps(record_payload),
+        )
+
+    expect_status(record({}), 401, "record trade without api key")
+    expect_status(record({"X-Bot-Api-Key": "wrong"}), 401, "record trade with wrong api key")
+    expect_ok(record({"X-Bot-Api-Key": api_key}), "record trade")
+
+    # The other side of the same tx must get its own row rather than being dropped as a duplicate
+    record_payload["role"] = "follower" if role == "leader" else "leader"
+    expect_ok(record({"X-Bot-Api-Key": api_key}), "record counterpart trade with same tx_hash")
+    record_payload["role"] = role
+    resp = call("get", "/api/trades", params={"bot_pubkey": bot_pk, "limit": 1000})
+    expect_ok(resp, "list trades")
+    roles = {t["role"] for t in resp.json()["trades"] if t.get("tx_hash") == tx_hash}
+    if roles != {"leader", "follower"}:
+        print(f"[FAIL] leader and follower rows for {tx_hash}: got roles {sorted(roles)}")
+        sys.exit(1)
+    print("[OK] leader and follower rows share tx_hash")
+
     resp = call(
         "post",
-        "/api/trades/record",
+        "/api/trades/settlement",
         include_token=True,
         headers={"Content-Type": "application/json"},
-        data=json.dumps(record_payload),
+        data=json.dumps({"tx_hash": tx_hash, "status": "confirmd"}),
     )
-    expect_ok(resp, "record trade")
+    expect_status(resp, 400, "settlement update with unknown status")
 
     settlement_payload = {
         "tx_hash": tx_hash,
@@ -152,7 +335,15 @@ def maybe_exercise_trade() -> None:
 def main() -> None:
     print(f"Relayer base URL: {BASE_URL}")
     smoke_core()
+    admin_source_check()
+    if EXPECT_ADMIN_FORBIDDEN:
+        print("[DONE] admin source check passed; skipping admin-dependent tests")
+        return
+    toggle_relay_kinds()
+    pause_and_resume_ingestion()
     register_bots_and_subscription()
+    batch_reads()
+    net_positions()
     maybe_exercise_trade()
     print("[DONE] relayer smoke tests passed")
 